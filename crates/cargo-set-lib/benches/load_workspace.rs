@@ -0,0 +1,162 @@
+//! Measures time and heap usage of loading (and cloning) a large workspace.
+//!
+//! Run with `cargo bench -p cargo-set-lib`; `MEMBERS` overrides the workspace size.
+//! `owned` is the baseline of a deep copy of every parsed manifest, which a
+//! clone of the workspace made before members were shared between clones.
+//!
+//! With 1000 members, sharing members and interning the paths and names of
+//! the index took the retained heap of a load from 11123 KiB to 8457 KiB and
+//! a clone from 10521 KiB (6.2ms) to 1207 KiB (1.4ms). What remains is mostly
+//! the parsed manifests themselves, about 6.7 KiB per member.
+//! Concurrent loading is compared on a file system that adds `LATENCY_MS`
+//! (default 1) to every read, like a network file system, with `JOBS`
+//! (default 8) manifests read at the same time.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use cargo_set_lib::{CargoManifestService, FileSystem, RealFileSystem};
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static RESIDENT: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        RESIDENT.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        RESIDENT.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// The disk, with every read delayed by `latency`.
+struct SlowFileSystem {
+    latency: Duration,
+}
+
+impl FileSystem for SlowFileSystem {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::thread::sleep(self.latency);
+        RealFileSystem.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+        RealFileSystem.write(path, contents)
+    }
+
+    fn remove(&self, path: &Path) -> std::io::Result<()> {
+        RealFileSystem.remove(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+        RealFileSystem.canonicalize(path)
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn create_workspace(root: &Path, members: usize) -> std::io::Result<PathBuf> {
+    let names = (0..members)
+        .map(|i| format!("member-{i}"))
+        .collect::<Vec<_>>();
+
+    let mut root_manifest = String::from("[workspace]\nmembers = [\n");
+    for name in &names {
+        root_manifest.push_str(&format!("  \"crates/{name}\",\n"));
+    }
+    root_manifest.push_str("]\n\n[workspace.dependencies]\n");
+    for name in &names {
+        root_manifest.push_str(&format!(
+            "{name} = {{ path = \"crates/{name}\", version = \"0.1.0\" }}\n"
+        ));
+    }
+
+    for name in &names {
+        let dir = root.join("crates").join(name);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\nanyhow = \"1.0\"\nserde = {{ version = \"1.0\", features = [\"derive\"] }}\n"
+            ),
+        )?;
+    }
+
+    let root_manifest_path = root.join("Cargo.toml");
+    std::fs::write(&root_manifest_path, root_manifest)?;
+
+    Ok(root_manifest_path)
+}
+
+fn measure<T>(label: &str, f: impl FnOnce() -> T) -> T {
+    let allocated_before = ALLOCATED.load(Ordering::Relaxed);
+    let resident_before = RESIDENT.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let output = f();
+    let elapsed = start.elapsed();
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - allocated_before;
+    let retained = RESIDENT
+        .load(Ordering::Relaxed)
+        .saturating_sub(resident_before);
+
+    println!(
+        "{label:<8} {elapsed:>12.2?} {:>10.1} KiB allocated {:>10.1} KiB retained",
+        allocated as f64 / 1024.0,
+        retained as f64 / 1024.0
+    );
+
+    output
+}
+
+fn main() -> anyhow::Result<()> {
+    let members: usize = env_or("MEMBERS", 1000);
+    let latency = Duration::from_millis(env_or("LATENCY_MS", 1));
+    let jobs = NonZeroUsize::new(env_or("JOBS", 8)).unwrap_or(NonZeroUsize::MIN);
+
+    let root = std::env::temp_dir().join(format!("cargo-set-bench-{}", std::process::id()));
+    let root_manifest_path = create_workspace(&root, members)?;
+
+    println!("workspace with {members} members");
+    let service = CargoManifestService::new(RealFileSystem);
+    let manifest = measure("load", || service.load_manifest(&root_manifest_path))?;
+    let cloned = measure("clone", || manifest.clone());
+    drop(cloned);
+    let owned = measure("owned", || {
+        manifest
+            .manifests()
+            .map(|(path, manifest)| (path.to_path_buf(), manifest.clone()))
+            .collect::<Vec<_>>()
+    });
+    drop(owned);
+
+    println!("reads delayed by {latency:?}");
+    let slow = || SlowFileSystem { latency };
+    let service = CargoManifestService::new(slow());
+    measure("1 job", || service.load_manifest(&root_manifest_path))?;
+    let service = CargoManifestService::new(slow()).with_load_concurrency(jobs);
+    measure(&format!("{jobs} jobs"), || {
+        service.load_manifest(&root_manifest_path)
+    })?;
+
+    std::fs::remove_dir_all(&root)?;
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use toml_edit::{Document, TableLike};
+
+use crate::bump::BumpLevel;
+use crate::paths::display_path;
+
+/// An edit an editor can offer at a position in a manifest. Resolving it
+/// with `CargoManifestService::code_action_edits` yields the text edits of
+/// every manifest it touches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum CodeAction {
+    /// Bump a workspace package, updating every dependency on it.
+    Bump { package: String, level: BumpLevel },
+    /// Move a dependency into `[workspace.dependencies]` and inherit it.
+    Hoist {
+        #[serde(with = "crate::paths::serde_path")]
+        manifest: PathBuf,
+        key: Vec<String>,
+    },
+    /// Set an internal requirement to the current version of the member.
+    Sync {
+        #[serde(with = "crate::paths::serde_path")]
+        manifest: PathBuf,
+        key: Vec<String>,
+    },
+}
+
+impl fmt::Display for CodeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodeAction::Bump { package, level } => write!(f, "Bump {package} ({level})"),
+            CodeAction::Hoist { key, .. } => write!(
+                f,
+                "Move {} to workspace.dependencies",
+                key.last().map(String::as_str).unwrap_or_default()
+            ),
+            CodeAction::Sync { key, .. } => write!(
+                f,
+                "Sync the requirement on {}",
+                key.last().map(String::as_str).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+/// Replace the bytes `range` of the manifest at `path` with `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    #[serde(with = "crate::paths::serde_path")]
+    pub path: PathBuf,
+    pub range: Range<usize>,
+    pub new_text: String,
+}
+
+impl fmt::Display for TextEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}..{}: {:?}",
+            display_path(&self.path),
+            self.range.start,
+            self.range.end,
+            self.new_text
+        )
+    }
+}
+
+/// The single edit turning `old` into `new`, covering only the bytes
+/// between their common prefix and suffix. `None` if they're equal.
+pub(crate) fn text_edit(path: PathBuf, old: &str, new: &str) -> Option<TextEdit> {
+    if old == new {
+        return None;
+    }
+
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((i, _), _)| i)
+        .unwrap_or(old.len().min(new.len()));
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .chars()
+        .rev()
+        .zip(new_rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum::<usize>();
+
+    Some(TextEdit {
+        path,
+        range: prefix..old.len() - suffix,
+        new_text: new_rest[..new_rest.len() - suffix].to_string(),
+    })
+}
+
+/// The key path of the innermost entry of `source` whose key, value or table
+/// header spans byte `offset`, with legacy spellings (`[project]`, underscore
+/// dependency tables) mapped onto the current ones.
+pub(crate) fn key_at(source: &str, offset: usize) -> Option<Vec<String>> {
+    let document = Document::parse(source).ok()?;
+
+    let mut key = Vec::new();
+    find_key(document.as_table(), offset, &mut key).then_some(key)
+}
+
+/// The line of `source`, counting from 1, where `key` is declared, or its
+/// closest ancestor if `key` itself isn't.
+pub(crate) fn key_line(source: &str, key: &[String]) -> Option<usize> {
+    let document = Document::parse(source).ok()?;
+
+    let mut table: &dyn TableLike = document.as_table();
+    let mut start = None;
+    for name in key {
+        let Some((k, item)) = table.get_key_value(name) else {
+            break;
+        };
+        start = k
+            .span()
+            .or_else(|| item.span())
+            .map(|span| span.start)
+            .or(start);
+        match item.as_table_like() {
+            Some(child) => table = child,
+            None => break,
+        }
+    }
+
+    Some(source[..start?].matches('\n').count() + 1)
+}
+
+fn find_key(table: &dyn TableLike, offset: usize, key: &mut Vec<String>) -> bool {
+    let covers =
+        |span: Option<Range<usize>>| span.is_some_and(|s| s.start <= offset && offset <= s.end);
+
+    for (name, _) in table.iter() {
+        let Some((k, item)) = table.get_key_value(name) else {
+            continue;
+        };
+        let covered = covers(k.span()) || covers(item.span());
+
+        key.push(canonical(name));
+        if let Some(child) = item.as_table_like() {
+            if find_key(child, offset, key) {
+                return true;
+            }
+        }
+        if covered {
+            return true;
+        }
+        key.pop();
+    }
+
+    false
+}
+
+fn canonical(name: &str) -> String {
+    match name {
+        "project" => "package".to_string(),
+        "dev_dependencies" | "build_dependencies" => name.replace('_', "-"),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_keys_and_minimal_edits() {
+        let source = "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dev_dependencies]\nserde = { version = '1', features = ['derive'] }\ntoml = '0.7'\n";
+
+        let offset = |needle: &str| source.find(needle).unwrap() + 1;
+        assert_eq!(
+            key_at(source, offset("0.1.0")),
+            Some(vec!["package".to_string(), "version".to_string()])
+        );
+        assert_eq!(
+            key_at(source, offset("'1'")),
+            Some(vec![
+                "dev-dependencies".to_string(),
+                "serde".to_string(),
+                "version".to_string()
+            ])
+        );
+        assert_eq!(
+            key_at(source, offset("toml")),
+            Some(vec!["dev-dependencies".to_string(), "toml".to_string()])
+        );
+        let line = |key: &[&str]| {
+            key_line(
+                source,
+                &key.iter().map(|k| k.to_string()).collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(line(&["package", "version"]), Some(3));
+        assert_eq!(line(&["dev_dependencies", "serde", "version"]), Some(6));
+        assert_eq!(line(&["package", "missing"]), Some(1));
+        assert_eq!(line(&["missing"]), None);
+
+        assert_eq!(
+            text_edit(PathBuf::from("Cargo.toml"), "a = '0.1.0'", "a = '0.10.0'"),
+            Some(TextEdit {
+                path: PathBuf::from("Cargo.toml"),
+                range: 8..8,
+                new_text: "0".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// How GitHub shows an [`Annotation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationLevel {
+    Error,
+    Warning,
+    Notice,
+}
+
+impl fmt::Display for AnnotationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AnnotationLevel::Error => "error",
+            AnnotationLevel::Warning => "warning",
+            AnnotationLevel::Notice => "notice",
+        })
+    }
+}
+
+/// A message on a line of a manifest, displayed as the GitHub Actions
+/// workflow command that shows it inline on the diff of a pull request,
+/// e.g. `::error file=b/Cargo.toml,line=7::...`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Annotation {
+    pub level: AnnotationLevel,
+    #[serde(with = "crate::paths::serde_path")]
+    pub file: PathBuf,
+    /// The line, counting from 1; `None` annotates the whole file.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // GitHub reads `/` separated paths relative to the repository.
+        let file = self.file.to_string_lossy().replace('\\', "/");
+        write!(f, "::{} file={}", self.level, escape(&file, true))?;
+        if let Some(line) = self.line {
+            write!(f, ",line={line}")?;
+        }
+        write!(f, "::{}", escape(&self.message, false))
+    }
+}
+
+/// `value` escaped for a workflow command, as a property if `property`.
+fn escape(value: &str, property: bool) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            ':' if property => escaped.push_str("%3A"),
+            ',' if property => escaped.push_str("%2C"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Files describing the workspace's versions for build systems that don't
+/// read TOML, written after a version change.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Artifacts {
+    /// A file holding just the workspace version, e.g. `VERSION`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version_file: Option<PathBuf>,
+    /// A `.env` fragment with one `CARGO_SET_VERSION_<CRATE>` line per crate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<PathBuf>,
+    /// A JSON object mapping crate names to versions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_file: Option<PathBuf>,
+}
+
+impl Artifacts {
+    pub fn is_empty(&self) -> bool {
+        self.version_file.is_none() && self.env_file.is_none() && self.json_file.is_none()
+    }
+
+    /// Fill the paths unset in `self` from `defaults`.
+    pub fn or(self, defaults: Artifacts) -> Artifacts {
+        Artifacts {
+            version_file: self.version_file.or(defaults.version_file),
+            env_file: self.env_file.or(defaults.env_file),
+            json_file: self.json_file.or(defaults.json_file),
+        }
+    }
+
+    /// The contents of every configured artifact, given the workspace
+    /// `version` (if its crates share one) and every crate's version.
+    pub(crate) fn render(
+        &self,
+        version: Option<&str>,
+        versions: &BTreeMap<String, String>,
+    ) -> anyhow::Result<Vec<(PathBuf, String)>> {
+        let mut files = Vec::new();
+
+        if let Some(path) = &self.version_file {
+            let version = version.ok_or_else(|| {
+                anyhow::anyhow!("a version file needs the workspace's crates to share one version")
+            })?;
+            files.push((path.clone(), format!("{version}\n")));
+        }
+
+        if let Some(path) = &self.env_file {
+            let mut env = String::new();
+            if let Some(version) = version {
+                env.push_str(&format!("CARGO_SET_VERSION={version}\n"));
+            }
+            for (name, version) in versions {
+                env.push_str(&format!("CARGO_SET_VERSION_{}={version}\n", env_name(name)));
+            }
+            files.push((path.clone(), env));
+        }
+
+        if let Some(path) = &self.json_file {
+            let mut json = serde_json::to_string_pretty(versions)?;
+            json.push('\n');
+            files.push((path.clone(), json));
+        }
+
+        Ok(files)
+    }
+}
+
+/// `name` as an environment variable suffix, e.g. `CARGO_SET_LIB` for
+/// `cargo-set-lib`.
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_version_env_and_json_files() -> anyhow::Result<()> {
+        let artifacts = Artifacts {
+            version_file: Some(PathBuf::from("VERSION")),
+            env_file: Some(PathBuf::from("versions.env")),
+            json_file: Some(PathBuf::from("versions.json")),
+        };
+        let versions = BTreeMap::from([
+            ("cargo-set".to_string(), "1.2.0".to_string()),
+            ("cargo-set-lib".to_string(), "1.2.0".to_string()),
+        ]);
+
+        assert_eq!(
+            artifacts.render(Some("1.2.0"), &versions)?,
+            [
+                (PathBuf::from("VERSION"), "1.2.0\n".to_string()),
+                (
+                    PathBuf::from("versions.env"),
+                    "CARGO_SET_VERSION=1.2.0\nCARGO_SET_VERSION_CARGO_SET=1.2.0\nCARGO_SET_VERSION_CARGO_SET_LIB=1.2.0\n".to_string()
+                ),
+                (
+                    PathBuf::from("versions.json"),
+                    "{\n  \"cargo-set\": \"1.2.0\",\n  \"cargo-set-lib\": \"1.2.0\"\n}\n".to_string()
+                ),
+            ]
+        );
+        assert!(artifacts.render(None, &versions).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::io;
+#[cfg(feature = "real-fs")]
+use std::io::Write;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "real-fs")]
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+#[cfg(feature = "real-fs")]
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::diff;
+use crate::filesystem::FileSystem;
+use crate::history::{JournalEntry, JournalFile};
+use crate::paths::display_path;
+
+/// Where the `[audit]` table of `.cargo-set.toml` sends a record of every
+/// command that changed files. Nothing is recorded unless one is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AuditConfig {
+    /// A JSON lines file, relative to the workspace root, records are
+    /// appended to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Also send every record to syslog through `logger`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub syslog: bool,
+}
+
+/// Who changed which files, when and how.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditRecord {
+    pub timestamp: String,
+    pub user: Option<String>,
+    pub command: String,
+    /// The commit checked out when the command ran.
+    pub git_commit: Option<String>,
+    /// The error the command failed with, after writing some of the files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub files: Vec<AuditedFile>,
+}
+
+/// A file written or removed, with the change as a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditedFile {
+    pub path: String,
+    pub diff: String,
+}
+
+impl AuditConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Append `record` to the log of the workspace rooted at `root_manifest`.
+    /// Needs the `real-fs` feature.
+    #[cfg(feature = "real-fs")]
+    pub fn append(&self, root_manifest: &Path, record: &AuditRecord) -> crate::Result<()> {
+        let line = serde_json::to_string(record)?;
+
+        if let Some(file) = &self.file {
+            let path = root_manifest
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(file);
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut log| writeln!(log, "{line}"))
+                .with_context(|| format!("failed to append to {}", display_path(&path)))?;
+        }
+        if self.syslog {
+            let mut logger = Command::new("logger")
+                .args(["--tag", "cargo-set"])
+                .stdin(Stdio::piped())
+                .spawn()
+                .context("failed to run logger")?;
+            logger
+                .stdin
+                .take()
+                .context("failed to pass the record to logger")?
+                .write_all(line.as_bytes())?;
+            if !logger.wait()?.success() {
+                crate::error::bail!("logger failed to send the audit record to syslog");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wraps another file system and remembers the original and final contents
+/// of every path written or removed through it, for an [`AuditRecord`].
+#[derive(Debug)]
+pub struct AuditedFileSystem<F> {
+    fs: F,
+    changes: Mutex<BTreeMap<PathBuf, Change>>,
+}
+
+/// Contents of a changed path; `None` for a file that doesn't exist.
+#[derive(Debug)]
+struct Change {
+    original: Option<Vec<u8>>,
+    current: Option<Vec<u8>>,
+}
+
+impl<F: FileSystem> AuditedFileSystem<F> {
+    pub fn new(fs: F) -> Self {
+        Self {
+            fs,
+            changes: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Perform `op`, which leaves `path` with `contents`, remembering what
+    /// `path` held before the first change.
+    fn change(
+        &self,
+        path: &Path,
+        contents: Option<Vec<u8>>,
+        op: impl FnOnce(&F) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let mut changes = self.changes.lock().unwrap();
+        let original = match changes.get(path) {
+            Some(_) => None,
+            None => match self.fs.read(path) {
+                Ok(original) => Some(original),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            },
+        };
+        op(&self.fs)?;
+
+        changes
+            .entry(path.to_path_buf())
+            .or_insert(Change {
+                original,
+                current: None,
+            })
+            .current = contents;
+
+        Ok(())
+    }
+
+    /// The record of everything changed so far, or `None` if nothing was.
+    pub fn record(
+        &self,
+        now: SystemTime,
+        command: impl Into<String>,
+        git_commit: Option<String>,
+        error: Option<&anyhow::Error>,
+    ) -> crate::Result<Option<AuditRecord>> {
+        let files = self
+            .changes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, change)| change.original != change.current)
+            .map(|(path, change)| {
+                let path = display_path(path).replace('\\', "/");
+                let old = change.original.as_deref().map(String::from_utf8_lossy);
+                let new = change.current.as_deref().map(String::from_utf8_lossy);
+                let diff = diff::unified_diff(&path, old.as_deref(), new.as_deref());
+                AuditedFile { path, diff }
+            })
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(AuditRecord {
+            timestamp: crate::snapshot::rfc3339(now)?,
+            user: ["USER", "USERNAME", "LOGNAME"]
+                .into_iter()
+                .find_map(|name| std::env::var(name).ok().filter(|user| !user.is_empty())),
+            command: command.into(),
+            git_commit,
+            error: error.map(|e| format!("{e:#}")),
+            files,
+        }))
+    }
+
+    /// The journal entry of everything changed so far in the workspace
+    /// rooted at `root_manifest`, or `None` if nothing was.
+    pub fn journal_entry(
+        &self,
+        now: SystemTime,
+        command: impl Into<String>,
+        root_manifest: &Path,
+    ) -> crate::Result<Option<JournalEntry>> {
+        let files = self
+            .changes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, change)| change.original != change.current)
+            .map(|(path, change)| {
+                let text = |contents: &Option<Vec<u8>>| {
+                    contents
+                        .as_deref()
+                        .map(|contents| String::from_utf8_lossy(contents).into_owned())
+                };
+                JournalFile {
+                    path: path.clone(),
+                    original: text(&change.original),
+                    contents: text(&change.current),
+                }
+            })
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(JournalEntry::new(now, command, files, root_manifest)?))
+    }
+}
+
+impl<F: FileSystem> FileSystem for AuditedFileSystem<F> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.fs.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        self.change(path, Some(contents.clone()), |fs| fs.write(path, contents))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.change(path, None, |fs| fs.remove(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.fs.canonicalize(path)
+    }
+
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        self.fs.probe_write(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.list_dir(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+    use crate::filesystem::{FileSystem, MockFileSystem};
+
+    #[test]
+    fn records_the_changes_of_a_command() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(PathBuf::from("VERSION"), b"0.1.0\n".to_vec());
+        let fs = AuditedFileSystem::new(fs);
+        let now = UNIX_EPOCH + Duration::from_secs(1_717_243_200);
+
+        assert_eq!(fs.record(now, "cargo set bump", None, None)?, None);
+
+        fs.write(Path::new("VERSION"), b"0.1.1\n".to_vec())?;
+        fs.write(Path::new("VERSION"), b"0.2.0\n".to_vec())?;
+        fs.write(Path::new("unchanged"), Vec::new())?;
+        fs.remove(Path::new("unchanged"))?;
+        let record = fs
+            .record(now, "cargo set bump", Some("abc1234".to_string()), None)?
+            .unwrap();
+
+        assert_eq!(record.timestamp, "2024-06-01T12:00:00Z");
+        assert_eq!(record.command, "cargo set bump");
+        assert_eq!(record.git_commit.as_deref(), Some("abc1234"));
+        assert_eq!(
+            record.files,
+            [AuditedFile {
+                path: "VERSION".to_string(),
+                diff: "diff --git a/VERSION b/VERSION\n--- a/VERSION\n+++ b/VERSION\n@@ -1 +1 @@\n-0.1.0\n+0.2.0\n".to_string(),
+            }]
+        );
+
+        Ok(())
+    }
+}
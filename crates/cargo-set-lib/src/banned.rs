@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::duplicates::minimum_version;
+use crate::index::DependencyLocation;
+use crate::paths::display_path;
+
+/// Dependency versions no manifest may use, e.g. known-bad releases, from
+/// the `[banned-versions]` table of `.cargo-set.toml`:
+///
+/// ```toml
+/// [banned-versions]
+/// openssl = ["=0.10.45", ">=0.10.50, <0.10.55"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct BannedVersions {
+    bans: BTreeMap<String, Vec<semver::VersionReq>>,
+}
+
+/// A declaration or lockfile entry using a banned version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BannedDependency {
+    pub package: String,
+    /// The declaration, or `None` for a version resolved in `Cargo.lock`.
+    pub location: Option<DependencyLocation>,
+    pub version: semver::Version,
+    /// The banned range the version falls in.
+    pub ban: String,
+}
+
+impl BannedVersions {
+    pub fn new(bans: BTreeMap<String, Vec<semver::VersionReq>>) -> Self {
+        Self { bans }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bans.is_empty()
+    }
+
+    /// The ban `version` of `package` falls under, if any.
+    pub fn ban(&self, package: &str, version: &semver::Version) -> Option<&semver::VersionReq> {
+        self.bans
+            .get(package)?
+            .iter()
+            .find(|ban| ban.matches(version))
+    }
+
+    /// The lowest version `requirement` allows, if `package` is banned at
+    /// that version.
+    pub(crate) fn check_requirement(
+        &self,
+        package: &str,
+        location: Option<&DependencyLocation>,
+        requirement: &str,
+    ) -> Option<BannedDependency> {
+        let version = minimum_version(requirement)?;
+        let ban = self.ban(package, &version)?.to_string();
+
+        Some(BannedDependency {
+            package: package.to_string(),
+            location: location.cloned(),
+            version,
+            ban,
+        })
+    }
+}
+
+impl TryFrom<BTreeMap<String, Vec<String>>> for BannedVersions {
+    type Error = String;
+
+    fn try_from(bans: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let bans = bans
+            .into_iter()
+            .map(|(package, ranges)| {
+                let ranges = ranges
+                    .iter()
+                    .map(|range| {
+                        semver::VersionReq::parse(range).map_err(|e| {
+                            format!("`{range}`, banned for {package}, is not a version range: {e}")
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((package, ranges))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self { bans })
+    }
+}
+
+impl From<BannedVersions> for BTreeMap<String, Vec<String>> {
+    fn from(banned: BannedVersions) -> Self {
+        banned
+            .bans
+            .into_iter()
+            .map(|(package, ranges)| (package, ranges.iter().map(ToString::to_string).collect()))
+            .collect()
+    }
+}
+
+impl fmt::Display for BannedDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(
+                f,
+                "{}: `{}` requires {} {}",
+                display_path(&location.manifest),
+                location.key().join("."),
+                self.package,
+                self.version
+            )?,
+            None => write!(
+                f,
+                "Cargo.lock: {} is locked at {}",
+                self.package, self.version
+            )?,
+        }
+        write!(f, ", banned by `{}`", self.ban)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches_the_lowest_allowed_version() -> anyhow::Result<()> {
+        let banned: BannedVersions =
+            toml::from_str("openssl = ['=0.10.45', '>=0.10.50, <0.10.55']")?;
+
+        let check = |requirement| {
+            banned
+                .check_requirement("openssl", None, requirement)
+                .map(|b| b.to_string())
+        };
+        assert_eq!(
+            check("0.10.45").as_deref(),
+            Some("Cargo.lock: openssl is locked at 0.10.45, banned by `=0.10.45`")
+        );
+        assert!(check("^0.10.51").is_some());
+        assert_eq!(check("0.10.46"), None);
+        assert_eq!(check("0.10.55"), None);
+        assert_eq!(
+            banned.ban("native-tls", &semver::Version::new(0, 10, 45)),
+            None
+        );
+
+        assert!(toml::from_str::<BannedVersions>("openssl = ['latest']").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// How `build-info` records a crate's version, commit and build time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuildInfoFormat {
+    /// A generated Rust source with `VERSION`, `GIT_SHA` and
+    /// `BUILD_TIMESTAMP` constants.
+    #[default]
+    Rust,
+    /// A `[package.metadata.build-info]` table in the crate's manifest.
+    Metadata,
+}
+
+/// Which crates get build info and where, from `[build-info]` in
+/// `.cargo-set.toml`. Release operations regenerate it when `crates` is set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BuildInfoConfig {
+    #[serde(default)]
+    pub crates: Vec<String>,
+    #[serde(default)]
+    pub format: BuildInfoFormat,
+    /// Where the Rust source goes, relative to each crate's directory.
+    #[serde(default = "BuildInfoConfig::default_path")]
+    pub path: PathBuf,
+}
+
+impl BuildInfoConfig {
+    fn default_path() -> PathBuf {
+        PathBuf::from("src/build_info.rs")
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for BuildInfoConfig {
+    fn default() -> Self {
+        Self {
+            crates: Vec::new(),
+            format: BuildInfoFormat::default(),
+            path: Self::default_path(),
+        }
+    }
+}
+
+/// The commit and time a build was made from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub commit: String,
+    /// RFC 3339 UTC timestamp.
+    pub timestamp: String,
+}
+
+impl BuildInfo {
+    pub fn new(commit: impl Into<String>, time: std::time::SystemTime) -> crate::Result<Self> {
+        Ok(Self {
+            commit: commit.into(),
+            timestamp: crate::snapshot::rfc3339(time)?,
+        })
+    }
+
+    /// The `(key, value)` pairs recorded for a crate at `version`.
+    pub(crate) fn entries<'a>(&'a self, version: &'a str) -> [(&'static str, &'a str); 3] {
+        [
+            ("version", version),
+            ("git-sha", &self.commit),
+            ("timestamp", &self.timestamp),
+        ]
+    }
+
+    /// The generated Rust source for a crate at `version`.
+    pub(crate) fn rust_source(&self, version: &str) -> String {
+        format!(
+            "// @generated by `cargo set build-info`, do not edit.\n\
+             \n\
+             pub const VERSION: &str = {version:?};\n\
+             pub const GIT_SHA: &str = {:?};\n\
+             pub const BUILD_TIMESTAMP: &str = {:?};\n",
+            self.commit, self.timestamp
+        )
+    }
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Which component of a version to increment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+    /// The next pre-release, e.g. `1.4.0-rc.1` to `1.4.0-rc.2`.
+    Pre,
+    /// The final release of a pre-release, e.g. `1.4.0-rc.2` to `1.4.0`.
+    Release,
+    /// The next calendar version, e.g. `2024.6.0`, `calver` or
+    /// `calver:FORMAT` on the command line.
+    Calver(CalverFormat),
+}
+
+/// A calendar versioning scheme: the year, the month and a counter or the
+/// day, e.g. `YYYY.MM.MICRO`. Semver doesn't allow leading zeros, so the
+/// zero-padded `0M` and `0D` of CalVer can't be used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CalverFormat {
+    /// `YY` instead of `YYYY`, e.g. `24.6.0`.
+    short_year: bool,
+    /// `DD` instead of `MICRO`, one release a day.
+    day: bool,
+}
+
+impl CalverFormat {
+    /// The version `format` gives a release at `now` coming after `version`:
+    /// the first of the month, ending in `0`, or the one after `version` if
+    /// it is from the same month already.
+    pub fn next(
+        self,
+        version: &semver::Version,
+        now: SystemTime,
+    ) -> crate::Result<semver::Version> {
+        let (year, month, day) = crate::snapshot::date(now)?;
+        let year = if self.short_year { year % 100 } else { year };
+        let same_month = version.major == year && version.minor == u64::from(month);
+
+        let patch = match (self.day, same_month) {
+            (true, _) => u64::from(day),
+            (false, true) if version.pre.is_empty() => version.patch + 1,
+            // `2024.6.1-rc.1` is released as `2024.6.1`.
+            (false, true) => version.patch,
+            (false, false) => 0,
+        };
+        let next = semver::Version::new(year, u64::from(month), patch);
+        if next <= *version {
+            crate::error::bail!(
+                "{next}, the {self} version of today, would not come after {version}"
+            );
+        }
+
+        Ok(next)
+    }
+}
+
+impl fmt::Display for CalverFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let year = if self.short_year { "YY" } else { "YYYY" };
+        let last = if self.day { "DD" } else { "MICRO" };
+        write!(f, "{year}.MM.{last}")
+    }
+}
+
+impl FromStr for CalverFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split('.').collect::<Vec<_>>();
+        let format = match parts.as_slice() {
+            [year, "MM", last] => {
+                let short_year = match *year {
+                    "YYYY" => false,
+                    "YY" => true,
+                    _ => crate::error::bail!("`{year}` is not a CalVer year, use YYYY or YY"),
+                };
+                let day = match *last {
+                    "MICRO" => false,
+                    "DD" => true,
+                    _ => crate::error::bail!("`{last}` can't end a CalVer version, use MICRO or DD"),
+                };
+                CalverFormat { short_year, day }
+            }
+            _ => crate::error::bail!(
+                "`{s}` is not a CalVer format, use YYYY.MM.MICRO, YY.MM.MICRO, YYYY.MM.DD or YY.MM.DD"
+            ),
+        };
+
+        Ok(format)
+    }
+}
+
+impl BumpLevel {
+    /// `version` with the component incremented and every lower one reset.
+    /// Pre-release and build metadata are dropped.
+    ///
+    /// With a pre-release `label`, `Patch`, `Minor` and `Major` start the
+    /// first pre-release of the bumped version, e.g. `1.5.0-rc.1`, and `Pre`
+    /// switches to the label, which has to sort after the current one.
+    /// Without a label `Pre` continues the current pre-release.
+    pub fn apply(
+        self,
+        version: &semver::Version,
+        label: Option<&str>,
+    ) -> crate::Result<semver::Version> {
+        // Only calendar versions read the clock, which targets like
+        // `wasm32-unknown-unknown` don't have.
+        let now = match self {
+            BumpLevel::Calver(_) => SystemTime::now(),
+            _ => SystemTime::UNIX_EPOCH,
+        };
+        self.apply_at(version, label, now)
+    }
+
+    /// [`apply`](Self::apply) with `now` as the date of a `Calver` bump.
+    pub fn apply_at(
+        self,
+        version: &semver::Version,
+        label: Option<&str>,
+        now: SystemTime,
+    ) -> crate::Result<semver::Version> {
+        let mut bumped = match self {
+            BumpLevel::Patch => {
+                semver::Version::new(version.major, version.minor, version.patch + 1)
+            }
+            BumpLevel::Minor => semver::Version::new(version.major, version.minor + 1, 0),
+            BumpLevel::Major => semver::Version::new(version.major + 1, 0, 0),
+            BumpLevel::Pre => return Ok(next_pre_release(version, label)?),
+            BumpLevel::Release => {
+                if version.pre.is_empty() {
+                    crate::error::bail!(
+                        "{version} is not a pre-release, there is nothing to release"
+                    );
+                }
+                semver::Version::new(version.major, version.minor, version.patch)
+            }
+            BumpLevel::Calver(format) => format.next(version, now)?,
+        };
+        if let Some(label) = label {
+            bumped.pre = first_pre_release(label)?;
+        }
+
+        Ok(bumped)
+    }
+}
+
+/// `label.1`, the first pre-release with `label`.
+fn first_pre_release(label: &str) -> anyhow::Result<semver::Prerelease> {
+    semver::Prerelease::new(&format!("{label}.1"))
+        .map_err(|e| anyhow::anyhow!("`{label}` is not a valid pre-release label: {e}"))
+}
+
+fn next_pre_release(
+    version: &semver::Version,
+    label: Option<&str>,
+) -> anyhow::Result<semver::Version> {
+    let mut next = semver::Version::new(version.major, version.minor, version.patch);
+    if version.pre.is_empty() {
+        let label = label.with_context(|| {
+            format!("{version} is not a pre-release, pass a pre-release label to start one")
+        })?;
+        next.patch += 1;
+        next.pre = first_pre_release(label)?;
+        return Ok(next);
+    }
+
+    // `rc.1` continues as `rc.2`, a bare `rc` as `rc.1`.
+    let current = version.pre.as_str();
+    let (current_label, number) = match current.rsplit_once('.') {
+        Some((label, number)) if number.parse::<u64>().is_ok() => (label, number.parse().ok()),
+        _ => (current, None),
+    };
+    next.pre = match label {
+        Some(label) if label != current_label => first_pre_release(label)?,
+        _ => semver::Prerelease::new(&format!(
+            "{current_label}.{}",
+            number.map_or(1, |n: u64| n + 1)
+        ))?,
+    };
+    if next <= *version {
+        crate::error::bail!(
+            "{next} would not come after {version}, pre-release labels are compared \
+             alphabetically, e.g. alpha < beta < rc"
+        );
+    }
+
+    Ok(next)
+}
+
+impl fmt::Display for BumpLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BumpLevel::Patch => "patch",
+            BumpLevel::Minor => "minor",
+            BumpLevel::Major => "major",
+            BumpLevel::Pre => "pre",
+            BumpLevel::Release => "release",
+            BumpLevel::Calver(format) if *format == CalverFormat::default() => "calver",
+            BumpLevel::Calver(format) => return write!(f, "calver:{format}"),
+        })
+    }
+}
+
+impl FromStr for BumpLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "patch" => Ok(BumpLevel::Patch),
+            "minor" => Ok(BumpLevel::Minor),
+            "major" => Ok(BumpLevel::Major),
+            "pre" => Ok(BumpLevel::Pre),
+            "release" => Ok(BumpLevel::Release),
+            "calver" => Ok(BumpLevel::Calver(CalverFormat::default())),
+            _ => match s.strip_prefix("calver:") {
+                Some(format) => Ok(BumpLevel::Calver(format.parse()?)),
+                None => crate::error::bail!(
+                    "`{s}` is not a bump level, use major, minor, patch, pre, release or \
+                     calver[:FORMAT]"
+                ),
+            },
+        }
+    }
+}
+
+impl From<BumpLevel> for String {
+    fn from(level: BumpLevel) -> Self {
+        level.to_string()
+    }
+}
+
+impl TryFrom<String> for BumpLevel {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bump(level: BumpLevel, version: &str, label: Option<&str>) -> anyhow::Result<String> {
+        Ok(level
+            .apply(&semver::Version::parse(version)?, label)?
+            .to_string())
+    }
+
+    #[test]
+    fn bumps_pre_releases_by_precedence() -> anyhow::Result<()> {
+        assert_eq!(bump(BumpLevel::Minor, "1.4.2-rc.1", None)?, "1.5.0");
+        assert_eq!(
+            bump(BumpLevel::Minor, "1.4.2", Some("alpha"))?,
+            "1.5.0-alpha.1"
+        );
+        assert_eq!(
+            bump(BumpLevel::Pre, "1.4.0-rc.1", Some("rc"))?,
+            "1.4.0-rc.2"
+        );
+        assert_eq!(
+            bump(BumpLevel::Pre, "1.4.0-alpha.3", None)?,
+            "1.4.0-alpha.4"
+        );
+        assert_eq!(bump(BumpLevel::Pre, "1.4.0-beta", None)?, "1.4.0-beta.1");
+        assert_eq!(
+            bump(BumpLevel::Pre, "1.4.0-alpha.3", Some("rc"))?,
+            "1.4.0-rc.1"
+        );
+        assert_eq!(bump(BumpLevel::Pre, "1.4.0", Some("rc"))?, "1.4.1-rc.1");
+        assert_eq!(bump(BumpLevel::Release, "1.4.0-rc.2+abc", None)?, "1.4.0");
+
+        assert!(bump(BumpLevel::Pre, "1.4.0-rc.1", Some("alpha")).is_err());
+        assert!(bump(BumpLevel::Pre, "1.4.0", None).is_err());
+        assert!(bump(BumpLevel::Release, "1.4.0", None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bumps_calendar_versions() -> anyhow::Result<()> {
+        // 2024-06-15T12:00:00Z
+        let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_718_452_800);
+        let bump = |level: &str, version: &str| -> anyhow::Result<String> {
+            Ok(level
+                .parse::<BumpLevel>()?
+                .apply_at(&semver::Version::parse(version)?, None, now)?
+                .to_string())
+        };
+
+        assert_eq!(bump("calver", "2024.5.3")?, "2024.6.0");
+        assert_eq!(bump("calver", "2024.6.0")?, "2024.6.1");
+        assert_eq!(bump("calver", "2024.6.1-rc.1")?, "2024.6.1");
+        assert_eq!(bump("calver", "0.4.2")?, "2024.6.0");
+        assert_eq!(bump("calver:YY.MM.MICRO", "24.6.4")?, "24.6.5");
+        assert_eq!(bump("calver:YYYY.MM.DD", "2024.6.3")?, "2024.6.15");
+        assert!(bump("calver:YYYY.MM.DD", "2024.6.15").is_err());
+        assert!(bump("calver", "2025.1.0").is_err());
+
+        let level = "calver:YY.MM.DD".parse::<BumpLevel>()?;
+        assert_eq!(level.to_string(), "calver:YY.MM.DD");
+        assert!("calver:YYYY.0M.MICRO".parse::<BumpLevel>().is_err());
+
+        Ok(())
+    }
+}
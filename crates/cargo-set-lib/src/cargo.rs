@@ -1,20 +1,125 @@
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::Context;
-use cargo_toml::{Dependency, Manifest};
+use cargo_toml::{Dependency, Manifest, Publish};
 
+use crate::actions::{self, CodeAction, TextEdit};
+use crate::annotation::{Annotation, AnnotationLevel};
+use crate::artifacts::Artifacts;
+use crate::banned::{BannedDependency, BannedVersions};
+use crate::build_info::{BuildInfo, BuildInfoConfig, BuildInfoFormat};
+use crate::bump::BumpLevel;
+use crate::cancellation::{CancellationToken, Cancelled};
+use crate::changelog::{self, CHANGELOG_FILE};
+use crate::check::CheckViolation;
+use crate::config::{self, Versioning, WorkspaceSurvey};
+use crate::dependencies::{
+    FeatureEdit, GitReference, HoistedDependency, NewDependency, PatchEntry, PatchSource,
+};
+use crate::diagnostic;
+use crate::duplicates::{self, DeclaredRequirement, DuplicateDependency};
+use crate::edit::{self, Operation};
+use crate::explain::{ExplainedEdit, Explanation};
+use crate::features::{self, FeatureInconsistency, MemberFeatures};
 use crate::filesystem::FileSystem;
+use crate::format::{self, FormatStyle};
+use crate::glob;
+use crate::history::{self, JournalEntry};
+use crate::hooks::Hooks;
+use crate::hygiene::{LintFinding, LintFix, LintRule};
+use crate::index::{DependencyKind, DependencyLocation, DependencyTable, WorkspaceIndex};
+use crate::internal::{self, RequirementDrift};
+use crate::keys::{KeyPath, KeyValue, MetadataEdit, MetadataScope};
+use crate::lints::{LintInheritance, LintSetting};
+use crate::lockfile::{self, Lockfile};
+use crate::matcher::{self, CrateMatcher};
+use crate::metadata::{self, Discovery};
+use crate::occurrences::Occurrence;
+use crate::order::{self, OrderedMember};
+use crate::outdated::{OutdatedDependency, OutdatedMember, OutdatedReport};
+use crate::parallel;
+use crate::patch::{self, ValueEdit};
+use crate::paths::{display_path, is_within, normalize};
+use crate::plan::{self, ChangeSet};
+use crate::policy::{Policy, PolicyContext, PolicyError, PolicyViolation};
+use crate::profile;
+use crate::registry::{self, Latest, Registry};
+use crate::repair::{self, Repair};
+use crate::report::{Change, ChangeReport, ChangedFile};
+use crate::requirements::{self, RequirementChange, RequirementStyle, UnfreezeStyle};
+use crate::resume::{FileWrite, PartialWriteError, ResumeMode, ResumeState};
+use crate::stats::{Instant, Stats};
+use crate::tags::{self, TagCheck};
+use crate::targets::{self, TargetEdit};
+use crate::version;
+use crate::versions_file::{VersionTarget, VersionsFile};
+use crate::why::{self, DependencyPath};
+
+/// The editions `set_edition` accepts, those `cargo_toml` can read back.
+const EDITIONS: &[&str] = &["2015", "2018", "2021"];
 
 pub struct CargoManifestService<F: FileSystem> {
     fs: F,
+    cancellation: Option<CancellationToken>,
+    write_concurrency: NonZeroUsize,
+    load_concurrency: NonZeroUsize,
+    lenient: bool,
+    format: Option<FormatStyle>,
+    sort_dependencies: bool,
+    policy: Option<(Policy, PolicyContext)>,
+    banned: BannedVersions,
+    allow_downgrade: bool,
+    atomic: bool,
+    default_members_only: bool,
+    dev_dependencies: bool,
+    requirement_style: RequirementStyle,
+    if_needed: bool,
+    /// The registry external dependencies are looked up in before they are
+    /// set, and whether their yanked versions only warn.
+    registry: Option<(Arc<dyn Registry + Send + Sync>, bool)>,
+    hooks: Hooks,
+    discovery: Discovery,
 }
 
 #[derive(Debug, Clone)]
 pub struct CargoManifest {
     root_path: PathBuf,
     root_manifest: Manifest,
-    members: Option<BTreeMap<PathBuf, Manifest>>,
+    /// Shared like `sources`: a clone copies a member's manifest only once
+    /// it changes.
+    members: Option<BTreeMap<PathBuf, Arc<Manifest>>>,
+    /// Original file contents, used to write minimal diffs. Shared so that
+    /// cloning a loaded workspace doesn't copy every manifest's text.
+    sources: BTreeMap<PathBuf, Arc<str>>,
+    index: WorkspaceIndex,
+    failures: Vec<LoadFailure>,
+    /// Members whose manifest is reached through a symlink pointing outside
+    /// the workspace root, mapped to the resolved target. They are loaded
+    /// but never written.
+    external: BTreeMap<PathBuf, PathBuf>,
+    /// Manifests written since the workspace was loaded.
+    written: BTreeSet<PathBuf>,
+    /// While writes are held back by `write_once`, the original contents of
+    /// every manifest an update would have written; `None` for new files.
+    staged: Option<BTreeMap<PathBuf, Option<Arc<str>>>>,
+    stats: Stats,
+}
+
+/// A member manifest that couldn't be loaded in lenient mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+impl std::fmt::Display for LoadFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", display_path(&self.path), self.error)
+    }
 }
 
 impl CargoManifest {
@@ -23,175 +128,8290 @@ impl CargoManifest {
             root_path,
             root_manifest,
             members: None,
+            sources: BTreeMap::new(),
+            index: WorkspaceIndex::default(),
+            failures: Vec::new(),
+            external: BTreeMap::new(),
+            written: BTreeSet::new(),
+            staged: None,
+            stats: Stats::default(),
         }
     }
-}
 
-impl<F: FileSystem> CargoManifestService<F> {
-    pub fn new(fs: F) -> Self {
-        Self { fs }
+    /// The parsed manifest at `path`, the root or a member.
+    pub fn manifest(&self, path: &Path) -> Option<&Manifest> {
+        if path == self.root_path {
+            return Some(&self.root_manifest);
+        }
+
+        self.members.as_ref()?.get(path).map(|manifest| &**manifest)
     }
 
-    pub fn load_manifest(&self, path: &PathBuf) -> anyhow::Result<CargoManifest> {
-        let manifest = self.load_cargo(path)?;
-        let mut s = CargoManifest::new(path.clone(), manifest);
+    fn manifest_mut(&mut self, path: &Path) -> Option<&mut Manifest> {
+        if path == self.root_path {
+            return Some(&mut self.root_manifest);
+        }
 
-        let s = self.load_children(&mut s)?;
+        self.members.as_mut()?.get_mut(path).map(Arc::make_mut)
+    }
 
-        Ok(s.to_owned())
+    /// The root manifest followed by every member, ordered by path.
+    pub fn manifests(&self) -> impl Iterator<Item = (&Path, &Manifest)> {
+        std::iter::once((self.root_path.as_path(), &self.root_manifest)).chain(
+            self.members
+                .iter()
+                .flatten()
+                .map(|(path, manifest)| (path.as_path(), &**manifest)),
+        )
     }
 
-    fn load_cargo(&self, path: &PathBuf) -> anyhow::Result<Manifest> {
-        let content = self
-            .fs
-            .read(path)
-            .context("failed to read Cargo.toml from path")?;
+    fn reindex(&mut self) {
+        self.index = WorkspaceIndex::build(self.manifests());
+    }
 
-        let manifest = Manifest::from_slice(&content).context("failed to parse Cargo.toml")?;
+    /// Members skipped because their manifest couldn't be read or parsed.
+    ///
+    /// Only populated when the service is lenient; otherwise the first such
+    /// failure aborts the load.
+    pub fn load_failures(&self) -> &[LoadFailure] {
+        &self.failures
+    }
 
-        Ok(manifest)
+    /// Timings and counts accumulated by every operation on this workspace.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
     }
 
-    fn load_children<'s>(&self, s: &'s mut CargoManifest) -> anyhow::Result<&'s mut CargoManifest> {
-        if let Some(workspace) = &s.root_manifest.workspace {
-            let mut members = BTreeMap::new();
+    /// The code actions available at byte `offset` of the manifest at `path`.
+    pub fn code_actions(&self, path: &Path, offset: usize) -> Vec<CodeAction> {
+        let path = normalize(path);
+        let Some(key) = self
+            .sources
+            .get(&path)
+            .and_then(|source| actions::key_at(source, offset))
+        else {
+            return Vec::new();
+        };
+
+        if key.first().is_some_and(|k| k == "package") {
+            let Some(package) = self.manifest(&path).and_then(|m| m.package.as_ref()) else {
+                return Vec::new();
+            };
+            return [BumpLevel::Patch, BumpLevel::Minor, BumpLevel::Major]
+                .into_iter()
+                .map(|level| CodeAction::Bump {
+                    package: package.name.clone(),
+                    level,
+                })
+                .collect();
+        }
+
+        let Some(location) = self
+            .index
+            .all_dependencies()
+            .find(|l| *l.manifest == *path && key.starts_with(&l.key()))
+        else {
+            return Vec::new();
+        };
+        let mut actions = Vec::new();
+        if self
+            .requirement_drift()
+            .iter()
+            .any(|d| d.location == *location)
+        {
+            actions.push(CodeAction::Sync {
+                manifest: path.clone(),
+                key: location.key(),
+            });
+        }
+        let hoistable = match self.manifest(&path).and_then(|m| location.get(m)) {
+            Some(Dependency::Simple(_)) => true,
+            Some(Dependency::Detailed(detail)) => detail.path.is_none(),
+            _ => false,
+        };
+        if hoistable
+            && location.table.kind != DependencyKind::Workspace
+            && self.root_manifest.workspace.is_some()
+        {
+            actions.push(CodeAction::Hoist {
+                manifest: path,
+                key: location.key(),
+            });
+        }
+
+        actions
+    }
+
+    fn location_at(&self, manifest: &Path, key: &[String]) -> anyhow::Result<&DependencyLocation> {
+        self.index
+            .all_dependencies()
+            .find(|l| *l.manifest == *manifest && l.key() == key)
+            .with_context(|| {
+                format!(
+                    "{} has no dependency at `{}`",
+                    display_path(manifest),
+                    key.join(".")
+                )
+            })
+    }
+
+    /// Every package of the workspace with its current version, in name
+    /// order.
+    pub fn package_versions(&self) -> Vec<(String, Option<String>)> {
+        self.index
+            .package_names()
+            .map(|name| (name.to_string(), self.package_version(name)))
+            .collect()
+    }
+
+    /// The manifests written since the workspace was loaded, in path order.
+    pub fn written_files(&self) -> impl Iterator<Item = &Path> {
+        self.written.iter().map(PathBuf::as_path)
+    }
 
-            for member in &workspace.members {
-                let mut member_path = s.root_path.parent().unwrap().to_path_buf();
-                member_path.push(member);
-                member_path.push("Cargo.toml");
+    /// The path of the root manifest.
+    pub fn root_path(&self) -> &Path {
+        &self.root_path
+    }
+
+    pub fn root_manifest(&self) -> &Manifest {
+        &self.root_manifest
+    }
+
+    /// Every member manifest, ordered by path, without the root.
+    pub fn members(&self) -> impl Iterator<Item = (&Path, &Manifest)> {
+        self.manifests().skip(1)
+    }
+
+    /// The manifest of the package `name`, if it is part of the workspace.
+    pub fn package_manifest(&self, name: &str) -> Option<&Path> {
+        self.index.packages(name).first().map(PathBuf::as_path)
+    }
+
+    /// Every declaration of a dependency on `name` in the workspace.
+    pub fn dependents(&self, name: &str) -> &[DependencyLocation] {
+        self.index.dependencies(name)
+    }
+
+    /// Everywhere the crate `name` appears: its package version, then every
+    /// declaration depending on it, renamed ones included.
+    pub fn occurrences(&self, name: &str) -> Vec<Occurrence> {
+        let mut occurrences = Vec::new();
+        occurrences.extend(self.version_occurrence(name));
+
+        let workspace_dependencies = self
+            .root_manifest
+            .workspace
+            .as_ref()
+            .map(|w| &w.dependencies);
+        for location in self.index.all_dependencies() {
+            let Some(dependency) = self
+                .manifest(&location.manifest)
+                .and_then(|manifest| location.get(manifest))
+            else {
+                continue;
+            };
+            let mut key = location.key();
+            let (package, version) = match dependency {
+                Dependency::Simple(requirement) => {
+                    key.push("version".to_string());
+                    (&*location.name, Some(requirement.clone()))
+                }
+                Dependency::Detailed(detail) => {
+                    if detail.version.is_some() {
+                        key.push("version".to_string());
+                    }
+                    (
+                        detail.package.as_deref().unwrap_or(&location.name),
+                        detail.version.clone(),
+                    )
+                }
+                Dependency::Inherited(_) => {
+                    let package = match workspace_dependencies.and_then(|d| d.get(&*location.name))
+                    {
+                        Some(Dependency::Detailed(detail)) => detail.package.as_deref(),
+                        _ => None,
+                    };
+                    (package.unwrap_or(&location.name), None)
+                }
+            };
+            if package == name {
+                occurrences.push(Occurrence {
+                    manifest: location.manifest.to_path_buf(),
+                    key,
+                    version,
+                });
+            }
+        }
+
+        occurrences
+    }
+
+    /// Every member after the members it depends on, the order to publish
+    /// them in. Normal and build dependencies count, and dev-dependencies
+    /// with a version, which cargo keeps when publishing; path-only
+    /// dev-dependencies are dropped and can't hold a member back.
+    ///
+    /// Fails when members depend on each other in a cycle.
+    pub fn publish_order(&self) -> crate::Result<Vec<OrderedMember>> {
+        let workspace_dependencies = self
+            .root_manifest
+            .workspace
+            .as_ref()
+            .map(|w| &w.dependencies);
 
-                let manifest = self.load_cargo(&member_path)?;
-                members.insert(member_path, manifest);
+        let mut graph: BTreeMap<String, BTreeSet<String>> = self
+            .index
+            .package_names()
+            .map(|name| (name.to_string(), BTreeSet::new()))
+            .collect();
+        for location in self.index.all_dependencies() {
+            if location.table.kind == DependencyKind::Workspace {
+                continue;
+            }
+            let Some(manifest) = self.manifest(&location.manifest) else {
+                continue;
+            };
+            let (Some(member), Some(dependency)) = (&manifest.package, location.get(manifest))
+            else {
+                continue;
+            };
+            let dependency = match dependency {
+                Dependency::Inherited(_) => {
+                    match workspace_dependencies.and_then(|d| d.get(&*location.name)) {
+                        Some(dependency) => dependency,
+                        None => continue,
+                    }
+                }
+                dependency => dependency,
+            };
+            let versioned = match dependency {
+                Dependency::Detailed(detail) => detail.version.is_some(),
+                _ => true,
+            };
+            if location.table.kind == DependencyKind::Dev && !versioned {
+                continue;
             }
 
-            if members.len() > 0 {
-                s.members = Some(members);
+            let (name, _, _) = enabled_features(&location.name, dependency);
+            if name != member.name && graph.contains_key(name) {
+                if let Some(dependencies) = graph.get_mut(&member.name) {
+                    dependencies.insert(name.to_string());
+                }
             }
         }
 
-        Ok(s)
+        let names = order::topological_order(&graph).map_err(|cycle| {
+            anyhow::anyhow!(
+                "members depend on each other in a cycle: {}",
+                cycle.join(" -> ")
+            )
+        })?;
+
+        Ok(names
+            .into_iter()
+            .filter_map(|name| {
+                let manifest = self.package_manifest(&name)?.to_path_buf();
+                Some(OrderedMember {
+                    version: self.package_version(&name),
+                    dependencies: graph[&name].iter().cloned().collect(),
+                    publish: self.publishes(self.manifest(&manifest)?),
+                    manifest,
+                    name,
+                })
+            })
+            .collect())
+    }
+
+    /// Whether the package of `manifest` is published: `false` for
+    /// `publish = false` or an empty list of registries, set by the package
+    /// or inherited from `workspace.package`.
+    fn publishes(&self, manifest: &Manifest) -> bool {
+        let Some(package) = &manifest.package else {
+            return false;
+        };
+        let publish = match package.publish.get() {
+            Ok(publish) => Some(publish),
+            Err(_) => self
+                .root_manifest
+                .workspace
+                .as_ref()
+                .and_then(|w| w.package.as_ref())
+                .map(|p| &p.publish),
+        };
+
+        match publish {
+            Some(Publish::Flag(publish)) => *publish,
+            Some(Publish::Registry(registries)) => !registries.is_empty(),
+            None => true,
+        }
+    }
+
+    /// Where the version of the member `name` is set: its `package.version`
+    /// or `workspace.package.version` if it inherits it.
+    fn version_occurrence(&self, name: &str) -> Option<Occurrence> {
+        let path = self.package_manifest(name)?;
+        let (manifest, key) = if self.inherits_version(name) {
+            (
+                self.root_path.clone(),
+                ["workspace", "package", "version"].as_slice(),
+            )
+        } else {
+            (path.to_path_buf(), ["package", "version"].as_slice())
+        };
+
+        Some(Occurrence {
+            manifest,
+            key: key.iter().map(|k| k.to_string()).collect(),
+            version: self.package_version(name),
+        })
     }
 
-    pub fn update_version<'s>(
+    /// An annotation of `key` in the manifest at `file`, on the line it is
+    /// declared on if the manifest was loaded from disk.
+    pub fn annotate(
         &self,
-        s: &'s mut CargoManifest,
-        package: impl Into<String>,
-        version: impl Into<String>,
-    ) -> anyhow::Result<&'s mut CargoManifest> {
-        let version = version.into();
-        let package = package.into();
+        level: AnnotationLevel,
+        file: &Path,
+        key: &[String],
+        message: impl Into<String>,
+    ) -> Annotation {
+        Annotation {
+            level,
+            file: file.to_path_buf(),
+            line: self
+                .sources
+                .get(file)
+                .and_then(|source| actions::key_line(source, key)),
+            message: message.into(),
+        }
+    }
 
-        // Update version in root manifest
-        if let Some(pkg) = &s.root_manifest.package {
-            if pkg.name == package {
-                s.root_manifest
-                    .package
-                    .as_mut()
-                    .map(|p| p.version.set(version.clone()));
+    /// An error annotation of the value `violation` is about.
+    pub fn violation_annotation(&self, violation: &CheckViolation) -> Annotation {
+        let (file, key) = match violation {
+            CheckViolation::Requirement(drift) => {
+                (drift.location.manifest.to_path_buf(), drift.location.key())
             }
-        } else {
-            self.update_dependencies(&mut s.root_manifest.dependencies, &package, &version);
+            CheckViolation::Lockstep { package, .. } | CheckViolation::Expected { package, .. } => {
+                match self.version_occurrence(package) {
+                    Some(occurrence) => (occurrence.manifest, occurrence.key),
+                    None => (self.root_path.clone(), Vec::new()),
+                }
+            }
+        };
+
+        self.annotate(AnnotationLevel::Error, &file, &key, violation.to_string())
+    }
+
+    /// A notice annotation of every value `report` lists as changed, on its
+    /// line in this workspace, the one after the change.
+    pub fn change_annotations(&self, report: &ChangeReport) -> Vec<Annotation> {
+        report
+            .files
+            .iter()
+            .flat_map(|file| {
+                file.changes.iter().map(|change| {
+                    let message = match &change.from {
+                        Some(from) => format!(
+                            "`{}` changed from {from} to {} for {}",
+                            change.key.join("."),
+                            change.to,
+                            change.package
+                        ),
+                        None => format!(
+                            "`{}` set to {} for {}",
+                            change.key.join("."),
+                            change.to,
+                            change.package
+                        ),
+                    };
+                    self.annotate(AnnotationLevel::Notice, &file.path, &change.key, message)
+                })
+            })
+            .collect()
+    }
+
+    /// A warning annotation of every member and requirement `report` finds
+    /// behind the registry, on its version or requirement.
+    pub fn outdated_annotations(&self, report: &OutdatedReport) -> Vec<Annotation> {
+        let members = report.members.iter().map(|member| {
+            let (file, key) = match self.version_occurrence(&member.name) {
+                Some(occurrence) => (occurrence.manifest, occurrence.key),
+                None => (self.root_path.clone(), Vec::new()),
+            };
+            let message = match &member.published {
+                Some(published) => format!(
+                    "{} is at {}, its latest release is {published}",
+                    member.name, member.local
+                ),
+                None => format!("{} {} was never published", member.name, member.local),
+            };
+            self.annotate(AnnotationLevel::Warning, &file, &key, message)
+        });
+        let dependencies = report.dependencies.iter().map(|dependency| {
+            let message = format!(
+                "{} {} doesn't match `{}`",
+                dependency.package, dependency.latest, dependency.requirement
+            );
+            self.annotate(
+                AnnotationLevel::Warning,
+                &dependency.manifest,
+                &dependency.key,
+                message,
+            )
+        });
+
+        members.chain(dependencies).collect()
+    }
+
+    /// The crates `matcher` selects, see [`CargoManifestService::update_version`].
+    pub fn matching_packages(&self, matcher: &CrateMatcher) -> crate::Result<Vec<String>> {
+        Ok(matcher.select(self.index.package_names())?)
+    }
+
+    /// The `[patch]` entries of the root manifest, ordered by registry and
+    /// name. Entries without a git repository, path or version are left out.
+    pub fn patches(&self) -> Vec<PatchEntry> {
+        self.root_manifest
+            .patch
+            .iter()
+            .flat_map(|(registry, patches)| {
+                patches.iter().filter_map(move |(name, dependency)| {
+                    Some(PatchEntry {
+                        registry: registry.clone(),
+                        name: name.clone(),
+                        source: PatchSource::from_dependency(dependency)?,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// The current version of the package `name`, taken from
+    /// `[workspace.package]` if it inherits it.
+    pub fn package_version(&self, name: &str) -> Option<String> {
+        let path = self.index.packages(name).first()?;
+        let package = self.manifest(path)?.package.as_ref()?;
+
+        match package.version.get() {
+            Ok(version) => Some(version.clone()),
+            Err(_) => self
+                .root_manifest
+                .workspace
+                .as_ref()?
+                .package
+                .as_ref()?
+                .version
+                .clone(),
+        }
+    }
+
+    /// The `version` of `[workspace.package]`, which members with
+    /// `version.workspace = true` inherit.
+    pub fn workspace_version(&self) -> Option<&str> {
+        self.root_manifest
+            .workspace
+            .as_ref()?
+            .package
+            .as_ref()?
+            .version
+            .as_deref()
+    }
+
+    /// The packages that inherit `workspace.package.version`, in order.
+    pub fn version_inheritors(&self) -> Vec<String> {
+        self.index
+            .package_names()
+            .filter(|name| self.inherits_version(name))
+            .map(String::from)
+            .collect()
+    }
+
+    fn inherits_version(&self, name: &str) -> bool {
+        self.package_manifest(name)
+            .and_then(|path| self.manifest(path)?.package.as_ref())
+            .is_some_and(|package| package.version.get().is_err())
+    }
+
+    /// A commented `.cargo-set.toml` with defaults inferred from the
+    /// workspace's versions and layout, and from its release `tags`.
+    pub fn starter_config(&self, tags: &[String]) -> String {
+        let packages = self.index.package_names().collect::<Vec<_>>();
+        let versions = packages
+            .iter()
+            .filter_map(|name| self.package_version(name))
+            .collect::<std::collections::BTreeSet<_>>();
+
+        config::starter(&WorkspaceSurvey {
+            versioning: if versions.len() > 1 {
+                Versioning::Independent
+            } else {
+                Versioning::Lockstep
+            },
+            tag_format: config::infer_tag_format(tags, &packages),
+            members: self
+                .root_manifest
+                .workspace
+                .as_ref()
+                .map(|w| w.members.clone())
+                .unwrap_or_default(),
+            packages: packages.len(),
+        })
+    }
+
+    /// Dependencies the members enable with different features, e.g. some
+    /// on `rustls` and others on `native-tls`. Only `[dependencies]` tables
+    /// are compared; inheriting declarations add their features to the
+    /// workspace's.
+    pub fn feature_inconsistencies(&self) -> Vec<FeatureInconsistency> {
+        let workspace_dependencies = self
+            .root_manifest
+            .workspace
+            .as_ref()
+            .map(|w| &w.dependencies);
+
+        let mut uses: BTreeMap<String, BTreeSet<MemberFeatures>> = BTreeMap::new();
+        for location in self.index.all_dependencies() {
+            if location.table.kind != DependencyKind::Normal {
+                continue;
+            }
+            let Some(manifest) = self.manifest(&location.manifest) else {
+                continue;
+            };
+            let (Some(member), Some(dependency)) = (&manifest.package, location.get(manifest))
+            else {
+                continue;
+            };
+
+            let (package, mut features, default_features) = match dependency {
+                Dependency::Inherited(inherited) => {
+                    let Some(workspace) =
+                        workspace_dependencies.and_then(|d| d.get(&*location.name))
+                    else {
+                        continue;
+                    };
+                    let (package, mut features, default_features) =
+                        enabled_features(&location.name, workspace);
+                    features.extend(inherited.features.iter().cloned());
+                    (package, features, default_features)
+                }
+                dependency => enabled_features(&location.name, dependency),
+            };
+
+            // A member declaring the dependency in several target tables
+            // enables the union of their features.
+            let members = uses.entry(package.to_string()).or_default();
+            if let Some(previous) = members.iter().find(|m| m.member == member.name).cloned() {
+                members.remove(&previous);
+                features.extend(previous.features);
+            }
+            members.insert(MemberFeatures {
+                member: member.name.clone(),
+                features,
+                default_features,
+            });
         }
-        if let Some(workspace) = s.root_manifest.workspace.as_mut() {
-            self.update_dependencies(&mut workspace.dependencies, &package, &version);
+
+        features::inconsistencies(uses)
+    }
+
+    /// Crates that one manifest requires at different versions in different
+    /// tables, ordered by manifest and crate name.
+    pub fn duplicate_dependencies(&self) -> Vec<DuplicateDependency> {
+        let workspace_dependencies = self
+            .root_manifest
+            .workspace
+            .as_ref()
+            .map(|w| &w.dependencies);
+
+        let mut declared: BTreeMap<(PathBuf, String), Vec<DeclaredRequirement>> = BTreeMap::new();
+        for location in self.index.all_dependencies() {
+            let Some(dependency) = self
+                .manifest(&location.manifest)
+                .and_then(|manifest| location.get(manifest))
+            else {
+                continue;
+            };
+            // Inheriting declarations are compared using the workspace's requirement.
+            let (package, requirement, inherited) = match dependency {
+                Dependency::Inherited(_) => {
+                    let Some((package, requirement)) = workspace_dependencies
+                        .and_then(|deps| deps.get(&*location.name))
+                        .and_then(|dependency| declared_requirement(location, dependency))
+                    else {
+                        continue;
+                    };
+                    (package, requirement, true)
+                }
+                dependency => match declared_requirement(location, dependency) {
+                    Some((package, requirement)) => (package, requirement, false),
+                    None => continue,
+                },
+            };
+            if location.table.kind == DependencyKind::Workspace {
+                continue;
+            }
+
+            declared
+                .entry((location.manifest.to_path_buf(), package.to_string()))
+                .or_default()
+                .push(DeclaredRequirement {
+                    location: location.clone(),
+                    requirement: requirement.to_string(),
+                    inherited,
+                });
         }
-        self.fs.write(
-            &s.root_path,
-            toml::to_string_pretty(&s.root_manifest)?
-                .as_bytes()
-                .to_vec(),
-        )?;
 
-        // If there are workspace members, update version in each of them
-        if let Some(members) = &mut s.members {
-            for (path, manifest) in members.iter_mut() {
-                let member_path = path;
-
-                if let Some(pkg) = &manifest.package {
-                    if pkg.name == package {
-                        manifest
-                            .package
-                            .as_mut()
-                            .map(|p| p.version.set(version.clone()));
+        declared
+            .into_iter()
+            .filter(|(_, declarations)| {
+                declarations
+                    .iter()
+                    .any(|d| d.requirement != declarations[0].requirement)
+            })
+            .map(|((manifest, package), mut declarations)| {
+                declarations.sort_by(|a, b| a.location.cmp(&b.location));
+                DuplicateDependency {
+                    manifest,
+                    package,
+                    declarations,
+                }
+            })
+            .collect()
+    }
+
+    /// `[workspace.dependencies]` entries that no manifest of the workspace
+    /// inherits with `workspace = true`, in name order.
+    pub fn unused_workspace_dependencies(&self) -> Vec<String> {
+        let Some(workspace) = &self.root_manifest.workspace else {
+            return Vec::new();
+        };
+
+        let mut unused = workspace
+            .dependencies
+            .keys()
+            .filter(|name| {
+                !self.index.dependencies(name).iter().any(|location| {
+                    self.manifest(&location.manifest)
+                        .and_then(|manifest| location.get(manifest))
+                        .is_some_and(|dependency| matches!(dependency, Dependency::Inherited(_)))
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        unused.sort();
+
+        unused
+    }
+
+    /// The member declarations of `package` that inherit it with
+    /// `workspace = true`. They follow its `[workspace.dependencies]` entry
+    /// and aren't edited themselves.
+    pub fn inherited_dependencies(&self, package: &str) -> Vec<DependencyLocation> {
+        self.index
+            .dependencies(package)
+            .iter()
+            .filter(|location| {
+                self.manifest(&location.manifest)
+                    .and_then(|manifest| location.get(manifest))
+                    .is_some_and(|dependency| matches!(dependency, Dependency::Inherited(_)))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The names of the members inheriting `package`, in order.
+    pub fn inheriting_members(&self, package: &str) -> Vec<String> {
+        self.inherited_dependencies(package)
+            .iter()
+            .filter_map(|location| self.manifest(&location.manifest)?.package.as_ref())
+            .map(|dependent| dependent.name.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Internal path dependencies whose version requirement the member they
+    /// point at no longer satisfies.
+    pub fn requirement_drift(&self) -> Vec<RequirementDrift> {
+        let workspace_version = self
+            .root_manifest
+            .workspace
+            .as_ref()
+            .and_then(|w| w.package.as_ref())
+            .and_then(|p| p.version.as_deref());
+
+        internal::find_drift(
+            self.manifests().map(|(_, manifest)| manifest),
+            |path| self.manifest(path),
+            &self.index,
+            workspace_version,
+        )
+    }
+
+    /// The declarations breaking a [`LintRule`], ordered by manifest and
+    /// key. Dev-dependencies and members that aren't published may leave the
+    /// `version` of path dependencies out.
+    pub fn lint(&self) -> Vec<LintFinding> {
+        let workspace_dependencies = self
+            .root_manifest
+            .workspace
+            .as_ref()
+            .map(|w| &w.dependencies);
+        let member_dirs = self
+            .manifests()
+            .filter_map(|(path, manifest)| {
+                let package = manifest.package.as_ref()?;
+                let dir = normalize(path.parent().unwrap_or_else(|| Path::new("")));
+                Some((dir, package.name.as_str()))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut findings = Vec::new();
+        // crate -> (requirement, declaring manifest)
+        let mut requirements: BTreeMap<&str, Vec<(&str, &DependencyLocation)>> = BTreeMap::new();
+        for location in self.index.all_dependencies() {
+            if location.table.kind == DependencyKind::Workspace {
+                continue;
+            }
+            let Some(manifest) = self.manifest(&location.manifest) else {
+                continue;
+            };
+            let Some(dependency) = location.get(manifest) else {
+                continue;
+            };
+            let name = &*location.name;
+
+            let inherited = workspace_dependencies.and_then(|deps| deps.get(name));
+            if let (Some(workspace), false) =
+                (inherited, matches!(dependency, Dependency::Inherited(_)))
+            {
+                // Declarations with settings inheriting would drop are left to
+                // the member to decide.
+                let fix = match dependency {
+                    Dependency::Detailed(detail) => (detail.path.is_none()
+                        && detail.git.is_none()
+                        && detail.registry.is_none()
+                        && detail.registry_index.is_none()
+                        && detail.package.is_none()
+                        && detail.default_features)
+                        .then_some(LintFix::Inherit),
+                    _ => Some(LintFix::Inherit),
+                };
+                let requirement = declared_requirement(location, workspace)
+                    .map(|(_, requirement)| format!(" at {requirement}"))
+                    .unwrap_or_default();
+                findings.push(LintFinding {
+                    rule: LintRule::NotInherited,
+                    manifest: location.manifest.to_path_buf(),
+                    key: location.key(),
+                    message: format!(
+                        "{name} is declared in `[workspace.dependencies]`{requirement}, \
+                         inherit it with `{name}.workspace = true`"
+                    ),
+                    fix,
+                });
+            }
+
+            if let Some(detail) = dependency.detail() {
+                if let (Some(path), None) = (&detail.path, &detail.version) {
+                    if location.table.kind != DependencyKind::Dev && self.publishes(manifest) {
+                        let dir = location.manifest.parent().unwrap_or_else(|| Path::new(""));
+                        let target = member_dirs.get(&normalize(&dir.join(path)));
+                        let package = manifest.package.as_ref().map(|p| p.name.as_str());
+                        findings.push(LintFinding {
+                            rule: LintRule::PathWithoutVersion,
+                            manifest: location.manifest.to_path_buf(),
+                            key: location.key(),
+                            message: format!(
+                                "the path dependency {name} has no `version`, so {} can't be \
+                                 published",
+                                package.unwrap_or("the package")
+                            ),
+                            fix: target
+                                .and_then(|target| self.package_version(target))
+                                .map(LintFix::SetVersion),
+                        });
                     }
-                } else {
-                    self.update_dependencies(&mut manifest.dependencies, &package, &version);
-                    self.fs.write(
-                        &member_path,
-                        toml::to_string_pretty(&manifest)?.as_bytes().to_vec(),
-                    )?;
                 }
             }
+
+            // Path and git dependencies are compared on what they point at,
+            // not on their requirement.
+            let declared = match dependency {
+                Dependency::Inherited(_) => inherited,
+                dependency => Some(dependency),
+            };
+            let Some(declared) = declared else {
+                continue;
+            };
+            if declared
+                .detail()
+                .is_some_and(|detail| detail.path.is_some() || detail.git.is_some())
+            {
+                continue;
+            }
+            if let Some((package, requirement)) = declared_requirement(location, declared) {
+                requirements
+                    .entry(package)
+                    .or_default()
+                    .push((requirement, location));
+            }
         }
 
-        Ok(s)
+        for (package, declarations) in requirements {
+            let mut by_requirement: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+            for (requirement, location) in &declarations {
+                let member = self
+                    .manifest(&location.manifest)
+                    .and_then(|manifest| manifest.package.as_ref())
+                    .map(|package| package.name.clone())
+                    .unwrap_or_else(|| display_path(&location.manifest));
+                by_requirement
+                    .entry(requirement)
+                    .or_default()
+                    .insert(member);
+            }
+            if by_requirement.len() < 2 {
+                continue;
+            }
+
+            let required = by_requirement
+                .iter()
+                .map(|(requirement, members)| {
+                    let members = members.iter().cloned().collect::<Vec<_>>();
+                    format!("{requirement} by {}", members.join(", "))
+                })
+                .collect::<Vec<_>>();
+            let (_, first) = declarations[0];
+            findings.push(LintFinding {
+                rule: LintRule::DivergentVersions,
+                manifest: first.manifest.to_path_buf(),
+                key: first.key(),
+                message: format!(
+                    "{package} is required at different versions: {}",
+                    required.join("; ")
+                ),
+                fix: None,
+            });
+        }
+        findings.sort_by(|a, b| (&a.manifest, &a.key, a.rule).cmp(&(&b.manifest, &b.key, b.rule)));
+
+        findings
+    }
+
+    /// A warning annotation of the declaration `finding` is about.
+    pub fn lint_annotation(&self, finding: &LintFinding) -> Annotation {
+        self.annotate(
+            AnnotationLevel::Warning,
+            &finding.manifest,
+            &finding.key,
+            format!("{} [{}]", finding.message, finding.rule),
+        )
+    }
+
+    /// Whether the current version of every published member has the tag
+    /// `format` names, e.g. `{crate}-v{version}`, among `tags`, in name
+    /// order. Members with `publish = false` are never released and left
+    /// out.
+    pub fn verify_tags(&self, tags: &[String], format: &str) -> crate::Result<Vec<TagCheck>> {
+        let mut checks = Vec::new();
+        for (name, version) in self.package_versions() {
+            let published = self
+                .package_manifest(&name)
+                .and_then(|path| self.manifest(path))
+                .is_some_and(|manifest| self.publishes(manifest));
+            let Some(version) = version.filter(|_| published) else {
+                continue;
+            };
+            checks.push(tags::check(tags, format, &name, &version)?);
+        }
+
+        Ok(checks)
+    }
+
+    /// An error annotation of the version `check` found untagged.
+    pub fn tag_annotation(&self, check: &TagCheck) -> Annotation {
+        let (file, key) = match self.version_occurrence(&check.package) {
+            Some(occurrence) => (occurrence.manifest, occurrence.key),
+            None => (self.root_path.clone(), Vec::new()),
+        };
+
+        self.annotate(AnnotationLevel::Error, &file, &key, check.to_string())
     }
 
-    fn update_dependencies(
+    /// Every version invariant the workspace breaks, without changing it:
+    /// internal requirements its members don't meet, members of a
+    /// `Lockstep` workspace off the shared version, and crates of `expected`
+    /// at another version than the one given.
+    pub fn check(
         &self,
-        dependencies: &mut BTreeMap<String, Dependency>,
-        package: &String,
-        version: &String,
-    ) {
-        for (_name, dep_version) in dependencies
-            .iter_mut()
-            .filter(|(name, _)| name.eq(&package))
-        {
-            match dep_version {
-                Dependency::Simple(dep) => *dep = version.clone(),
-                Dependency::Inherited(_) => {}
-                Dependency::Detailed(dep) => dep.version = Some(version.clone()),
+        expected: &[(String, String)],
+        versioning: Option<Versioning>,
+    ) -> Vec<CheckViolation> {
+        let mut violations = self
+            .requirement_drift()
+            .into_iter()
+            .map(CheckViolation::Requirement)
+            .collect::<Vec<_>>();
+
+        let versions = self.package_versions();
+        if versioning == Some(Versioning::Lockstep) {
+            let shared = versions
+                .iter()
+                .filter_map(|(_, version)| semver::Version::parse(version.as_deref()?).ok())
+                .max()
+                .map(|version| version.to_string());
+            for (package, version) in &versions {
+                if let (Some(version), Some(shared)) = (version, &shared) {
+                    if version != shared {
+                        violations.push(CheckViolation::Lockstep {
+                            package: package.clone(),
+                            version: version.clone(),
+                            shared: shared.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        for (package, expected) in expected {
+            let version = self.package_version(package);
+            if version.as_ref() != Some(expected) {
+                violations.push(CheckViolation::Expected {
+                    package: package.clone(),
+                    version,
+                    expected: expected.clone(),
+                });
             }
         }
+
+        violations
     }
 }
 
-#[cfg(test)]
-mod test {
-    use std::assert_eq;
+/// The crate a declaration refers to (its `package` if renamed) and its
+/// version requirement, if it has one of its own.
+/// The package, features and default-features flag a declaration enables.
+fn enabled_features<'a>(
+    name: &'a str,
+    dependency: &'a Dependency,
+) -> (&'a str, BTreeSet<String>, bool) {
+    match dependency {
+        Dependency::Detailed(detail) => (
+            detail.package.as_deref().unwrap_or(name),
+            detail.features.iter().cloned().collect(),
+            detail.default_features,
+        ),
+        Dependency::Simple(_) | Dependency::Inherited(_) => (name, BTreeSet::new(), true),
+    }
+}
 
-    use crate::filesystem::MockFileSystem;
+fn declared_requirement<'a>(
+    location: &'a DependencyLocation,
+    dependency: &'a Dependency,
+) -> Option<(&'a str, &'a str)> {
+    match dependency {
+        Dependency::Simple(requirement) => Some((&location.name, requirement)),
+        Dependency::Detailed(detail) => Some((
+            detail.package.as_deref().unwrap_or(&location.name),
+            detail.version.as_deref()?,
+        )),
+        Dependency::Inherited(_) => None,
+    }
+}
 
-    use super::*;
+/// Manifest paths of the members declared by `workspace`, in declaration
+/// order, each with the entry of `members` that names it. Glob patterns like
+/// `crates/*` expand, in path order, to the matching directories that have a
+/// manifest. Members in or under a directory of `exclude` are left out.
+fn member_manifest_paths<F: FileSystem>(
+    fs: &F,
+    root_path: &Path,
+    workspace: &cargo_toml::Workspace,
+) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let root_dir = root_path.parent().unwrap_or_else(|| Path::new(""));
+    let excluded = workspace
+        .exclude
+        .iter()
+        .map(|dir| normalize(&root_dir.join(dir)))
+        .collect::<Vec<_>>();
 
-    #[test]
-    fn can_load_children() -> anyhow::Result<()> {
-        let root_manifest_toml =
-            b"name = 'root'\nversion = '0.1.0'\n workspace = { members = ['child'] }";
-        let child_manifest_toml = b"name = 'child'\nversion = '0.2.0'";
+    let mut paths = expand_members(fs, root_dir, &workspace.members)?;
+    paths.retain(|(path, _)| {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        !excluded.iter().any(|excluded| dir.starts_with(excluded))
+    });
 
-        let root_manifest_path = PathBuf::from("Cargo.toml");
-        let child_manifest_path = PathBuf::from("child/Cargo.toml");
+    Ok(paths)
+}
 
-        let mut fs = MockFileSystem::new();
-        fs.add_file(root_manifest_path.clone(), root_manifest_toml.to_vec());
-        fs.add_file(child_manifest_path.clone(), child_manifest_toml.to_vec());
+/// Manifest paths of the `default-members` of `workspace`, or `None` if it
+/// doesn't declare any.
+fn default_member_manifest_paths<F: FileSystem>(
+    fs: &F,
+    root_path: &Path,
+    workspace: &cargo_toml::Workspace,
+) -> anyhow::Result<Option<BTreeSet<PathBuf>>> {
+    if workspace.default_members.is_empty() {
+        return Ok(None);
+    }
+    let root_dir = root_path.parent().unwrap_or_else(|| Path::new(""));
 
-        let cargo_manifest = CargoManifestService::new(fs)
-            .load_manifest(&root_manifest_path)
-            .unwrap();
-        let members = cargo_manifest.members.as_ref().unwrap();
+    Ok(Some(
+        expand_members(fs, root_dir, &workspace.default_members)?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect(),
+    ))
+}
 
-        assert_eq!(1, members.len());
-        assert!(members.contains_key(&child_manifest_path));
+/// The manifest paths the member entries `patterns` name, relative to
+/// `root_dir`, each with its entry. An entry is a member's directory, or its
+/// manifest if it already ends in `Cargo.toml`.
+fn expand_members<F: FileSystem>(
+    fs: &F,
+    root_dir: &Path,
+    patterns: &[String],
+) -> anyhow::Result<Vec<(PathBuf, String)>> {
+    let mut paths = Vec::new();
+    for member in patterns {
+        let dir = Path::new(member);
+        let dir = match dir.file_name().is_some_and(|name| name == "Cargo.toml") {
+            true => dir.parent().unwrap_or_else(|| Path::new("")),
+            false => dir,
+        };
+        if !glob::is_pattern(member) {
+            let manifest = normalize(&root_dir.join(dir).join("Cargo.toml"));
+            paths.push((manifest, member.clone()));
+            continue;
+        }
 
-        Ok(())
+        let matches = glob::expand(fs, root_dir, &dir.to_string_lossy())
+            .with_context(|| format!("failed to expand the workspace member `{member}`"))?;
+        for dir in matches {
+            let manifest = dir.join("Cargo.toml");
+            let has_manifest = fs
+                .list_dir(&dir)
+                .is_ok_and(|entries| entries.contains(&manifest));
+            if has_manifest {
+                paths.push((normalize(&manifest), member.clone()));
+            }
+        }
     }
 
-    #[test]
-    fn can_no_children() -> anyhow::Result<()> {
-        let root_manifest_toml = b"name = 'root'\nversion = '0.1.0'\n workspace = { members = [] }";
+    Ok(paths)
+}
 
-        let root_manifest_path = PathBuf::from("Cargo.toml");
+/// The crates whose declaration differs between `old` and `new`, two
+/// versions of a manifest, by the name of the package they resolve to.
+fn changed_dependencies(old: &Manifest, new: &Manifest) -> BTreeSet<String> {
+    let tables = DependencyTable::all(old)
+        .into_iter()
+        .chain(DependencyTable::all(new))
+        .collect::<BTreeSet<_>>();
 
-        let mut fs = MockFileSystem::new();
-        fs.add_file(root_manifest_path.clone(), root_manifest_toml.to_vec());
+    let mut changed = BTreeSet::new();
+    for table in tables {
+        let (old, new) = (table.get(old), table.get(new));
+        let keys = old
+            .into_iter()
+            .chain(new)
+            .flat_map(|dependencies| dependencies.keys())
+            .collect::<BTreeSet<_>>();
+        for key in keys {
+            let (before, after) = (old.and_then(|d| d.get(key)), new.and_then(|d| d.get(key)));
+            if before != after {
+                let renamed = before.or(after).and_then(Dependency::package);
+                changed.insert(renamed.unwrap_or(key).to_string());
+            }
+        }
+    }
 
-        let cargo_manifest = CargoManifestService::new(fs)
-            .load_manifest(&root_manifest_path)
-            .unwrap();
-        assert!(cargo_manifest.members.is_none());
+    changed
+}
+
+/// `updates` with every member inheriting `workspace.package.version` added
+/// at the version one of them is set to, since they can only move together.
+fn with_inheritors<P: AsRef<str>, V: AsRef<str>>(
+    s: &CargoManifest,
+    updates: &[(P, V)],
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut all = updates
+        .iter()
+        .map(|(package, version)| (package.as_ref().to_string(), version.as_ref().to_string()))
+        .collect::<Vec<_>>();
+
+    let shared = all
+        .iter()
+        .filter(|(package, _)| s.inherits_version(package))
+        .collect::<Vec<_>>();
+    let Some((first, version)) = shared.first().cloned() else {
+        return Ok(all);
+    };
+    if let Some((other, conflicting)) = shared.iter().find(|(_, v)| v != version) {
+        crate::error::bail!(
+            "`{first}` and `{other}` inherit `workspace.package.version`, they can't be set to \
+             {version} and {conflicting}"
+        );
+    }
+
+    let version = version.clone();
+    for inheritor in s.version_inheritors() {
+        if !all.iter().any(|(package, _)| *package == inheritor) {
+            all.push((inheritor, version.clone()));
+        }
+    }
+
+    Ok(all)
+}
+
+impl<F: FileSystem> CargoManifestService<F> {
+    pub fn new(fs: F) -> Self {
+        Self {
+            fs,
+            cancellation: None,
+            write_concurrency: NonZeroUsize::MIN,
+            load_concurrency: NonZeroUsize::MIN,
+            lenient: false,
+            format: None,
+            sort_dependencies: false,
+            policy: None,
+            banned: BannedVersions::default(),
+            allow_downgrade: false,
+            atomic: false,
+            default_members_only: false,
+            dev_dependencies: true,
+            requirement_style: RequirementStyle::default(),
+            if_needed: false,
+            registry: None,
+            hooks: Hooks::default(),
+            discovery: Discovery::default(),
+        }
+    }
+
+    /// The file system the service reads and writes through, e.g. to load
+    /// the workspace's `.cargo-set.toml` from the same tree.
+    pub fn file_system(&self) -> &F {
+        &self.fs
+    }
+
+    /// Keep loading when a member manifest can't be read or parsed.
+    ///
+    /// Broken members are recorded in `CargoManifest::load_failures` and left
+    /// out of the workspace, so updates never write to them. The root manifest
+    /// must always load.
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Keep dependency tables alphabetically sorted in every manifest an
+    /// update writes, so inserted entries don't cause merge churn.
+    pub fn with_sorted_dependencies(mut self, sort: bool) -> Self {
+        self.sort_dependencies = sort;
+        self
+    }
+
+    /// Normalize every manifest written by an update with `style`, as
+    /// `normalize` does. By default only the edited values change.
+    pub fn with_format(mut self, style: FormatStyle) -> Self {
+        self.format = Some(style);
+        self
+    }
+
+    /// Abort loads and updates once `token` is cancelled.
+    ///
+    /// The token is checked before every manifest read and once more before
+    /// the write phase; writes themselves are never interrupted, so a
+    /// cancelled update leaves either all files or none of them modified.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Write up to `limit` manifests at the same time. Defaults to one, which
+    /// writes them sequentially.
+    pub fn with_write_concurrency(mut self, limit: NonZeroUsize) -> Self {
+        self.write_concurrency = limit;
+        self
+    }
+
+    /// Read and parse up to `limit` member manifests at the same time.
+    /// Defaults to one, which loads them sequentially. Members load in
+    /// batches, one per level of nested workspaces.
+    pub fn with_load_concurrency(mut self, limit: NonZeroUsize) -> Self {
+        self.load_concurrency = limit;
+        self
+    }
+
+    /// Check every version change against `policy` before writing anything,
+    /// failing with a `PolicyError` that lists each violation.
+    pub fn with_policy(mut self, policy: Policy, context: PolicyContext) -> Self {
+        self.policy = Some((policy, context));
+        self
+    }
+
+    /// Refuse to add or rewrite dependency requirements whose lowest allowed
+    /// version is `banned`.
+    pub fn with_banned_versions(mut self, banned: BannedVersions) -> Self {
+        self.banned = banned;
+        self
+    }
+
+    /// Don't warn about updates that set a crate to a lower version than it
+    /// has.
+    pub fn with_allow_downgrade(mut self, allow_downgrade: bool) -> Self {
+        self.allow_downgrade = allow_downgrade;
+        self
+    }
+
+    /// Make every update all-or-nothing: when writing one manifest fails, the
+    /// manifests already written are restored instead of saving a resume
+    /// state. The resume state is still saved if restoring fails too.
+    pub fn with_atomic_writes(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Load only the `default-members` of workspaces that declare them, so
+    /// every operation is scoped to those. The root manifest is always
+    /// loaded.
+    pub fn with_default_members_only(mut self, default_members_only: bool) -> Self {
+        self.default_members_only = default_members_only;
+        self
+    }
+
+    /// How the members of a workspace are found. With [`Discovery::Cargo`]
+    /// nested workspaces aren't followed, as cargo doesn't have them.
+    pub fn with_discovery(mut self, discovery: Discovery) -> Self {
+        self.discovery = discovery;
+        self
+    }
+
+    /// Whether version changes also update `[dev-dependencies]` requirements
+    /// on the changed crates. Defaults to `true`.
+    pub fn with_dev_dependencies(mut self, dev_dependencies: bool) -> Self {
+        self.dev_dependencies = dev_dependencies;
+        self
+    }
+
+    /// How requirements on a crate are rewritten when its version changes,
+    /// keeping their operators by default.
+    pub fn with_requirement_style(mut self, style: RequirementStyle) -> Self {
+        self.requirement_style = style;
+        self
+    }
+
+    /// Leave requirements that the new version of a crate already meets as
+    /// they are, e.g. `^1.4` when it is set to 1.4.7.
+    pub fn with_if_needed(mut self, if_needed: bool) -> Self {
+        self.if_needed = if_needed;
+        self
+    }
+
+    /// Before setting a dependency that isn't a member to a version, look
+    /// the version up in `registry`, refusing ones that were never published
+    /// and yanked ones, which only warn if `allow_yanked`.
+    pub fn with_registry_check(
+        mut self,
+        registry: impl Registry + Send + Sync + 'static,
+        allow_yanked: bool,
+    ) -> Self {
+        self.registry = Some((Arc::new(registry), allow_yanked));
+        self
+    }
+
+    /// Run the `pre` hooks of `hooks` before writing manifests, refusing the
+    /// write if one fails, and the `post` hooks after.
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// How every member depending on `package` comes to, directly or through
+    /// other crates. Edges between members come from their manifests, those
+    /// past the workspace from `Cargo.lock`; without a lockfile only direct
+    /// and internal dependencies are found.
+    pub fn why(&self, s: &CargoManifest, package: &str) -> Vec<DependencyPath> {
+        let members = s.index.package_names().collect::<BTreeSet<_>>();
+        let workspace_dependencies = s.root_manifest.workspace.as_ref().map(|w| &w.dependencies);
+
+        let mut graph: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        if let Ok(lockfile) = Lockfile::load(&self.fs, &s.root_path) {
+            for locked in &lockfile.packages {
+                if !members.contains(locked.name.as_str()) {
+                    graph
+                        .entry(locked.name.clone())
+                        .or_default()
+                        .extend(locked.dependency_names().map(str::to_string));
+                }
+            }
+        }
+
+        let mut declarations: BTreeMap<(&str, &str), &DependencyLocation> = BTreeMap::new();
+        for location in s.index.all_dependencies() {
+            if location.table.kind == DependencyKind::Workspace {
+                continue;
+            }
+            let Some(manifest) = s.manifest(&location.manifest) else {
+                continue;
+            };
+            let (Some(member), Some(dependency)) = (&manifest.package, location.get(manifest))
+            else {
+                continue;
+            };
+            let dependency = match dependency {
+                Dependency::Inherited(_) => {
+                    match workspace_dependencies.and_then(|d| d.get(&*location.name)) {
+                        Some(dependency) => dependency,
+                        None => continue,
+                    }
+                }
+                dependency => dependency,
+            };
+            let (dependency, _, _) = enabled_features(&location.name, dependency);
+
+            graph
+                .entry(member.name.clone())
+                .or_default()
+                .push(dependency.to_string());
+            declarations
+                .entry((member.name.as_str(), dependency))
+                .or_insert(location);
+        }
+
+        members
+            .iter()
+            .filter(|member| **member != package)
+            .filter_map(|member| {
+                let path = why::shortest_path(&graph, member, package)?;
+                // The path leaves the workspace after its last member.
+                let last = path[..path.len() - 1]
+                    .iter()
+                    .rposition(|name| members.contains(name.as_str()))?;
+                let declaration =
+                    declarations.get(&(path[last].as_str(), path[last + 1].as_str()))?;
+
+                Some(DependencyPath {
+                    member: member.to_string(),
+                    declaration: (*declaration).clone(),
+                    path,
+                })
+            })
+            .collect()
+    }
+
+    /// Compare every member's version with its latest release on `registry`,
+    /// and every third-party requirement with the latest release of the
+    /// dependency. Path, git and alternative registry dependencies are
+    /// skipped.
+    pub fn outdated(
+        &self,
+        s: &CargoManifest,
+        registry: &impl Registry,
+    ) -> crate::Result<OutdatedReport> {
+        let mut latest: BTreeMap<String, Option<semver::Version>> = BTreeMap::new();
+        let mut lookup = |name: &str| -> anyhow::Result<Option<semver::Version>> {
+            if let Some(version) = latest.get(name) {
+                return Ok(version.clone());
+            }
+            let versions = registry
+                .versions(name)
+                .with_context(|| format!("failed to look up {name}"))?;
+            let version = registry::latest(&versions);
+            latest.insert(name.to_string(), version.clone());
+            Ok(version)
+        };
+
+        let mut report = OutdatedReport::default();
+        for (name, local) in s.package_versions() {
+            let Some(local) = local else {
+                continue;
+            };
+            let published = lookup(&name)?.map(|v| v.to_string());
+            if published.as_deref() != Some(local.as_str()) {
+                report.members.push(OutdatedMember {
+                    name,
+                    local,
+                    published,
+                });
+            }
+        }
+
+        for location in s.index.all_dependencies() {
+            let Some(dependency) = s
+                .manifest(&location.manifest)
+                .and_then(|manifest| location.get(manifest))
+            else {
+                continue;
+            };
+            if let Dependency::Detailed(detail) = dependency {
+                if detail.path.is_some() || detail.git.is_some() || detail.registry.is_some() {
+                    continue;
+                }
+            }
+            let Some((package, requirement)) = declared_requirement(location, dependency) else {
+                continue;
+            };
+            if !s.index.packages(package).is_empty() {
+                continue;
+            }
+            let Ok(parsed) = semver::VersionReq::parse(requirement) else {
+                continue;
+            };
+            let Some(newest) = lookup(package)? else {
+                continue;
+            };
+            let outdated = !parsed.matches(&newest)
+                && duplicates::minimum_version(requirement).is_some_and(|min| newest > min);
+            if outdated {
+                report.dependencies.push(OutdatedDependency {
+                    manifest: location.manifest.to_path_buf(),
+                    key: location.key(),
+                    package: package.to_string(),
+                    requirement: requirement.to_string(),
+                    latest: newest.to_string(),
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Every declaration requiring, and every `Cargo.lock` entry locked at, a
+    /// banned version. Without a lockfile only declarations are checked.
+    pub fn banned_dependencies(&self, s: &CargoManifest) -> Vec<BannedDependency> {
+        let mut banned = s
+            .index
+            .all_dependencies()
+            .filter_map(|location| {
+                let (package, requirement) = s
+                    .manifest(&location.manifest)
+                    .and_then(|manifest| location.get(manifest))
+                    .and_then(|dependency| declared_requirement(location, dependency))?;
+                self.banned
+                    .check_requirement(package, Some(location), requirement)
+            })
+            .collect::<Vec<_>>();
+
+        if let Ok(lockfile) = Lockfile::load(&self.fs, &s.root_path) {
+            banned.extend(lockfile.packages.iter().filter_map(|locked| {
+                let version = semver::Version::parse(&locked.version).ok()?;
+                let ban = self.banned.ban(&locked.name, &version)?.to_string();
+                Some(BannedDependency {
+                    package: locked.name.clone(),
+                    location: None,
+                    version,
+                    ban,
+                })
+            }));
+        }
+
+        banned
+    }
+
+    /// Refuse new versions that aren't semantic versions, and warn about
+    /// downgrades unless they are allowed.
+    /// Fail if a crate of `updates` is neither a package nor a dependency
+    /// anywhere in the workspace, which is most likely a typo.
+    fn check_found<P: AsRef<str>, V: AsRef<str>>(
+        &self,
+        s: &CargoManifest,
+        updates: &[(P, V)],
+    ) -> anyhow::Result<()> {
+        let mut missing = Vec::new();
+        for (package, _) in updates {
+            let package = package.as_ref();
+            let found = !s.index.packages(package).is_empty()
+                || s.index.dependencies_on(package).next().is_some();
+            if found {
+                continue;
+            }
+
+            let known = s
+                .index
+                .package_names()
+                .chain(s.index.all_dependencies().map(|d| &*d.name));
+            match matcher::suggest(package, known) {
+                Some(suggestion) => missing.push(format!(
+                    "\n  `{package}` is not a package or dependency of the workspace, \
+                     did you mean `{suggestion}`?"
+                )),
+                None => missing.push(format!(
+                    "\n  `{package}` is not a package or dependency of the workspace"
+                )),
+            }
+        }
+        if !missing.is_empty() {
+            crate::error::bail!(
+                "{} crate(s) not found, nothing was changed:{}",
+                missing.len(),
+                missing.concat()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// With a registry to check against, fail if a dependency that isn't a
+    /// member of the workspace is set to a version the registry doesn't
+    /// have, or has yanked, which is most likely a typo.
+    fn check_published<P: AsRef<str>, V: AsRef<str>>(
+        &self,
+        s: &CargoManifest,
+        updates: &[(P, V)],
+    ) -> anyhow::Result<()> {
+        let Some((registry, allow_yanked)) = &self.registry else {
+            return Ok(());
+        };
+
+        let mut unpublished = Vec::new();
+        for (package, version) in updates {
+            let (package, version) = (package.as_ref(), version.as_ref());
+            if !s.index.packages(package).is_empty() {
+                continue;
+            }
+            let versions = registry
+                .versions(package)
+                .with_context(|| format!("failed to look up {package}"))?;
+            match versions
+                .iter()
+                .find(|published| published.version == version)
+            {
+                None => unpublished.push(format!("\n  {package} {version} was never published")),
+                Some(published) if published.yanked && *allow_yanked => {
+                    tracing::warn!("{package} {version} is yanked");
+                }
+                Some(published) if published.yanked => unpublished.push(format!(
+                    "\n  {package} {version} is yanked, pass `--allow-yanked` to set it anyway"
+                )),
+                Some(_) => {}
+            }
+        }
+        if !unpublished.is_empty() {
+            crate::error::bail!(
+                "{} version(s) not on the registry, nothing was changed:{}",
+                unpublished.len(),
+                unpublished.concat()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn check_versions<P: AsRef<str>, V: AsRef<str>>(
+        &self,
+        s: &CargoManifest,
+        updates: &[(P, V)],
+    ) -> anyhow::Result<()> {
+        let mut invalid = Vec::new();
+        for (package, version) in updates {
+            let (package, version) = (package.as_ref(), version.as_ref());
+            let new = match version::validate(package, version) {
+                Ok(new) => new,
+                Err(e) => {
+                    invalid.push(format!("\n  {e}"));
+                    continue;
+                }
+            };
+            let downgrade = s
+                .package_version(package)
+                .and_then(|current| version::downgrade(package, &current, &new));
+            if let Some(downgrade) = downgrade.filter(|_| !self.allow_downgrade) {
+                tracing::warn!("{downgrade}, pass `--allow-downgrade` if that is intended");
+            }
+        }
+        if !invalid.is_empty() {
+            crate::error::bail!(
+                "{} invalid version(s), nothing was changed:{}",
+                invalid.len(),
+                invalid.concat()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn check_banned<'a>(
+        &self,
+        requirements: impl IntoIterator<Item = (&'a str, Option<&'a DependencyLocation>, &'a str)>,
+    ) -> anyhow::Result<()> {
+        let banned = requirements
+            .into_iter()
+            .filter_map(|(package, location, requirement)| {
+                self.banned
+                    .check_requirement(package, location, requirement)
+            })
+            .map(|banned| format!("\n  {banned}"))
+            .collect::<Vec<_>>();
+        if !banned.is_empty() {
+            crate::error::bail!(
+                "{} requirement(s) would use a banned version, nothing was changed:{}",
+                banned.len(),
+                banned.concat()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn check_policy<P: AsRef<str>, V: AsRef<str>>(
+        &self,
+        s: &CargoManifest,
+        updates: &[(P, V)],
+    ) -> anyhow::Result<()> {
+        let Some((policy, context)) = &self.policy else {
+            return Ok(());
+        };
+
+        let violations = updates
+            .iter()
+            .flat_map(|(package, version)| {
+                self.violations(s, policy, context, package.as_ref(), version.as_ref())
+            })
+            .collect::<Vec<_>>();
+        if !violations.is_empty() {
+            return Err(PolicyError { violations }.into());
+        }
+
+        Ok(())
+    }
+
+    fn violations(
+        &self,
+        s: &CargoManifest,
+        policy: &Policy,
+        context: &PolicyContext,
+        package: &str,
+        version: &str,
+    ) -> Vec<PolicyViolation> {
+        let root = s.root_path.parent().unwrap_or_else(|| Path::new(""));
+        policy.check(
+            context,
+            package,
+            s.package_version(package).as_deref(),
+            version,
+            |file| self.fs.exists(&root.join(file)).unwrap_or(false),
+        )
+    }
+
+    /// Fail if any of `writes` edits the manifest of a protected crate.
+    fn check_protected(
+        &self,
+        s: &CargoManifest,
+        writes: &[(PathBuf, String)],
+    ) -> anyhow::Result<()> {
+        let Some((policy, context)) = &self.policy else {
+            return Ok(());
+        };
+
+        let mut violations = writes
+            .iter()
+            .filter_map(|(path, _)| {
+                let package = s.manifest(path)?.package.as_ref()?;
+                policy.check_write(context, &package.name, path)
+            })
+            .collect::<Vec<_>>();
+        for (path, contents) in writes {
+            let Some(source) = s.sources.get(path) else {
+                continue;
+            };
+            let (Ok(old), Ok(new)) = (Manifest::from_str(source), Manifest::from_str(contents))
+            else {
+                continue;
+            };
+            violations.extend(
+                changed_dependencies(&old, &new)
+                    .iter()
+                    .filter_map(|name| policy.check_dependency_write(context, name, path)),
+            );
+        }
+        if !violations.is_empty() {
+            return Err(PolicyError { violations }.into());
+        }
+
+        Ok(())
+    }
+
+    fn check_cancelled(&self) -> anyhow::Result<()> {
+        if let Some(token) = &self.cancellation {
+            token.check()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load_manifest(&self, path: &Path) -> crate::Result<CargoManifest> {
+        let path = &normalize(path);
+        let mut stats = Stats::default();
+        let (manifest, source) = self.load_cargo(path, &mut stats)?;
+        let mut s = CargoManifest::new(path.to_path_buf(), manifest);
+        s.sources.insert(path.to_path_buf(), source);
+        s.stats = stats;
+
+        self.load_children(&mut s)?;
+        let index_start = Instant::now();
+        s.reindex();
+        s.stats.resolve += index_start.elapsed();
+
+        Ok(s)
+    }
+
+    fn load_cargo(&self, path: &Path, stats: &mut Stats) -> anyhow::Result<(Manifest, Arc<str>)> {
+        self.check_cancelled()?;
+
+        Stats::time(&mut stats.load, || {
+            let content = self
+                .fs
+                .read(path)
+                .with_context(|| format!("failed to read {}", display_path(path)))?;
+            stats.files_read += 1;
+
+            let content = String::from_utf8(content)
+                .with_context(|| format!("failed to parse {}", display_path(path)))?;
+            let manifest = diagnostic::parse_manifest(path, &content)?;
+
+            Ok((manifest, content.into()))
+        })
+    }
+
+    fn load_children(&self, s: &mut CargoManifest) -> anyhow::Result<()> {
+        if let Some(workspace) = &s.root_manifest.workspace {
+            // The `members` entry, and the manifest declaring it, each member
+            // was found through, for errors about members that don't load.
+            let mut entries = BTreeMap::new();
+            let member_paths = Stats::time(&mut s.stats.resolve, || {
+                let (mut paths, defaults) = match self.discovery {
+                    Discovery::Manifest => (
+                        member_manifest_paths(&self.fs, &s.root_path, workspace)?
+                            .into_iter()
+                            .map(|(path, entry)| {
+                                entries.insert(path.clone(), (entry, s.root_path.clone()));
+                                path
+                            })
+                            .collect(),
+                        match self.default_members_only {
+                            true => {
+                                default_member_manifest_paths(&self.fs, &s.root_path, workspace)?
+                            }
+                            false => None,
+                        },
+                    ),
+                    Discovery::Cargo => {
+                        let members = metadata::cargo_members(&s.root_path)?;
+                        (members.members, members.default_members)
+                    }
+                };
+                if let Some(defaults) = defaults.filter(|_| self.default_members_only) {
+                    paths.retain(|path| defaults.contains(path));
+                }
+                anyhow::Ok(paths)
+            })?;
+
+            // Symlinked members are kept under the path they are declared
+            // with; one that resolves to an already loaded manifest is the
+            // same crate and is skipped. That also ends cycles between
+            // nested workspaces.
+            let root_target = self
+                .fs
+                .canonicalize(&s.root_path)
+                .map(|path| normalize(&path))
+                .with_context(|| format!("failed to resolve {}", display_path(&s.root_path)))?;
+            let root_dir = root_target.parent().unwrap_or_else(|| Path::new(""));
+
+            let mut members = BTreeMap::new();
+            let mut resolved: BTreeMap<PathBuf, PathBuf> =
+                BTreeMap::from([(root_target.clone(), s.root_path.clone())]);
+            let mut queue = member_paths;
+            while !queue.is_empty() {
+                let mut batch = Vec::new();
+                for member_path in std::mem::take(&mut queue) {
+                    let target = self
+                        .fs
+                        .canonicalize(&member_path)
+                        .map(|path| normalize(&path))
+                        .unwrap_or_else(|_| member_path.clone());
+                    if let Some(first) = resolved.get(&target) {
+                        tracing::debug!(
+                            member = display_path(&member_path),
+                            same_as = display_path(first),
+                            "skipping member that resolves to an already loaded manifest"
+                        );
+                        continue;
+                    }
+                    resolved.insert(target.clone(), member_path.clone());
+                    if !is_within(&target, root_dir) {
+                        s.external.insert(member_path.clone(), target);
+                    }
+                    batch.push(member_path);
+                }
+
+                let load_start = Instant::now();
+                let loaded = parallel::map_bounded(&batch, self.load_concurrency, |path| {
+                    let mut stats = Stats::default();
+                    let result = self.load_cargo(path, &mut stats);
+                    (stats.files_read, result)
+                });
+                s.stats.load += load_start.elapsed();
+
+                for (member_path, (files_read, result)) in batch.into_iter().zip(loaded) {
+                    s.stats.files_read += files_read;
+                    match result {
+                        Ok((manifest, source)) => {
+                            // A member that is a workspace itself brings its
+                            // own members along.
+                            if let Some(workspace) = manifest
+                                .workspace
+                                .as_ref()
+                                .filter(|_| self.discovery == Discovery::Manifest)
+                            {
+                                let nested =
+                                    member_manifest_paths(&self.fs, &member_path, workspace)?;
+                                for (path, entry) in nested {
+                                    entries.insert(path.clone(), (entry, member_path.clone()));
+                                    queue.push(path);
+                                }
+                            }
+                            s.sources.insert(member_path.clone(), source);
+                            members.insert(member_path, Arc::new(manifest));
+                        }
+                        Err(e) if self.lenient && !e.is::<Cancelled>() => {
+                            s.failures.push(LoadFailure {
+                                path: member_path,
+                                error: format!("{:#}", e.root_cause()),
+                            });
+                        }
+                        Err(e) => {
+                            return Err(match entries.get(&member_path) {
+                                Some((entry, declared_in)) => e.context(format!(
+                                    "failed to load the workspace member `{entry}` of {}",
+                                    display_path(declared_in)
+                                )),
+                                None => e,
+                            })
+                        }
+                    }
+                }
+            }
+
+            s.stats.members = members.len();
+            if !members.is_empty() {
+                s.members = Some(members);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set every crate matching `packages`, a name or a [`CrateMatcher`], to
+    /// `version`, updating every dependency on them. Returns the crates that
+    /// matched.
+    pub fn update_version(
+        &self,
+        s: &mut CargoManifest,
+        packages: impl Into<CrateMatcher>,
+        version: impl Into<String>,
+    ) -> crate::Result<Vec<String>> {
+        let version = version.into();
+        let matched = s.matching_packages(&packages.into())?;
+        let updates = matched
+            .iter()
+            .map(|package| (package.as_str(), version.as_str()))
+            .collect::<Vec<_>>();
+        self.update_versions(s, &updates)?;
+
+        Ok(matched)
+    }
+
+    /// Set `[workspace.package] version` to `version`, and with it every
+    /// member inheriting it, updating every dependency on them. Returns the
+    /// inheriting members.
+    pub fn update_workspace_version(
+        &self,
+        s: &mut CargoManifest,
+        version: impl Into<String>,
+    ) -> crate::Result<Vec<String>> {
+        let version = version.into();
+        if s.workspace_version().is_none() {
+            crate::error::bail!(
+                "{} has no `[workspace.package]` version to set",
+                display_path(&s.root_path)
+            );
+        }
+
+        let inheritors = s.version_inheritors();
+        if inheritors.is_empty() {
+            version::validate("workspace.package.version", &version)?;
+            let edits = BTreeMap::from([(
+                s.root_path.clone(),
+                vec![ValueEdit::new(
+                    ["workspace", "package", "version"],
+                    &version,
+                )],
+            )]);
+            let writes = self.render_all(s, edits)?;
+            self.write_manifests(s, writes)?;
+            let root = s
+                .root_manifest
+                .workspace
+                .as_mut()
+                .and_then(|w| w.package.as_mut());
+            if let Some(package) = root {
+                package.version = Some(version);
+            }
+            return Ok(inheritors);
+        }
+
+        let updates = inheritors
+            .iter()
+            .map(|package| (package.as_str(), version.as_str()))
+            .collect::<Vec<_>>();
+        self.update_versions(s, &updates)?;
+
+        Ok(inheritors)
+    }
+
+    /// The version `update_workspace_version` would set for a `level` bump
+    /// of `[workspace.package] version`.
+    pub fn bumped_workspace_version(
+        &self,
+        s: &CargoManifest,
+        level: BumpLevel,
+        label: Option<&str>,
+    ) -> crate::Result<String> {
+        let current = s.workspace_version().with_context(|| {
+            format!(
+                "{} has no `[workspace.package]` version to bump",
+                display_path(&s.root_path)
+            )
+        })?;
+        let current = semver::Version::parse(current).with_context(|| {
+            format!("`workspace.package.version` is {current}, which can't be bumped")
+        })?;
+
+        Ok(level.apply(&current, label)?.to_string())
+    }
+
+    /// Set several crates to new versions, writing each manifest at most once.
+    ///
+    /// For every `(package, version)` pair the package's own version is
+    /// updated, as is every dependency declaration naming it. Manifests are
+    /// written in path order, and if writes fail the first failing path in
+    /// that order is reported, independent of platform or write concurrency.
+    ///
+    /// A package inheriting its version gets it set in
+    /// `[workspace.package]`, which moves every other inheriting member to
+    /// it as well.
+    pub fn update_versions<'s, P: AsRef<str>, V: AsRef<str>>(
+        &self,
+        s: &'s mut CargoManifest,
+        updates: &[(P, V)],
+    ) -> crate::Result<&'s mut CargoManifest> {
+        let updates = with_inheritors(s, updates)?;
+        let updates = updates.as_slice();
+        self.check_versions(s, updates)?;
+        self.check_found(s, updates)?;
+        self.check_published(s, updates)?;
+        self.check_policy(s, updates)?;
+
+        let compute_start = Instant::now();
+        let writes = self.compute_version_updates(s, updates)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(s)
+    }
+
+    /// Set every crate listed in `file` to its target version in one
+    /// `update_versions` pass. Bump levels are applied to the crate's current
+    /// version.
+    ///
+    /// Returns the `(crate, version)` pairs that were applied, in name order.
+    pub fn apply_versions(
+        &self,
+        s: &mut CargoManifest,
+        file: &VersionsFile,
+    ) -> crate::Result<Vec<(String, String)>> {
+        let updates = self.resolve_versions(s, file)?;
+        self.update_versions(s, &updates)?;
+
+        Ok(updates)
+    }
+
+    /// The `(crate, version)` pairs `apply_versions` would apply for `file`.
+    pub fn resolve_versions(
+        &self,
+        s: &CargoManifest,
+        file: &VersionsFile,
+    ) -> crate::Result<Vec<(String, String)>> {
+        let updates = file
+            .targets
+            .iter()
+            .map(|(name, target)| {
+                let version = match target {
+                    VersionTarget::Exact(version) => version.to_string(),
+                    VersionTarget::Bump(level) => self.bumped_version(s, name, *level, None)?,
+                };
+                Ok((name.clone(), version))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let missing = updates
+            .iter()
+            .filter(|(name, _)| s.index.packages(name).is_empty())
+            .map(|(name, _)| format!("`{name}`"))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            crate::error::bail!(
+                "{} not a package of the workspace, nothing was changed",
+                match missing.as_slice() {
+                    [name] => format!("{name} is"),
+                    names => format!("{} are", names.join(", ")),
+                }
+            );
+        }
+
+        Ok(updates)
+    }
+
+    /// Bump every package of the workspace by `level`, then give the result
+    /// the pre-release `pre` if set, e.g. `1.4.0` to `1.5.0-dev` to start the
+    /// next development cycle after a release.
+    ///
+    /// Returns the `(crate, version)` pairs that were applied, in name order.
+    pub fn bump_workspace(
+        &self,
+        s: &mut CargoManifest,
+        level: BumpLevel,
+        pre: Option<&str>,
+    ) -> crate::Result<Vec<(String, String)>> {
+        let updates = self.bumped_versions(s, level, pre)?;
+        self.update_versions(s, &updates)?;
+
+        Ok(updates)
+    }
+
+    /// Set every package of the workspace to one version, `to` or else the
+    /// highest version any of them is at, for workspaces released in
+    /// lockstep.
+    ///
+    /// Returns the `(crate, version)` pairs that were applied, in name order,
+    /// leaving out the crates already at the version.
+    pub fn align_versions(
+        &self,
+        s: &mut CargoManifest,
+        to: Option<&str>,
+    ) -> crate::Result<Vec<(String, String)>> {
+        let current = s.package_versions();
+        let version = match to {
+            Some(version) => version.to_string(),
+            None => current
+                .iter()
+                .filter_map(|(_, version)| semver::Version::parse(version.as_deref()?).ok())
+                .max()
+                .context("no package of the workspace has a valid version to align on")?
+                .to_string(),
+        };
+
+        let updates = current
+            .into_iter()
+            .filter(|(_, current)| current.as_deref() != Some(version.as_str()))
+            .map(|(name, _)| (name, version.clone()))
+            .collect::<Vec<_>>();
+        self.update_versions(s, &updates)?;
+
+        Ok(updates)
+    }
+
+    /// Bump `package` by `level`, updating every dependency on it like
+    /// `update_version`. Returns the new version. `label` is the
+    /// pre-release label, see [`BumpLevel::apply`].
+    pub fn bump_version(
+        &self,
+        s: &mut CargoManifest,
+        package: &str,
+        level: BumpLevel,
+        label: Option<&str>,
+    ) -> crate::Result<String> {
+        let version = self.bumped_version(s, package, level, label)?;
+        self.update_version(s, package, version.clone())?;
+
+        Ok(version)
+    }
+
+    /// The version `bump_version` would set `package` to.
+    pub fn bumped_version(
+        &self,
+        s: &CargoManifest,
+        package: &str,
+        level: BumpLevel,
+        label: Option<&str>,
+    ) -> crate::Result<String> {
+        let current = s
+            .package_version(package)
+            .with_context(|| format!("`{package}` is not a package of the workspace"))?;
+        let current = semver::Version::parse(&current)
+            .with_context(|| format!("`{package}` is at {current}, which can't be bumped"))?;
+
+        Ok(level.apply(&current, label)?.to_string())
+    }
+
+    /// `updates` followed by every member depending on one of them,
+    /// directly or through other members, with a `level` bump of its current
+    /// version so it can be published again. The dependencies that count are
+    /// those of [`CargoManifest::publish_order`]; members moving along with
+    /// `workspace.package.version` get the version of the member they follow.
+    pub fn cascaded_versions<P: AsRef<str>, V: AsRef<str>>(
+        &self,
+        s: &CargoManifest,
+        updates: &[(P, V)],
+        level: BumpLevel,
+    ) -> crate::Result<Vec<(String, String)>> {
+        let mut all = with_inheritors(s, updates)?;
+        for member in s.publish_order()? {
+            let changed = |name: &String| all.iter().any(|(package, _)| package == name);
+            if changed(&member.name) || !member.dependencies.iter().any(changed) {
+                continue;
+            }
+            let version = self.bumped_version(s, &member.name, level, None)?;
+            all = with_inheritors(s, &[all, vec![(member.name, version)]].concat())?;
+        }
+
+        Ok(all)
+    }
+
+    /// The release of `package` on `registry` that `latest` picks, e.g. the
+    /// newest `1.x` for `latest-minor` when `package` is at 1.2.0.
+    pub fn latest_version(
+        &self,
+        s: &CargoManifest,
+        package: &str,
+        latest: Latest,
+        registry: &impl Registry,
+    ) -> crate::Result<String> {
+        let current = s
+            .package_version(package)
+            .with_context(|| format!("`{package}` is not a package of the workspace"))?;
+        let current = semver::Version::parse(&current)
+            .with_context(|| format!("`{package}` is at {current}, which isn't a version"))?;
+        let versions = registry
+            .versions(package)
+            .with_context(|| format!("failed to look up {package}"))?;
+
+        Ok(latest
+            .select(&current, &versions)
+            .map(|version| version.to_string())
+            .with_context(|| format!("no release of {package} matches `{latest}`"))?)
+    }
+
+    /// The `(crate, version)` pairs `bump_workspace` would apply.
+    pub fn bumped_versions(
+        &self,
+        s: &CargoManifest,
+        level: BumpLevel,
+        pre: Option<&str>,
+    ) -> crate::Result<Vec<(String, String)>> {
+        let pre = pre
+            .map(semver::Prerelease::new)
+            .transpose()
+            .context("invalid pre-release")?;
+
+        let updates = s
+            .index
+            .package_names()
+            .map(|name| {
+                let current = s
+                    .package_version(name)
+                    .with_context(|| format!("`{name}` has no version"))?;
+                let current = semver::Version::parse(&current)
+                    .with_context(|| format!("`{name}` is at {current}, which can't be bumped"))?;
+
+                let mut version = level.apply(&current, None)?;
+                if let Some(pre) = &pre {
+                    version.pre = pre.clone();
+                }
+                Ok((name.to_string(), version.to_string()))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(updates)
+    }
+
+    /// Why setting `package` to `version` would change what it changes.
+    /// `reasons` says how the caller chose the version; the policy verdict
+    /// and the edited declarations are added.
+    pub fn explain(
+        &self,
+        s: &CargoManifest,
+        package: &str,
+        version: &str,
+        reasons: Vec<String>,
+    ) -> Explanation {
+        let violations = match &self.policy {
+            Some((policy, context)) => self.violations(s, policy, context, package, version),
+            None => Vec::new(),
+        };
+
+        let edits = version_keys(s, package)
+            .into_iter()
+            .map(|(path, key, reason)| ExplainedEdit {
+                path,
+                key: key.join("."),
+                reason,
+            })
+            .collect();
+
+        Explanation {
+            package: package.to_string(),
+            from: s.package_version(package),
+            to: version.to_string(),
+            reasons,
+            violations,
+            edits,
+        }
+    }
+
+    /// The values changing `packages` edited between `before` and `after`, the
+    /// same workspace loaded before and after the change.
+    pub fn change_report(
+        &self,
+        before: &CargoManifest,
+        after: &CargoManifest,
+        packages: &[String],
+    ) -> crate::Result<ChangeReport> {
+        let mut files: BTreeMap<PathBuf, Vec<Change>> = BTreeMap::new();
+        for package in packages {
+            for (path, key, _) in version_keys(before, package) {
+                let value = |s: &CargoManifest| match s.sources.get(&path) {
+                    Some(source) => patch::get_str(source, &key)
+                        .with_context(|| format!("failed to parse {}", display_path(&path))),
+                    None => Ok(None),
+                };
+                let from = value(before)?;
+                let Some(to) = value(after)?.filter(|to| Some(to) != from.as_ref()) else {
+                    continue;
+                };
+                let inherited_by = if key.first().is_some_and(|k| k == "workspace") {
+                    after.inheriting_members(package)
+                } else {
+                    Vec::new()
+                };
+                files.entry(path.clone()).or_default().push(Change {
+                    package: package.clone(),
+                    key,
+                    from,
+                    to,
+                    inherited_by,
+                });
+            }
+        }
+
+        Ok(ChangeReport {
+            root: after.root_path.clone(),
+            files: files
+                .into_iter()
+                .map(|(path, changes)| ChangedFile { path, changes })
+                .collect(),
+        })
+    }
+
+    /// Write the `artifacts` describing the workspace's current versions.
+    /// Relative artifact paths are taken from the workspace root.
+    ///
+    /// Returns the paths written.
+    pub fn write_artifacts(
+        &self,
+        s: &CargoManifest,
+        artifacts: &Artifacts,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let versions = s
+            .package_versions()
+            .into_iter()
+            .filter_map(|(name, version)| Some((name, version?)))
+            .collect::<BTreeMap<_, _>>();
+        let shared = versions.values().collect::<std::collections::BTreeSet<_>>();
+        let version = match shared.into_iter().collect::<Vec<_>>().as_slice() {
+            [version] => Some(version.as_str()),
+            _ => None,
+        };
+
+        let root = s.root_path.parent().unwrap_or_else(|| Path::new(""));
+        let mut written = Vec::new();
+        for (path, contents) in artifacts.render(version, &versions)? {
+            let path = normalize(&root.join(path));
+            self.fs
+                .write(&path, contents.into_bytes())
+                .with_context(|| format!("failed to write {}", display_path(&path)))?;
+            written.push(path);
+        }
+
+        Ok(written)
+    }
+
+    /// Bring the members' entries in the `Cargo.lock` next to the root
+    /// manifest in line with their current versions, so the next build
+    /// doesn't rewrite it.
+    ///
+    /// Returns the lockfile's path if it was written; a workspace without
+    /// one has nothing to update.
+    pub fn update_lockfile(&self, s: &CargoManifest) -> crate::Result<Option<PathBuf>> {
+        let path = Lockfile::path_for(&s.root_path);
+        let contents = match self.fs.read(&path) {
+            Ok(contents) => String::from_utf8(contents)
+                .with_context(|| format!("failed to parse {}", display_path(&path)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", display_path(&path)))?,
+        };
+        let versions = s
+            .package_versions()
+            .into_iter()
+            .filter_map(|(name, version)| Some((name, version?)))
+            .collect::<BTreeMap<_, _>>();
+
+        let Some(contents) = lockfile::set_member_versions(&contents, &versions)
+            .with_context(|| format!("failed to parse {}", display_path(&path)))?
+        else {
+            return Ok(None);
+        };
+        self.fs
+            .write(&path, contents.into_bytes())
+            .with_context(|| format!("failed to write {}", display_path(&path)))?;
+
+        Ok(Some(path))
+    }
+
+    /// Add `section`, see [`changelog_section`](crate::changelog_section),
+    /// to the `CHANGELOG.md` next to the manifest of `package`, starting one
+    /// if there is none.
+    ///
+    /// Returns the changelog's path.
+    pub fn write_changelog(
+        &self,
+        s: &CargoManifest,
+        package: &str,
+        section: &str,
+    ) -> crate::Result<PathBuf> {
+        let manifest = s
+            .package_manifest(package)
+            .with_context(|| format!("`{package}` is not a package of the workspace"))?;
+        let path = manifest
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(CHANGELOG_FILE);
+        let existing = match self.fs.read(&path) {
+            Ok(contents) => Some(
+                String::from_utf8(contents)
+                    .with_context(|| format!("failed to parse {}", display_path(&path)))?,
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", display_path(&path)))?,
+        };
+
+        let contents = changelog::insert_section(existing.as_deref(), section);
+        self.fs
+            .write(&path, contents.into_bytes())
+            .with_context(|| format!("failed to write {}", display_path(&path)))?;
+
+        Ok(path)
+    }
+
+    /// Record `info` and the current version of each of `config.crates` in
+    /// the way `config.format` selects.
+    ///
+    /// Returns the paths written.
+    pub fn write_build_info(
+        &self,
+        s: &mut CargoManifest,
+        config: &BuildInfoConfig,
+        info: &BuildInfo,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let mut targets = Vec::new();
+        for name in &config.crates {
+            let path = s
+                .index
+                .packages(name)
+                .first()
+                .with_context(|| format!("`{name}` is not a package of the workspace"))?
+                .clone();
+            let version = s
+                .package_version(name)
+                .with_context(|| format!("`{name}` has no version"))?;
+            targets.push((path, version));
+        }
+
+        match config.format {
+            BuildInfoFormat::Rust => {
+                let mut written = Vec::new();
+                for (manifest, version) in targets {
+                    let dir = manifest.parent().unwrap_or_else(|| Path::new(""));
+                    let path = normalize(&dir.join(&config.path));
+                    self.fs
+                        .write(&path, info.rust_source(&version).into_bytes())
+                        .with_context(|| format!("failed to write {}", display_path(&path)))?;
+                    written.push(path);
+                }
+
+                Ok(written)
+            }
+            BuildInfoFormat::Metadata => {
+                let compute_start = Instant::now();
+                let mut writes = Vec::new();
+                for (path, version) in targets {
+                    let source = s.sources.get(&path).with_context(|| {
+                        format!("{} was not loaded from disk", display_path(&path))
+                    })?;
+                    let contents = patch::set_table_strings(
+                        source,
+                        &["package", "metadata", "build-info"],
+                        &info.entries(&version),
+                    )
+                    .with_context(|| format!("failed to update {}", display_path(&path)))?;
+                    let contents = self.finish(&path, contents)?;
+                    if **source != contents {
+                        writes.push((path, contents));
+                    }
+                }
+                s.stats.compute += compute_start.elapsed();
+
+                let written = writes.iter().map(|(path, _)| path.clone()).collect();
+                self.write_manifests(s, writes)?;
+
+                Ok(written)
+            }
+        }
+    }
+
+    /// The text edits `action` makes to the manifests of the workspace,
+    /// without writing them.
+    pub fn code_action_edits(
+        &self,
+        s: &CargoManifest,
+        action: &CodeAction,
+    ) -> crate::Result<Vec<TextEdit>> {
+        let mut planned = s.clone();
+        let writes = match action {
+            CodeAction::Bump { package, level } => {
+                let current = s
+                    .package_version(package)
+                    .with_context(|| format!("`{package}` is not a package of the workspace"))?;
+                let current = semver::Version::parse(&current).with_context(|| {
+                    format!("`{package}` is at {current}, which can't be bumped")
+                })?;
+                let version = level.apply(&current, None)?.to_string();
+
+                self.compute_version_updates(&mut planned, &[(package, version)])?
+            }
+            CodeAction::Sync { manifest, key } => {
+                let location = s.location_at(manifest, key)?;
+                let drift = s
+                    .requirement_drift()
+                    .into_iter()
+                    .find(|d| d.location == *location)
+                    .with_context(|| format!("`{}` is already in sync", key.join(".")))?;
+                let edits = self
+                    .update_dependency(&mut planned, &drift.location, &drift.version)
+                    .into_iter()
+                    .collect::<Vec<_>>();
+
+                self.render_all(&planned, BTreeMap::from([(manifest.clone(), edits)]))?
+            }
+            CodeAction::Hoist { manifest, key } => {
+                let location = s.location_at(manifest, key)?;
+                let source = s.sources.get(manifest).with_context(|| {
+                    format!("{} was not loaded from disk", display_path(manifest))
+                })?;
+                let (member, declaration) = patch::inherit_dependency(source, key)
+                    .with_context(|| format!("failed to update {}", display_path(manifest)))?;
+
+                let root_source = if *manifest == s.root_path {
+                    member.as_str()
+                } else {
+                    s.sources.get(&s.root_path).with_context(|| {
+                        format!("{} was not loaded from disk", display_path(&s.root_path))
+                    })?
+                };
+                let root =
+                    patch::add_workspace_dependency(root_source, &location.name, declaration)
+                        .with_context(|| {
+                            format!("failed to update {}", display_path(&s.root_path))
+                        })?;
+
+                let mut writes = vec![(s.root_path.clone(), root)];
+                if *manifest != s.root_path {
+                    writes.push((manifest.clone(), member));
+                }
+                writes
+                    .into_iter()
+                    .map(|(path, contents)| Ok((path.clone(), self.finish(&path, contents)?)))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            }
+        };
+
+        Ok(writes
+            .into_iter()
+            .filter_map(|(path, contents)| {
+                let original = s.sources.get(&path).map(AsRef::as_ref).unwrap_or_default();
+                actions::text_edit(path, original, &contents)
+            })
+            .collect())
+    }
+
+    /// What running `operations` on `s` would change, without writing
+    /// anything or touching `s`. The operations check the policy as they
+    /// would when applied.
+    pub fn plan(&self, s: &CargoManifest, operations: &[Operation]) -> crate::Result<ChangeSet> {
+        let mut planned = s.clone();
+        planned.staged = Some(BTreeMap::new());
+        edit::run(self, &mut planned, operations)?;
+        let staged = planned.staged.take().unwrap_or_default();
+
+        let mut change_set = ChangeSet::default();
+        for (path, original) in staged {
+            let Some(contents) = planned.sources.get(&path) else {
+                continue;
+            };
+            if original.as_ref() == Some(contents) {
+                continue;
+            }
+            change_set
+                .edits
+                .extend(plan::value_changes(&path, original.as_deref(), contents)?);
+            change_set.writes.push(FileWrite {
+                path,
+                original: original.map(|original| original.to_string()),
+                contents: contents.to_string(),
+            });
+        }
+
+        Ok(change_set)
+    }
+
+    /// Write the manifests of `change_set`, planned with
+    /// [`plan`](Self::plan). Fails without writing anything if one of them
+    /// changed since it was planned.
+    ///
+    /// Returns the manifests written, in path order.
+    pub fn apply(
+        &self,
+        s: &mut CargoManifest,
+        change_set: &ChangeSet,
+    ) -> crate::Result<Vec<PathBuf>> {
+        for write in &change_set.writes {
+            if s.sources.get(&write.path).map(AsRef::as_ref) != write.original.as_deref() {
+                crate::error::bail!(
+                    "{} changed since the change set was planned, plan it again",
+                    display_path(&write.path)
+                );
+            }
+        }
+
+        let writes = change_set
+            .writes
+            .iter()
+            .map(|write| (write.path.clone(), write.contents.clone()))
+            .collect::<Vec<_>>();
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        reparse(s, &writes)?;
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// Run `edits` with every write held back, then write each manifest they
+    /// changed once, as a single update. Later edits see the changes of
+    /// earlier ones. If an edit fails nothing is written and the workspace is
+    /// restored.
+    ///
+    /// Returns the manifests written, in path order.
+    pub(crate) fn write_once(
+        &self,
+        s: &mut CargoManifest,
+        edits: impl FnOnce(&Self, &mut CargoManifest) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        s.staged = Some(BTreeMap::new());
+        let result = edits(self, s);
+        let staged = s.staged.take().unwrap_or_default();
+
+        let mut writes = Vec::new();
+        for (path, original) in staged {
+            let contents = match original {
+                Some(original) => s.sources.insert(path.clone(), original),
+                None => s.sources.remove(&path),
+            };
+            let contents = contents.map(|contents| contents.to_string());
+            if contents.as_deref() != s.sources.get(&path).map(|source| &**source) {
+                writes.extend(contents.map(|contents| (path, contents)));
+            }
+        }
+        if let Err(e) = result {
+            let originals = writes
+                .iter()
+                .filter_map(|(path, _)| Some((path.clone(), s.sources.get(path)?.to_string())))
+                .collect::<Vec<_>>();
+            reparse(s, &originals)?;
+            return Err(e);
+        }
+
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// Write the rendered `writes`, after checking that every one of them can
+    /// succeed. On partial failure the written manifests are restored with
+    /// atomic writes, otherwise (or if that fails) the resume state is saved.
+    fn write_manifests(
+        &self,
+        s: &mut CargoManifest,
+        mut writes: Vec<(PathBuf, String)>,
+    ) -> anyhow::Result<()> {
+        // Manifests that come out as they were read are left alone, so their
+        // mtimes and the git diff stay quiet.
+        writes.retain(|(path, contents)| {
+            let unchanged = s
+                .sources
+                .get(path)
+                .is_some_and(|source| **source == *contents);
+            if unchanged {
+                tracing::debug!("{} is unchanged, not writing it", display_path(path));
+            }
+            !unchanged
+        });
+        for (path, _) in &writes {
+            if let Some(target) = s.external.get(path) {
+                crate::error::bail!(
+                    "refusing to write {}: it resolves to {}, outside the workspace root",
+                    display_path(path),
+                    display_path(target)
+                );
+            }
+        }
+        if let Some(mut staged) = s.staged.take() {
+            for (path, contents) in writes {
+                staged
+                    .entry(path.clone())
+                    .or_insert_with(|| s.sources.get(&path).cloned());
+                s.sources.insert(path, contents.into());
+            }
+            s.staged = Some(staged);
+            return Ok(());
+        }
+        self.check_protected(s, &writes)?;
+        self.probe_writes(writes.iter().map(|(path, _)| path.as_path()))?;
+        let hooked = match self.hooks.is_empty() || writes.is_empty() {
+            true => None,
+            false => {
+                let change_set = hooked_changes(s, &writes)?;
+                self.hooks
+                    .run_pre(&s.root_path, &change_set)
+                    .context("a pre-hook refused the write, nothing was changed")?;
+                Some(change_set)
+            }
+        };
+
+        // Everything is serialized up front so a cancellation can't leave the
+        // workspace half-written.
+        self.check_cancelled()?;
+
+        let write_start = Instant::now();
+        let results = parallel::map_bounded(&writes, self.write_concurrency, |(path, contents)| {
+            self.fs
+                .write(path, contents.as_bytes().to_vec())
+                .with_context(|| format!("failed to write {}", display_path(path)))
+        });
+        s.stats.write += write_start.elapsed();
+
+        let mut state = ResumeState::default();
+        let mut first_error = None;
+        for ((path, contents), result) in writes.into_iter().zip(results) {
+            let write = FileWrite {
+                original: s.sources.get(&path).map(|source| source.to_string()),
+                path,
+                contents,
+            };
+            match result {
+                Ok(()) => {
+                    s.sources
+                        .insert(write.path.clone(), write.contents.as_str().into());
+                    s.written.insert(write.path.clone());
+                    s.stats.files_written += 1;
+                    state.completed.push(write);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    state.pending.push(write);
+                }
+            }
+        }
+
+        if let Some(e) = first_error {
+            if state.completed.is_empty() {
+                return Err(e);
+            }
+            if self.atomic {
+                let written = state.completed.len();
+                if self.roll_back(s, &mut state) {
+                    return Err(e.context(format!(
+                        "the update failed, restored the {written} manifest(s) already written"
+                    )));
+                }
+            }
+
+            let state_path = match state.save(&self.fs, &s.root_path) {
+                Ok(path) => Some(path),
+                Err(save_error) => {
+                    tracing::warn!(error = %format!("{save_error:#}"), "failed to save resume state");
+                    None
+                }
+            };
+            return Err(PartialWriteError {
+                state,
+                state_path,
+                source: e,
+            }
+            .into());
+        }
+        if let Some(change_set) = hooked {
+            self.hooks.run_post(&s.root_path, &change_set)?;
+        }
+
+        Ok(())
+    }
+
+    /// Restore the original contents of the `completed` writes of `state`,
+    /// moving each restored one to `pending`. Returns whether all of them
+    /// were; files an update created are removed.
+    fn roll_back(&self, s: &mut CargoManifest, state: &mut ResumeState) -> bool {
+        while let Some(write) = state.completed.pop() {
+            let restored = match &write.original {
+                Some(original) => self.fs.write(&write.path, original.as_bytes().to_vec()),
+                None => self.fs.remove(&write.path),
+            };
+            if let Err(e) = restored {
+                tracing::warn!(path = %display_path(&write.path), error = %e, "failed to restore");
+                state.completed.push(write);
+                return false;
+            }
+            s.written.remove(&write.path);
+            state.pending.push(write);
+        }
+
+        let originals = state
+            .pending
+            .iter()
+            .filter_map(|write| Some((write.path.clone(), write.original.clone()?)))
+            .collect::<Vec<_>>();
+        for (path, original) in &originals {
+            s.sources.insert(path.clone(), original.as_str().into());
+        }
+        if let Err(e) = reparse(s, &originals) {
+            tracing::warn!(error = %format!("{e:#}"), "failed to reload the restored manifests");
+        }
+
+        true
+    }
+
+    /// Detect and repair common hand-editing mistakes in the workspace rooted
+    /// at `root_manifest` and in its members.
+    ///
+    /// Every repair found is passed to `accept` together with its manifest;
+    /// only accepted repairs are applied. The root is repaired first so its
+    /// member list can be read. Returns the accepted repairs per manifest.
+    pub fn fix(
+        &self,
+        root_manifest: &Path,
+        mut accept: impl FnMut(&Path, &Repair) -> bool,
+    ) -> crate::Result<Vec<(PathBuf, Vec<Repair>)>> {
+        let root_manifest = normalize(root_manifest);
+        let mut fixed = Vec::new();
+        let mut queue = vec![root_manifest.clone()];
+        while let Some(path) = queue.pop() {
+            self.check_cancelled()?;
+            let source = self
+                .fs
+                .read(&path)
+                .with_context(|| format!("failed to read {}", display_path(&path)))?;
+            let source = String::from_utf8(source)
+                .with_context(|| format!("failed to parse {}", display_path(&path)))?;
+
+            let repairs = repair::diagnose(&source)
+                .into_iter()
+                .filter(|r| accept(&path, r))
+                .collect::<Vec<_>>();
+            let source = repair::apply(&source, &repairs);
+
+            if path == root_manifest {
+                let manifest = diagnostic::parse_manifest(&path, &source)?;
+                if let Some(workspace) = &manifest.workspace {
+                    let members = member_manifest_paths(&self.fs, &root_manifest, workspace)?;
+                    queue.extend(members.into_iter().rev().map(|(path, _)| path));
+                }
+            }
+
+            if !repairs.is_empty() {
+                self.probe_writes([path.as_path()])?;
+                self.fs
+                    .write(&path, source.into_bytes())
+                    .with_context(|| format!("failed to write {}", display_path(&path)))?;
+                fixed.push((path, repairs));
+            }
+        }
+
+        Ok(fixed)
+    }
+
+    /// Fail, listing every offender, if any of `paths` can't be written.
+    fn probe_writes<'p>(&self, paths: impl IntoIterator<Item = &'p Path>) -> anyhow::Result<()> {
+        let unwritable = paths
+            .into_iter()
+            .filter_map(|path| {
+                let e = self.fs.probe_write(path).err()?;
+                Some(format!("\n  {}: {e}", display_path(path)))
+            })
+            .collect::<Vec<_>>();
+        if !unwritable.is_empty() {
+            crate::error::bail!(
+                "{} manifest(s) can't be written, nothing was changed:{}",
+                unwritable.len(),
+                unwritable.concat()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Rewrite every drifted internal requirement (see
+    /// `CargoManifest::requirement_drift`) to the member's current version.
+    ///
+    /// Returns the requirements that were rewritten.
+    pub fn sync_versions(&self, s: &mut CargoManifest) -> crate::Result<Vec<RequirementDrift>> {
+        let compute_start = Instant::now();
+        let drift = s.requirement_drift();
+
+        let mut edits: BTreeMap<PathBuf, Vec<ValueEdit>> = BTreeMap::new();
+        for d in &drift {
+            if let Some(edit) = self.update_dependency(s, &d.location, &d.version) {
+                edits
+                    .entry(d.location.manifest.to_path_buf())
+                    .or_default()
+                    .push(edit);
+            }
+        }
+        let writes = self.render_all(s, edits)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(drift)
+    }
+
+    /// Pin dependency requirements to the exact versions in the workspace's
+    /// `Cargo.lock`, e.g. `1.2` to `=1.2.7`.
+    ///
+    /// Only dependencies on `crates` are pinned, or every dependency if it is
+    /// empty. Requirements that are already pinned, or that no locked version
+    /// meets, are left alone. Returns the rewritten requirements.
+    pub fn freeze<C: AsRef<str>>(
+        &self,
+        s: &mut CargoManifest,
+        crates: &[C],
+    ) -> crate::Result<Vec<RequirementChange>> {
+        let lockfile = Lockfile::load(&self.fs, &s.root_path)?;
+
+        Ok(self.rewrite_requirements(s, |package, requirement| {
+            if !crates.is_empty() && !crates.iter().any(|c| c.as_ref() == package) {
+                return None;
+            }
+            let Some(locked) = lockfile.resolve(package, requirement) else {
+                tracing::warn!(
+                    package,
+                    requirement,
+                    "no locked version meets the requirement, leaving it unpinned"
+                );
+                return None;
+            };
+
+            requirements::freeze(requirement, locked)
+        })?)
+    }
+
+    /// Raise dependency requirements to the versions in the workspace's
+    /// `Cargo.lock`, e.g. `1.2` to `1.2.7`, without consulting a registry.
+    ///
+    /// Only dependencies on `crates` are upgraded, or every dependency if it
+    /// is empty. Pinned, wildcard and multi-part requirements are left alone.
+    /// Returns the rewritten requirements.
+    pub fn upgrade<C: AsRef<str>>(
+        &self,
+        s: &mut CargoManifest,
+        crates: &[C],
+    ) -> crate::Result<Vec<RequirementChange>> {
+        let lockfile = Lockfile::load(&self.fs, &s.root_path)?;
+
+        Ok(self.rewrite_requirements(s, |package, requirement| {
+            if !crates.is_empty() && !crates.iter().any(|c| c.as_ref() == package) {
+                return None;
+            }
+
+            requirements::upgrade(requirement, lockfile.resolve(package, requirement)?)
+        })?)
+    }
+
+    /// Turn exact pins such as `=1.2.3` back into range requirements written
+    /// in `style`, reversing `freeze`.
+    ///
+    /// Only dependencies on `crates` are changed, or every dependency if it
+    /// is empty. Returns the rewritten requirements.
+    pub fn unfreeze<C: AsRef<str>>(
+        &self,
+        s: &mut CargoManifest,
+        crates: &[C],
+        style: UnfreezeStyle,
+    ) -> crate::Result<Vec<RequirementChange>> {
+        Ok(self.rewrite_requirements(s, |package, requirement| {
+            if !crates.is_empty() && !crates.iter().any(|c| c.as_ref() == package) {
+                return None;
+            }
+
+            requirements::unfreeze(requirement, style)
+        })?)
+    }
+
+    /// Replace the version requirement of every dependency declaration for
+    /// which `rewrite(package, requirement)` returns a new one.
+    fn rewrite_requirements(
+        &self,
+        s: &mut CargoManifest,
+        mut rewrite: impl FnMut(&str, &str) -> Option<String>,
+    ) -> anyhow::Result<Vec<RequirementChange>> {
+        let compute_start = Instant::now();
+
+        let mut changes = Vec::new();
+        for location in s.index.all_dependencies() {
+            let Some((package, requirement)) = s
+                .manifest(&location.manifest)
+                .and_then(|manifest| location.get(manifest))
+                .and_then(|dependency| declared_requirement(location, dependency))
+            else {
+                continue;
+            };
+
+            if let Some(to) = rewrite(package, requirement) {
+                if to != *requirement {
+                    changes.push(RequirementChange {
+                        location: location.clone(),
+                        from: requirement.to_string(),
+                        to,
+                    });
+                }
+            }
+        }
+
+        self.apply_requirement_changes(s, changes, compute_start)
+    }
+
+    /// Rewrite every declaration of each duplicate (see
+    /// `CargoManifest::duplicate_dependencies`) to its unified requirement.
+    /// Inherited declarations follow the workspace and are left alone.
+    ///
+    /// Returns the rewritten requirements.
+    pub fn unify_duplicate_dependencies(
+        &self,
+        s: &mut CargoManifest,
+    ) -> crate::Result<Vec<RequirementChange>> {
+        let compute_start = Instant::now();
+
+        let mut changes = Vec::new();
+        for duplicate in s.duplicate_dependencies() {
+            let unified = duplicate.unified().to_string();
+            for declaration in duplicate.declarations {
+                if !declaration.inherited && declaration.requirement != unified {
+                    changes.push(RequirementChange {
+                        location: declaration.location,
+                        from: declaration.requirement,
+                        to: unified.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(self.apply_requirement_changes(s, changes, compute_start)?)
+    }
+
+    fn apply_requirement_changes(
+        &self,
+        s: &mut CargoManifest,
+        mut changes: Vec<RequirementChange>,
+        compute_start: Instant,
+    ) -> anyhow::Result<Vec<RequirementChange>> {
+        changes.sort_by(|a, b| a.location.cmp(&b.location));
+        let packages = changes
+            .iter()
+            .map(|change| {
+                s.manifest(&change.location.manifest)
+                    .and_then(|manifest| change.location.get(manifest))
+                    .and_then(|dependency| declared_requirement(&change.location, dependency))
+                    .map_or(change.location.name.to_string(), |(package, _)| {
+                        package.to_string()
+                    })
+            })
+            .collect::<Vec<_>>();
+        self.check_banned(changes.iter().zip(&packages).map(|(change, package)| {
+            (package.as_str(), Some(&change.location), change.to.as_str())
+        }))?;
+
+        let mut edits: BTreeMap<PathBuf, Vec<ValueEdit>> = BTreeMap::new();
+        for change in &changes {
+            if let Some(edit) = self.update_dependency(s, &change.location, &change.to) {
+                edits
+                    .entry(change.location.manifest.to_path_buf())
+                    .or_default()
+                    .push(edit);
+            }
+        }
+        let writes = self.render_all(s, edits)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(changes)
+    }
+
+    /// Format every manifest of the workspace with the service's style (see
+    /// `with_format`), or the default style if none is set.
+    ///
+    /// Returns the manifests whose formatting changed.
+    pub fn normalize(&self, s: &mut CargoManifest) -> crate::Result<Vec<PathBuf>> {
+        let compute_start = Instant::now();
+        let style = self.format.clone().unwrap_or_default();
+
+        let mut writes = Vec::new();
+        for (path, source) in &s.sources {
+            let formatted = format::format(source, &style)
+                .with_context(|| format!("failed to format {}", display_path(path)))?;
+            if formatted != **source {
+                writes.push((path.clone(), formatted));
+            }
+        }
+        s.stats.compute += compute_start.elapsed();
+
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// Remove the `[workspace.dependencies]` entries no manifest inherits (see
+    /// `CargoManifest::unused_workspace_dependencies`).
+    ///
+    /// Returns the names of the removed entries.
+    pub fn remove_unused_workspace_dependencies(
+        &self,
+        s: &mut CargoManifest,
+    ) -> crate::Result<Vec<String>> {
+        let unused = s.unused_workspace_dependencies();
+        if unused.is_empty() {
+            return Ok(unused);
+        }
+
+        let root_path = s.root_path.clone();
+        let source = s
+            .sources
+            .get(&root_path)
+            .with_context(|| format!("{} was not loaded from disk", display_path(&root_path)))?;
+        let keys = unused
+            .iter()
+            .map(|name| vec!["workspace".into(), "dependencies".into(), name.clone()])
+            .collect::<Vec<_>>();
+        let contents = patch::remove_keys(source, &keys)
+            .with_context(|| format!("failed to update {}", display_path(&root_path)))?;
+        let contents = self.finish(&root_path, contents)?;
+
+        if let Some(workspace) = &mut s.root_manifest.workspace {
+            for name in &unused {
+                workspace.dependencies.remove(name);
+            }
+        }
+        s.reindex();
+
+        self.write_manifests(s, vec![(root_path, contents)])?;
+
+        Ok(unused)
+    }
+
+    /// Set the Rust edition of the root manifest, or with `members` of the
+    /// root and every member. `[workspace.package]` is updated too when it
+    /// sets an edition; packages inheriting theirs are left alone.
+    ///
+    /// Returns the manifests that changed.
+    pub fn set_edition(
+        &self,
+        s: &mut CargoManifest,
+        edition: &str,
+        members: bool,
+    ) -> crate::Result<Vec<PathBuf>> {
+        if !EDITIONS.contains(&edition) {
+            crate::error::bail!(
+                "`{edition}` is not a Rust edition, use one of {}",
+                EDITIONS.join(", ")
+            );
+        }
+
+        Ok(self.set_package_field(s, "edition", edition, members)?)
+    }
+
+    /// Set the minimum supported Rust version, `package.rust-version`, like
+    /// `set_edition` sets the edition.
+    ///
+    /// Returns the manifests that changed.
+    pub fn set_rust_version(
+        &self,
+        s: &mut CargoManifest,
+        rust_version: &str,
+        members: bool,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let parts = rust_version.split('.').collect::<Vec<_>>();
+        let numeric = parts
+            .iter()
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()));
+        if !(2..=3).contains(&parts.len()) || !numeric {
+            crate::error::bail!(
+                "`{rust_version}` is not a Rust version, use e.g. `1.70` or `1.70.0`"
+            );
+        }
+
+        Ok(self.set_package_field(s, "rust-version", rust_version, members)?)
+    }
+
+    /// Set `settings`, each a key of `[profile.<profile>]` and its value as
+    /// given on the command line, e.g. `("lto", "thin")`, in the root
+    /// manifest. Keys and values are checked against what cargo accepts, and
+    /// a custom profile needs `inherits`, set before or among `settings`.
+    ///
+    /// Returns whether the manifest changed.
+    pub fn set_profile(
+        &self,
+        s: &mut CargoManifest,
+        profile: &str,
+        settings: &[(String, String)],
+    ) -> crate::Result<bool> {
+        profile::check_name(profile)?;
+        let values = settings
+            .iter()
+            .map(|(key, value)| Ok((key.as_str(), profile::setting_value(key, value)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let compute_start = Instant::now();
+        let root = s.root_path.clone();
+        let source = s
+            .sources
+            .get(&root)
+            .with_context(|| format!("{} was not loaded from disk", display_path(&root)))?;
+        let table = ["profile".to_string(), profile.to_string()];
+        let inherits = [table[0].clone(), table[1].clone(), "inherits".to_string()];
+        let custom = !profile::BUILTIN_PROFILES.contains(&profile);
+        if custom
+            && !patch::contains_key(source, &inherits)?
+            && values.iter().all(|(key, _)| *key != "inherits")
+        {
+            crate::error::bail!(
+                "the custom profile `{profile}` needs `inherits`, e.g. `inherits=release`"
+            );
+        }
+        if !custom && values.iter().any(|(key, _)| *key == "inherits") {
+            crate::error::bail!("the built-in profile `{profile}` can't inherit from another");
+        }
+
+        let mut contents = source.to_string();
+        for (key, value) in values {
+            let key = [&table[..], &[key.to_string()]].concat();
+            contents = patch::set_value(&contents, &key, value)
+                .with_context(|| format!("failed to update {}", display_path(&root)))?;
+        }
+        let contents = self.finish(&root, contents)?;
+        let changed = **source != contents;
+        let writes = if changed {
+            vec![(root, contents)]
+        } else {
+            Vec::new()
+        };
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(changed)
+    }
+
+    /// Set the levels of `settings` in `[workspace.lints]` of the root
+    /// manifest, replacing the level a lint had.
+    ///
+    /// Returns whether the manifest changed.
+    pub fn set_workspace_lints(
+        &self,
+        s: &mut CargoManifest,
+        settings: &[LintSetting],
+    ) -> crate::Result<bool> {
+        let root = s.root_path.clone();
+        if s.root_manifest.workspace.is_none() {
+            crate::error::bail!("{} is not a workspace", display_path(&root));
+        }
+
+        let compute_start = Instant::now();
+        let source = s
+            .sources
+            .get(&root)
+            .with_context(|| format!("{} was not loaded from disk", display_path(&root)))?;
+        let mut contents = source.to_string();
+        for setting in settings {
+            let key = ["workspace", "lints", &setting.tool, &setting.lint].map(String::from);
+            contents = patch::set_value(&contents, &key, setting.to_value())
+                .with_context(|| format!("failed to update {}", display_path(&root)))?;
+        }
+        let contents = self.finish(&root, contents)?;
+        let changed = **source != contents;
+        let writes = if changed {
+            vec![(root, contents)]
+        } else {
+            Vec::new()
+        };
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(changed)
+    }
+
+    /// Make every member package inherit `[workspace.lints]` with
+    /// `lints.workspace = true`, except members with lints of their own.
+    pub fn inherit_workspace_lints(&self, s: &mut CargoManifest) -> crate::Result<LintInheritance> {
+        let lints = vec!["lints".to_string()];
+        let workspace = vec!["lints".to_string(), "workspace".to_string()];
+
+        let compute_start = Instant::now();
+        let mut inheritance = LintInheritance::default();
+        let mut writes = Vec::new();
+        for (path, manifest) in s.members() {
+            let Some(source) = s.sources.get(path) else {
+                continue;
+            };
+            if manifest.package.is_none() || patch::is_inherited(source, &lints)? {
+                continue;
+            }
+            if patch::contains_key(source, &lints)? {
+                inheritance.own_lints.push(path.to_path_buf());
+                continue;
+            }
+
+            let contents = patch::set_value(source, &workspace, true.into())
+                .with_context(|| format!("failed to update {}", display_path(path)))?;
+            writes.push((path.to_path_buf(), self.finish(path, contents)?));
+            inheritance.inherited.push(path.to_path_buf());
+        }
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(inheritance)
+    }
+
+    /// Set the value at `key` in the root manifest, or with `members` in the
+    /// root and every member package. `[workspace]` keys are only set in the
+    /// root. The name and version have their own commands, which keep
+    /// dependencies in line, and are refused.
+    ///
+    /// Returns the manifests that changed.
+    pub fn set_key(
+        &self,
+        s: &mut CargoManifest,
+        key: &KeyPath,
+        value: &KeyValue,
+        members: bool,
+    ) -> crate::Result<Vec<PathBuf>> {
+        match key.parts() {
+            [package, field] if package == "package" && (field == "name" || field == "version") => {
+                crate::error::bail!("`{key}` can't be set directly, use `rename` or `set` instead")
+            }
+            _ => {}
+        }
+        let value = value.to_value()?;
+
+        let compute_start = Instant::now();
+        let paths = if members && !key.is_workspace() {
+            s.manifests()
+                .filter(|(path, manifest)| *path == s.root_path || manifest.package.is_some())
+                .map(|(path, _)| path.to_path_buf())
+                .collect()
+        } else {
+            vec![s.root_path.clone()]
+        };
+
+        let mut writes = Vec::new();
+        for path in paths {
+            let source = s
+                .sources
+                .get(&path)
+                .with_context(|| format!("{} was not loaded from disk", display_path(&path)))?;
+            let contents = patch::set_value(source, key.parts(), value.clone())
+                .with_context(|| format!("failed to update {}", display_path(&path)))?;
+            let contents = self.finish(&path, contents)?;
+            if **source != contents {
+                writes.push((path, contents));
+            }
+        }
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// Apply `edit` to the `metadata` table of `tool`, e.g. `docs.rs` for
+    /// `[package.metadata.docs.rs]`, in the manifests of `scope`. Keys the
+    /// edit doesn't name are kept, and tables a deletion leaves empty are
+    /// removed.
+    ///
+    /// Returns the manifests that changed.
+    pub fn edit_metadata(
+        &self,
+        s: &mut CargoManifest,
+        scope: MetadataScope,
+        tool: &KeyPath,
+        edit: &MetadataEdit,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let table = match scope {
+            MetadataScope::Packages => ["package", "metadata"],
+            MetadataScope::Workspace => ["workspace", "metadata"],
+        }
+        .into_iter()
+        .map(String::from)
+        .chain(tool.parts().iter().cloned())
+        .collect::<Vec<_>>();
+        let merged = match edit {
+            MetadataEdit::Merge(document) => document
+                .parse::<toml_edit::DocumentMut>()
+                .with_context(|| format!("`{document}` is not a TOML document"))?,
+            _ => toml_edit::DocumentMut::new(),
+        };
+        if let MetadataEdit::Set(_, value) = edit {
+            value.to_value()?;
+        }
+
+        let compute_start = Instant::now();
+        let paths = match scope {
+            MetadataScope::Packages => s
+                .manifests()
+                .filter(|(_, manifest)| manifest.package.is_some())
+                .map(|(path, _)| path.to_path_buf())
+                .collect(),
+            MetadataScope::Workspace => {
+                if s.root_manifest().workspace.is_none() {
+                    crate::error::bail!("{} is not a workspace", display_path(&s.root_path));
+                }
+                vec![s.root_path.clone()]
+            }
+        };
+
+        let mut writes = Vec::new();
+        for path in paths {
+            let source = s
+                .sources
+                .get(&path)
+                .with_context(|| format!("{} was not loaded from disk", display_path(&path)))?;
+            let contents = match edit {
+                MetadataEdit::Set(key, value) => {
+                    let key = [table.as_slice(), key.parts()].concat();
+                    patch::set_value(source, &key, value.to_value()?)
+                }
+                MetadataEdit::Merge(_) => patch::merge_table(source, &table, merged.as_table()),
+                MetadataEdit::Delete(key) => {
+                    let key = [table.as_slice(), key.as_ref().map_or(&[], KeyPath::parts)].concat();
+                    // The tool's tables and `metadata` go too once empty.
+                    let parents = (2..key.len())
+                        .rev()
+                        .map(|len| key[..len].to_vec())
+                        .collect::<Vec<_>>();
+                    patch::remove_keys(source, &[key])
+                        .and_then(|contents| patch::remove_empty_tables(&contents, &parents))
+                }
+            }
+            .with_context(|| format!("failed to update {}", display_path(&path)))?;
+            let contents = self.finish(&path, contents)?;
+            if **source != contents {
+                writes.push((path, contents));
+            }
+        }
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// Apply `edit` to the `[[bin]]` or `[lib]` target of the members
+    /// `packages` selects. Members without the binary, or without a library
+    /// for `[lib]` edits, are skipped, but at least one has to have it.
+    ///
+    /// Returns the manifests that changed.
+    pub fn edit_targets(
+        &self,
+        s: &mut CargoManifest,
+        packages: &CrateMatcher,
+        edit: &TargetEdit,
+    ) -> crate::Result<Vec<PathBuf>> {
+        edit.check()?;
+
+        let compute_start = Instant::now();
+        let mut members = Vec::new();
+        for package in s.matching_packages(packages)? {
+            let paths = s.index.packages(&package);
+            if paths.is_empty() {
+                crate::error::bail!("`{package}` is not a package of the workspace");
+            }
+            members.extend(paths.iter().map(|path| (path.clone(), package.clone())));
+        }
+
+        let mut found = false;
+        let mut writes = Vec::new();
+        for (path, package) in members {
+            let source = s
+                .sources
+                .get(&path)
+                .with_context(|| format!("{} was not loaded from disk", display_path(&path)))?;
+            let manifest = s
+                .manifest(&path)
+                .with_context(|| format!("{} is not part of the workspace", display_path(&path)))?;
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+            let contents = match (edit.bin(), edit.lib_value()) {
+                (Some(name), _) => {
+                    let Some(bin) = manifest
+                        .bin
+                        .iter()
+                        .find(|bin| bin.name.as_deref() == Some(name))
+                    else {
+                        continue;
+                    };
+                    self.edit_bin(&path, &package, manifest, bin, edit, source)?
+                }
+                (None, Some((key, value))) => {
+                    let has_lib = manifest.lib.is_some()
+                        || self.fs.exists(&dir.join("src/lib.rs")).unwrap_or(false);
+                    if !has_lib {
+                        continue;
+                    }
+                    patch::set_value(source, &["lib".to_string(), key.to_string()], value)
+                        .with_context(|| format!("failed to update {}", display_path(&path)))?
+                }
+                (None, None) => unreachable!("every target edit is for a binary or the library"),
+            };
+            found = true;
+
+            let contents = self.finish(&path, contents)?;
+            if **source != contents {
+                writes.push((path, contents));
+            }
+        }
+        if !found {
+            match edit.bin() {
+                Some(bin) => crate::error::bail!("no member has a `[[bin]]` named `{bin}`"),
+                None => crate::error::bail!("no member has a library"),
+            }
+        }
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// `source` with `edit` applied to the `[[bin]]` `bin` of `package`.
+    fn edit_bin(
+        &self,
+        path: &Path,
+        package: &str,
+        manifest: &Manifest,
+        bin: &cargo_toml::Product,
+        edit: &TargetEdit,
+        source: &str,
+    ) -> anyhow::Result<String> {
+        let name = bin.name.as_deref().unwrap_or_default();
+        let set = |source: &str, name: &str, key: &str, value: Option<toml_edit::Value>| {
+            patch::set_named_table_value(source, "bin", name, key, value)
+                .with_context(|| format!("failed to update {}", display_path(path)))?
+                .with_context(|| {
+                    format!(
+                        "{} declares `[[bin]]` `{name}` in a way that can't be edited",
+                        display_path(path)
+                    )
+                })
+        };
+
+        match edit {
+            TargetEdit::RenameBin { to, .. } => {
+                if manifest
+                    .bin
+                    .iter()
+                    .any(|bin| bin.name.as_deref() == Some(to))
+                {
+                    crate::error::bail!(
+                        "{} already has a `[[bin]]` named `{to}`",
+                        display_path(path)
+                    );
+                }
+                let contents = set(source, name, "name", Some(to.as_str().into()))?;
+                if bin.path.is_some() {
+                    return Ok(contents);
+                }
+
+                let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                let inferred = targets::inferred_bin_path(package, name, |file| {
+                    self.fs.exists(&dir.join(file)).unwrap_or(false)
+                });
+                match inferred {
+                    Some(file) => set(&contents, to, "path", Some(file.into())),
+                    None => Ok(contents),
+                }
+            }
+            TargetEdit::RequiredFeatures { features, .. } => {
+                let optional = |feature: &str| {
+                    manifest
+                        .dependencies
+                        .get(feature)
+                        .is_some_and(Dependency::optional)
+                };
+                let unknown = features.iter().find(|feature| {
+                    let feature = feature.split('/').next().unwrap_or_default();
+                    !manifest.features.contains_key(feature) && !optional(feature)
+                });
+                if let Some(unknown) = unknown {
+                    crate::error::bail!("`{unknown}` is not a feature of {package}");
+                }
+
+                let value = (!features.is_empty()).then(|| {
+                    toml_edit::Value::Array(
+                        features
+                            .iter()
+                            .map(String::as_str)
+                            .collect::<toml_edit::Array>(),
+                    )
+                });
+                set(source, name, "required-features", value)
+            }
+            TargetEdit::CrateType(_) | TargetEdit::Lib { .. } => {
+                unreachable!("library edits have no binary")
+            }
+        }
+    }
+
+    /// Set the string `field` of `[package]` in the root manifest, or with
+    /// `members` in the root and every member, and of `[workspace.package]`
+    /// where it's already set.
+    fn set_package_field(
+        &self,
+        s: &mut CargoManifest,
+        field: &str,
+        value: &str,
+        members: bool,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let compute_start = Instant::now();
+        let paths = if members {
+            s.manifests().map(|(path, _)| path.to_path_buf()).collect()
+        } else {
+            vec![s.root_path.clone()]
+        };
+
+        let mut writes = Vec::new();
+        for path in paths {
+            let (Some(manifest), Some(source)) = (s.manifest(&path), s.sources.get(&path)) else {
+                continue;
+            };
+            let mut edits = Vec::new();
+            let package_key = vec!["package".to_string(), field.to_string()];
+            if manifest.package.is_some() && !patch::is_inherited(source, &package_key)? {
+                edits.push(ValueEdit::new(package_key, value));
+            }
+            let workspace_key = vec!["workspace".to_string(), "package".to_string(), field.into()];
+            if path == s.root_path && patch::contains_key(source, &workspace_key)? {
+                edits.push(ValueEdit::new(workspace_key, value));
+            }
+
+            let contents = patch::apply_edits(source, &edits)
+                .with_context(|| format!("failed to update {}", display_path(&path)))?;
+            let contents = self.finish(&path, contents)?;
+            if **source != contents {
+                writes.push((path, contents));
+            }
+        }
+
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// Declare `dependency` in the manifest at `manifest`, replacing an
+    /// existing declaration under the same name in the same table.
+    pub fn add_dependency(
+        &self,
+        s: &mut CargoManifest,
+        manifest: &Path,
+        dependency: &NewDependency,
+    ) -> crate::Result<()> {
+        if dependency.table.kind == DependencyKind::Workspace && manifest != s.root_path {
+            crate::error::bail!(
+                "only the root manifest has `[workspace.dependencies]`, not {}",
+                display_path(manifest)
+            );
+        }
+        let location = DependencyLocation {
+            manifest: manifest.into(),
+            table: dependency.table.clone(),
+            name: dependency.name.as_str().into(),
+        };
+        self.check_banned([(
+            dependency.package.as_deref().unwrap_or(&dependency.name),
+            Some(&location),
+            dependency.requirement.as_str(),
+        )])?;
+
+        let source = s
+            .sources
+            .get(manifest)
+            .with_context(|| format!("{} was not loaded from disk", display_path(manifest)))?;
+        let contents = patch::set_dependency(
+            source,
+            &dependency.table.key(),
+            &dependency.name,
+            dependency.to_value(),
+            true,
+        )
+        .with_context(|| format!("failed to update {}", display_path(manifest)))?;
+        let contents = self.finish(manifest, contents)?;
+
+        let parsed = diagnostic::parse_manifest(manifest, &contents)?;
+        *s.manifest_mut(manifest).with_context(|| {
+            format!("{} is not part of the workspace", display_path(manifest))
+        })? = parsed;
+        s.reindex();
+
+        Ok(self.write_manifests(s, vec![(manifest.to_path_buf(), contents)])?)
+    }
+
+    /// Remove the dependency declared as `name` from those of `tables` of the
+    /// manifest at `manifest` that declare it.
+    ///
+    /// Returns the tables it was removed from.
+    pub fn remove_dependency(
+        &self,
+        s: &mut CargoManifest,
+        manifest: &Path,
+        name: &str,
+        tables: &[DependencyTable],
+    ) -> crate::Result<Vec<DependencyTable>> {
+        let model = s
+            .manifest(manifest)
+            .with_context(|| format!("{} is not part of the workspace", display_path(manifest)))?;
+        let declared = tables
+            .iter()
+            .filter(|table| table.get(model).is_some_and(|deps| deps.contains_key(name)))
+            .cloned()
+            .collect::<Vec<_>>();
+        if declared.is_empty() {
+            crate::error::bail!("`{name}` is not a dependency of {}", display_path(manifest));
+        }
+
+        let source = s
+            .sources
+            .get(manifest)
+            .with_context(|| format!("{} was not loaded from disk", display_path(manifest)))?;
+        let keys = declared
+            .iter()
+            .map(|table| {
+                let mut key = table.key();
+                key.push(name.to_string());
+                key
+            })
+            .collect::<Vec<_>>();
+        let contents = patch::remove_keys(source, &keys)
+            .with_context(|| format!("failed to update {}", display_path(manifest)))?;
+        let contents = self.finish(manifest, contents)?;
+
+        if let Some(model) = s.manifest_mut(manifest) {
+            for table in &declared {
+                if let Some(deps) = table.get_mut(model) {
+                    deps.remove(name);
+                }
+            }
+        }
+        s.reindex();
+
+        self.write_manifests(s, vec![(manifest.to_path_buf(), contents)])?;
+
+        Ok(declared)
+    }
+
+    /// Add `entry` to `[patch.<registry>]` of the root manifest, replacing
+    /// any patch of the same crate.
+    pub fn set_patch(&self, s: &mut CargoManifest, entry: &PatchEntry) -> crate::Result<()> {
+        let root = s.root_path.clone();
+        let source = s
+            .sources
+            .get(&root)
+            .with_context(|| format!("{} was not loaded from disk", display_path(&root)))?;
+        let table = vec!["patch".to_string(), entry.registry.clone()];
+        let contents =
+            patch::set_dependency(source, &table, &entry.name, entry.source.to_value(), true)
+                .with_context(|| format!("failed to update {}", display_path(&root)))?;
+        let contents = self.finish(&root, contents)?;
+
+        reparse(s, &[(root.clone(), contents.clone())])?;
+        Ok(self.write_manifests(s, vec![(root, contents)])?)
+    }
+
+    /// Remove the patch of `name` from `[patch.<registry>]` of the root
+    /// manifest, and the table if that empties it.
+    pub fn remove_patch(
+        &self,
+        s: &mut CargoManifest,
+        registry: &str,
+        name: &str,
+    ) -> crate::Result<()> {
+        let root = s.root_path.clone();
+        let patched = s
+            .root_manifest
+            .patch
+            .get(registry)
+            .is_some_and(|patches| patches.contains_key(name));
+        if !patched {
+            crate::error::bail!(
+                "`{name}` is not patched in `[patch.{registry}]` of {}",
+                display_path(&root)
+            );
+        }
+
+        let source = s
+            .sources
+            .get(&root)
+            .with_context(|| format!("{} was not loaded from disk", display_path(&root)))?;
+        let table = vec!["patch".to_string(), registry.to_string()];
+        let key = [table.clone(), vec![name.to_string()]].concat();
+        let contents = patch::remove_keys(source, &[key])
+            .and_then(|contents| {
+                patch::remove_empty_tables(&contents, &[table, vec!["patch".to_string()]])
+            })
+            .with_context(|| format!("failed to update {}", display_path(&root)))?;
+        let contents = self.finish(&root, contents)?;
+
+        reparse(s, &[(root.clone(), contents.clone())])?;
+        Ok(self.write_manifests(s, vec![(root, contents)])?)
+    }
+
+    /// Add the directory `dir`, relative to the workspace root, to
+    /// `workspace.members`, taking it out of `workspace.exclude`. With
+    /// `create`, a directory without a manifest gets a minimal one for a
+    /// package named after it, inheriting the `version` and `edition` of
+    /// `[workspace.package]` if it sets them, and an empty `src/lib.rs`; the
+    /// directories have to exist on file systems with directories.
+    ///
+    /// Returns the manifest of the new member.
+    pub fn add_member(
+        &self,
+        s: &mut CargoManifest,
+        dir: &str,
+        create: bool,
+    ) -> crate::Result<PathBuf> {
+        let root = s.root_path.clone();
+        let Some(workspace) = &s.root_manifest.workspace else {
+            crate::error::bail!("{} has no `[workspace]`", display_path(&root));
+        };
+        let root_dir = root.parent().unwrap_or_else(|| Path::new(""));
+        let manifest_path = normalize(&root_dir.join(dir).join("Cargo.toml"));
+        // Matched against the entries rather than the files, as a new
+        // member doesn't exist yet for a glob to find.
+        let member_dir = normalize(Path::new(dir));
+        let names_dir = |entry: &String| {
+            let entry = entry.strip_suffix("Cargo.toml").unwrap_or(entry);
+            match glob::is_pattern(entry) {
+                true => glob::matches(entry, &member_dir),
+                false => normalize(Path::new(entry)) == member_dir,
+            }
+        };
+        let listed = workspace.members.iter().any(names_dir);
+        let (excluded, excluded_by): (Vec<_>, Vec<_>) = workspace
+            .exclude
+            .iter()
+            .filter(|excluded| member_dir.starts_with(normalize(Path::new(excluded))))
+            .partition(|excluded| names_dir(excluded));
+        if let Some(parent) = excluded_by.first() {
+            crate::error::bail!("`{dir}` is in `{parent}`, which `workspace.exclude` leaves out");
+        }
+
+        let existing =
+            match self.fs.read(&manifest_path) {
+                Ok(contents) => Some(String::from_utf8(contents).with_context(|| {
+                    format!("{} is not valid UTF-8", display_path(&manifest_path))
+                })?),
+                Err(e) if e.kind() == io::ErrorKind::NotFound && create => None,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    crate::error::bail!("there is no {} to add", display_path(&manifest_path))
+                }
+                Err(e) => Err(e)
+                    .with_context(|| format!("failed to read {}", display_path(&manifest_path)))?,
+            };
+        if listed && excluded.is_empty() && existing.is_some() {
+            crate::error::bail!("`{dir}` is already a member of the workspace");
+        }
+        let mut writes = Vec::new();
+        let member = match existing {
+            Some(contents) => {
+                s.sources
+                    .insert(manifest_path.clone(), contents.as_str().into());
+                diagnostic::parse_manifest(&manifest_path, &contents)?
+            }
+            None => {
+                let name = Path::new(dir)
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .with_context(|| format!("`{dir}` doesn't name a package directory"))?;
+                let shared = workspace.package.as_ref();
+                let version = match shared.is_some_and(|p| p.version.is_some()) {
+                    true => "version.workspace = true".to_string(),
+                    false => "version = \"0.1.0\"".to_string(),
+                };
+                let edition = match shared.is_some_and(|p| p.edition.is_some()) {
+                    true => "edition.workspace = true".to_string(),
+                    false => "edition = \"2021\"".to_string(),
+                };
+                let contents = self.finish(
+                    &manifest_path,
+                    format!("[package]\nname = \"{name}\"\n{version}\n{edition}\n"),
+                )?;
+                let member = diagnostic::parse_manifest(&manifest_path, &contents)?;
+                writes.push((manifest_path.clone(), contents));
+                member
+            }
+        };
+
+        let source = s
+            .sources
+            .get(&root)
+            .with_context(|| format!("{} was not loaded from disk", display_path(&root)))?;
+        let workspace_key = |key: &str| vec!["workspace".to_string(), key.to_string()];
+        let added = match listed {
+            true => Vec::new(),
+            false => vec![dir.to_string()],
+        };
+        let excluded = excluded.into_iter().cloned().collect::<Vec<_>>();
+        let contents = patch::edit_string_array(source, &workspace_key("members"), &added, &[])
+            .and_then(|contents| match excluded.len() {
+                0 => Ok(contents),
+                n if n == workspace.exclude.len() => {
+                    patch::remove_keys(&contents, &[workspace_key("exclude")])
+                }
+                _ => patch::edit_string_array(&contents, &workspace_key("exclude"), &[], &excluded),
+            })
+            .with_context(|| format!("failed to update {}", display_path(&root)))?;
+        let contents = self.finish(&root, contents)?;
+        let created = !writes.is_empty();
+        if **source != *contents {
+            writes.insert(0, (root.clone(), contents));
+        }
+
+        s.members
+            .get_or_insert_with(BTreeMap::new)
+            .insert(manifest_path.clone(), Arc::new(member));
+        reparse(s, &writes)?;
+        self.write_manifests(s, writes)?;
+
+        // A package needs a target for cargo to load the workspace.
+        let src = manifest_path.with_file_name("src");
+        if created && !self.fs.exists(&src.join("main.rs"))? {
+            let lib = src.join("lib.rs");
+            match self.fs.create(&lib, Vec::new()) {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+                    Err(e).with_context(|| format!("failed to write {}", display_path(&lib)))?
+                }
+                _ => {}
+            }
+        }
+
+        Ok(manifest_path)
+    }
+
+    /// Take the member in the directory `dir`, relative to the workspace
+    /// root, out of the workspace: out of `members` and `default-members`,
+    /// or into `exclude` if a glob pattern of `members` matches it. Its
+    /// files stay. With `remove_dependencies`, the entries of
+    /// `[workspace.dependencies]` with a path to it are removed too.
+    ///
+    /// Returns the keys of the removed `[workspace.dependencies]` entries.
+    pub fn remove_member(
+        &self,
+        s: &mut CargoManifest,
+        dir: &str,
+        remove_dependencies: bool,
+    ) -> crate::Result<Vec<String>> {
+        let root = s.root_path.clone();
+        let Some(workspace) = &s.root_manifest.workspace else {
+            crate::error::bail!("{} has no `[workspace]`", display_path(&root));
+        };
+        let root_dir = root.parent().unwrap_or_else(|| Path::new(""));
+        let member_dir = normalize(&root_dir.join(dir));
+        let manifest_path = member_dir.join("Cargo.toml");
+        let members = member_manifest_paths(&self.fs, &root, workspace)?;
+        let Some((_, entry)) = members.iter().find(|(path, _)| *path == manifest_path) else {
+            crate::error::bail!("`{dir}` is not a member of the workspace");
+        };
+        let default_entries = expand_members(&self.fs, root_dir, &workspace.default_members)?
+            .into_iter()
+            .filter(|(path, entry)| *path == manifest_path && !glob::is_pattern(entry))
+            .map(|(_, entry)| entry)
+            .collect::<Vec<_>>();
+        let dependencies = match remove_dependencies {
+            true => workspace
+                .dependencies
+                .iter()
+                .filter(|(_, dependency)| {
+                    dependency
+                        .detail()
+                        .and_then(|detail| detail.path.as_deref())
+                        .is_some_and(|path| normalize(&root_dir.join(path)) == member_dir)
+                })
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>(),
+            false => Vec::new(),
+        };
+
+        let source = s
+            .sources
+            .get(&root)
+            .with_context(|| format!("{} was not loaded from disk", display_path(&root)))?;
+        let workspace_key = |key: &str| vec!["workspace".to_string(), key.to_string()];
+        let contents = match glob::is_pattern(entry) {
+            true => {
+                patch::edit_string_array(source, &workspace_key("exclude"), &[dir.to_string()], &[])
+            }
+            false => patch::edit_string_array(
+                source,
+                &workspace_key("members"),
+                &[],
+                std::slice::from_ref(entry),
+            ),
+        }
+        .and_then(|contents| {
+            // An empty `default-members` would mean every member again.
+            match default_entries.len() {
+                0 => Ok(contents),
+                n if n == workspace.default_members.len() => {
+                    patch::remove_keys(&contents, &[workspace_key("default-members")])
+                }
+                _ => patch::edit_string_array(
+                    &contents,
+                    &workspace_key("default-members"),
+                    &[],
+                    &default_entries,
+                ),
+            }
+        })
+        .and_then(|contents| {
+            let keys = dependencies
+                .iter()
+                .map(|name| {
+                    ["workspace", "dependencies", name]
+                        .map(str::to_string)
+                        .to_vec()
+                })
+                .collect::<Vec<_>>();
+            patch::remove_keys(&contents, &keys)
+        })
+        .with_context(|| format!("failed to update {}", display_path(&root)))?;
+        let contents = self.finish(&root, contents)?;
+
+        if let Some(members) = &mut s.members {
+            members.remove(&manifest_path);
+        }
+        s.sources.remove(&manifest_path);
+        reparse(s, &[(root.clone(), contents.clone())])?;
+        self.write_manifests(s, vec![(root, contents)])?;
+
+        Ok(dependencies)
+    }
+
+    /// Change the features of every declaration of the dependency `name` in
+    /// the workspace. Inherited declarations only get their own `features`
+    /// changed.
+    ///
+    /// Returns the declarations that changed.
+    pub fn edit_features(
+        &self,
+        s: &mut CargoManifest,
+        name: &str,
+        edit: &FeatureEdit,
+    ) -> crate::Result<Vec<DependencyLocation>> {
+        if edit.is_empty() {
+            crate::error::bail!("nothing to change, pass features to add or remove");
+        }
+        let compute_start = Instant::now();
+        let locations = s.index.dependencies(name).to_vec();
+        if locations.is_empty() {
+            crate::error::bail!("`{name}` is not a dependency anywhere in the workspace");
+        }
+
+        let mut contents = BTreeMap::<PathBuf, String>::new();
+        let mut changed = Vec::new();
+        for location in locations {
+            let source = match contents.get(&*location.manifest) {
+                Some(source) => source.clone(),
+                None => s
+                    .sources
+                    .get(&*location.manifest)
+                    .map(|source| source.to_string())
+                    .with_context(|| {
+                        format!(
+                            "{} was not loaded from disk",
+                            display_path(&location.manifest)
+                        )
+                    })?,
+            };
+            let edited = patch::edit_features(
+                &source,
+                &location.key(),
+                &edit.add,
+                &edit.remove,
+                edit.default_features,
+            )
+            .with_context(|| format!("failed to update {}", display_path(&location.manifest)))?;
+            if edited != source {
+                contents.insert(location.manifest.to_path_buf(), edited);
+                changed.push(location);
+            }
+        }
+
+        let writes = contents
+            .into_iter()
+            .map(|(path, contents)| {
+                let contents = self.finish(&path, contents)?;
+                Ok((path, contents))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(changed)
+    }
+
+    /// Point every git dependency on the crates `matcher` matches, by key or
+    /// `package`, at `reference`, replacing the `rev`, `branch` or `tag` it
+    /// had. Inherited declarations follow their `[workspace.dependencies]`
+    /// entry, which is updated instead.
+    ///
+    /// Returns the declarations that changed.
+    pub fn update_git_reference(
+        &self,
+        s: &mut CargoManifest,
+        matcher: &CrateMatcher,
+        reference: &GitReference,
+    ) -> crate::Result<Vec<DependencyLocation>> {
+        let compute_start = Instant::now();
+        let locations = s
+            .index
+            .all_dependencies()
+            .filter(|location| {
+                let detail = s
+                    .manifest(&location.manifest)
+                    .and_then(|manifest| location.get(manifest))
+                    .and_then(Dependency::detail);
+                let Some(detail) = detail.filter(|detail| detail.git.is_some()) else {
+                    return false;
+                };
+                matcher.matches(&location.name)
+                    || detail
+                        .package
+                        .as_deref()
+                        .is_some_and(|package| matcher.matches(package))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if locations.is_empty() {
+            crate::error::bail!("no git dependency of the workspace matches the given crates");
+        }
+
+        let mut contents = BTreeMap::<PathBuf, String>::new();
+        let mut changed = Vec::new();
+        for location in locations {
+            let source = match contents.get(&*location.manifest) {
+                Some(source) => source.clone(),
+                None => s
+                    .sources
+                    .get(&*location.manifest)
+                    .map(|source| source.to_string())
+                    .with_context(|| {
+                        format!(
+                            "{} was not loaded from disk",
+                            display_path(&location.manifest)
+                        )
+                    })?,
+            };
+            let edited = patch::set_git_reference(&source, &location.key(), reference)
+                .with_context(|| {
+                    format!("failed to update {}", display_path(&location.manifest))
+                })?;
+            if edited != source {
+                contents.insert(location.manifest.to_path_buf(), edited);
+                changed.push(location);
+            }
+        }
+
+        let writes = contents
+            .into_iter()
+            .map(|(path, contents)| {
+                let contents = self.finish(&path, contents)?;
+                Ok((path, contents))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(changed)
+    }
+
+    /// Rename the workspace crate `old` to `new`: its `package.name`, the
+    /// keys of dependencies declared under its name, `package = "old"` in
+    /// renamed ones and the `[features]` referring to renamed keys. Its
+    /// directory isn't moved, so `workspace.members` stays as it is.
+    ///
+    /// Returns the manifests that changed.
+    pub fn rename_package(
+        &self,
+        s: &mut CargoManifest,
+        old: &str,
+        new: &str,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let compute_start = Instant::now();
+        let package_path = s
+            .package_manifest(old)
+            .with_context(|| format!("`{old}` is not a package of the workspace"))?
+            .to_path_buf();
+        if s.package_manifest(new).is_some() {
+            crate::error::bail!("the workspace already has a package named `{new}`");
+        }
+
+        let mut edits = BTreeMap::<PathBuf, Vec<ValueEdit>>::new();
+        let mut renames = BTreeMap::<PathBuf, Vec<(Vec<String>, String)>>::new();
+        edits
+            .entry(package_path)
+            .or_default()
+            .push(ValueEdit::new(["package", "name"], new));
+        for location in s.index.all_dependencies() {
+            let Some(dependency) = s
+                .manifest(&location.manifest)
+                .and_then(|manifest| location.get(manifest))
+            else {
+                continue;
+            };
+            let package = match dependency {
+                Dependency::Detailed(detail) => detail.package.as_deref(),
+                _ => None,
+            };
+            match package {
+                Some(package) if package == old => {
+                    let mut key = location.key();
+                    key.push("package".to_string());
+                    edits
+                        .entry(location.manifest.to_path_buf())
+                        .or_default()
+                        .push(ValueEdit::new(key, new));
+                }
+                None if *location.name == *old => renames
+                    .entry(location.manifest.to_path_buf())
+                    .or_default()
+                    .push((location.key(), new.to_string())),
+                _ => {}
+            }
+        }
+
+        let paths = edits
+            .keys()
+            .chain(renames.keys())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        let mut writes = Vec::new();
+        for path in paths {
+            let source = s
+                .sources
+                .get(&path)
+                .with_context(|| format!("{} was not loaded from disk", display_path(&path)))?;
+            let mut contents =
+                patch::apply_edits(source, edits.get(&path).map_or(&[], Vec::as_slice))
+                    .with_context(|| format!("failed to update {}", display_path(&path)))?;
+            if let Some(renames) = renames.get(&path) {
+                contents = patch::rename_keys(&contents, renames)
+                    .and_then(|contents| patch::rename_feature_references(&contents, old, new))
+                    .with_context(|| format!("failed to update {}", display_path(&path)))?;
+            }
+            writes.push((path.clone(), self.finish(&path, contents)?));
+        }
+        // Checked against the names before the rename, so a protected crate
+        // isn't renamed out of its protection.
+        self.check_protected(s, &writes)?;
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        let paths = writes.iter().map(|(path, _)| path.clone()).collect();
+        self.write_manifests(s, writes)?;
+
+        Ok(paths)
+    }
+
+    /// Remove every declaration of the dependency `name` from the root
+    /// manifest, or with `members` from every manifest, in any dependency
+    /// table: `[workspace.dependencies]`, target tables and the rest.
+    ///
+    /// Returns the removed declarations.
+    pub fn remove_dependency_everywhere(
+        &self,
+        s: &mut CargoManifest,
+        name: &str,
+        members: bool,
+    ) -> crate::Result<Vec<DependencyLocation>> {
+        let locations = s
+            .index
+            .dependencies(name)
+            .iter()
+            .filter(|location| members || *location.manifest == *s.root_path)
+            .cloned()
+            .collect::<Vec<_>>();
+        match (locations.is_empty(), members) {
+            (true, true) => {
+                crate::error::bail!("`{name}` is not a dependency anywhere in the workspace")
+            }
+            (true, false) => crate::error::bail!(
+                "`{name}` is not a dependency of {}",
+                display_path(&s.root_path)
+            ),
+            _ => {}
+        }
+
+        let mut keys = BTreeMap::<&Path, Vec<Vec<String>>>::new();
+        for location in &locations {
+            keys.entry(&location.manifest)
+                .or_default()
+                .push(location.key());
+        }
+        let mut writes = Vec::new();
+        for (path, keys) in keys {
+            let source = s
+                .sources
+                .get(path)
+                .with_context(|| format!("{} was not loaded from disk", display_path(path)))?;
+            let contents = patch::remove_keys(source, &keys)
+                .with_context(|| format!("failed to update {}", display_path(path)))?;
+            writes.push((path.to_path_buf(), self.finish(path, contents)?));
+        }
+
+        for location in &locations {
+            if let Some(deps) = s
+                .manifest_mut(&location.manifest)
+                .and_then(|model| location.table.get_mut(model))
+            {
+                deps.remove(name);
+            }
+        }
+        s.reindex();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(locations)
+    }
+
+    /// Move every third-party dependency declared by at least `min_manifests`
+    /// manifests into `[workspace.dependencies]` and make those declarations
+    /// inherit it, keeping their `features` and `optional`. The workspace
+    /// entry takes the highest requirement declared, and turns off default
+    /// features only if every declaration did.
+    ///
+    /// Path, git, renamed and alternative registry dependencies, and those
+    /// the workspace already declares, are left alone. Returns what was
+    /// hoisted, in name order.
+    pub fn hoist_dependencies(
+        &self,
+        s: &mut CargoManifest,
+        min_manifests: usize,
+    ) -> crate::Result<Vec<HoistedDependency>> {
+        let compute_start = Instant::now();
+        let workspace_dependencies = s
+            .root_manifest
+            .workspace
+            .as_ref()
+            .context("the root manifest has no `[workspace]`")?
+            .dependencies
+            .clone();
+
+        // name -> (declaration location, requirement, default features)
+        let mut candidates: BTreeMap<&str, Vec<(&DependencyLocation, &str, bool)>> =
+            BTreeMap::new();
+        for location in s.index.all_dependencies() {
+            if location.table.kind == DependencyKind::Workspace
+                || workspace_dependencies.contains_key(&*location.name)
+            {
+                continue;
+            }
+            let declaration = match s.manifest(&location.manifest).and_then(|m| location.get(m)) {
+                Some(Dependency::Simple(requirement)) => (requirement.as_str(), true),
+                Some(Dependency::Detailed(detail))
+                    if detail.path.is_none()
+                        && detail.git.is_none()
+                        && detail.registry.is_none()
+                        && detail.registry_index.is_none()
+                        && detail.package.is_none() =>
+                {
+                    let Some(requirement) = detail.version.as_deref() else {
+                        continue;
+                    };
+                    (requirement, detail.default_features)
+                }
+                _ => continue,
+            };
+            candidates.entry(&*location.name).or_default().push((
+                location,
+                declaration.0,
+                declaration.1,
+            ));
+        }
+
+        let mut hoisted = Vec::new();
+        let mut member_keys = BTreeMap::<PathBuf, Vec<Vec<String>>>::new();
+        let mut entries = Vec::new();
+        for (name, declarations) in candidates {
+            let manifests = declarations
+                .iter()
+                .map(|(location, _, _)| location.manifest.to_path_buf())
+                .collect::<BTreeSet<_>>();
+            if manifests.len() < min_manifests.max(1) {
+                continue;
+            }
+
+            let requirement = declarations
+                .iter()
+                .rev()
+                .max_by_key(|(_, requirement, _)| duplicates::minimum_version(requirement))
+                .map(|(_, requirement, _)| requirement.to_string())
+                .unwrap_or_default();
+            let value = if declarations.iter().all(|(_, _, default)| !default) {
+                let mut table = toml_edit::InlineTable::new();
+                table.insert("version", requirement.as_str().into());
+                table.insert("default-features", false.into());
+                table.fmt();
+                toml_edit::Value::InlineTable(table)
+            } else {
+                requirement.as_str().into()
+            };
+            for (location, _, _) in &declarations {
+                member_keys
+                    .entry(location.manifest.to_path_buf())
+                    .or_default()
+                    .push(location.key());
+            }
+            entries.push((name.to_string(), value));
+            hoisted.push(HoistedDependency {
+                name: name.to_string(),
+                requirement,
+                manifests: manifests.into_iter().collect(),
+            });
+        }
+        if hoisted.is_empty() {
+            return Ok(hoisted);
+        }
+
+        let mut sources = BTreeMap::new();
+        for (path, keys) in &member_keys {
+            let mut source = s
+                .sources
+                .get(path)
+                .with_context(|| format!("{} was not loaded from disk", display_path(path)))?
+                .to_string();
+            for key in keys {
+                source = patch::inherit_dependency(&source, key)
+                    .with_context(|| format!("failed to update {}", display_path(path)))?
+                    .0;
+            }
+            sources.insert(path.clone(), source);
+        }
+        let mut root = match sources.remove(&s.root_path) {
+            Some(root) => root,
+            None => s
+                .sources
+                .get(&s.root_path)
+                .with_context(|| {
+                    format!("{} was not loaded from disk", display_path(&s.root_path))
+                })?
+                .to_string(),
+        };
+        for (name, value) in entries {
+            root = patch::add_workspace_dependency(&root, &name, value.decorated(" ", ""))
+                .with_context(|| format!("failed to update {}", display_path(&s.root_path)))?;
+        }
+        sources.insert(s.root_path.clone(), root);
+
+        let writes = sources
+            .into_iter()
+            .map(|(path, contents)| Ok((path.clone(), self.finish(&path, contents)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        reparse(s, &writes)?;
+        s.stats.compute += compute_start.elapsed();
+
+        self.write_manifests(s, writes)?;
+
+        Ok(hoisted)
+    }
+
+    /// Fix every finding of [`CargoManifest::lint`] that has a
+    /// [`LintFix`], in one write per manifest.
+    ///
+    /// Returns the findings fixed.
+    pub fn fix_lints(&self, s: &mut CargoManifest) -> crate::Result<Vec<LintFinding>> {
+        let fixable = s
+            .lint()
+            .into_iter()
+            .filter(|finding| finding.fix.is_some())
+            .collect::<Vec<_>>();
+
+        let mut sources = BTreeMap::new();
+        for finding in &fixable {
+            let path = &finding.manifest;
+            let source = match sources.remove(path) {
+                Some(source) => source,
+                None => s
+                    .sources
+                    .get(path)
+                    .with_context(|| format!("{} was not loaded from disk", display_path(path)))?
+                    .to_string(),
+            };
+            let source = match &finding.fix {
+                Some(LintFix::Inherit) => {
+                    patch::inherit_dependency(&source, &finding.key).map(|(source, _)| source)
+                }
+                Some(LintFix::SetVersion(version)) => {
+                    let mut key = finding.key.clone();
+                    key.push("version".to_string());
+                    patch::apply_edits(&source, &[ValueEdit::new(key, version.as_str())])
+                }
+                None => Ok(source),
+            }
+            .with_context(|| format!("failed to update {}", display_path(path)))?;
+            sources.insert(path.clone(), source);
+        }
+
+        let writes = sources
+            .into_iter()
+            .map(|(path, contents)| Ok((path.clone(), self.finish(&path, contents)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        reparse(s, &writes)?;
+        self.write_manifests(s, writes)?;
+
+        Ok(fixable)
+    }
+
+    /// Settle an operation that was interrupted after writing some of its
+    /// manifests, using the resume state saved next to `root_manifest`.
+    ///
+    /// Returns the state that was acted on. The saved state is removed once
+    /// every write succeeded; if some fail it is updated so `resume` can be
+    /// retried.
+    pub fn resume(&self, root_manifest: &Path, mode: ResumeMode) -> crate::Result<ResumeState> {
+        let mut state = ResumeState::load(&self.fs, root_manifest)?
+            .context("there is no interrupted operation to resume")?;
+
+        let (todo, contents_of): (_, fn(&FileWrite) -> Option<&String>) = match mode {
+            ResumeMode::Finish => (std::mem::take(&mut state.pending), |w| Some(&w.contents)),
+            ResumeMode::Rollback => (std::mem::take(&mut state.completed), |w| {
+                w.original.as_ref()
+            }),
+        };
+
+        let mut failed = Vec::new();
+        let mut first_error = None;
+        for write in todo {
+            let result = contents_of(&write)
+                .with_context(|| {
+                    format!(
+                        "cannot restore {}: its original contents are unknown",
+                        display_path(&write.path)
+                    )
+                })
+                .and_then(|contents| {
+                    self.fs
+                        .write(&write.path, contents.as_bytes().to_vec())
+                        .with_context(|| format!("failed to write {}", display_path(&write.path)))
+                });
+            match result {
+                Ok(()) => match mode {
+                    ResumeMode::Finish => state.completed.push(write),
+                    ResumeMode::Rollback => state.pending.push(write),
+                },
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    failed.push(write);
+                }
+            }
+        }
+
+        if let Some(e) = first_error {
+            match mode {
+                ResumeMode::Finish => state.pending = failed,
+                ResumeMode::Rollback => state.completed = failed,
+            }
+            state.save(&self.fs, root_manifest)?;
+            return Err(e.into());
+        }
+
+        let state_path = ResumeState::path_for(root_manifest);
+        self.fs
+            .remove(&state_path)
+            .with_context(|| format!("failed to remove {}", display_path(&state_path)))?;
+
+        Ok(state)
+    }
+
+    /// Undo a run recorded in the journal of the workspace rooted at
+    /// `root_manifest`: the run `id`, or the last one. Every file it changed
+    /// gets back what it held before.
+    ///
+    /// Returns the entry of the run, which stays in the journal until it is
+    /// [removed](JournalEntry::remove). Fails without changing anything if
+    /// a file changed since the run.
+    pub fn revert(&self, root_manifest: &Path, id: Option<&str>) -> crate::Result<JournalEntry> {
+        let mut entries = JournalEntry::load_all(&self.fs, root_manifest)?;
+        let entry = match id {
+            Some(id) => {
+                let index = entries
+                    .iter()
+                    .position(|entry| entry.id == id)
+                    .with_context(|| format!("there is no run `{id}` in the history"))?;
+                entries.remove(index)
+            }
+            None => entries
+                .pop()
+                .context("there is no recorded run to revert")?,
+        };
+
+        let dir = history::workspace_dir(root_manifest);
+        for file in &entry.files {
+            let path = dir.join(&file.path);
+            let current = match self.fs.read(&path) {
+                Ok(current) => Some(current),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    Err(e).with_context(|| format!("failed to read {}", display_path(&path)))?
+                }
+            };
+            if current.as_deref() != file.contents.as_ref().map(|c| c.as_bytes()) {
+                crate::error::bail!(
+                    "{} changed since run {}, nothing was reverted",
+                    display_path(&path),
+                    entry.id
+                );
+            }
+        }
+        for file in &entry.files {
+            let path = dir.join(&file.path);
+            match &file.original {
+                Some(original) => self.fs.write(&path, original.as_bytes().to_vec()),
+                None => self.fs.remove(&path),
+            }
+            .with_context(|| format!("failed to restore {}", display_path(&path)))?;
+        }
+
+        Ok(entry)
+    }
+
+    fn compute_version_updates<P: AsRef<str>, V: AsRef<str>>(
+        &self,
+        s: &mut CargoManifest,
+        updates: &[(P, V)],
+    ) -> anyhow::Result<Vec<(PathBuf, String)>> {
+        let mut edits: BTreeMap<PathBuf, Vec<ValueEdit>> = BTreeMap::new();
+        let index = std::mem::take(&mut s.index);
+
+        for (package, version) in updates {
+            let (package, version) = (package.as_ref(), version.as_ref());
+
+            for path in index.packages(package) {
+                let Some(p) = s.manifest_mut(path).and_then(|m| m.package.as_mut()) else {
+                    continue;
+                };
+                if p.version.get().is_ok() {
+                    p.version.set(version.to_string());
+                    edits
+                        .entry(path.clone())
+                        .or_default()
+                        .push(ValueEdit::new(["package", "version"], version));
+                    continue;
+                }
+
+                // Every inheritor is set to the same version, see
+                // `with_inheritors`, so the shared one is edited once.
+                let workspace = s.root_manifest.workspace.as_mut();
+                if let Some(shared) = workspace.and_then(|w| w.package.as_mut()) {
+                    if shared.version.as_deref() != Some(version) {
+                        shared.version = Some(version.to_string());
+                        edits
+                            .entry(s.root_path.clone())
+                            .or_default()
+                            .push(ValueEdit::new(["workspace", "package", "version"], version));
+                    }
+                }
+            }
+
+            for location in index.dependencies_on(package) {
+                if !self.dev_dependencies && location.table.kind == DependencyKind::Dev {
+                    continue;
+                }
+                if let Some(edit) = self.update_dependency(s, location, version) {
+                    edits
+                        .entry(location.manifest.to_path_buf())
+                        .or_default()
+                        .push(edit);
+                }
+            }
+        }
+
+        s.index = index;
+
+        self.render_all(s, edits)
+    }
+
+    fn render_all(
+        &self,
+        s: &CargoManifest,
+        edits: BTreeMap<PathBuf, Vec<ValueEdit>>,
+    ) -> anyhow::Result<Vec<(PathBuf, String)>> {
+        edits
+            .into_iter()
+            .map(|(path, edits)| {
+                let contents = self.render(s, &path, &edits)?;
+                Ok((path, contents))
+            })
+            .collect()
+    }
+
+    /// Produce the new contents of the manifest at `path`.
+    ///
+    /// Manifests loaded from disk are patched in place so only the edited
+    /// values change; manifests constructed in memory have no source to patch
+    /// and are serialized from the model instead.
+    fn render(
+        &self,
+        s: &CargoManifest,
+        path: &Path,
+        edits: &[ValueEdit],
+    ) -> anyhow::Result<String> {
+        let contents = match s.sources.get(path) {
+            Some(source) => patch::apply_edits(source, edits)
+                .with_context(|| format!("failed to update {}", display_path(path)))?,
+            None => {
+                let manifest = s.manifest(path).with_context(|| {
+                    format!("{} is not part of the workspace", display_path(path))
+                })?;
+                toml::to_string_pretty(manifest)?
+            }
+        };
+
+        self.finish(path, contents)
+    }
+
+    /// Apply the configured sorting and formatting to new manifest contents.
+    fn finish(&self, path: &Path, contents: String) -> anyhow::Result<String> {
+        let contents = if self.sort_dependencies {
+            format::sort_dependencies(&contents)
+                .with_context(|| format!("failed to sort {}", display_path(path)))?
+        } else {
+            contents
+        };
+
+        match &self.format {
+            Some(style) => format::format(&contents, style)
+                .with_context(|| format!("failed to format {}", display_path(path))),
+            None => Ok(contents),
+        }
+    }
+
+    fn update_dependency(
+        &self,
+        s: &mut CargoManifest,
+        location: &DependencyLocation,
+        version: &str,
+    ) -> Option<ValueEdit> {
+        let dependency = location
+            .table
+            .get_mut(s.manifest_mut(&location.manifest)?)?
+            .get_mut(&*location.name)?;
+        if self.if_needed {
+            let current = match &*dependency {
+                Dependency::Simple(dep) => Some(dep.as_str()),
+                Dependency::Detailed(dep) => dep.version.as_deref(),
+                Dependency::Inherited(_) => None,
+            };
+            // A missing requirement is met by any version.
+            if current.is_none_or(|current| requirements::admits(current, version)) {
+                return None;
+            }
+        }
+
+        let mut key = location.key();
+        let requirement = match dependency {
+            Dependency::Simple(dep) => {
+                *dep = requirements::rewrite(dep, version, self.requirement_style);
+                dep.clone()
+            }
+            // Inherited entries follow `[workspace.dependencies]`, which is
+            // indexed and updated on its own.
+            Dependency::Inherited(_) => return None,
+            Dependency::Detailed(dep) => {
+                let current = dep.version.as_deref().unwrap_or_default();
+                let requirement = requirements::rewrite(current, version, self.requirement_style);
+                dep.version = Some(requirement.clone());
+                key.push("version".to_string());
+                requirement
+            }
+        };
+
+        Some(ValueEdit::new(key, requirement))
+    }
+}
+
+/// The change set of writing `writes` over the manifests of `s`, for hooks.
+fn hooked_changes(s: &CargoManifest, writes: &[(PathBuf, String)]) -> anyhow::Result<ChangeSet> {
+    let mut change_set = ChangeSet::default();
+    for (path, contents) in writes {
+        let original = s.sources.get(path).map(|source| source.to_string());
+        change_set
+            .edits
+            .extend(plan::value_changes(path, original.as_deref(), contents)?);
+        change_set.writes.push(FileWrite {
+            path: path.clone(),
+            original,
+            contents: contents.clone(),
+        });
+    }
+
+    Ok(change_set)
+}
+
+/// The manifest values that hold `package`'s version: its `package.version`
+/// and every requirement on it, with why each one follows the version.
+/// Inherited requirements follow `[workspace.dependencies]` and aren't
+/// included.
+fn version_keys(s: &CargoManifest, package: &str) -> Vec<(PathBuf, Vec<String>, String)> {
+    let mut keys = s
+        .index
+        .packages(package)
+        .iter()
+        .map(|path| {
+            (
+                path.clone(),
+                vec!["package".to_string(), "version".to_string()],
+                format!("the version of {package}"),
+            )
+        })
+        .collect::<Vec<_>>();
+    for location in s.index.dependencies_on(package) {
+        let Some(manifest) = s.manifest(&location.manifest) else {
+            continue;
+        };
+        let mut key = location.key();
+        match location.get(manifest) {
+            Some(Dependency::Simple(_)) => {}
+            Some(Dependency::Detailed(_)) => key.push("version".to_string()),
+            Some(Dependency::Inherited(_)) | None => continue,
+        }
+        let reason = match (&manifest.package, location.table.kind) {
+            (_, DependencyKind::Workspace) => match s.inheriting_members(package).as_slice() {
+                [] => format!("members inheriting {package} follow it"),
+                members => format!(
+                    "members inheriting {package} follow it: {}",
+                    members.join(", ")
+                ),
+            },
+            (Some(dependent), _) => format!("{} requires {package}", dependent.name),
+            (None, _) => format!("the root manifest requires {package}"),
+        };
+        keys.push((location.manifest.to_path_buf(), key, reason));
+    }
+
+    keys
+}
+
+/// Replace the models of the manifests about to be written with `writes`.
+fn reparse(s: &mut CargoManifest, writes: &[(PathBuf, String)]) -> anyhow::Result<()> {
+    for (path, contents) in writes {
+        let parsed = diagnostic::parse_manifest(path, contents)?;
+        if let Some(manifest) = s.manifest_mut(path) {
+            *manifest = parsed;
+        }
+    }
+    s.reindex();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::assert_eq;
+
+    use crate::filesystem::MockFileSystem;
+    use crate::testing::WorkspaceFixture;
+    use crate::Error;
+
+    use super::*;
+
+    #[test]
+    fn clones_share_members_until_they_change() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.1.0")
+            .member("b", "0.1.0");
+        let workspace =
+            CargoManifestService::new(fixture.file_system()).load_manifest(&fixture.root_path())?;
+        let (a, b) = (fixture.manifest_path("a"), fixture.manifest_path("b"));
+
+        let mut cloned = workspace.clone();
+        let shared = |cloned: &CargoManifest, path: &Path| {
+            std::ptr::eq(
+                workspace.manifest(path).unwrap(),
+                cloned.manifest(path).unwrap(),
+            )
+        };
+        assert!(shared(&cloned, &a) && shared(&cloned, &b));
+        cloned
+            .manifest_mut(&a)
+            .unwrap()
+            .package
+            .as_mut()
+            .unwrap()
+            .name = "c".to_string();
+        assert!(!shared(&cloned, &a) && shared(&cloned, &b));
+        assert_eq!(workspace.manifest(&a).unwrap().package().name, "a");
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_load_children() -> anyhow::Result<()> {
+        let root_manifest_toml =
+            b"name = 'root'\nversion = '0.1.0'\n workspace = { members = ['child'] }";
+        let child_manifest_toml = b"name = 'child'\nversion = '0.2.0'";
+
+        let root_manifest_path = PathBuf::from("Cargo.toml");
+        let child_manifest_path = PathBuf::from("child/Cargo.toml");
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(root_manifest_path.clone(), root_manifest_toml.to_vec());
+        fs.add_file(child_manifest_path.clone(), child_manifest_toml.to_vec());
+
+        let cargo_manifest = CargoManifestService::new(fs)
+            .load_manifest(&root_manifest_path)
+            .unwrap();
+        let members = cargo_manifest.members.as_ref().unwrap();
+
+        assert_eq!(1, members.len());
+        assert!(members.contains_key(&child_manifest_path));
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_load_members_matching_globs() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['crates/*', 'libs/**']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("crates/a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nb = { path = '../../libs/nested/b', version = '0.1.0' }\n".to_vec(),
+        );
+        fs.add_file(PathBuf::from("crates/notes/README.md"), Vec::new());
+        fs.add_file(
+            PathBuf::from("libs/nested/b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(
+            cargo_manifest
+                .members
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            [
+                Path::new("crates/a/Cargo.toml"),
+                Path::new("libs/nested/b/Cargo.toml")
+            ]
+        );
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "b", "0.2.0")?;
+        assert!(
+            String::from_utf8(fs.read(Path::new("crates/a/Cargo.toml"))?)?
+                .contains("version = '0.2.0'")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn loads_members_declared_by_their_manifest() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a/Cargo.toml', './b/', 'crates/*/Cargo.toml']\n".to_vec(),
+        );
+        for member in ["a", "b", "crates/c"] {
+            fs.add_file(
+                PathBuf::from(format!("{member}/Cargo.toml")),
+                b"[package]\nname = 'child'\nversion = '0.1.0'\n".to_vec(),
+            );
+        }
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(
+            cargo_manifest
+                .members
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            [
+                Path::new("a/Cargo.toml"),
+                Path::new("b/Cargo.toml"),
+                Path::new("crates/c/Cargo.toml")
+            ]
+        );
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'missing/Cargo.toml']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let error = CargoManifestService::new(&fs)
+            .load_manifest(Path::new("Cargo.toml"))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "failed to load the workspace member `missing/Cargo.toml` of Cargo.toml"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn honors_excluded_and_default_members() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['crates/*']\nexclude = ['crates/old']\ndefault-members = ['crates/a']\n".to_vec(),
+        );
+        for member in ["a", "b", "old"] {
+            fs.add_file(
+                PathBuf::from(format!("crates/{member}/Cargo.toml")),
+                format!("[package]\nname = '{member}'\nversion = '0.1.0'\n").into_bytes(),
+            );
+        }
+
+        let members = |service: &CargoManifestService<&MockFileSystem>| -> anyhow::Result<_> {
+            let cargo_manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+            Ok(cargo_manifest
+                .package_versions()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>())
+        };
+        assert_eq!(members(&CargoManifestService::new(&fs))?, ["a", "b"]);
+        assert_eq!(
+            members(&CargoManifestService::new(&fs).with_default_members_only(true))?,
+            ["a"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_load_nested_workspaces() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['platform']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("platform/Cargo.toml"),
+            b"[package]\nname = 'platform'\nversion = '0.1.0'\n\n[workspace]\nmembers = ['core', '..']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("platform/core/Cargo.toml"),
+            b"[package]\nname = 'core'\nversion = '0.1.0'\n\n[workspace]\nmembers = ['..']\n"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(
+            cargo_manifest
+                .members
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            [
+                Path::new("platform/Cargo.toml"),
+                Path::new("platform/core/Cargo.toml")
+            ]
+        );
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "core", "0.2.0")?;
+        assert!(
+            String::from_utf8(fs.read(Path::new("platform/core/Cargo.toml"))?)?
+                .contains("version = '0.2.0'")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn exposes_the_root_and_members() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("b", "0.2.0")
+            .member("a", "0.3.0");
+        let service = CargoManifestService::new(fixture.file_system());
+        let manifest = service.load_manifest(&fixture.root_path())?;
+
+        assert_eq!(
+            manifest
+                .root_manifest()
+                .package
+                .as_ref()
+                .map(|p| p.name.as_str()),
+            Some("root")
+        );
+        assert_eq!(
+            manifest
+                .members()
+                .map(|(_, m)| m.package.as_ref().unwrap().name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(manifest.manifests().count(), 3);
+        assert_eq!(manifest.package_version("a").as_deref(), Some("0.3.0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loads_members_concurrently() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c', 'nested']\n".to_vec(),
+        );
+        for name in ["a", "b", "c", "nested/d", "nested/e"] {
+            fs.add_file(
+                PathBuf::from(format!("{name}/Cargo.toml")),
+                format!(
+                    "[package]\nname = '{}'\nversion = '0.1.0'\n",
+                    &name[name.len() - 1..]
+                )
+                .into_bytes(),
+            );
+        }
+        fs.add_file(
+            PathBuf::from("nested/Cargo.toml"),
+            b"[workspace]\nmembers = ['d', 'e', '../a']\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let sequential =
+            CargoManifestService::new(fs.clone()).load_manifest(Path::new("Cargo.toml"))?;
+        let concurrent = CargoManifestService::new(fs.clone())
+            .with_load_concurrency(NonZeroUsize::new(4).unwrap())
+            .load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(concurrent.members, sequential.members);
+        assert_eq!(concurrent.stats.files_read, 7);
+        assert_eq!(concurrent.package_versions(), sequential.package_versions());
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_leave_dev_dependencies_alone() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na = { path = '../a', version = '0.1.0' }\n\n[dev-dependencies]\na = { path = '../a', version = '0.1.0' }\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let service = CargoManifestService::new(fs.clone()).with_dev_dependencies(false);
+        let mut manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+        service.update_version(&mut manifest, "a", "0.2.0")?;
+
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na = { path = '../a', version = '0.2.0' }\n\n[dev-dependencies]\na = { path = '../a', version = '0.1.0' }\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_no_children() -> anyhow::Result<()> {
+        let root_manifest_toml = b"name = 'root'\nversion = '0.1.0'\n workspace = { members = [] }";
+
+        let root_manifest_path = PathBuf::from("Cargo.toml");
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(root_manifest_path.clone(), root_manifest_toml.to_vec());
+
+        let cargo_manifest = CargoManifestService::new(fs)
+            .load_manifest(&root_manifest_path)
+            .unwrap();
+        assert!(cargo_manifest.members.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn service_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        #[cfg(feature = "real-fs")]
+        assert_send_sync::<CargoManifestService<crate::RealFileSystem>>();
+        assert_send_sync::<CargoManifestService<MockFileSystem>>();
+        assert_send_sync::<CargoManifest>();
+    }
+
+    #[test]
+    fn can_update_disjoint_workspaces_concurrently() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        for workspace in ["first", "second"] {
+            fs.add_file(
+                PathBuf::from(format!("{workspace}/Cargo.toml")),
+                b"[workspace]\nmembers = ['child']".to_vec(),
+            );
+            fs.add_file(
+                PathBuf::from(format!("{workspace}/child/Cargo.toml")),
+                b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec(),
+            );
+        }
+
+        let cargo_manifest_service = CargoManifestService::new(fs);
+
+        std::thread::scope(|scope| {
+            let handles = [("first", "1.0.0"), ("second", "2.0.0")].map(|(workspace, version)| {
+                let cargo_manifest_service = &cargo_manifest_service;
+                scope.spawn(move || -> anyhow::Result<()> {
+                    let mut cargo_manifest = cargo_manifest_service
+                        .load_manifest(&PathBuf::from(format!("{workspace}/Cargo.toml")))?;
+                    cargo_manifest_service.update_version(&mut cargo_manifest, "child", version)?;
+
+                    let child = cargo_manifest
+                        .members
+                        .as_ref()
+                        .unwrap()
+                        .get(&PathBuf::from(format!("{workspace}/child/Cargo.toml")))
+                        .unwrap();
+                    assert_eq!(child.package().version(), version);
+
+                    Ok(())
+                })
+            });
+
+            handles
+                .into_iter()
+                .try_for_each(|handle| handle.join().unwrap())
+        })
+    }
+
+    #[test]
+    fn cancelled_load_fails_without_reading() {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[package]\nname = 'root'\nversion = '0.1.0'".to_vec(),
+        );
+
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let err = CargoManifestService::new(fs)
+            .with_cancellation(token)
+            .load_manifest(&PathBuf::from("Cargo.toml"))
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Cancelled(Cancelled)));
+    }
+
+    #[test]
+    fn cancelled_update_writes_nothing() -> anyhow::Result<()> {
+        let root_manifest_toml = b"[package]\nname = 'root'\nversion = '0.1.0'";
+        let root_manifest_path = PathBuf::from("Cargo.toml");
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(root_manifest_path.clone(), root_manifest_toml.to_vec());
+        let fs = std::sync::Arc::new(fs);
+
+        let token = CancellationToken::new();
+        let cargo_manifest_service =
+            CargoManifestService::new(fs.clone()).with_cancellation(token.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&root_manifest_path)?;
+
+        token.cancel();
+        let err = cargo_manifest_service
+            .update_version(&mut cargo_manifest, "root", "0.2.0")
+            .unwrap_err();
+
+        assert!(matches!(err, Error::Cancelled(Cancelled)));
+        assert_eq!(fs.read(&root_manifest_path)?, root_manifest_toml.to_vec());
+
+        Ok(())
+    }
+
+    #[test]
+    fn collects_stats() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new().member("child", "0.1.0");
+
+        let cargo_manifest_service = CargoManifestService::new(fixture.file_system());
+        let mut cargo_manifest =
+            cargo_manifest_service.load_manifest(&PathBuf::from("Cargo.toml"))?;
+        assert_eq!(cargo_manifest.stats().files_read, 2);
+        assert_eq!(cargo_manifest.stats().members, 1);
+        assert_eq!(cargo_manifest.stats().files_written, 0);
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "root", "0.2.0")?;
+        assert_eq!(cargo_manifest.stats().files_read, 2);
+        assert!(cargo_manifest.stats().files_written >= 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bumps_a_crate_and_its_dependents() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("child", "0.2.3-rc.1")
+            .dep("child", "root");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let version = cargo_manifest_service.bump_version(
+            &mut cargo_manifest,
+            "child",
+            BumpLevel::Minor,
+            None,
+        )?;
+
+        assert_eq!(version, "0.3.0");
+        fixture.version("child", "0.3.0").assert_files(&fs);
+        assert!(cargo_manifest_service
+            .bump_version(&mut cargo_manifest, "missing", BumpLevel::Patch, None)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn cascades_bumps_to_dependent_members() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("core", "0.4.2")
+            .member("app", "0.1.0")
+            .member("cli", "0.2.5")
+            .member("other", "0.1.0")
+            .dep("core", "app")
+            .dep("app", "cli");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let updates = cargo_manifest_service.cascaded_versions(
+            &cargo_manifest,
+            &[("core", "0.5.0")],
+            BumpLevel::Patch,
+        )?;
+        assert_eq!(
+            updates,
+            [
+                ("core".to_string(), "0.5.0".to_string()),
+                ("app".to_string(), "0.1.1".to_string()),
+                ("cli".to_string(), "0.2.6".to_string()),
+            ]
+        );
+
+        cargo_manifest_service.update_versions(&mut cargo_manifest, &updates)?;
+        fixture
+            .version("core", "0.5.0")
+            .version("app", "0.1.1")
+            .version("cli", "0.2.6")
+            .assert_files(&fs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn updates_path_dependencies_under_other_keys() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['crates/child', 'crates/parent']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("crates/child/Cargo.toml"),
+            b"[package]\nname = 'child'\nversion = '0.2.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("crates/parent/Cargo.toml"),
+            b"[package]\nname = 'parent'\nversion = '0.1.0'\n\n[dependencies]\nchild = { path = '../child', version = '0.2.0' }\n\n[build-dependencies]\nkid = { path = '../child/', version = '0.2.0', package = 'child' }\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        cargo_manifest_service.update_version(&mut cargo_manifest, "child", "0.3.0")?;
+
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("crates/parent/Cargo.toml"))?)?,
+            "[package]\nname = 'parent'\nversion = '0.1.0'\n\n[dependencies]\nchild = { path = '../child', version = '0.3.0' }\n\n[build-dependencies]\nkid = { path = '../child/', version = '0.3.0', package = 'child' }\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn updates_renamed_and_optional_dependencies() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a']\n\n[workspace.dependencies]\nfoo1 = { package = 'foo', version = '1.0' }\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nfoo = { version = '1.0', optional = true, default-features = false }\nfoo2 = { package = 'foo', version = '1.0', features = ['std'] }\nfoo1.workspace = true\nbar = { package = 'foo1', version = '0.3' }\n\n[features]\nextra = ['dep:foo', 'foo2/std']\n"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        cargo_manifest_service.update_version(&mut cargo_manifest, "foo", "1.2.0")?;
+
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a']\n\n[workspace.dependencies]\nfoo1 = { package = 'foo', version = '1.2.0' }\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nfoo = { version = '1.2.0', optional = true, default-features = false }\nfoo2 = { package = 'foo', version = '1.2.0', features = ['std'] }\nfoo1.workspace = true\nbar = { package = 'foo1', version = '0.3' }\n\n[features]\nextra = ['dep:foo', 'foo2/std']\n"
+        );
+        // `foo2` is only a key, not a crate.
+        assert!(cargo_manifest_service
+            .update_version(&mut cargo_manifest, "foo2", "2.0.0")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_preserves_formatting_byte_for_byte() -> anyhow::Result<()> {
+        let root_manifest_toml = r#"# The workspace.
+[workspace]
+members = [
+    "child", # the only member
+]
+
+[package]
+version   =   "1.0.0"  # not a member's version
+name = "root"
+
+[dependencies]
+zeta = "1"
+child = { version = "0.2.0",    path = "child" } # inline
+alpha = "1"
+
+[target.'cfg(unix)'.dependencies.child]
+path = "child"
+version = '0.2.0'
+"#;
+        let child_manifest_toml = "[package]\r\nname = \"child\"\r\nversion = \"0.2.0\"\r\n";
+        let root_manifest_path = PathBuf::from("Cargo.toml");
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            root_manifest_path.clone(),
+            root_manifest_toml.as_bytes().to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("child/Cargo.toml"),
+            child_manifest_toml.as_bytes().to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&root_manifest_path)?;
+        cargo_manifest_service.update_version(&mut cargo_manifest, "child", "0.3.0")?;
+
+        assert_eq!(
+            String::from_utf8(fs.read(&root_manifest_path)?)?,
+            root_manifest_toml.replace("0.2.0", "0.3.0")
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(&PathBuf::from("child/Cargo.toml"))?)?,
+            child_manifest_toml.replace("0.2.0", "0.3.0")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_invalid_versions() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("child", "0.2.0")
+            .dep("child", "root");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let err = cargo_manifest_service
+            .update_versions(
+                &mut cargo_manifest,
+                &[("child", "banana"), ("root", "0.2.0")],
+            )
+            .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .starts_with("1 invalid version(s), nothing was changed:\n  `banana` is not a valid version for child"));
+        fixture.assert_files(&fs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_writes_minimal_diff() -> anyhow::Result<()> {
+        let root_manifest_toml = r#"[workspace]
+members = ["child"]
+
+[workspace.dependencies]
+# shared between members
+child = { path = "child", version = "0.2.0" }
+"#;
+        let root_manifest_path = PathBuf::from("Cargo.toml");
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            root_manifest_path.clone(),
+            root_manifest_toml.as_bytes().to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("child/Cargo.toml"),
+            b"[package]\nname = 'child'\nversion = '0.2.0' # keep me".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&root_manifest_path)?;
+        cargo_manifest_service.update_version(&mut cargo_manifest, "child", "0.3.0")?;
+
+        assert_eq!(
+            String::from_utf8(fs.read(&root_manifest_path)?)?,
+            root_manifest_toml.replace("0.2.0", "0.3.0")
+        );
+        assert_eq!(
+            fs.read(&PathBuf::from("child/Cargo.toml"))?,
+            b"[package]\nname = 'child'\nversion = '0.3.0' # keep me".to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_write_concurrently() -> anyhow::Result<()> {
+        let members = (0..8).map(|i| format!("child-{i}")).collect::<Vec<_>>();
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            format!("[workspace]\nmembers = {members:?}\n").into_bytes(),
+        );
+        for member in &members {
+            fs.add_file(
+                PathBuf::from(format!("{member}/Cargo.toml")),
+                b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec(),
+            );
+        }
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone())
+            .with_write_concurrency(NonZeroUsize::new(3).unwrap());
+        let mut cargo_manifest =
+            cargo_manifest_service.load_manifest(&PathBuf::from("Cargo.toml"))?;
+        cargo_manifest_service.update_version(&mut cargo_manifest, "child", "0.2.0")?;
+
+        for member in &members {
+            assert_eq!(
+                fs.read(&PathBuf::from(format!("{member}/Cargo.toml")))?,
+                b"[package]\nname = 'child'\nversion = '0.2.0'".to_vec()
+            );
+        }
+        assert_eq!(cargo_manifest.stats().files_written, members.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_update_many_versions_in_one_pass() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n[workspace.dependencies]\na = '0.1.0'\nb = '0.1.0'"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n[dev-dependencies]\nb = '0.1.0'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n[dependencies]\na.workspace = true"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest =
+            cargo_manifest_service.load_manifest(&PathBuf::from("Cargo.toml"))?;
+        cargo_manifest_service
+            .update_versions(&mut cargo_manifest, &[("a", "1.0.0"), ("b", "2.0.0")])?;
+
+        assert_eq!(cargo_manifest.stats().files_written, 3);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a', 'b']\n[workspace.dependencies]\na = '1.0.0'\nb = '2.0.0'"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '1.0.0'\n[dev-dependencies]\nb = '2.0.0'"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '2.0.0'\n[dependencies]\na.workspace = true"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient_load_skips_broken_members() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['good', 'broken', 'missing']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("good/Cargo.toml"),
+            b"[package]\nname = 'good'\nversion = '0.1.0'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("broken/Cargo.toml"),
+            b"[package\nname = 'broken'".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        assert!(CargoManifestService::new(fs.clone())
+            .load_manifest(Path::new("Cargo.toml"))
+            .is_err());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone()).with_lenient(true);
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let failed = cargo_manifest
+            .load_failures()
+            .iter()
+            .map(|f| f.path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failed,
+            [
+                PathBuf::from("broken/Cargo.toml"),
+                PathBuf::from("missing/Cargo.toml")
+            ]
+        );
+        assert_eq!(cargo_manifest.members.as_ref().unwrap().len(), 1);
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "good", "0.2.0")?;
+        assert_eq!(
+            fs.read(Path::new("good/Cargo.toml"))?,
+            b"[package]\nname = 'good'\nversion = '0.2.0'".to_vec()
+        );
+        assert_eq!(
+            fs.read(Path::new("broken/Cargo.toml"))?,
+            b"[package\nname = 'broken'".to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn symlinked_members_resolve_inside_the_root_only() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['crates/inside', 'vendor/inside', 'crates/outside']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("vendor/inside/Cargo.toml"),
+            b"[package]\nname = 'inside'\nversion = '0.1.0'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("../elsewhere/Cargo.toml"),
+            b"[package]\nname = 'outside'\nversion = '0.1.0'".to_vec(),
+        );
+        fs.add_symlink(
+            PathBuf::from("crates/inside"),
+            PathBuf::from("../vendor/inside"),
+        );
+        fs.add_symlink(
+            PathBuf::from("crates/outside"),
+            PathBuf::from("../../elsewhere"),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(
+            cargo_manifest
+                .members
+                .as_ref()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            [
+                Path::new("crates/inside/Cargo.toml"),
+                Path::new("crates/outside/Cargo.toml")
+            ]
+        );
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "inside", "0.2.0")?;
+        assert_eq!(
+            fs.read(Path::new("vendor/inside/Cargo.toml"))?,
+            b"[package]\nname = 'inside'\nversion = '0.2.0'".to_vec()
+        );
+
+        let error = cargo_manifest_service
+            .update_version(&mut cargo_manifest, "outside", "0.2.0")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "refusing to write crates/outside/Cargo.toml: it resolves to ../elsewhere/Cargo.toml, outside the workspace root"
+        );
+        assert_eq!(
+            fs.read(Path::new("../elsewhere/Cargo.toml"))?,
+            b"[package]\nname = 'outside'\nversion = '0.1.0'".to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sets_inherited_versions_in_workspace_package() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c']\n\n[workspace.package]\nversion = '0.1.0'\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion.workspace = true\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion.workspace = true\n\n[dependencies]\na = { path = '../a', version = '0.1.0' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("c/Cargo.toml"),
+            b"[package]\nname = 'c'\nversion = '0.5.0'\n\n[dependencies]\nb = { path = '../b', version = '0.1.0' }\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(cargo_manifest.workspace_version(), Some("0.1.0"));
+        assert_eq!(cargo_manifest.version_inheritors(), ["a", "b"]);
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "a", "0.2.0")?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a', 'b', 'c']\n\n[workspace.package]\nversion = '0.2.0'\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion.workspace = true\n"
+        );
+        assert!(String::from_utf8(fs.read(Path::new("c/Cargo.toml"))?)?
+            .contains("b = { path = '../b', version = '0.2.0' }"));
+
+        let inheritors =
+            cargo_manifest_service.update_workspace_version(&mut cargo_manifest, "0.3.0")?;
+        assert_eq!(inheritors, ["a", "b"]);
+        assert_eq!(
+            cargo_manifest.package_version("b").as_deref(),
+            Some("0.3.0")
+        );
+        assert!(String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?
+            .contains("a = { path = '../a', version = '0.3.0' }"));
+        assert_eq!(
+            cargo_manifest_service.bumped_workspace_version(
+                &cargo_manifest,
+                BumpLevel::Minor,
+                None
+            )?,
+            "0.4.0"
+        );
+
+        assert!(cargo_manifest_service
+            .update_versions(&mut cargo_manifest, &[("a", "0.4.0"), ("b", "0.5.0")])
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn edits_binary_and_library_targets() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['app', 'core']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("app/Cargo.toml"),
+            b"[package]\nname = 'app'\nversion = '0.1.0'\n\n[features]\nfull = []\n\n[[bin]]\nname = 'app-cli' # the main one\n".to_vec(),
+        );
+        fs.add_file(PathBuf::from("app/src/bin/app-cli.rs"), Vec::new());
+        fs.add_file(
+            PathBuf::from("core/Cargo.toml"),
+            b"[package]\nname = 'core'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(PathBuf::from("core/src/lib.rs"), Vec::new());
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let all = CrateMatcher::new(["*"]);
+        let rename = TargetEdit::RenameBin {
+            from: "app-cli".to_string(),
+            to: "app".to_string(),
+        };
+        let changed = cargo_manifest_service.edit_targets(&mut cargo_manifest, &all, &rename)?;
+        assert_eq!(changed, [PathBuf::from("app/Cargo.toml")]);
+        let features = TargetEdit::RequiredFeatures {
+            bin: "app".to_string(),
+            features: vec!["full".to_string()],
+        };
+        cargo_manifest_service.edit_targets(&mut cargo_manifest, &all, &features)?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("app/Cargo.toml"))?)?,
+            "[package]\nname = 'app'\nversion = '0.1.0'\n\n[features]\nfull = []\n\n[[bin]]\nname = \"app\" # the main one\npath = \"src/bin/app-cli.rs\"\nrequired-features = [\"full\"]\n"
+        );
+
+        let crate_type = TargetEdit::CrateType(vec!["cdylib".to_string(), "rlib".to_string()]);
+        let changed =
+            cargo_manifest_service.edit_targets(&mut cargo_manifest, &all, &crate_type)?;
+        assert_eq!(changed, [PathBuf::from("core/Cargo.toml")]);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("core/Cargo.toml"))?)?,
+            "[package]\nname = 'core'\nversion = '0.1.0'\n\n[lib]\ncrate-type = [\"cdylib\", \"rlib\"]\n"
+        );
+
+        let unknown = TargetEdit::RequiredFeatures {
+            bin: "app".to_string(),
+            features: vec!["nope".to_string()],
+        };
+        assert!(cargo_manifest_service
+            .edit_targets(&mut cargo_manifest, &all, &unknown)
+            .is_err());
+        assert!(cargo_manifest_service
+            .edit_targets(&mut cargo_manifest, &all, &rename)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_manifests_that_stay_the_same_alone() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\na = '0.2.0'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na = '0.2.0'".to_vec(),
+        );
+        // Writing either of these would fail.
+        fs.set_read_only(PathBuf::from("Cargo.toml"));
+        fs.set_read_only(PathBuf::from("b/Cargo.toml"));
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        cargo_manifest_service.update_version(&mut cargo_manifest, "a", "0.2.0")?;
+
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\nversion = '0.2.0'".to_vec()
+        );
+        assert_eq!(cargo_manifest.stats().files_written, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unwritable_manifests_are_reported_before_writing() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c']".to_vec(),
+        );
+        for member in ["a", "b", "c"] {
+            fs.add_file(
+                PathBuf::from(format!("{member}/Cargo.toml")),
+                b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec(),
+            );
+        }
+        fs.set_read_only(PathBuf::from("b"));
+        fs.set_read_only(PathBuf::from("c/Cargo.toml"));
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let err = cargo_manifest_service
+            .update_version(&mut cargo_manifest, "child", "0.2.0")
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "2 manifest(s) can't be written, nothing was changed:\n  b/Cargo.toml: read-only file system\n  c/Cargo.toml: read-only file system"
+        );
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fix_repairs_root_before_reading_members() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n[workspace.dependency]\na = 0.1\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = 0.1.0\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let fixed = CargoManifestService::new(fs.clone())
+            .fix(Path::new("Cargo.toml"), |path, _| {
+                path != Path::new("Cargo.toml")
+            })?;
+
+        assert_eq!(
+            fixed
+                .iter()
+                .map(|(path, repairs)| (display_path(path), repairs.len()))
+                .collect::<Vec<_>>(),
+            [("a/Cargo.toml".to_string(), 1)]
+        );
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\nversion = \"0.1.0\"\n".to_vec()
+        );
+        assert_eq!(
+            fs.read(Path::new("Cargo.toml"))?,
+            b"[workspace]\nmembers = ['a', 'b']\n[workspace.dependency]\na = 0.1\n".to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sync_aligns_drifted_internal_requirements() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c']\npackage = { version = '0.3.0' }".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.2.1'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion.workspace = true".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("c/Cargo.toml"),
+            b"[package]\nname = 'c'\nversion = '0.1.0'\n[dependencies]\na = { path = '../a', version = '0.1' }\nb = { path = '../b', version = '0.3' }\nc2 = { path = '../a', version = '0.1' }"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let drift = cargo_manifest_service.sync_versions(&mut cargo_manifest)?;
+        assert_eq!(
+            drift.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["c/Cargo.toml: `dependencies.a` requires 0.1, but a is at 0.2.1"]
+        );
+        assert_eq!(
+            fs.read(Path::new("c/Cargo.toml"))?,
+            b"[package]\nname = 'c'\nversion = '0.1.0'\n[dependencies]\na = { path = '../a', version = '0.2.1' }\nb = { path = '../b', version = '0.3' }\nc2 = { path = '../a', version = '0.1' }"
+                .to_vec()
+        );
+        assert!(cargo_manifest.requirement_drift().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn freeze_pins_requirements_to_locked_versions() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[package]\nname = 'root'\nversion = '0.1.0'\n[dependencies]\nserde = '1'\nsyn = { version = '2.0', features = ['full'] }\nanyhow = '=1.0.70'\n[dev-dependencies]\nold-syn = { package = 'syn', version = '1' }"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("Cargo.lock"),
+            b"[[package]]\nname = 'serde'\nversion = '1.0.163'\n[[package]]\nname = 'syn'\nversion = '1.0.109'\n[[package]]\nname = 'syn'\nversion = '2.0.18'\n[[package]]\nname = 'anyhow'\nversion = '1.0.71'"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let changes = cargo_manifest_service.freeze(&mut cargo_manifest, &["serde", "syn"])?;
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "Cargo.toml: `dependencies.serde` 1 -> =1.0.163",
+                "Cargo.toml: `dependencies.syn` 2.0 -> =2.0.18",
+                "Cargo.toml: `dev-dependencies.old-syn` 1 -> =1.0.109",
+            ]
+        );
+        assert_eq!(
+            fs.read(Path::new("Cargo.toml"))?,
+            b"[package]\nname = 'root'\nversion = '0.1.0'\n[dependencies]\nserde = '=1.0.163'\nsyn = { version = '=2.0.18', features = ['full'] }\nanyhow = '=1.0.70'\n[dev-dependencies]\nold-syn = { package = 'syn', version = '=1.0.109' }"
+                .to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn formats_after_edits_when_configured() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nversion='0.1.0'\nname='a'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nversion='0.1.0'\nname='b'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service =
+            CargoManifestService::new(fs.clone()).with_format(FormatStyle::default());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "a", "0.2.0")?;
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\nversion = '0.2.0'\n".to_vec()
+        );
+        assert_eq!(
+            fs.read(Path::new("b/Cargo.toml"))?,
+            b"[package]\nversion='0.1.0'\nname='b'\n".to_vec()
+        );
+
+        let normalized = cargo_manifest_service.normalize(&mut cargo_manifest)?;
+        assert_eq!(
+            normalized,
+            [PathBuf::from("Cargo.toml"), PathBuf::from("b/Cargo.toml")]
+        );
+        assert!(cargo_manifest_service
+            .normalize(&mut cargo_manifest)?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn removes_unused_workspace_dependencies() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a']\n\n[workspace.dependencies]\nanyhow = '1'\nserde = '1' # unused\ntoml = '0.7'\n\n[workspace.dependencies.tracing]\nversion = '0.1'\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n[dependencies]\nanyhow.workspace = true\ntoml = '0.7'\n"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(
+            cargo_manifest.unused_workspace_dependencies(),
+            ["serde", "toml", "tracing"]
+        );
+
+        cargo_manifest_service.remove_unused_workspace_dependencies(&mut cargo_manifest)?;
+        assert_eq!(
+            fs.read(Path::new("Cargo.toml"))?,
+            b"[workspace]\nmembers = ['a']\n\n[workspace.dependencies]\nanyhow = '1'\n".to_vec()
+        );
+        assert!(cargo_manifest.unused_workspace_dependencies().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sets_the_edition_across_the_workspace() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[package]\nname = 'root'\nversion = '0.1.0'\nedition = '2015'\n\n[workspace]\nmembers = ['a', 'b']\n\n[workspace.package]\nedition = \"2018\" # shared\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\nedition.workspace = true\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert!(cargo_manifest_service
+            .set_edition(&mut cargo_manifest, "2024", true)
+            .is_err());
+
+        let changed = cargo_manifest_service.set_edition(&mut cargo_manifest, "2021", true)?;
+        assert_eq!(
+            changed,
+            [PathBuf::from("Cargo.toml"), PathBuf::from("b/Cargo.toml")]
+        );
+        assert_eq!(
+            fs.read(Path::new("Cargo.toml"))?,
+            b"[package]\nname = 'root'\nversion = '0.1.0'\nedition = '2021'\n\n[workspace]\nmembers = ['a', 'b']\n\n[workspace.package]\nedition = \"2021\" # shared\n"
+                .to_vec()
+        );
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\nversion = '0.1.0'\nedition.workspace = true\n".to_vec()
+        );
+        assert_eq!(
+            fs.read(Path::new("b/Cargo.toml"))?,
+            b"[package]\nname = 'b'\nversion = '0.1.0'\nedition = \"2021\"\n".to_vec()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sets_the_rust_version_of_members() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new().member("a", "0.1.0");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        for invalid in ["1", "1.70.0.1", "1.x", "stable"] {
+            assert!(cargo_manifest_service
+                .set_rust_version(&mut cargo_manifest, invalid, true)
+                .is_err());
+        }
+
+        cargo_manifest_service.set_rust_version(&mut cargo_manifest, "1.70", false)?;
+        cargo_manifest_service.set_rust_version(&mut cargo_manifest, "1.74.1", true)?;
+        assert_eq!(
+            String::from_utf8(fs.read(&fixture.manifest_path("a"))?)?,
+            format!("{}rust-version = \"1.74.1\"\n", fixture.manifest("a"))
+        );
+        assert!(String::from_utf8(fs.read(&fixture.root_path())?)?
+            .contains("version = \"0.1.0\"\nrust-version = \"1.74.1\"\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn adds_workspace_dependencies_to_the_root_only() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new().member("a", "0.1.0");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let serde = NewDependency {
+            table: DependencyTable::new(DependencyKind::Workspace),
+            features: vec!["derive".to_string()],
+            ..NewDependency::new("serde", "1.0")
+        };
+        assert!(cargo_manifest_service
+            .add_dependency(&mut cargo_manifest, &fixture.manifest_path("a"), &serde)
+            .is_err());
+
+        cargo_manifest_service.add_dependency(&mut cargo_manifest, &fixture.root_path(), &serde)?;
+        assert_eq!(
+            String::from_utf8(fs.read(&fixture.root_path())?)?,
+            format!(
+                "{}\n[workspace.dependencies]\nserde = {{ version = \"1.0\", features = [\"derive\"] }}\n",
+                fixture.manifest("root")
+            )
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(&fixture.manifest_path("a"))?)?,
+            fixture.manifest("a")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn removes_a_dependency_everywhere() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\nold-lib = '1' # going away\nserde = '1'\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nold-lib.workspace = true\nserde.workspace = true\n\n[target.'cfg(unix)'.dev-dependencies]\nold-lib = '1'\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let removed = cargo_manifest_service.remove_dependency_everywhere(
+            &mut cargo_manifest,
+            "old-lib",
+            true,
+        )?;
+        assert_eq!(removed.len(), 3);
+        assert_eq!(
+            fs.read(Path::new("Cargo.toml"))?,
+            b"[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\nserde = '1'\n"
+                .to_vec()
+        );
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nserde.workspace = true\n\n[target.'cfg(unix)'.dev-dependencies]\n"
+                .to_vec()
+        );
+        assert!(cargo_manifest_service
+            .remove_dependency_everywhere(&mut cargo_manifest, "old-lib", true)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn adds_and_removes_members() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = [\n    # the tool\n    'cli',\n    'libs/*',\n]\ndefault-members = ['cli']\n\n[workspace.package]\nversion = '0.3.0'\n\n[workspace.dependencies]\ncli = { path = 'cli', version = '0.3' }\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("cli/Cargo.toml"),
+            b"[package]\nname = 'cli'\nversion.workspace = true\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("libs/core/Cargo.toml"),
+            b"[package]\nname = 'core'\nversion.workspace = true\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert!(cargo_manifest_service
+            .add_member(&mut cargo_manifest, "tools/gen", false)
+            .is_err());
+        assert!(cargo_manifest_service
+            .add_member(&mut cargo_manifest, "libs/core", true)
+            .is_err());
+        let added = cargo_manifest_service.add_member(&mut cargo_manifest, "tools/gen", true)?;
+        assert_eq!(added, Path::new("tools/gen/Cargo.toml"));
+        assert_eq!(
+            String::from_utf8(fs.read(&added)?)?,
+            "[package]\nname = \"gen\"\nversion.workspace = true\nedition = \"2021\"\n"
+        );
+        assert_eq!(
+            cargo_manifest.package_version("gen").as_deref(),
+            Some("0.3.0")
+        );
+        assert!(fs.exists(Path::new("tools/gen/src/lib.rs"))?);
+        // `libs/*` already takes it in.
+        cargo_manifest_service.add_member(&mut cargo_manifest, "libs/util", true)?;
+        assert!(cargo_manifest.package_version("util").is_some());
+
+        let removed = cargo_manifest_service.remove_member(&mut cargo_manifest, "cli", true)?;
+        assert_eq!(removed, ["cli"]);
+        cargo_manifest_service.remove_member(&mut cargo_manifest, "libs/core", false)?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = [\n    'libs/*',\n    \"tools/gen\",\n]\nexclude = [\"libs/core\"]\n\n[workspace.package]\nversion = '0.3.0'\n\n[workspace.dependencies]\n"
+        );
+        assert_eq!(
+            cargo_manifest
+                .members()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>(),
+            [
+                Path::new("libs/util/Cargo.toml"),
+                Path::new("tools/gen/Cargo.toml")
+            ]
+        );
+        assert!(cargo_manifest_service
+            .remove_member(&mut cargo_manifest, "libs/core", false)
+            .is_err());
+        cargo_manifest_service.add_member(&mut cargo_manifest, "./libs/core/", false)?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = [\n    'libs/*',\n    \"tools/gen\",\n]\n\n[workspace.package]\nversion = '0.3.0'\n\n[workspace.dependencies]\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn edits_the_features_of_every_declaration() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\ntokio = { version = '1', features = ['macros', 'rt'] } # async\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\ntokio = { workspace = true, features = ['macros'] }\n\n[dev-dependencies.tokio]\nversion = '1'\ndefault-features = true\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\ntokio = '1'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let edit = FeatureEdit {
+            add: vec!["rt-multi-thread".to_string()],
+            remove: vec!["macros".to_string()],
+            default_features: Some(false),
+        };
+        let changed = cargo_manifest_service.edit_features(&mut cargo_manifest, "tokio", &edit)?;
+        assert_eq!(changed.len(), 4);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\ntokio = { version = '1', features = ['rt', \"rt-multi-thread\"], default-features = false } # async\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\ntokio = { workspace = true, features = [\"rt-multi-thread\"] }\n\n[dev-dependencies.tokio]\nversion = '1'\ndefault-features = false\nfeatures = [\"rt-multi-thread\"]\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\ntokio = { version = \"1\", features = [\"rt-multi-thread\"], default-features = false }\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn points_git_dependencies_at_a_new_reference() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\ninternal-lib = { git = 'https://example.com/lib', rev = 'abc123' } # pinned\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\ninternal-lib.workspace = true\n\n[dev-dependencies.lib]\npackage = 'internal-lib'\ngit = 'https://example.com/lib'\nbranch = 'main'\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\ninternal-lib = '1'\n"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let reference = GitReference::Tag("v2.1.0".to_string());
+        let changed = cargo_manifest_service.update_git_reference(
+            &mut cargo_manifest,
+            &CrateMatcher::new(["internal-*"]),
+            &reference,
+        )?;
+        assert_eq!(changed.len(), 2);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\ninternal-lib = { git = 'https://example.com/lib', tag = \"v2.1.0\" } # pinned\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\ninternal-lib.workspace = true\n\n[dev-dependencies.lib]\npackage = 'internal-lib'\ngit = 'https://example.com/lib'\ntag = \"v2.1.0\"\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\ninternal-lib = '1'\n"
+        );
+
+        let changed = cargo_manifest_service.update_git_reference(
+            &mut cargo_manifest,
+            &CrateMatcher::new(["internal-lib"]),
+            &reference,
+        )?;
+        assert!(changed.is_empty());
+        assert!(cargo_manifest_service
+            .update_git_reference(&mut cargo_manifest, &CrateMatcher::new(["b"]), &reference)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn renames_a_crate_and_its_dependents() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['foo', 'a', 'b']\n\n[workspace.dependencies]\nfoo = { path = 'foo', version = '0.1' } # shared\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("foo/Cargo.toml"),
+            b"[package]\nname = 'foo'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nfoo = { workspace = true, optional = true }\n\n[features]\nextra = ['dep:foo', 'foo/std', 'foobar/std']\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dev-dependencies]\nold = { package = 'foo', path = '../foo' }\n"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert!(cargo_manifest_service
+            .rename_package(&mut cargo_manifest, "foo", "a")
+            .is_err());
+
+        let changed = cargo_manifest_service.rename_package(&mut cargo_manifest, "foo", "bar")?;
+        assert_eq!(changed.len(), 4);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['foo', 'a', 'b']\n\n[workspace.dependencies]\nbar = { path = 'foo', version = '0.1' } # shared\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("foo/Cargo.toml"))?)?,
+            "[package]\nname = 'bar'\nversion = '0.1.0'\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nbar = { workspace = true, optional = true }\n\n[features]\nextra = [\"dep:bar\", \"bar/std\", 'foobar/std']\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '0.1.0'\n\n[dev-dependencies]\nold = { package = 'bar', path = '../foo' }\n"
+        );
+        assert_eq!(
+            cargo_manifest.package_manifest("bar"),
+            Some(Path::new("foo/Cargo.toml"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sets_any_key_across_members() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new().member("a", "0.1.0");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let repository = KeyValue::String("https://example.com/repo".to_string());
+        assert!(cargo_manifest_service
+            .set_key(
+                &mut cargo_manifest,
+                &"package.version".parse()?,
+                &repository,
+                true
+            )
+            .is_err());
+
+        cargo_manifest_service.set_key(
+            &mut cargo_manifest,
+            &"package.repository".parse()?,
+            &repository,
+            true,
+        )?;
+        cargo_manifest_service.set_key(
+            &mut cargo_manifest,
+            &"workspace.metadata.release.tags".parse()?,
+            &KeyValue::Literal("['v1', 'v2']".to_string()),
+            true,
+        )?;
+        assert_eq!(
+            String::from_utf8(fs.read(&fixture.manifest_path("a"))?)?,
+            format!(
+                "{}repository = \"https://example.com/repo\"\n",
+                fixture.manifest("a")
+            )
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(&fixture.root_path())?)?,
+            "[package]\nname = \"root\"\nversion = \"0.1.0\"\nrepository = \"https://example.com/repo\"\n\n[workspace]\nmembers = [\"a\"]\n\n[workspace.metadata.release]\ntags = ['v1', 'v2']\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn edits_tool_metadata_keeping_other_keys() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[package.metadata.docs.rs]\nall-features = false # for now\ntargets = ['x86_64-unknown-linux-gnu']\n\n[package.metadata.other]\nkeep = true\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+        let service = CargoManifestService::new(fs.clone());
+        let mut manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let merge =
+            MetadataEdit::Merge("all-features = true\nrustdoc-args = ['--cfg', 'docsrs']".into());
+        service.edit_metadata(
+            &mut manifest,
+            MetadataScope::Packages,
+            &"docs.rs".parse()?,
+            &merge,
+        )?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[package.metadata.docs.rs]\nall-features = true # for now\ntargets = ['x86_64-unknown-linux-gnu']\nrustdoc-args = ['--cfg', 'docsrs']\n\n[package.metadata.other]\nkeep = true\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '0.1.0'\n\n[package.metadata.docs.rs]\nall-features = true\nrustdoc-args = ['--cfg', 'docsrs']\n"
+        );
+
+        let delete = MetadataEdit::Delete(None);
+        service.edit_metadata(
+            &mut manifest,
+            MetadataScope::Packages,
+            &"docs.rs".parse()?,
+            &delete,
+        )?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '0.1.0'\n"
+        );
+        assert!(String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?
+            .ends_with("version = '0.1.0'\n\n[package.metadata.other]\nkeep = true\n"));
+
+        let set = MetadataEdit::Set("tag-prefix".parse()?, KeyValue::String("v".to_string()));
+        let changed = service.edit_metadata(
+            &mut manifest,
+            MetadataScope::Workspace,
+            &"release".parse()?,
+            &set,
+        )?;
+        assert_eq!(changed, [Path::new("Cargo.toml")]);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a', 'b']\n\n[workspace.metadata.release]\ntag-prefix = \"v\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn adds_lists_and_removes_patches() -> anyhow::Result<()> {
+        use crate::dependencies::GitReference;
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = []\n\n[patch.crates-io]\nlocal = { path = '../local' }\n"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+        let service = CargoManifestService::new(fs.clone());
+        let mut manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let serde = PatchEntry {
+            registry: "crates-io".to_string(),
+            name: "serde".to_string(),
+            source: PatchSource::Git {
+                url: "https://github.com/serde-rs/serde".to_string(),
+                reference: Some(GitReference::Rev("abc123".to_string())),
+            },
+        };
+        service.set_patch(&mut manifest, &serde)?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = []\n\n[patch.crates-io]\nlocal = { path = '../local' }\nserde = { git = \"https://github.com/serde-rs/serde\", rev = \"abc123\" }\n"
+        );
+        assert_eq!(
+            manifest
+                .patches()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "local from crates-io: path ../local",
+                "serde from crates-io: git https://github.com/serde-rs/serde at rev abc123",
+            ]
+        );
+
+        service.remove_patch(&mut manifest, "crates-io", "local")?;
+        service.remove_patch(&mut manifest, "crates-io", "serde")?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = []\n"
+        );
+        assert!(service
+            .remove_patch(&mut manifest, "crates-io", "serde")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sets_checked_profile_settings() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = []\n\n[profile.release]\nlto = false # measured\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+        let service = CargoManifestService::new(fs.clone());
+        let mut manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+        let settings = |settings: &[(&str, &str)]| {
+            settings
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        service.set_profile(
+            &mut manifest,
+            "release",
+            &settings(&[("lto", "thin"), ("codegen-units", "1"), ("strip", "true")]),
+        )?;
+        service.set_profile(
+            &mut manifest,
+            "dist",
+            &settings(&[("inherits", "release"), ("opt-level", "z")]),
+        )?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = []\n\n[profile.release]\nlto = \"thin\" # measured\ncodegen-units = 1\nstrip = true\n\n[profile.dist]\ninherits = \"release\"\nopt-level = \"z\"\n"
+        );
+
+        let err = service
+            .set_profile(&mut manifest, "fast", &settings(&[("lto", "fat")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("needs `inherits`"));
+        assert!(service
+            .set_profile(&mut manifest, "dev", &settings(&[("opt-level", "fast")]))
+            .is_err());
+        assert!(!service.set_profile(&mut manifest, "dist", &settings(&[("opt-level", "z")]))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rolls_out_workspace_lints() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c']\n\n[workspace.lints.rust]\nunsafe_code = 'warn'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[lints.rust]\nunused = 'allow'\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("c/Cargo.toml"),
+            b"[package]\nname = 'c'\nversion = '0.1.0'\n\n[lints]\nworkspace = true\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+        let service = CargoManifestService::new(fs.clone());
+        let mut manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let settings = ["unsafe_code=deny", "clippy::pedantic=warn:-1"]
+            .map(|setting| setting.parse::<LintSetting>())
+            .into_iter()
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert!(service.set_workspace_lints(&mut manifest, &settings)?);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a', 'b', 'c']\n\n[workspace.lints.rust]\nunsafe_code = \"deny\"\n\n[workspace.lints.clippy]\npedantic = { level = \"warn\", priority = -1 }\n"
+        );
+
+        let inheritance = service.inherit_workspace_lints(&mut manifest)?;
+        assert_eq!(
+            inheritance,
+            LintInheritance {
+                inherited: vec![PathBuf::from("a/Cargo.toml")],
+                own_lints: vec![PathBuf::from("b/Cargo.toml")],
+            }
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[lints]\nworkspace = true\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn updates_every_crate_matching_a_pattern() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("my-org-core", "0.1.0")
+            .member("my-org-cli", "0.1.0")
+            .member("other", "0.1.0")
+            .dep("my-org-core", "my-org-cli")
+            .dep("my-org-core", "other");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let matched = cargo_manifest_service.update_version(
+            &mut cargo_manifest,
+            CrateMatcher::new(["my-org-*"]),
+            "0.2.0",
+        )?;
+        assert_eq!(matched, ["my-org-cli", "my-org-core"]);
+        fixture
+            .version("my-org-core", "0.2.0")
+            .version("my-org-cli", "0.2.0")
+            .assert_files(&fs);
+
+        assert!(cargo_manifest_service
+            .update_version(&mut cargo_manifest, CrateMatcher::new(["their-*"]), "0.3.0")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn lints_and_fixes_dependency_declarations() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c']\n\n[workspace.dependencies]\nserde = '1.0.200'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nlog = '0.4.20'\nserde = { version = '1.0.100', features = ['derive'] }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na = { path = '../a' }\nlog = '0.4.21'\nserde.workspace = true\n\n[dev-dependencies]\nc = { path = '../c' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("c/Cargo.toml"),
+            b"[package]\nname = 'c'\nversion = '0.3.0'\npublish = false\n\n[dependencies]\na = { path = '../a' }\nserde = { version = '1.0.200', default-features = false }\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let findings = cargo_manifest.lint();
+        assert_eq!(
+            findings
+                .iter()
+                .map(|f| (
+                    display_path(&f.manifest),
+                    f.key.join("."),
+                    f.rule,
+                    f.fix.clone()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "a/Cargo.toml".to_string(),
+                    "dependencies.log".to_string(),
+                    LintRule::DivergentVersions,
+                    None
+                ),
+                (
+                    "a/Cargo.toml".to_string(),
+                    "dependencies.serde".to_string(),
+                    LintRule::NotInherited,
+                    Some(LintFix::Inherit)
+                ),
+                (
+                    "a/Cargo.toml".to_string(),
+                    "dependencies.serde".to_string(),
+                    LintRule::DivergentVersions,
+                    None
+                ),
+                (
+                    "b/Cargo.toml".to_string(),
+                    "dependencies.a".to_string(),
+                    LintRule::PathWithoutVersion,
+                    Some(LintFix::SetVersion("0.1.0".to_string()))
+                ),
+                (
+                    "c/Cargo.toml".to_string(),
+                    "dependencies.serde".to_string(),
+                    LintRule::NotInherited,
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            findings[2].message,
+            "serde is required at different versions: 1.0.100 by a; 1.0.200 by b, c"
+        );
+        assert_eq!(cargo_manifest.lint_annotation(&findings[3]).line, Some(6));
+
+        let fixed = cargo_manifest_service.fix_lints(&mut cargo_manifest)?;
+        assert_eq!(fixed.len(), 2);
+        assert!(String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?
+            .contains("serde = { workspace = true, features = ['derive'] }\n"));
+        assert!(String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?
+            .contains("a = { path = '../a', version = \"0.1.0\" }\n"));
+        assert_eq!(
+            cargo_manifest
+                .lint()
+                .iter()
+                .map(|f| f.rule)
+                .collect::<Vec<_>>(),
+            [LintRule::DivergentVersions, LintRule::NotInherited]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn plans_changes_before_applying_them() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.1.0")
+            .member("b", "0.1.0")
+            .dep("a", "b");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let change_set = cargo_manifest_service.plan(
+            &cargo_manifest,
+            &[Operation::SetVersion("a".to_string(), "0.2.0".to_string())],
+        )?;
+        fixture.assert_files(&*fs);
+        assert_eq!(
+            cargo_manifest.package_version("a").as_deref(),
+            Some("0.1.0")
+        );
+        assert_eq!(
+            change_set
+                .edits
+                .iter()
+                .map(|edit| (
+                    display_path(&edit.file),
+                    edit.table.as_str(),
+                    edit.key.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("a/Cargo.toml".to_string(), "package", "version"),
+                ("b/Cargo.toml".to_string(), "dependencies", "a.version"),
+            ]
+        );
+
+        let change_set: ChangeSet = serde_json::from_str(&serde_json::to_string(&change_set)?)?;
+        let written = cargo_manifest_service.apply(&mut cargo_manifest, &change_set)?;
+        assert_eq!(written.len(), 2);
+        fixture.clone().version("a", "0.2.0").assert_files(&*fs);
+        assert_eq!(
+            cargo_manifest.package_version("a").as_deref(),
+            Some("0.2.0")
+        );
+
+        let err = cargo_manifest_service
+            .apply(&mut cargo_manifest, &change_set)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("changed since the change set was planned"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_updates_unmet_requirements_if_needed() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.1.0")
+            .member("b", "0.1.0")
+            .member("c", "0.1.0")
+            .requires("a", "serde", "^1.4")
+            .requires("b", "serde", "=1.4.2")
+            .requires("c", "serde", "~1.3");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone()).with_if_needed(true);
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        cargo_manifest_service.update_versions(&mut cargo_manifest, &[("serde", "1.4.7")])?;
+
+        assert_eq!(
+            String::from_utf8(fs.read(&fixture.manifest_path("a"))?)?,
+            fixture.manifest("a")
+        );
+        let requirement = |member: &str| -> anyhow::Result<String> {
+            let manifest = String::from_utf8(fs.read(&fixture.manifest_path(member))?)?;
+            Ok(manifest.lines().last().unwrap_or_default().to_string())
+        };
+        assert_eq!(requirement("b")?, "serde = \"=1.4.7\"");
+        assert_eq!(requirement("c")?, "serde = \"~1.4.7\"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn setting_the_current_version_writes_nothing() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.1.0")
+            .member("b", "0.1.0")
+            .requires("b", "a", "0.1");
+        let fs = crate::filesystem::TrackingFileSystem::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(&fs);
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        cargo_manifest_service.update_versions(&mut cargo_manifest, &[("a", "0.1.0")])?;
+
+        assert_eq!(fs.changed_files(), Vec::<PathBuf>::new());
+        fixture.assert_files(&fs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_versions_missing_from_the_registry() -> anyhow::Result<()> {
+        struct FakeRegistry;
+
+        impl Registry for FakeRegistry {
+            fn versions(&self, _: &str) -> anyhow::Result<Vec<registry::PublishedVersion>> {
+                Ok([("1.0.199", false), ("1.0.200", true)]
+                    .map(|(version, yanked)| registry::PublishedVersion {
+                        version: version.to_string(),
+                        yanked,
+                    })
+                    .to_vec())
+            }
+        }
+
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.1.0")
+            .member("b", "0.1.0")
+            .requires("b", "serde", "1.0.190");
+        let fs = std::sync::Arc::new(fixture.file_system());
+        let service =
+            CargoManifestService::new(fs.clone()).with_registry_check(FakeRegistry, false);
+        let mut cargo_manifest = service.load_manifest(&fixture.root_path())?;
+
+        let error = service
+            .update_versions(
+                &mut cargo_manifest,
+                &[("serde", "1.0.1999"), ("a", "0.2.0")],
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "1 version(s) not on the registry, nothing was changed:\n  serde 1.0.1999 was never published"
+        );
+        let error = service
+            .update_versions(&mut cargo_manifest, &[("serde", "1.0.200")])
+            .unwrap_err();
+        assert!(error.to_string().contains("serde 1.0.200 is yanked"));
+        fixture.assert_files(&*fs);
+
+        service.update_versions(&mut cargo_manifest, &[("serde", "1.0.199")])?;
+        CargoManifestService::new(fs.clone())
+            .with_registry_check(FakeRegistry, true)
+            .update_versions(&mut cargo_manifest, &[("serde", "1.0.200")])?;
+        assert!(String::from_utf8(fs.read(&fixture.manifest_path("b"))?)?
+            .ends_with("serde = \"1.0.200\"\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn fails_for_crates_found_nowhere() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("my-org-core", "0.1.0")
+            .member("my-org-cli", "0.1.0")
+            .dep("my-org-core", "my-org-cli");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let error = cargo_manifest_service
+            .update_versions(
+                &mut cargo_manifest,
+                &[("my-org-cor", "0.2.0"), ("tpyo", "1.0.0")],
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "2 crate(s) not found, nothing was changed:\n  `my-org-cor` is not a package or dependency of the workspace, did you mean `my-org-core`?\n  `tpyo` is not a package or dependency of the workspace"
+        );
+        fixture.assert_files(&fs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_the_values_a_change_edited() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.1.0")
+            .member("b", "0.1.0")
+            .dep("a", "b");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let before = cargo_manifest.clone();
+        let updated = cargo_manifest_service.update_version(&mut cargo_manifest, "a", "0.2.0")?;
+        let report = cargo_manifest_service.change_report(&before, &cargo_manifest, &updated)?;
+
+        let change = |key: &[&str]| Change {
+            package: "a".to_string(),
+            key: key.iter().map(|k| k.to_string()).collect(),
+            from: Some("0.1.0".to_string()),
+            to: "0.2.0".to_string(),
+            inherited_by: Vec::new(),
+        };
+        assert_eq!(
+            report,
+            ChangeReport {
+                root: fixture.root_path(),
+                files: vec![
+                    ChangedFile {
+                        path: fixture.manifest_path("a"),
+                        changes: vec![change(&["package", "version"])],
+                    },
+                    ChangedFile {
+                        path: fixture.manifest_path("b"),
+                        changes: vec![change(&["dependencies", "a", "version"])],
+                    },
+                ],
+            }
+        );
+        assert!(cargo_manifest_service
+            .change_report(&cargo_manifest, &cargo_manifest, &updated)?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn annotates_violations_and_changes_on_their_lines() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.2.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na = { path = '../a', version = '0.1' }\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let violations = cargo_manifest.check(&[("b".to_string(), "1.0.0".to_string())], None);
+        assert_eq!(
+            violations
+                .iter()
+                .map(|violation| cargo_manifest.violation_annotation(violation).to_string())
+                .collect::<Vec<_>>(),
+            [
+                "::error file=b/Cargo.toml,line=6::b/Cargo.toml: `dependencies.a` requires 0.1, but a is at 0.2.0",
+                "::error file=b/Cargo.toml,line=3::b is at 0.1.0, expected 1.0.0",
+            ]
+        );
+
+        let before = cargo_manifest.clone();
+        let updated = cargo_manifest_service.update_version(&mut cargo_manifest, "a", "0.3.0")?;
+        let report = cargo_manifest_service.change_report(&before, &cargo_manifest, &updated)?;
+        assert_eq!(
+            cargo_manifest
+                .change_annotations(&report)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "::notice file=a/Cargo.toml,line=3::`package.version` changed from 0.2.0 to 0.3.0 for a",
+                "::notice file=b/Cargo.toml,line=6::`dependencies.a.version` changed from 0.1 to 0.3.0 for a",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_members_inheriting_a_changed_dependency() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c']\n[workspace.dependencies]\na = { path = 'a', version = '0.1.0' }".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'".to_vec(),
+        );
+        let inheriting = b"[package]\nname = 'b'\nversion = '0.1.0'\n[dependencies]\na = { workspace = true, features = ['x'] }".to_vec();
+        fs.add_file(PathBuf::from("b/Cargo.toml"), inheriting.clone());
+        fs.add_file(
+            PathBuf::from("c/Cargo.toml"),
+            b"[package]\nname = 'c'\nversion = '0.1.0'\n[dev-dependencies]\na.workspace = true"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(cargo_manifest.inheriting_members("a"), ["b", "c"]);
+
+        let before = cargo_manifest.clone();
+        let updated = cargo_manifest_service.update_version(&mut cargo_manifest, "a", "0.2.0")?;
+        let report = cargo_manifest_service.change_report(&before, &cargo_manifest, &updated)?;
+        assert_eq!(
+            report
+                .files
+                .iter()
+                .flat_map(|file| &file.changes)
+                .map(|change| (change.key.join("."), change.inherited_by.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "workspace.dependencies.a.version".to_string(),
+                    vec!["b".to_string(), "c".to_string()]
+                ),
+                ("package.version".to_string(), Vec::new()),
+            ]
+        );
+        assert_eq!(fs.read(Path::new("b/Cargo.toml"))?, inheriting);
+
+        Ok(())
+    }
+
+    #[test]
+    fn hoists_shared_dependencies_into_the_workspace() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nserde = { version = '1.0.100', features = ['derive'] }\ntoml = '0.7'\nlocal = { path = '../local', version = '0.1' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\nserde = '1.0.160'\nlocal = { path = '../local', version = '0.1' }\n\n[dev-dependencies]\nsyn = '2'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let hoisted = cargo_manifest_service.hoist_dependencies(&mut cargo_manifest, 2)?;
+        assert_eq!(
+            hoisted.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["serde 1.0.160 from a/Cargo.toml, b/Cargo.toml"]
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?,
+            "[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\nserde = \"1.0.160\"\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nserde = { workspace = true, features = ['derive'] }\ntoml = '0.7'\nlocal = { path = '../local', version = '0.1' }\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?,
+            "[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\nserde = { workspace = true }\nlocal = { path = '../local', version = '0.1' }\n\n[dev-dependencies]\nsyn = '2'\n"
+        );
+        assert!(cargo_manifest_service
+            .hoist_dependencies(&mut cargo_manifest, 2)?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn checks_the_version_invariants() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.2.0'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n[dependencies]\na = { path = '../a', version = '0.1' }"
+                .to_vec(),
+        );
+
+        let cargo_manifest_service = CargoManifestService::new(fs);
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let expected = [
+            ("a".to_string(), "0.2.0".to_string()),
+            ("b".to_string(), "0.2.0".to_string()),
+            ("c".to_string(), "1.0.0".to_string()),
+        ];
+        assert_eq!(
+            cargo_manifest
+                .check(&expected, Some(Versioning::Lockstep))
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "b/Cargo.toml: `dependencies.a` requires 0.1, but a is at 0.2.0",
+                "b is at 0.1.0, but the workspace is versioned in lockstep at 0.2.0",
+                "b is at 0.1.0, expected 0.2.0",
+                "c was expected at 1.0.0, but it isn't a versioned member of the workspace",
+            ]
+        );
+        assert_eq!(
+            cargo_manifest
+                .check(&expected[..1], Some(Versioning::Independent))
+                .len(),
+            1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn updates_members_of_a_virtual_workspace() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .virtual_manifest()
+            .member("a", "0.1.0")
+            .member("b", "0.2.0")
+            .dep("a", "b");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        assert_eq!(
+            cargo_manifest.package_versions(),
+            [
+                ("a".to_string(), Some("0.1.0".to_string())),
+                ("b".to_string(), Some("0.2.0".to_string()))
+            ]
+        );
+
+        cargo_manifest_service.update_version(&mut cargo_manifest, "a", "0.3.0")?;
+        let fixture = fixture.version("a", "0.3.0");
+        fixture.assert_files(&fs);
+
+        let bumped =
+            cargo_manifest_service.bump_workspace(&mut cargo_manifest, BumpLevel::Minor, None)?;
+        assert_eq!(
+            bumped,
+            [
+                ("a".to_string(), "0.4.0".to_string()),
+                ("b".to_string(), "0.3.0".to_string())
+            ]
+        );
+        fixture
+            .version("a", "0.4.0")
+            .version("b", "0.3.0")
+            .assert_files(&fs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn aligns_every_crate_on_one_version() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.3.0")
+            .member("b", "0.10.0")
+            .dep("a", "b")
+            .dep("b", "root");
+        let fs = std::sync::Arc::new(fixture.file_system());
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(&fixture.root_path())?;
+        let aligned = cargo_manifest_service.align_versions(&mut cargo_manifest, None)?;
+        assert_eq!(
+            aligned,
+            [
+                ("a".to_string(), "0.10.0".to_string()),
+                ("root".to_string(), "0.10.0".to_string())
+            ]
+        );
+        let fixture = fixture.version("a", "0.10.0").version("root", "0.10.0");
+        fixture.assert_files(&fs);
+        assert_eq!(
+            cargo_manifest.written_files().collect::<Vec<_>>(),
+            [
+                fixture.root_path(),
+                fixture.manifest_path("a"),
+                fixture.manifest_path("b")
+            ]
+        );
+
+        cargo_manifest_service.align_versions(&mut cargo_manifest, Some("1.0.0"))?;
+        fixture
+            .version("a", "1.0.0")
+            .version("b", "1.0.0")
+            .version("root", "1.0.0")
+            .assert_files(&fs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unifies_conflicting_declarations_within_a_manifest() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a']\n[workspace.dependencies]\nserde = '1.0.160'".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n[dependencies]\nsyn = '1.0.100'\nserde.workspace = true\ntoml = '0.7'\n[dev-dependencies]\nsyn = { version = '1.0.109', features = ['full'] }\nserde = '1'\ntoml = '0.7'"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(
+            cargo_manifest
+                .duplicate_dependencies()
+                .iter()
+                .map(|d| d.package.as_str())
+                .collect::<Vec<_>>(),
+            ["serde", "syn"]
+        );
+
+        let changes = cargo_manifest_service.unify_duplicate_dependencies(&mut cargo_manifest)?;
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "a/Cargo.toml: `dependencies.syn` 1.0.100 -> 1.0.109",
+                "a/Cargo.toml: `dev-dependencies.serde` 1 -> 1.0.160",
+            ]
+        );
+        assert!(cargo_manifest.duplicate_dependencies().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn applies_a_versions_file_in_one_pass() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.4'\n[dependencies]\nb = { path = '../b', version = '1' }".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '1.2.0'".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let file = VersionsFile::parse("a = 'minor'\nc = '1.0.0'")?;
+        assert_eq!(
+            cargo_manifest_service
+                .apply_versions(&mut cargo_manifest, &file)
+                .unwrap_err()
+                .to_string(),
+            "`c` is not a package of the workspace, nothing was changed"
+        );
+
+        let file = VersionsFile::parse("a = 'minor'\nb = '2.0.0'")?;
+        let applied = cargo_manifest_service.apply_versions(&mut cargo_manifest, &file)?;
+        assert_eq!(
+            applied,
+            [
+                ("a".to_string(), "0.2.0".to_string()),
+                ("b".to_string(), "2.0.0".to_string())
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.2.0'\n[dependencies]\nb = { path = '../b', version = '2.0.0' }"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bumps_the_workspace_to_the_next_development_version() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '1.4.0'\n[dependencies]\nb = { path = '../b', version = '0.3.1' }".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.3.1'".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let bumped = cargo_manifest_service.bump_workspace(
+            &mut cargo_manifest,
+            BumpLevel::Minor,
+            Some("alpha.0"),
+        )?;
+        assert_eq!(
+            bumped,
+            [
+                ("a".to_string(), "1.5.0-alpha.0".to_string()),
+                ("b".to_string(), "0.4.0-alpha.0".to_string())
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '1.5.0-alpha.0'\n[dependencies]\nb = { path = '../b', version = '0.4.0-alpha.0' }"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_code_actions_to_text_edits() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n".to_vec(),
+        );
+        let a = "[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nb = { path = '../b', version = '0.1' }\nserde = { version = '1.0.160', features = ['derive'] }\n";
+        fs.add_file(PathBuf::from("a/Cargo.toml"), a.as_bytes().to_vec());
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.2.0'\n".to_vec(),
+        );
+
+        let cargo_manifest_service = CargoManifestService::new(fs);
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let a_path = Path::new("a/Cargo.toml");
+        let offset = |needle: &str| a.find(needle).unwrap();
+
+        let actions = cargo_manifest.code_actions(a_path, offset("'0.1'"));
+        assert_eq!(
+            actions,
+            [CodeAction::Sync {
+                manifest: a_path.to_path_buf(),
+                key: vec!["dependencies".to_string(), "b".to_string()],
+            }]
+        );
+        let edits = cargo_manifest_service.code_action_edits(&cargo_manifest, &actions[0])?;
+        assert_eq!(
+            edits.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["a/Cargo.toml:89..90: \"2.0\""]
+        );
+
+        let actions = cargo_manifest.code_actions(a_path, offset("serde"));
+        let edits = cargo_manifest_service.code_action_edits(&cargo_manifest, &actions[0])?;
+        assert_eq!(
+            actions[0].to_string(),
+            "Move serde to workspace.dependencies"
+        );
+        assert_eq!(
+            edits,
+            [
+                TextEdit {
+                    path: PathBuf::from("Cargo.toml"),
+                    range: 33..33,
+                    new_text: "\n[workspace.dependencies]\nserde = '1.0.160'\n".to_string(),
+                },
+                TextEdit {
+                    path: a_path.to_path_buf(),
+                    range: 104..123,
+                    new_text: "workspace = true".to_string(),
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_build_info_as_metadata_or_rust() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let info = BuildInfo {
+            commit: "abc1234".to_string(),
+            timestamp: "2024-06-01T12:00:00Z".to_string(),
+        };
+
+        let mut config = BuildInfoConfig {
+            crates: vec!["a".to_string()],
+            format: BuildInfoFormat::Metadata,
+            ..BuildInfoConfig::default()
+        };
+        cargo_manifest_service.write_build_info(&mut cargo_manifest, &config, &info)?;
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?,
+            "[package]\nname = 'a'\nversion = '0.1.0'\n\n[package.metadata.build-info]\nversion = \"0.1.0\"\ngit-sha = \"abc1234\"\ntimestamp = \"2024-06-01T12:00:00Z\"\n\n[dependencies]\n"
+        );
+
+        config.format = BuildInfoFormat::Rust;
+        let written =
+            cargo_manifest_service.write_build_info(&mut cargo_manifest, &config, &info)?;
+        assert_eq!(written, [PathBuf::from("a/src/build_info.rs")]);
+        assert!(String::from_utf8(fs.read(&written[0])?)?
+            .contains("pub const GIT_SHA: &str = \"abc1234\";"));
+
+        Ok(())
+    }
+
+    /// Fails every write to one of `paths`.
+    struct FailingWrites<F> {
+        fs: F,
+        paths: Vec<PathBuf>,
+    }
+
+    impl<F: FileSystem> FileSystem for FailingWrites<F> {
+        fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+            self.fs.read(path)
+        }
+
+        fn write(&self, path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+            if self.paths.iter().any(|p| p == path) {
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.fs.write(path, contents)
+        }
+
+        fn remove(&self, path: &Path) -> std::io::Result<()> {
+            self.fs.remove(path)
+        }
+    }
+
+    #[test]
+    fn adds_removes_and_upgrades_dependencies() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[package]\nname = 'root'\nversion = '0.1.0'\n\n[dependencies]\nserde = '1.0'\nsyn = '=2.0.0'\n"
+                .to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("Cargo.lock"),
+            b"[[package]]\nname = 'serde'\nversion = '1.0.163'\n[[package]]\nname = 'syn'\nversion = '2.0.0'\n[[package]]\nname = 'anyhow'\nversion = '1.0.71'"
+                .to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let anyhow = NewDependency {
+            table: DependencyTable::new(DependencyKind::Dev),
+            features: vec!["backtrace".to_string()],
+            ..NewDependency::new("anyhow", "1")
+        };
+        cargo_manifest_service.add_dependency(
+            &mut cargo_manifest,
+            Path::new("Cargo.toml"),
+            &anyhow,
+        )?;
+        let removed = cargo_manifest_service.remove_dependency(
+            &mut cargo_manifest,
+            Path::new("Cargo.toml"),
+            "syn",
+            &[
+                DependencyTable::new(DependencyKind::Normal),
+                DependencyTable::new(DependencyKind::Dev),
+            ],
+        )?;
+        assert_eq!(removed, [DependencyTable::new(DependencyKind::Normal)]);
+
+        let changes = cargo_manifest_service.upgrade(&mut cargo_manifest, &[] as &[&str])?;
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "Cargo.toml: `dependencies.serde` 1.0 -> 1.0.163",
+                "Cargo.toml: `dev-dependencies.anyhow` 1 -> 1.0.71",
+            ]
+        );
+        assert_eq!(
+            std::str::from_utf8(&fs.read(Path::new("Cargo.toml"))?)?,
+            "[package]\nname = 'root'\nversion = '0.1.0'\n\n[dependencies]\nserde = '1.0.163'\n\n[dev-dependencies]\nanyhow = { version = \"1.0.71\", features = [\"backtrace\"] }\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn policy_is_checked_before_writing() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '1.4.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let policy = Policy {
+            major_requires_flag: true,
+            release_branches: vec!["main".to_string()],
+            approval_files: BTreeMap::from([("b".to_string(), PathBuf::from("APPROVED"))]),
+            protected: Vec::new(),
+        };
+        let context = PolicyContext {
+            allow_major: false,
+            branch: Some("main".to_string()),
+            override_protection: false,
+        };
+        let cargo_manifest_service =
+            CargoManifestService::new(fs.clone()).with_policy(policy, context);
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let error = cargo_manifest_service
+            .update_versions(&mut cargo_manifest, &[("a", "2.0.0"), ("b", "0.2.0-rc.1")])
+            .unwrap_err();
+        let Error::Policy(error) = error else {
+            panic!("expected a policy error, got {error:#}");
+        };
+        assert_eq!(
+            error
+                .violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "a 1.4.0 -> 2.0.0: major bumps need `--allow-major`",
+                "b 0.1.0 -> 0.2.0-rc.1: pre-releases aren't allowed on `main`",
+                "b 0.1.0 -> 0.2.0-rc.1: needs the approval file APPROVED",
+            ]
+        );
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\nversion = '1.4.0'\n".to_vec()
+        );
+
+        fs.write(Path::new("APPROVED"), Vec::new())?;
+        cargo_manifest_service
+            .update_versions(&mut cargo_manifest, &[("a", "1.5.0"), ("b", "0.2.0")])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn banned_versions_are_refused_and_reported() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\nopenssl = '0.10.40'\nlog = '=0.4.1'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("Cargo.lock"),
+            b"[[package]]\nname = 'openssl'\nversion = '0.10.45'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let banned = toml::from_str("openssl = ['=0.10.45']\nlog = ['<0.4.5']")?;
+        let cargo_manifest_service =
+            CargoManifestService::new(fs.clone()).with_banned_versions(banned);
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        assert_eq!(
+            cargo_manifest_service
+                .banned_dependencies(&cargo_manifest)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "Cargo.toml: `dependencies.log` requires log 0.4.1, banned by `<0.4.5`",
+                "Cargo.lock: openssl is locked at 0.10.45, banned by `=0.10.45`",
+            ]
+        );
+
+        let error = cargo_manifest_service
+            .upgrade(&mut cargo_manifest, &["openssl"])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "1 requirement(s) would use a banned version, nothing was changed:\n  \
+             Cargo.toml: `dependencies.openssl` requires openssl 0.10.45, banned by `=0.10.45`"
+        );
+        let error = cargo_manifest_service
+            .add_dependency(
+                &mut cargo_manifest,
+                Path::new("Cargo.toml"),
+                &NewDependency::new("log", "0.4.2"),
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("banned by `<0.4.5`"));
+        assert!(
+            String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?.contains("openssl = '0.10.40'")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn traces_why_members_depend_on_a_crate() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['app', 'core', 'cli']\n\n[workspace.dependencies]\nhttp = { package = 'reqwest', version = '0.11' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("app/Cargo.toml"),
+            b"[package]\nname = 'app'\nversion = '0.1.0'\n\n[dependencies]\ncore = { path = '../core' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("core/Cargo.toml"),
+            b"[package]\nname = 'core'\nversion = '0.1.0'\n\n[dependencies]\nhttp.workspace = true\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("cli/Cargo.toml"),
+            b"[package]\nname = 'cli'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("Cargo.lock"),
+            b"[[package]]\nname = 'core'\nversion = '0.1.0'\ndependencies = ['reqwest']\n\n[[package]]\nname = 'reqwest'\nversion = '0.11.18'\ndependencies = ['hyper 0.14.26']\n\n[[package]]\nname = 'hyper'\nversion = '0.14.26'\ndependencies = ['tokio 1.28.0 (registry+https://github.com/rust-lang/crates.io-index)']\n".to_vec(),
+        );
+
+        let cargo_manifest_service = CargoManifestService::new(fs);
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        assert_eq!(
+            cargo_manifest_service
+                .why(&cargo_manifest, "tokio")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "app: app -> core -> reqwest -> hyper -> tokio (via `dependencies.http` in core/Cargo.toml)",
+                "core: core -> reqwest -> hyper -> tokio (via `dependencies.http` in core/Cargo.toml)",
+            ]
+        );
+        assert_eq!(cargo_manifest_service.why(&cargo_manifest, "core").len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_what_is_behind_the_registry() -> anyhow::Result<()> {
+        struct FakeRegistry(BTreeMap<&'static str, Vec<&'static str>>);
+
+        impl Registry for FakeRegistry {
+            fn versions(&self, name: &str) -> anyhow::Result<Vec<registry::PublishedVersion>> {
+                Ok(self
+                    .0
+                    .get(name)
+                    .into_iter()
+                    .flatten()
+                    .map(|version| registry::PublishedVersion {
+                        version: version.to_string(),
+                        yanked: false,
+                    })
+                    .collect())
+            }
+        }
+
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b', 'c']\n\n[workspace.dependencies]\nserde = '1.0.100'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.2.0'\n\n[dependencies]\nb = { path = '../b', version = '0.1' }\nserde.workspace = true\nlog = '0.3'\nhttp = { package = 'reqwest', version = '0.11' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("c/Cargo.toml"),
+            b"[package]\nname = 'c'\nversion = '0.1.0'\n".to_vec(),
+        );
+
+        let registry = FakeRegistry(BTreeMap::from([
+            ("a", vec!["0.1.0"]),
+            ("b", vec!["0.1.0"]),
+            ("serde", vec!["1.0.100", "1.0.200"]),
+            ("log", vec!["0.3.9", "0.4.20"]),
+            ("reqwest", vec!["0.12.0", "0.11.0"]),
+        ]));
+        let cargo_manifest_service = CargoManifestService::new(fs);
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let report = cargo_manifest_service.outdated(&cargo_manifest, &registry)?;
+        assert_eq!(
+            report.to_string(),
+            "member  local  published\n\
+             a       0.2.0  0.1.0\n\
+             c       0.1.0  -\n\
+             \n\
+             dependency  requirement  latest  declared in\n\
+             reqwest     0.11         0.12.0  a/Cargo.toml `dependencies.http`\n\
+             log         0.3          0.4.20  a/Cargo.toml `dependencies.log`\n"
+        );
+        assert_eq!(
+            cargo_manifest
+                .outdated_annotations(&report)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "::warning file=a/Cargo.toml,line=3::a is at 0.2.0, its latest release is 0.1.0",
+                "::warning file=c/Cargo.toml,line=3::c 0.1.0 was never published",
+                "::warning file=a/Cargo.toml,line=9::reqwest 0.12.0 doesn't match `0.11`",
+                "::warning file=a/Cargo.toml,line=8::log 0.4.20 doesn't match `0.3`",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_every_occurrence_of_a_crate() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n\n[workspace.package]\nversion = '0.3.0'\n\n[workspace.dependencies]\nalias = { package = 'a', path = 'a', version = '0.3.0' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion.workspace = true\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\nalias.workspace = true\n\n[dev-dependencies]\na = { path = '../a' }\n\n[build-dependencies]\na = '0.3'\n".to_vec(),
+        );
+
+        let cargo_manifest_service = CargoManifestService::new(fs);
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        assert_eq!(
+            cargo_manifest
+                .occurrences("a")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "Cargo.toml: `workspace.package.version` = 0.3.0",
+                "b/Cargo.toml: `dev-dependencies.a` (no version)",
+                "b/Cargo.toml: `build-dependencies.a.version` = 0.3",
+                "Cargo.toml: `workspace.dependencies.alias.version` = 0.3.0",
+                "b/Cargo.toml: `dependencies.alias` (no version)",
+            ]
+        );
+        assert!(cargo_manifest.occurrences("missing").is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn orders_members_for_publishing() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['app', 'core', 'macros']\n\n[workspace.dependencies]\nmacros = { path = 'macros', version = '0.1.0' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("app/Cargo.toml"),
+            b"[package]\nname = 'app'\nversion = '1.0.0'\n\n[dependencies]\nbase = { path = '../core', version = '0.2', package = 'core' }\nserde = '1'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("core/Cargo.toml"),
+            b"[package]\nname = 'core'\nversion = '0.2.0'\n\n[build-dependencies]\nmacros.workspace = true\n\n[dev-dependencies]\napp = { path = '../app' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("macros/Cargo.toml"),
+            b"[package]\nname = 'macros'\nversion = '0.1.0'\npublish = false\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(fs.clone());
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let order = cargo_manifest.publish_order()?;
+        assert_eq!(
+            order
+                .iter()
+                .map(|member| (member.name.as_str(), member.dependencies.clone()))
+                .collect::<Vec<_>>(),
+            [
+                ("macros", vec![]),
+                ("core", vec!["macros".to_string()]),
+                ("app", vec!["core".to_string()]),
+            ]
+        );
+        assert_eq!(order[1].manifest, Path::new("core/Cargo.toml"));
+        assert_eq!(order[1].version.as_deref(), Some("0.2.0"));
+        assert!(!order[0].publish && order[1].publish);
+
+        fs.write(
+            Path::new("core/Cargo.toml"),
+            b"[package]\nname = 'core'\nversion = '0.2.0'\n\n[dev-dependencies]\napp = { path = '../app', version = '1' }\n".to_vec(),
+        )?;
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        assert_eq!(
+            cargo_manifest.publish_order().unwrap_err().to_string(),
+            "members depend on each other in a cycle: app -> core -> app"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn explains_version_changes() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n\n[workspace.dependencies]\na = { path = 'a', version = '1.4.0' }\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '1.4.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na.workspace = true\n\n[dev-dependencies]\na = '1.4'\n".to_vec(),
+        );
+
+        let policy = Policy {
+            major_requires_flag: true,
+            ..Policy::default()
+        };
+        let cargo_manifest_service =
+            CargoManifestService::new(fs).with_policy(policy, PolicyContext::default());
+        let cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let explanation = cargo_manifest_service.explain(
+            &cargo_manifest,
+            "a",
+            "2.0.0",
+            vec!["a `major` bump".to_string()],
+        );
+        assert_eq!(
+            explanation.to_string(),
+            "a 1.4.0 -> 2.0.0\n\
+             \x20 chosen: a `major` bump\n\
+             \x20 policy: refused, major bumps need `--allow-major`\n\
+             \x20 edits:  a/Cargo.toml `package.version`, the version of a\n\
+             \x20 edits:  Cargo.toml `workspace.dependencies.a.version`, members inheriting a follow it: b\n\
+             \x20 edits:  b/Cargo.toml `dev-dependencies.a`, b requires a"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn protected_crates_are_not_edited() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na = { path = '../a', version = '0.1.0' }\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let policy = Policy {
+            protected: vec!["b".to_string()],
+            ..Policy::default()
+        };
+        let cargo_manifest_service = CargoManifestService::new(fs.clone())
+            .with_policy(policy.clone(), PolicyContext::default());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let error = cargo_manifest_service
+            .update_versions(&mut cargo_manifest, &[("a", "0.2.0")])
+            .unwrap_err();
+        let Error::Policy(error) = error else {
+            panic!("expected a policy error, got {error:#}");
+        };
+        assert_eq!(
+            error.violations[0].to_string(),
+            "b editing b/Cargo.toml: the crate is protected, pass `--override-protection` to change it"
+        );
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n".to_vec()
+        );
+
+        let context = PolicyContext {
+            override_protection: true,
+            ..PolicyContext::default()
+        };
+        let cargo_manifest_service =
+            CargoManifestService::new(fs.clone()).with_policy(policy, context);
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        cargo_manifest_service.update_versions(&mut cargo_manifest, &[("a", "0.2.0")])?;
+        assert!(
+            String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?.contains("version = '0.2.0'")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn protected_crates_are_not_renamed() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['core-a']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("core-a/Cargo.toml"),
+            b"[package]\nname = 'core-a'\nversion = '0.1.0'\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let policy = Policy {
+            protected: vec!["core-*".to_string()],
+            ..Policy::default()
+        };
+        let cargo_manifest_service =
+            CargoManifestService::new(fs.clone()).with_policy(policy, PolicyContext::default());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let error = cargo_manifest_service
+            .rename_package(&mut cargo_manifest, "core-a", "a")
+            .unwrap_err();
+        let Error::Policy(error) = error else {
+            panic!("expected a policy error, got {error:#}");
+        };
+        assert_eq!(
+            error.violations[0].to_string(),
+            "core-a editing core-a/Cargo.toml: the crate is protected, pass `--override-protection` to change it"
+        );
+        assert_eq!(
+            fs.read(Path::new("core-a/Cargo.toml"))?,
+            b"[package]\nname = 'core-a'\nversion = '0.1.0'\n".to_vec()
+        );
+        assert!(cargo_manifest.package_manifest("core-a").is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn protected_dependencies_are_not_edited() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a']".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n\n[dependencies]\ntls = { package = 'openssl', version = '0.10' }\n".to_vec(),
+        );
+        let fs = std::sync::Arc::new(fs);
+
+        let policy = Policy {
+            protected: vec!["openssl*".to_string()],
+            ..Policy::default()
+        };
+        let cargo_manifest_service =
+            CargoManifestService::new(fs.clone()).with_policy(policy, PolicyContext::default());
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let mut dependency = NewDependency::new("tls", "0.11");
+        dependency.package = Some("openssl".to_string());
+        let error = cargo_manifest_service
+            .add_dependency(&mut cargo_manifest, Path::new("a/Cargo.toml"), &dependency)
+            .unwrap_err();
+        let Error::Policy(error) = error else {
+            panic!("expected a policy error, got {error:#}");
+        };
+        assert_eq!(
+            error.violations[0].to_string(),
+            "openssl editing its declaration in a/Cargo.toml: the crate is protected, pass `--override-protection` to change it"
+        );
+
+        cargo_manifest_service.add_dependency(
+            &mut cargo_manifest,
+            Path::new("a/Cargo.toml"),
+            &NewDependency::new("serde", "1"),
+        )?;
+        assert!(String::from_utf8(fs.read(Path::new("a/Cargo.toml"))?)?.contains("serde"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reports_first_failed_write_in_path_order() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['c', 'a', 'b']".to_vec(),
+        );
+        for member in ["a", "b", "c"] {
+            fs.add_file(
+                PathBuf::from(format!("{member}/Cargo.toml")),
+                b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec(),
+            );
+        }
+        let fs = FailingWrites {
+            fs,
+            paths: vec![PathBuf::from("c/Cargo.toml"), PathBuf::from("b/Cargo.toml")],
+        };
+
+        let cargo_manifest_service =
+            CargoManifestService::new(fs).with_write_concurrency(NonZeroUsize::new(3).unwrap());
+        let mut cargo_manifest =
+            cargo_manifest_service.load_manifest(&PathBuf::from("Cargo.toml"))?;
+        let err = cargo_manifest_service
+            .update_version(&mut cargo_manifest, "child", "0.2.0")
+            .unwrap_err();
+
+        let Error::PartialWrite(partial) = err else {
+            panic!("expected a partial write, got {err:#}");
+        };
+        assert_eq!(partial.source.to_string(), "failed to write b/Cargo.toml");
+        assert_eq!(
+            partial.state.summary().pending,
+            ["b/Cargo.toml", "c/Cargo.toml"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn partial_write_can_be_resumed_or_rolled_back() -> anyhow::Result<()> {
+        for mode in [ResumeMode::Finish, ResumeMode::Rollback] {
+            let mut fs = MockFileSystem::new();
+            fs.add_file(
+                PathBuf::from("Cargo.toml"),
+                b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+            );
+            for member in ["a", "b"] {
+                fs.add_file(
+                    PathBuf::from(format!("{member}/Cargo.toml")),
+                    b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec(),
+                );
+            }
+            let fs = std::sync::Arc::new(fs);
+
+            let failing = CargoManifestService::new(FailingWrites {
+                fs: fs.clone(),
+                paths: vec![PathBuf::from("b/Cargo.toml")],
+            });
+            let mut cargo_manifest = failing.load_manifest(Path::new("Cargo.toml"))?;
+            let err = failing
+                .update_version(&mut cargo_manifest, "child", "0.2.0")
+                .unwrap_err();
+
+            let Error::PartialWrite(partial) = err else {
+                panic!("expected a partial write, got {err:#}");
+            };
+            assert_eq!(partial.state.summary().completed, ["a/Cargo.toml"]);
+            assert_eq!(partial.state.summary().pending, ["b/Cargo.toml"]);
+            assert_eq!(
+                partial.state_path.as_deref(),
+                Some(Path::new(crate::resume::RESUME_FILE))
+            );
+
+            CargoManifestService::new(fs.clone()).resume(Path::new("Cargo.toml"), mode)?;
+
+            let expected = match mode {
+                ResumeMode::Finish => "0.2.0",
+                ResumeMode::Rollback => "0.1.0",
+            };
+            for member in ["a", "b"] {
+                assert_eq!(
+                    String::from_utf8(fs.read(&PathBuf::from(format!("{member}/Cargo.toml")))?)?,
+                    format!("[package]\nname = 'child'\nversion = '{expected}'")
+                );
+            }
+            assert!(ResumeState::load(&fs, Path::new("Cargo.toml"))?.is_none());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn atomic_writes_restore_written_manifests_on_failure() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']".to_vec(),
+        );
+        for member in ["a", "b"] {
+            fs.add_file(
+                PathBuf::from(format!("{member}/Cargo.toml")),
+                b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec(),
+            );
+        }
+        let fs = std::sync::Arc::new(fs);
+
+        let cargo_manifest_service = CargoManifestService::new(FailingWrites {
+            fs: fs.clone(),
+            paths: vec![PathBuf::from("b/Cargo.toml")],
+        })
+        .with_atomic_writes(true);
+        let mut cargo_manifest = cargo_manifest_service.load_manifest(Path::new("Cargo.toml"))?;
+        let err = cargo_manifest_service
+            .update_version(&mut cargo_manifest, "child", "0.2.0")
+            .unwrap_err();
+
+        assert_eq!(
+            format!("{err:#}"),
+            "the update failed, restored the 1 manifest(s) already written: failed to write b/Cargo.toml: disk full"
+        );
+        assert_eq!(
+            fs.read(Path::new("a/Cargo.toml"))?,
+            b"[package]\nname = 'child'\nversion = '0.1.0'".to_vec()
+        );
+        assert!(ResumeState::load(&fs, Path::new("Cargo.toml"))?.is_none());
+        assert_eq!(
+            cargo_manifest.package_version("child").as_deref(),
+            Some("0.1.0")
+        );
+        assert_eq!(cargo_manifest.written_files().count(), 0);
 
         Ok(())
     }
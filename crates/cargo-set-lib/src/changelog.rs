@@ -0,0 +1,233 @@
+use std::time::SystemTime;
+
+/// The changelog of a crate, next to its manifest.
+pub const CHANGELOG_FILE: &str = "CHANGELOG.md";
+
+/// How the changelog section of a release is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangelogFormat {
+    /// <https://keepachangelog.com>: every commit under Added, Fixed or
+    /// Changed.
+    KeepAChangelog,
+    /// conventional-changelog: features, bug fixes, performance
+    /// improvements and breaking changes; other commits are left out.
+    Conventional,
+}
+
+/// A commit subject split into its conventional commit parts, e.g.
+/// `feat(cli)!: add --changelog`. Subjects without a type are kept whole.
+struct Commit<'a> {
+    kind: Option<&'a str>,
+    scope: Option<&'a str>,
+    breaking: bool,
+    description: &'a str,
+}
+
+impl<'a> Commit<'a> {
+    fn parse(subject: &'a str) -> Self {
+        let conventional = subject.split_once(": ").and_then(|(head, description)| {
+            let (head, breaking) = match head.strip_suffix('!') {
+                Some(head) => (head, true),
+                None => (head, false),
+            };
+            let (kind, scope) = match head.split_once('(') {
+                Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+                None => (head, None),
+            };
+            let valid = !kind.is_empty() && kind.bytes().all(|b| b.is_ascii_alphabetic());
+            valid.then_some(Commit {
+                kind: Some(kind),
+                scope,
+                breaking,
+                description: description.trim(),
+            })
+        });
+
+        conventional.unwrap_or(Commit {
+            kind: None,
+            scope: None,
+            breaking: false,
+            description: subject.trim(),
+        })
+    }
+
+    fn is(&self, kind: &str) -> bool {
+        self.kind.is_some_and(|k| k.eq_ignore_ascii_case(kind))
+    }
+}
+
+/// The changelog section of `version`, released at `now`, listing the
+/// commits with `subjects`, newest first.
+pub fn changelog_section(
+    format: ChangelogFormat,
+    version: &str,
+    now: SystemTime,
+    subjects: &[String],
+) -> crate::Result<String> {
+    let date = &crate::snapshot::rfc3339(now)?[..10];
+    let commits = subjects
+        .iter()
+        .map(|subject| Commit::parse(subject))
+        .collect::<Vec<_>>();
+
+    let (heading, groups) = match format {
+        ChangelogFormat::KeepAChangelog => {
+            let line = |c: &Commit| match c.breaking {
+                true => format!("- **Breaking:** {}", c.description),
+                false => format!("- {}", c.description),
+            };
+            (
+                format!("## [{version}] - {date}"),
+                vec![
+                    (
+                        "Added",
+                        commits.iter().filter(|c| c.is("feat")).map(line).collect(),
+                    ),
+                    (
+                        "Fixed",
+                        commits.iter().filter(|c| c.is("fix")).map(line).collect(),
+                    ),
+                    (
+                        "Changed",
+                        commits
+                            .iter()
+                            .filter(|c| !c.is("feat") && !c.is("fix"))
+                            .map(line)
+                            .collect::<Vec<_>>(),
+                    ),
+                ],
+            )
+        }
+        ChangelogFormat::Conventional => {
+            let line = |c: &Commit| match c.scope {
+                Some(scope) => format!("* **{scope}:** {}", c.description),
+                None => format!("* {}", c.description),
+            };
+            let of = |kind: &str| commits.iter().filter(|c| c.is(kind)).map(line).collect();
+            (
+                format!("## {version} ({date})"),
+                vec![
+                    (
+                        "BREAKING CHANGES",
+                        commits
+                            .iter()
+                            .filter(|c| c.breaking)
+                            .map(line)
+                            .collect::<Vec<_>>(),
+                    ),
+                    ("Features", of("feat")),
+                    ("Bug Fixes", of("fix")),
+                    ("Performance Improvements", of("perf")),
+                ],
+            )
+        }
+    };
+
+    let mut section = format!("{heading}\n");
+    for (title, lines) in groups {
+        if !lines.is_empty() {
+            section.push_str(&format!("\n### {title}\n\n{}\n", lines.join("\n")));
+        }
+    }
+
+    Ok(section)
+}
+
+/// `existing`, the contents of a changelog, with `section` added above the
+/// newest release and below an `Unreleased` section. Without a changelog a
+/// new one is started.
+pub(crate) fn insert_section(existing: Option<&str>, section: &str) -> String {
+    let Some(existing) = existing.filter(|existing| !existing.trim().is_empty()) else {
+        return format!("# Changelog\n\n{section}");
+    };
+
+    let mut offset = 0;
+    for line in existing.split_inclusive('\n') {
+        let is_release = line.starts_with("## ") && !line.to_lowercase().contains("unreleased");
+        if is_release {
+            return format!("{}{section}\n{}", &existing[..offset], &existing[offset..]);
+        }
+        offset += line.len();
+    }
+
+    let separator = if existing.ends_with("\n\n") {
+        ""
+    } else if existing.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    format!("{existing}{separator}{section}")
+}
+
+/// The newest of `tags` that `format` (with `{crate}` and `{version}`) names
+/// for a release of `package` before `version`.
+pub fn previous_tag(tags: &[String], format: &str, package: &str, version: &str) -> Option<String> {
+    let version = semver::Version::parse(version).ok()?;
+    let format = format.replace("{crate}", package);
+    let (prefix, suffix) = format.split_once("{version}")?;
+
+    tags.iter()
+        .filter_map(|tag| {
+            let released = tag.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            let released = semver::Version::parse(released).ok()?;
+            (released < version).then_some((released, tag))
+        })
+        .max()
+        .map(|(_, tag)| tag.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    #[test]
+    fn renders_and_inserts_release_sections() -> anyhow::Result<()> {
+        let now = UNIX_EPOCH + Duration::from_secs(1_717_243_200);
+        let subjects = [
+            "feat(cli)!: add --changelog",
+            "fix: keep comments in arrays",
+            "Update the readme",
+            "perf: index dependencies once",
+        ]
+        .map(String::from);
+
+        let section = changelog_section(ChangelogFormat::KeepAChangelog, "1.2.0", now, &subjects)?;
+        assert_eq!(
+            section,
+            "## [1.2.0] - 2024-06-01\n\n\
+             ### Added\n\n- **Breaking:** add --changelog\n\n\
+             ### Fixed\n\n- keep comments in arrays\n\n\
+             ### Changed\n\n- Update the readme\n- index dependencies once\n"
+        );
+        assert_eq!(
+            changelog_section(ChangelogFormat::Conventional, "1.2.0", now, &subjects)?,
+            "## 1.2.0 (2024-06-01)\n\n\
+             ### BREAKING CHANGES\n\n* **cli:** add --changelog\n\n\
+             ### Features\n\n* **cli:** add --changelog\n\n\
+             ### Bug Fixes\n\n* keep comments in arrays\n\n\
+             ### Performance Improvements\n\n* index dependencies once\n"
+        );
+
+        let existing = "# Changelog\n\n## [Unreleased]\n\n## [1.1.0] - 2024-01-01\n\n- Initial\n";
+        assert_eq!(
+            insert_section(Some(existing), "## [1.2.0] - 2024-06-01\n"),
+            "# Changelog\n\n## [Unreleased]\n\n## [1.2.0] - 2024-06-01\n\n## [1.1.0] - 2024-01-01\n\n- Initial\n"
+        );
+        assert_eq!(
+            insert_section(None, "## [1.2.0] - 2024-06-01\n"),
+            "# Changelog\n\n## [1.2.0] - 2024-06-01\n"
+        );
+
+        let tags = ["a-v1.0.0", "a-v1.1.0", "b-v1.5.0", "a-v1.2.0"].map(String::from);
+        assert_eq!(
+            previous_tag(&tags, "{crate}-v{version}", "a", "1.2.0").as_deref(),
+            Some("a-v1.1.0")
+        );
+        assert_eq!(previous_tag(&tags, "v{version}", "a", "1.2.0"), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+use crate::internal::RequirementDrift;
+
+/// A version invariant of the workspace that doesn't hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckViolation {
+    /// A path dependency on a member requires a version the member isn't at.
+    Requirement(RequirementDrift),
+    /// A member of a lockstep workspace isn't at the shared version, the
+    /// highest of the workspace.
+    Lockstep {
+        package: String,
+        version: String,
+        shared: String,
+    },
+    /// A crate isn't at the version it was expected at, or isn't a member.
+    Expected {
+        package: String,
+        version: Option<String>,
+        expected: String,
+    },
+}
+
+impl fmt::Display for CheckViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckViolation::Requirement(drift) => write!(f, "{drift}"),
+            CheckViolation::Lockstep {
+                package,
+                version,
+                shared,
+            } => write!(
+                f,
+                "{package} is at {version}, but the workspace is versioned in lockstep at {shared}"
+            ),
+            CheckViolation::Expected {
+                package,
+                version: Some(version),
+                expected,
+            } => write!(f, "{package} is at {version}, expected {expected}"),
+            CheckViolation::Expected {
+                package,
+                version: None,
+                expected,
+            } => write!(
+                f,
+                "{package} was expected at {expected}, but it isn't a versioned member of the workspace"
+            ),
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::artifacts::Artifacts;
+use crate::audit::AuditConfig;
+use crate::banned::BannedVersions;
+use crate::build_info::BuildInfoConfig;
+use crate::bump::BumpLevel;
+use crate::diagnostic;
+use crate::filesystem::FileSystem;
+use crate::hooks::Hooks;
+use crate::paths::display_path;
+use crate::policy::Policy;
+
+/// File name of the project configuration, stored next to the root manifest.
+pub const CONFIG_FILE: &str = ".cargo-set.toml";
+
+/// Whether the crates of a workspace share one version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Versioning {
+    Lockstep,
+    Independent,
+}
+
+/// How commands with a choice of output print their results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    Plain,
+    Json,
+}
+
+/// Project defaults from `.cargo-set.toml`, or from
+/// `[workspace.metadata.cargo-set]` of the root manifest when there is no
+/// such file. Unset values fall back to the command line defaults, and
+/// command line flags override them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// The root manifest commands use without `--path`, relative to the
+    /// configuration.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versioning: Option<Versioning>,
+    /// Format of release tags; `{crate}` and `{version}` are replaced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bump: Option<BumpLevel>,
+    /// Whether version changes update `[dev-dependencies]` too.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update_dev_dependencies: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+    /// Version files written after every version change.
+    #[serde(default, skip_serializing_if = "Artifacts::is_empty")]
+    pub artifacts: Artifacts,
+    #[serde(default, skip_serializing_if = "BuildInfoConfig::is_default")]
+    pub build_info: BuildInfoConfig,
+    #[serde(default, skip_serializing_if = "Policy::is_empty")]
+    pub policy: Policy,
+    #[serde(default, skip_serializing_if = "AuditConfig::is_empty")]
+    pub audit: AuditConfig,
+    /// Dependency versions no manifest may use.
+    #[serde(default, skip_serializing_if = "BannedVersions::is_empty")]
+    pub banned_versions: BannedVersions,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub hooks: Hooks,
+}
+
+impl Config {
+    /// Where the configuration of the workspace rooted at `root_manifest`
+    /// lives.
+    pub fn path_for(root_manifest: &Path) -> PathBuf {
+        root_manifest
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(CONFIG_FILE)
+    }
+
+    pub fn parse(source: &str) -> crate::Result<Self> {
+        Ok(toml::from_str(source)?)
+    }
+
+    pub fn to_toml(&self) -> crate::Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// The configuration of the workspace rooted at `root_manifest`, if it
+    /// has one: its `.cargo-set.toml`, or else the
+    /// `[workspace.metadata.cargo-set]` table of the manifest.
+    pub fn load<F: FileSystem>(fs: &F, root_manifest: &Path) -> crate::Result<Option<Self>> {
+        let path = Self::path_for(root_manifest);
+        let Some(contents) = read_optional(fs, &path)? else {
+            return Ok(Self::from_metadata(fs, root_manifest)?);
+        };
+
+        Ok(Some(diagnostic::parse_toml(&path, &contents)?))
+    }
+
+    /// The `[workspace.metadata.cargo-set]` table of `root_manifest`, if it
+    /// exists and has one. A manifest that doesn't parse has none, so a
+    /// broken manifest still gets to `fix`, which reports the parse error
+    /// itself.
+    fn from_metadata<F: FileSystem>(fs: &F, root_manifest: &Path) -> anyhow::Result<Option<Self>> {
+        let Some(contents) = read_optional(fs, root_manifest)? else {
+            return Ok(None);
+        };
+        let manifest: toml::Table = match diagnostic::parse_toml(root_manifest, &contents) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                tracing::warn!("not reading `workspace.metadata.cargo-set`: {e:#}");
+                return Ok(None);
+            }
+        };
+        let Some(table) = manifest
+            .get("workspace")
+            .and_then(|w| w.get("metadata"))
+            .and_then(|m| m.get("cargo-set"))
+        else {
+            return Ok(None);
+        };
+
+        table.clone().try_into().map(Some).with_context(|| {
+            format!(
+                "failed to parse `workspace.metadata.cargo-set` in {}",
+                display_path(root_manifest)
+            )
+        })
+    }
+}
+
+/// The contents of the file at `path`, or `None` if it doesn't exist.
+fn read_optional<F: FileSystem>(fs: &F, path: &Path) -> anyhow::Result<Option<String>> {
+    let contents = match fs.read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", display_path(path))),
+    };
+
+    String::from_utf8(contents)
+        .map(Some)
+        .with_context(|| format!("failed to parse {}", display_path(path)))
+}
+
+/// What `init` found out about a workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WorkspaceSurvey {
+    pub versioning: Versioning,
+    pub tag_format: Option<String>,
+    /// The `members` patterns of the root manifest.
+    pub members: Vec<String>,
+    pub packages: usize,
+}
+
+/// A commented `.cargo-set.toml` with defaults matching `survey`.
+pub(crate) fn starter(survey: &WorkspaceSurvey) -> String {
+    let versioning = match survey.versioning {
+        Versioning::Lockstep => "lockstep",
+        Versioning::Independent => "independent",
+    };
+    let members = match survey.members.as_slice() {
+        [] => "a single package".to_string(),
+        members => format!("members {}", members.join(", ")),
+    };
+
+    let mut config = format!(
+        "# cargo-set configuration, generated by `cargo set init`.\n\
+         # Found {} package(s): {members}.\n\
+         \n\
+         # `lockstep` if every crate shares one version, `independent` if each\n\
+         # crate is versioned on its own.\n\
+         versioning = \"{versioning}\"\n\
+         \n\
+         # Format of release tags; `{{crate}}` and `{{version}}` are replaced.\n",
+        survey.packages
+    );
+    match &survey.tag_format {
+        Some(format) => config.push_str(&format!("tag-format = \"{format}\"\n")),
+        None => config.push_str("# tag-format = \"v{version}\"\n"),
+    }
+    config.push_str(
+        "\n\
+         # Bump level used when none is given: `major`, `minor` or `patch`.\n\
+         # bump = \"minor\"\n\
+         \n\
+         # Files describing the new versions, written after every version change.\n\
+         [artifacts]\n\
+         # version-file = \"VERSION\"\n\
+         # env-file = \"versions.env\"\n\
+         # json-file = \"versions.json\"\n\
+         \n\
+         # Crates that embed their version, git commit and build time, either as\n\
+         # a generated Rust source (`rust`) or as `[package.metadata.build-info]`\n\
+         # (`metadata`). Kept up to date by every version change.\n\
+         [build-info]\n\
+         # crates = []\n\
+         # format = \"rust\"\n\
+         # path = \"src/build_info.rs\"\n\
+         \n\
+         # Rules every version change is checked against before writing.\n\
+         [policy]\n\
+         # Refuse major bumps unless `--allow-major` is passed.\n\
+         # major-requires-flag = true\n\
+         # Branches on which pre-release versions are refused.\n\
+         # release-branches = [\"main\"]\n\
+         # Crates whose version only changes while an approval file exists.\n\
+         # approval-files = { api-contract = \"approvals/api-contract\" }\n\
+         # Crates, or glob patterns, whose manifest, version and declarations\n\
+         # only change with `--override-protection`.\n\
+         # protected = [\"api-contract\", \"openssl\"]\n\
+         \n\
+         # Record who changed which files, when and how, for every command\n\
+         # that writes.\n\
+         [audit]\n\
+         # A JSON lines file, relative to the workspace root.\n\
+         # file = \"cargo-set-audit.jsonl\"\n\
+         # syslog = true\n\
+         \n\
+         # Dependency versions no manifest may require or lock, e.g. known-bad\n\
+         # releases. Checked by `audit` and refused by edits.\n\
+         [banned-versions]\n\
+         # openssl = [\"=0.10.45\", \">=0.10.50, <0.10.55\"]\n\
+         \n\
+         # Shell commands run from the workspace root before and after every\n\
+         # write of manifests, with the changes as JSON on stdin. A failing\n\
+         # `pre` hook stops the write.\n\
+         [hooks]\n\
+         # pre = [\"./scripts/check-policy.sh\"]\n\
+         # post = [\"cargo check --workspace\"]\n",
+    );
+
+    config
+}
+
+/// The release tag format most of `tags` follow, if any of them contain a
+/// version.
+pub(crate) fn infer_tag_format(tags: &[String], packages: &[&str]) -> Option<String> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for tag in tags {
+        let Some(start) = tag.find(|c: char| c.is_ascii_digit()) else {
+            continue;
+        };
+        let (prefix, version) = tag.split_at(start);
+        if semver::Version::parse(version).is_err() {
+            continue;
+        }
+
+        let format = match packages
+            .iter()
+            .filter(|name| prefix.starts_with(*name))
+            .max_by_key(|name| name.len())
+        {
+            Some(name) => format!("{{crate}}{}{{version}}", &prefix[name.len()..]),
+            None => format!("{prefix}{{version}}"),
+        };
+        *counts.entry(format).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(format, _)| format)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::filesystem::MockFileSystem;
+
+    #[test]
+    fn loads_the_workspace_metadata_without_a_config_file() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = []\n\n[workspace.metadata.cargo-set]\nbump = 'minor'\noutput = 'json'\n"
+                .to_vec(),
+        );
+        let config = Config::load(&fs, Path::new("Cargo.toml"))?.unwrap();
+        assert_eq!(config.bump, Some(BumpLevel::Minor));
+        assert_eq!(config.output, Some(OutputFormat::Json));
+
+        fs.add_file(
+            PathBuf::from(CONFIG_FILE),
+            b"update-dev-dependencies = false\n".to_vec(),
+        );
+        let config = Config::load(&fs, Path::new("Cargo.toml"))?.unwrap();
+        assert_eq!(config.bump, None);
+        assert_eq!(config.update_dev_dependencies, Some(false));
+
+        Ok(())
+    }
+
+    #[test]
+    fn a_manifest_that_does_not_parse_has_no_metadata() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = []\n\n[workspace.package]\nversion = 0.1.0\n".to_vec(),
+        );
+
+        assert!(Config::load(&fs, Path::new("Cargo.toml"))?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn starter_config_parses_with_inferred_defaults() -> anyhow::Result<()> {
+        let tags = [
+            "cargo-set-v0.1.0",
+            "cargo-set-lib-v0.1.0",
+            "cargo-set-v0.2.0",
+            "nightly",
+        ]
+        .map(String::from);
+        let tag_format = infer_tag_format(&tags, &["cargo-set", "cargo-set-lib"]);
+        assert_eq!(tag_format.as_deref(), Some("{crate}-v{version}"));
+
+        let config = starter(&WorkspaceSurvey {
+            versioning: Versioning::Independent,
+            tag_format,
+            members: vec!["crates/*".to_string()],
+            packages: 2,
+        });
+        assert_eq!(
+            Config::parse(&config)?,
+            Config {
+                manifest_path: None,
+                versioning: Some(Versioning::Independent),
+                tag_format: Some("{crate}-v{version}".to_string()),
+                bump: None,
+                update_dev_dependencies: None,
+                output: None,
+                artifacts: Artifacts::default(),
+                build_info: BuildInfoConfig::default(),
+                policy: Policy::default(),
+                audit: AuditConfig::default(),
+                banned_versions: BannedVersions::default(),
+                hooks: Hooks::default(),
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use toml_edit::{InlineTable, Value};
+
+use crate::index::{DependencyKind, DependencyTable};
+use crate::paths::display_path;
+
+/// A dependency declaration to add to a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewDependency {
+    /// The key it is declared under.
+    pub name: String,
+    /// The crate, if it differs from `name`.
+    pub package: Option<String>,
+    pub requirement: String,
+    pub table: DependencyTable,
+    pub features: Vec<String>,
+    pub optional: bool,
+    pub default_features: bool,
+}
+
+impl NewDependency {
+    pub fn new(name: impl Into<String>, requirement: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            package: None,
+            requirement: requirement.into(),
+            table: DependencyTable::new(DependencyKind::Normal),
+            features: Vec::new(),
+            optional: false,
+            default_features: true,
+        }
+    }
+
+    /// The declaration, as a plain requirement string when nothing else is
+    /// set.
+    pub(crate) fn to_value(&self) -> Value {
+        let plain = self.package.is_none()
+            && self.features.is_empty()
+            && !self.optional
+            && self.default_features;
+        if plain {
+            return Value::from(self.requirement.as_str());
+        }
+
+        let mut table = InlineTable::new();
+        table.insert("version", self.requirement.as_str().into());
+        if let Some(package) = &self.package {
+            table.insert("package", package.as_str().into());
+        }
+        if !self.default_features {
+            table.insert("default-features", false.into());
+        }
+        if !self.features.is_empty() {
+            table.insert(
+                "features",
+                Value::Array(self.features.iter().map(String::as_str).collect()),
+            );
+        }
+        if self.optional {
+            table.insert("optional", true.into());
+        }
+        table.fmt();
+
+        Value::InlineTable(table)
+    }
+}
+
+/// An entry of `[patch.<registry>]` in the root manifest, replacing a crate
+/// of the registry, usually `crates-io`, everywhere in the dependency graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchEntry {
+    pub registry: String,
+    pub name: String,
+    pub source: PatchSource,
+}
+
+/// Where a patched crate comes from instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchSource {
+    /// A git repository, at its default branch without a reference.
+    Git {
+        url: String,
+        reference: Option<GitReference>,
+    },
+    Path(String),
+    /// Another version of the registry.
+    Version(String),
+}
+
+/// The commit of a git repository to use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitReference {
+    Rev(String),
+    Branch(String),
+    Tag(String),
+}
+
+impl GitReference {
+    /// The key of the reference in a dependency declaration, e.g. `rev`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            GitReference::Rev(_) => "rev",
+            GitReference::Branch(_) => "branch",
+            GitReference::Tag(_) => "tag",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            GitReference::Rev(value) | GitReference::Branch(value) | GitReference::Tag(value) => {
+                value
+            }
+        }
+    }
+}
+
+impl PatchSource {
+    /// The patch declared as `dependency`, or `None` for declarations
+    /// without a git repository, path or version.
+    pub(crate) fn from_dependency(dependency: &cargo_toml::Dependency) -> Option<Self> {
+        let detail = match dependency {
+            cargo_toml::Dependency::Simple(version) => {
+                return Some(PatchSource::Version(version.clone()))
+            }
+            cargo_toml::Dependency::Inherited(_) => return None,
+            cargo_toml::Dependency::Detailed(detail) => detail,
+        };
+
+        if let Some(url) = &detail.git {
+            let reference = match (&detail.rev, &detail.branch, &detail.tag) {
+                (Some(rev), _, _) => Some(GitReference::Rev(rev.clone())),
+                (_, Some(branch), _) => Some(GitReference::Branch(branch.clone())),
+                (_, _, Some(tag)) => Some(GitReference::Tag(tag.clone())),
+                _ => None,
+            };
+            return Some(PatchSource::Git {
+                url: url.clone(),
+                reference,
+            });
+        }
+
+        detail
+            .path
+            .clone()
+            .map(PatchSource::Path)
+            .or_else(|| detail.version.clone().map(PatchSource::Version))
+    }
+
+    pub(crate) fn to_value(&self) -> Value {
+        let mut table = InlineTable::new();
+        match self {
+            PatchSource::Git { url, reference } => {
+                table.insert("git", url.as_str().into());
+                match reference {
+                    Some(GitReference::Rev(rev)) => table.insert("rev", rev.as_str().into()),
+                    Some(GitReference::Branch(branch)) => {
+                        table.insert("branch", branch.as_str().into())
+                    }
+                    Some(GitReference::Tag(tag)) => table.insert("tag", tag.as_str().into()),
+                    None => None,
+                };
+            }
+            PatchSource::Path(path) => {
+                table.insert("path", path.as_str().into());
+            }
+            PatchSource::Version(version) => {
+                table.insert("version", version.as_str().into());
+            }
+        }
+        table.fmt();
+
+        Value::InlineTable(table)
+    }
+}
+
+impl fmt::Display for PatchEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} from {}: ", self.name, self.registry)?;
+        match &self.source {
+            PatchSource::Git { url, reference } => {
+                write!(f, "git {url}")?;
+                match reference {
+                    Some(GitReference::Rev(rev)) => write!(f, " at rev {rev}"),
+                    Some(GitReference::Branch(branch)) => write!(f, " at branch {branch}"),
+                    Some(GitReference::Tag(tag)) => write!(f, " at tag {tag}"),
+                    None => Ok(()),
+                }
+            }
+            PatchSource::Path(path) => write!(f, "path {path}"),
+            PatchSource::Version(version) => write!(f, "version {version}"),
+        }
+    }
+}
+
+/// Changes to the features of existing dependency declarations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeatureEdit {
+    /// Features to enable, kept in order after the existing ones.
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+    /// A new `default-features`, or `None` to leave it alone.
+    pub default_features: Option<bool>,
+}
+
+impl FeatureEdit {
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.remove.is_empty() && self.default_features.is_none()
+    }
+}
+
+/// A dependency moved into `[workspace.dependencies]`, which the
+/// manifests that declared it now inherit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoistedDependency {
+    pub name: String,
+    /// The requirement of the workspace entry, the highest declared.
+    pub requirement: String,
+    pub manifests: Vec<PathBuf>,
+}
+
+impl fmt::Display for HoistedDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} from {}",
+            self.name,
+            self.requirement,
+            self.manifests
+                .iter()
+                .map(|path| display_path(path))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_plain_and_detailed_declarations() {
+        assert_eq!(
+            NewDependency::new("serde", "1.0").to_value().to_string(),
+            "\"1.0\""
+        );
+
+        let dependency = NewDependency {
+            package: Some("serde".to_string()),
+            features: vec!["derive".to_string()],
+            optional: true,
+            ..NewDependency::new("serde1", "1.0")
+        };
+        assert_eq!(
+            dependency.to_value().to_string(),
+            "{ version = \"1.0\", package = \"serde\", features = [\"derive\"], optional = true }"
+        );
+    }
+}
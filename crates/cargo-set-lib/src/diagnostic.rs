@@ -0,0 +1,163 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use cargo_toml::Manifest;
+use miette::{
+    Diagnostic, LabeledSpan, MietteError, MietteSpanContents, SourceCode, SourceSpan, SpanContents,
+};
+use serde::de::DeserializeOwned;
+
+use crate::paths::display_path;
+
+/// A manifest or settings file that isn't valid TOML, or doesn't have the
+/// shape it should, with the part of it that is wrong.
+///
+/// It is a [`miette::Diagnostic`], so a miette handler renders it as the
+/// file's lines with the span underlined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub path: PathBuf,
+    /// The contents of the file.
+    pub contents: String,
+    /// The byte range of `contents` the error is about, if it is about one.
+    pub span: Option<Range<usize>>,
+    pub message: String,
+}
+
+impl ParseError {
+    fn from_toml(path: &Path, contents: &str, error: &toml::de::Error) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            contents: contents.to_string(),
+            span: error.span(),
+            message: error.message().trim_end().to_string(),
+        }
+    }
+
+    /// The line and column, counting from 1, where the span starts.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        let start = self.span.as_ref()?.start.min(self.contents.len());
+        let before = self.contents.get(..start)?;
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1;
+
+        Some((line, column))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to parse {}", display_path(&self.path))?;
+        if let Some((line, column)) = self.location() {
+            write!(f, " at line {line}, column {column}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Diagnostic for ParseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new("cargo_set::parse"))
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.span.as_ref().map(|_| self as &dyn SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let span = self.span.clone()?;
+        let label = LabeledSpan::new_with_span(Some(self.message.clone()), span);
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+/// The contents, named after the file so snippets say where they are from.
+impl SourceCode for ParseError {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let contents = self
+            .contents
+            .read_span(span, context_lines_before, context_lines_after)?;
+        Ok(Box::new(MietteSpanContents::new_named(
+            display_path(&self.path),
+            contents.data(),
+            *contents.span(),
+            contents.line(),
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+/// The manifest at `path`, parsed from `contents`.
+pub(crate) fn parse_manifest(path: &Path, contents: &str) -> Result<Manifest, ParseError> {
+    Manifest::from_str(contents).map_err(|error| match &error {
+        cargo_toml::Error::Parse(error) => ParseError::from_toml(path, contents, error),
+        error => ParseError {
+            path: path.to_path_buf(),
+            contents: contents.to_string(),
+            span: None,
+            message: error.to_string(),
+        },
+    })
+}
+
+/// The file at `path` deserialized from `contents`.
+pub(crate) fn parse_toml<T: DeserializeOwned>(
+    path: &Path,
+    contents: &str,
+) -> Result<T, ParseError> {
+    toml::from_str(contents).map_err(|error| ParseError::from_toml(path, contents, &error))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cargo::CargoManifestService;
+    use crate::filesystem::MockFileSystem;
+    use crate::Error;
+
+    #[test]
+    fn points_at_the_invalid_part_of_a_manifest() {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = 0.1.0\n".to_vec(),
+        );
+        let service = CargoManifestService::new(Arc::new(fs));
+
+        let err = service.load_manifest(Path::new("Cargo.toml")).unwrap_err();
+        let Error::Parse(parse_error) = err else {
+            panic!("expected a parse error, got {err:#}");
+        };
+        assert_eq!(parse_error.path, Path::new("a/Cargo.toml"));
+        assert_eq!(parse_error.location(), Some((3, 14)));
+        assert!(parse_error
+            .to_string()
+            .starts_with("failed to parse a/Cargo.toml at line 3, column 14: "));
+        let labels = parse_error.labels().unwrap().collect::<Vec<_>>();
+        assert_eq!(labels[0].offset(), 34);
+
+        let err = parse_toml::<toml::Table>(Path::new(".cargo-set.toml"), "a = ").unwrap_err();
+        assert_eq!(err.location(), Some((1, 5)));
+    }
+}
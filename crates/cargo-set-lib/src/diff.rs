@@ -0,0 +1,215 @@
+/// Lines of context kept around every change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// Old line `.0` is kept as new line `.1`.
+    Equal(usize, usize),
+    /// Old line `.0` is removed, before new line `.1`.
+    Delete(usize, usize),
+    /// New line `.1` is inserted, before old line `.0`.
+    Insert(usize, usize),
+}
+
+impl Op {
+    fn positions(self) -> (usize, usize) {
+        match self {
+            Op::Equal(old, new) | Op::Delete(old, new) | Op::Insert(old, new) => (old, new),
+        }
+    }
+}
+
+/// The change from `old` to `new` of the file at `path` as a `git diff` style
+/// unified diff, which `git apply` accepts. `None` stands for a file that
+/// doesn't exist. Empty if nothing changed.
+pub(crate) fn unified_diff(path: &str, old: Option<&str>, new: Option<&str>) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let a = old
+        .unwrap_or_default()
+        .split_inclusive('\n')
+        .collect::<Vec<_>>();
+    let b = new
+        .unwrap_or_default()
+        .split_inclusive('\n')
+        .collect::<Vec<_>>();
+    let ops = diff_lines(&a, &b);
+
+    let mut patch = format!("diff --git a/{path} b/{path}\n");
+    match (old, new) {
+        (None, _) => patch.push_str("new file mode 100644\n--- /dev/null\n"),
+        (_, None) => patch.push_str(&format!("deleted file mode 100644\n--- a/{path}\n")),
+        _ => patch.push_str(&format!("--- a/{path}\n")),
+    }
+    match new {
+        Some(_) => patch.push_str(&format!("+++ b/{path}\n")),
+        None => patch.push_str("+++ /dev/null\n"),
+    }
+
+    for hunk in hunks(&ops) {
+        let ops = &ops[hunk];
+        let old_count = ops
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(..)))
+            .count();
+        let new_count = ops
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(..)))
+            .count();
+        let (old_start, new_start) = ops[0].positions();
+        patch.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_count),
+            range(new_start, new_count)
+        ));
+
+        for op in ops {
+            let (marker, line) = match *op {
+                Op::Equal(old, _) => (' ', a[old]),
+                Op::Delete(old, _) => ('-', a[old]),
+                Op::Insert(_, new) => ('+', b[new]),
+            };
+            patch.push(marker);
+            patch.push_str(line);
+            if !line.ends_with('\n') {
+                patch.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    patch
+}
+
+/// A hunk header range starting at the 0-based line `start`.
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{start},0"),
+        1 => format!("{}", start + 1),
+        count => format!("{},{count}", start + 1),
+    }
+}
+
+/// The changed stretches of `ops` with their context, merged where their
+/// context overlaps.
+fn hunks(ops: &[Op]) -> Vec<std::ops::Range<usize>> {
+    let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        if matches!(op, Op::Equal(..)) {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if last.end >= start => last.end = end,
+            _ => hunks.push(start..end),
+        }
+    }
+
+    hunks
+}
+
+/// The shortest edit script from `a` to `b`, by Myers' algorithm.
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    if max == 0 {
+        return Vec::new();
+    }
+    let at = |k: isize| (k + max) as usize;
+
+    // The furthest `x` reached on every diagonal `k = x - y`, after each
+    // number of edits.
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace = Vec::new();
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(Op::Insert(x as usize, prev_y as usize));
+            } else {
+                ops.push(Op::Delete(prev_x as usize, y as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+
+    ops
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn renders_git_style_hunks() {
+        let old = "[package]\nname = 'a'\nversion = '0.1.0'\nedition = '2021'\n\n[dependencies]\nb = '0.1'\nc = '1'\nd = '1'\ne = '1'\nf = '1'\n";
+        let new = "[package]\nname = 'a'\nversion = '0.2.0'\nedition = '2021'\n\n[dependencies]\nb = '0.1'\nc = '1'\nd = '1'\ne = '1'\nf = '2'";
+
+        assert_eq!(
+            unified_diff("a/Cargo.toml", Some(old), Some(new)),
+            "diff --git a/a/Cargo.toml b/a/Cargo.toml\n\
+             --- a/a/Cargo.toml\n\
+             +++ b/a/Cargo.toml\n\
+             @@ -1,6 +1,6 @@\n \
+             [package]\n \
+             name = 'a'\n\
+             -version = '0.1.0'\n\
+             +version = '0.2.0'\n \
+             edition = '2021'\n \
+             \n \
+             [dependencies]\n\
+             @@ -8,4 +8,4 @@\n \
+             c = '1'\n \
+             d = '1'\n \
+             e = '1'\n\
+             -f = '1'\n\
+             +f = '2'\n\
+             \\ No newline at end of file\n"
+        );
+        assert_eq!(
+            unified_diff("VERSION", None, Some("1.0.0\n")),
+            "diff --git a/VERSION b/VERSION\nnew file mode 100644\n--- /dev/null\n+++ b/VERSION\n@@ -0,0 +1 @@\n+1.0.0\n"
+        );
+        assert!(unified_diff("VERSION", Some("x"), Some("x")).is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::index::DependencyLocation;
+use crate::paths::display_path;
+
+/// A crate required at different versions by several tables of one manifest,
+/// e.g. `[dependencies]` and `[dev-dependencies]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateDependency {
+    pub manifest: PathBuf,
+    pub package: String,
+    pub declarations: Vec<DeclaredRequirement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclaredRequirement {
+    pub location: DependencyLocation,
+    pub requirement: String,
+    /// Declared with `workspace = true`; the requirement is the workspace's.
+    pub inherited: bool,
+}
+
+impl DuplicateDependency {
+    /// The requirement every declaration should share: the one allowing the
+    /// highest minimum version.
+    pub fn unified(&self) -> &str {
+        self.declarations
+            .iter()
+            .rev()
+            .max_by_key(|d| minimum_version(&d.requirement))
+            .map(|d| d.requirement.as_str())
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for DuplicateDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} is required as ",
+            display_path(&self.manifest),
+            self.package
+        )?;
+        for (i, d) in self.declarations.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{} in `{}`",
+                d.requirement,
+                d.location.table.key().join(".")
+            )?;
+            if d.inherited {
+                write!(f, " (inherited)")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The lowest version `requirement` allows, with missing components as zero.
+/// The lowest version `requirement` allows, ignoring pre-releases.
+pub(crate) fn minimum_version(requirement: &str) -> Option<semver::Version> {
+    let requirement = semver::VersionReq::parse(requirement).ok()?;
+
+    requirement
+        .comparators
+        .iter()
+        .filter(|c| !matches!(c.op, semver::Op::Less | semver::Op::LessEq))
+        .map(|c| semver::Version::new(c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0)))
+        .max()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::index::{DependencyKind, DependencyTable};
+
+    #[test]
+    fn unifies_on_the_highest_minimum() {
+        let declaration = |kind, requirement: &str| DeclaredRequirement {
+            location: DependencyLocation {
+                manifest: std::path::Path::new("Cargo.toml").into(),
+                table: DependencyTable::new(kind),
+                name: "syn".into(),
+            },
+            requirement: requirement.to_string(),
+            inherited: false,
+        };
+        let duplicate = DuplicateDependency {
+            manifest: PathBuf::from("Cargo.toml"),
+            package: "syn".to_string(),
+            declarations: vec![
+                declaration(DependencyKind::Normal, "1.0.100"),
+                declaration(DependencyKind::Dev, ">=1.0.109, <2"),
+                declaration(DependencyKind::Build, "1"),
+            ],
+        };
+
+        assert_eq!(duplicate.unified(), ">=1.0.109, <2");
+        assert_eq!(
+            duplicate.to_string(),
+            "Cargo.toml: syn is required as 1.0.100 in `dependencies`, >=1.0.109, <2 in `dev-dependencies`, 1 in `build-dependencies`"
+        );
+    }
+}
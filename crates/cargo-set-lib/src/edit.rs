@@ -0,0 +1,228 @@
+use std::path::PathBuf;
+
+use crate::bump::BumpLevel;
+use crate::cargo::{CargoManifest, CargoManifestService};
+use crate::filesystem::FileSystem;
+use crate::keys::{KeyPath, KeyValue};
+use crate::plan::ChangeSet;
+
+/// Several edits of a workspace, applied in one pass that writes every
+/// manifest at most once.
+///
+/// ```
+/// use std::path::Path;
+///
+/// use cargo_set_lib::{BumpLevel, CargoManifestService, ManifestEdit, MemoryFileSystem};
+///
+/// let fs = MemoryFileSystem::new()
+///     .with_file("Cargo.toml", "[workspace]\nmembers = [\"foo\", \"bar\"]\n")
+///     .with_file("foo/Cargo.toml", "[package]\nname = \"foo\"\nversion = \"1.0.0\"\n")
+///     .with_file("bar/Cargo.toml", "[package]\nname = \"bar\"\nversion = \"0.4.1\"\n");
+/// let service = CargoManifestService::new(fs);
+/// let mut workspace = service.load_manifest(Path::new("Cargo.toml"))?;
+///
+/// ManifestEdit::new(&mut workspace)
+///     .set_version("foo", "1.2.3")
+///     .bump("bar", BumpLevel::Minor)
+///     .set_edition("2021")
+///     .apply(&service)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct ManifestEdit<'s> {
+    manifest: &'s mut CargoManifest,
+    edits: Vec<Operation>,
+}
+
+/// One edit of a workspace, as [`ManifestEdit`] collects them and
+/// [`CargoManifestService::plan`] takes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// Set the package to the version, as `update_version` does.
+    SetVersion(String, String),
+    /// Bump the package from the version the operations before left it at.
+    Bump(String, BumpLevel),
+    /// Set the edition of the root and every member.
+    SetEdition(String),
+    /// Set the `rust-version` of the root and every member.
+    SetRustVersion(String),
+    /// Set the key in the root and every member, as `set_key` does.
+    SetKey(KeyPath, KeyValue),
+}
+
+impl<'s> ManifestEdit<'s> {
+    pub fn new(manifest: &'s mut CargoManifest) -> Self {
+        Self {
+            manifest,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Set `package` to `version`, as `update_version` does.
+    pub fn set_version(mut self, package: impl Into<String>, version: impl Into<String>) -> Self {
+        self.edits
+            .push(Operation::SetVersion(package.into(), version.into()));
+        self
+    }
+
+    /// Bump `package` by `level` from the version the edits before left it
+    /// at.
+    pub fn bump(mut self, package: impl Into<String>, level: BumpLevel) -> Self {
+        self.edits.push(Operation::Bump(package.into(), level));
+        self
+    }
+
+    /// Set the edition of the root and every member.
+    pub fn set_edition(mut self, edition: impl Into<String>) -> Self {
+        self.edits.push(Operation::SetEdition(edition.into()));
+        self
+    }
+
+    /// Set the `rust-version` of the root and every member.
+    pub fn set_rust_version(mut self, rust_version: impl Into<String>) -> Self {
+        self.edits
+            .push(Operation::SetRustVersion(rust_version.into()));
+        self
+    }
+
+    /// Set `key` to `value` in the root and every member, as `set_key` does.
+    pub fn set_key(mut self, key: KeyPath, value: KeyValue) -> Self {
+        self.edits.push(Operation::SetKey(key, value));
+        self
+    }
+
+    /// Apply the edits in order and write the manifests they changed. If an
+    /// edit fails nothing is written.
+    ///
+    /// Returns the manifests written, in path order.
+    pub fn apply<F: FileSystem>(
+        self,
+        service: &CargoManifestService<F>,
+    ) -> crate::Result<Vec<PathBuf>> {
+        let edits = self.edits;
+        Ok(service.write_once(self.manifest, |service, s| run(service, s, &edits))?)
+    }
+
+    /// What applying the edits would change, without writing anything.
+    pub fn plan<F: FileSystem>(
+        self,
+        service: &CargoManifestService<F>,
+    ) -> crate::Result<ChangeSet> {
+        service.plan(self.manifest, &self.edits)
+    }
+}
+
+/// Apply `operations` to `s` in order.
+pub(crate) fn run<F: FileSystem>(
+    service: &CargoManifestService<F>,
+    s: &mut CargoManifest,
+    operations: &[Operation],
+) -> anyhow::Result<()> {
+    for operation in operations {
+        match operation {
+            Operation::SetVersion(package, version) => {
+                service.update_version(s, package.as_str(), version.as_str())?;
+            }
+            Operation::Bump(package, level) => {
+                service.bump_version(s, package, *level, None)?;
+            }
+            Operation::SetEdition(edition) => {
+                service.set_edition(s, edition, true)?;
+            }
+            Operation::SetRustVersion(rust_version) => {
+                service.set_rust_version(s, rust_version, true)?;
+            }
+            Operation::SetKey(key, value) => {
+                service.set_key(s, key, value, true)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::filesystem::MockFileSystem;
+
+    /// Counts the writes to every path.
+    struct CountingFileSystem {
+        fs: MockFileSystem,
+        writes: std::sync::Mutex<Vec<PathBuf>>,
+    }
+
+    impl FileSystem for CountingFileSystem {
+        fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+            self.fs.read(path)
+        }
+
+        fn write(&self, path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+            self.writes.lock().unwrap().push(path.to_path_buf());
+            self.fs.write(path, contents)
+        }
+
+        fn remove(&self, path: &Path) -> std::io::Result<()> {
+            self.fs.remove(path)
+        }
+    }
+
+    #[test]
+    fn applies_every_edit_with_one_write_per_manifest() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['foo', 'bar']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("foo/Cargo.toml"),
+            b"[package]\nname = 'foo'\nversion = '0.1.0'\nedition = '2018'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("bar/Cargo.toml"),
+            b"[package]\nname = 'bar'\nversion = '0.1.0'\nedition = '2018'\n\n[dependencies]\nfoo = { path = '../foo', version = '0.1.0' }\n".to_vec(),
+        );
+        let fs = Arc::new(CountingFileSystem {
+            fs,
+            writes: Default::default(),
+        });
+        let service = CargoManifestService::new(fs.clone());
+        let mut manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+
+        let written = ManifestEdit::new(&mut manifest)
+            .set_version("foo", "1.2.3")
+            .bump("bar", BumpLevel::Minor)
+            .bump("foo", BumpLevel::Patch)
+            .set_edition("2021")
+            .apply(&service)?;
+
+        assert_eq!(
+            written,
+            [Path::new("bar/Cargo.toml"), Path::new("foo/Cargo.toml")]
+        );
+        assert_eq!(*fs.writes.lock().unwrap(), written);
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("bar/Cargo.toml"))?)?,
+            "[package]\nname = 'bar'\nversion = '0.2.0'\nedition = '2021'\n\n[dependencies]\nfoo = { path = '../foo', version = '1.2.4' }\n"
+        );
+        assert_eq!(
+            String::from_utf8(fs.read(Path::new("foo/Cargo.toml"))?)?,
+            "[package]\nname = 'foo'\nversion = '1.2.4'\nedition = '2021'\n"
+        );
+
+        let err = ManifestEdit::new(&mut manifest)
+            .set_version("foo", "2.0.0")
+            .set_edition("2077")
+            .apply(&service)
+            .unwrap_err();
+        assert!(err.to_string().contains("`2077` is not a Rust edition"));
+        assert_eq!(fs.writes.lock().unwrap().len(), 2);
+        assert!(manifest
+            .package_versions()
+            .contains(&("foo".to_string(), Some("1.2.4".to_string()))));
+
+        Ok(())
+    }
+}
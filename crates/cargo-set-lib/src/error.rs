@@ -0,0 +1,160 @@
+use std::fmt;
+
+use crate::{Cancelled, ParseError, PartialWriteError, PolicyError};
+
+/// The error of the library's operations.
+///
+/// The failures callers handle differently have a variant of their own;
+/// everything else is [`Error::Other`], with context naming the file and
+/// value involved.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A manifest or settings file doesn't parse.
+    Parse(ParseError),
+    /// A change breaks the rules of the workspace's policy; nothing was
+    /// written.
+    Policy(PolicyError),
+    /// An update failed after writing some manifests.
+    PartialWrite(PartialWriteError),
+    /// The cancellation token of the service fired.
+    Cancelled(Cancelled),
+    Other(anyhow::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// [`anyhow::bail!`] for the functions returning the crate's [`Result`] as
+/// well as those returning [`anyhow::Result`].
+macro_rules! bail {
+    ($($arg:tt)*) => {
+        return Err(anyhow::anyhow!($($arg)*).into())
+    };
+}
+pub(crate) use bail;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(error) => error.fmt(f),
+            Self::Policy(error) => error.fmt(f),
+            Self::PartialWrite(error) => error.fmt(f),
+            Self::Cancelled(error) => error.fmt(f),
+            Self::Other(error) => fmt::Display::fmt(error, f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    // The variants stand in for the error they hold, so the chain goes on
+    // with its source rather than repeating it.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(error) => error.source(),
+            Self::Policy(error) => error.source(),
+            Self::PartialWrite(error) => error.source(),
+            Self::Cancelled(error) => error.source(),
+            Self::Other(error) => error.source(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(error: anyhow::Error) -> Self {
+        // An `Error` that went through a function of the crate comes back
+        // as it was, keeping the context added to `Other` on the way.
+        match error.downcast_ref::<Self>() {
+            Some(Self::Other(_)) => return Self::Other(error),
+            Some(_) => return error.downcast().expect("checked above"),
+            None => {}
+        }
+        let error = match error.downcast() {
+            Ok(error) => return Self::Parse(error),
+            Err(error) => error,
+        };
+        let error = match error.downcast() {
+            Ok(error) => return Self::Policy(error),
+            Err(error) => error,
+        };
+        let error = match error.downcast() {
+            Ok(error) => return Self::PartialWrite(error),
+            Err(error) => error,
+        };
+        match error.downcast() {
+            Ok(error) => Self::Cancelled(error),
+            Err(error) => Self::Other(error),
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<PolicyError> for Error {
+    fn from(error: PolicyError) -> Self {
+        Self::Policy(error)
+    }
+}
+
+impl From<PartialWriteError> for Error {
+    fn from(error: PartialWriteError) -> Self {
+        Self::PartialWrite(error)
+    }
+}
+
+impl From<Cancelled> for Error {
+    fn from(error: Cancelled) -> Self {
+        Self::Cancelled(error)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Self::Other(error.into())
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Other(error.into())
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(error: toml::ser::Error) -> Self {
+        Self::Other(error.into())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Other(error.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classifies_the_typed_failures() -> anyhow::Result<()> {
+        let error = Error::from(anyhow::Error::new(Cancelled).context("setting a"));
+        assert!(matches!(error, Error::Cancelled(Cancelled)));
+
+        let error = Error::from(anyhow::anyhow!("no such crate").context("setting a"));
+        assert!(matches!(error, Error::Other(_)));
+        assert_eq!(format!("{error:#}"), "setting a: no such crate");
+
+        // Through `anyhow` and back, as when one operation calls another.
+        let error = Error::from(anyhow::Error::new(error).context("bumping"));
+        assert!(matches!(error, Error::Other(_)));
+        assert_eq!(format!("{error:#}"), "bumping: setting a: no such crate");
+        let error = Error::from(anyhow::Error::new(Error::Cancelled(Cancelled)));
+        assert!(matches!(error, Error::Cancelled(Cancelled)));
+
+        Ok(())
+    }
+}
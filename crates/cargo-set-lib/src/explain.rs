@@ -0,0 +1,65 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::paths::display_path;
+use crate::policy::PolicyViolation;
+
+/// Why a crate is about to be set to a version, and which files change
+/// because of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub package: String,
+    pub from: Option<String>,
+    pub to: String,
+    /// How the version was chosen, in the order the decisions were made.
+    pub reasons: Vec<String>,
+    /// What the policy refuses about the change; empty if it passes.
+    pub violations: Vec<PolicyViolation>,
+    pub edits: Vec<ExplainedEdit>,
+}
+
+/// A file the change edits, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainedEdit {
+    pub path: PathBuf,
+    /// The dotted key of the edited value, e.g. `dependencies.a.version`.
+    pub key: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}",
+            self.package,
+            self.from.as_deref().unwrap_or("(none)"),
+            self.to
+        )?;
+        for reason in &self.reasons {
+            write!(f, "\n  chosen: {reason}")?;
+        }
+        match self.violations.as_slice() {
+            [] => write!(f, "\n  policy: allowed")?,
+            violations => {
+                for violation in violations {
+                    write!(f, "\n  policy: refused, {}", violation.reason)?;
+                }
+            }
+        }
+        if self.edits.is_empty() {
+            write!(f, "\n  edits:  nothing, no manifest declares it")?;
+        }
+        for edit in &self.edits {
+            write!(
+                f,
+                "\n  edits:  {} `{}`, {}",
+                display_path(&edit.path),
+                edit.key,
+                edit.reason
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// An alternative of an exclusive group, and the substrings marking the
+/// features that choose it.
+type Alternative = (&'static str, &'static [&'static str]);
+
+/// Features of a dependency that select one of several alternative
+/// implementations; members choosing different ones are likely to break
+/// each other once cargo unifies their features.
+const EXCLUSIVE_GROUPS: &[(&str, &[Alternative])] = &[(
+    "TLS backends",
+    &[
+        ("rustls", &["rustls"]),
+        ("native-tls", &["native-tls", "openssl"]),
+    ],
+)];
+
+/// A dependency enabled with different features by different members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureInconsistency {
+    pub package: String,
+    pub members: Vec<MemberFeatures>,
+    /// Alternatives enabled by different members, e.g. `TLS backends
+    /// rustls and native-tls`.
+    pub conflict: Option<String>,
+}
+
+/// The features one member enables for a dependency.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MemberFeatures {
+    pub member: String,
+    pub features: BTreeSet<String>,
+    pub default_features: bool,
+}
+
+impl FeatureInconsistency {
+    /// The one declaration every member could share, for
+    /// `[workspace.dependencies]`.
+    pub fn unified(&self) -> String {
+        let features = self
+            .members
+            .iter()
+            .flat_map(|m| &m.features)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|feature| format!("\"{feature}\""))
+            .collect::<Vec<_>>();
+        let mut declaration = format!("{} = {{ ", self.package);
+        if self.members.iter().all(|m| !m.default_features) {
+            declaration.push_str("default-features = false, ");
+        }
+        declaration.push_str(&format!("features = [{}] }}", features.join(", ")));
+
+        declaration
+    }
+}
+
+impl fmt::Display for FeatureInconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is used with different features: ", self.package)?;
+        for (i, member) in self.members.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let features = member.features.iter().cloned().collect::<Vec<_>>();
+            write!(f, "{} [{}]", member.member, features.join(", "))?;
+            if !member.default_features {
+                write!(f, " without default features")?;
+            }
+        }
+
+        match &self.conflict {
+            Some(conflict) => write!(f, "; conflicting {conflict}, pick one for every member"),
+            None => write!(
+                f,
+                "; unify as `{}` in `[workspace.dependencies]`",
+                self.unified()
+            ),
+        }
+    }
+}
+
+/// The inconsistencies among `uses`, the features every member enables for
+/// each dependency.
+pub(crate) fn inconsistencies(
+    uses: BTreeMap<String, BTreeSet<MemberFeatures>>,
+) -> Vec<FeatureInconsistency> {
+    uses.into_iter()
+        .filter(|(_, members)| {
+            let mut enabled = members.iter().map(|m| (&m.features, m.default_features));
+            let first = enabled.next();
+            enabled.any(|other| Some(other) != first)
+        })
+        .map(|(package, members)| {
+            let conflict = conflict(&members);
+            FeatureInconsistency {
+                package,
+                members: members.into_iter().collect(),
+                conflict,
+            }
+        })
+        .collect()
+}
+
+fn conflict(members: &BTreeSet<MemberFeatures>) -> Option<String> {
+    EXCLUSIVE_GROUPS.iter().find_map(|(group, alternatives)| {
+        let chosen = alternatives
+            .iter()
+            .filter(|(_, markers)| {
+                members
+                    .iter()
+                    .flat_map(|m| &m.features)
+                    .any(|feature| markers.iter().any(|marker| feature.contains(marker)))
+            })
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+
+        (chosen.len() > 1).then(|| format!("{group} {}", chosen.join(" and ")))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn member(name: &str, features: &[&str], default_features: bool) -> MemberFeatures {
+        MemberFeatures {
+            member: name.to_string(),
+            features: features.iter().map(|f| f.to_string()).collect(),
+            default_features,
+        }
+    }
+
+    #[test]
+    fn reports_differing_and_conflicting_features() {
+        let uses = BTreeMap::from([
+            (
+                "reqwest".to_string(),
+                BTreeSet::from([
+                    member("a", &["rustls-tls"], false),
+                    member("b", &["json", "native-tls"], true),
+                ]),
+            ),
+            (
+                "serde".to_string(),
+                BTreeSet::from([member("a", &["derive"], true), member("b", &[], true)]),
+            ),
+            (
+                "log".to_string(),
+                BTreeSet::from([member("a", &[], true), member("b", &[], true)]),
+            ),
+        ]);
+
+        assert_eq!(
+            inconsistencies(uses)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "reqwest is used with different features: a [rustls-tls] without default features, b [json, native-tls]; conflicting TLS backends rustls and native-tls, pick one for every member",
+                "serde is used with different features: a [derive], b []; unify as `serde = { features = [\"derive\"] }` in `[workspace.dependencies]`",
+            ]
+        );
+    }
+}
@@ -1,64 +1,1140 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(any(test, feature = "test-util"))]
+use std::collections::{HashMap, HashSet};
 use std::io;
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
-pub trait FileSystem {
+use anyhow::Context;
+
+use crate::diagnostic;
+use crate::diff;
+use crate::paths::{display_path, normalize};
+
+/// Storage backend used by `CargoManifestService`.
+///
+/// Implementations must be `Send + Sync` so a single service can be shared
+/// between threads operating on disjoint workspaces.
+pub trait FileSystem: Send + Sync {
     fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
     fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()>;
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Resolve symlinks in `path`. File systems without symlinks return the
+    /// path unchanged.
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Ok(path.to_path_buf())
+    }
+
+    /// Check, without changing anything, that `write(path, ..)` can succeed.
+    /// Used to refuse an update up front instead of failing halfway through.
+    fn probe_write(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The entries of the directory `path`, each joined onto `path`, in path
+    /// order. Needed for glob patterns in `workspace.members`; file systems
+    /// that can't list directories return `Unsupported`.
+    fn list_dir(&self, _path: &Path) -> io::Result<Vec<PathBuf>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "listing directories is not supported",
+        ))
+    }
+
+    /// Whether a file or directory exists at `path`.
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        match self.read(path) {
+            Ok(_) => return Ok(true),
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            Err(_) => {}
+        }
+        match self.list_dir(path) {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write `contents` to `path`, failing with `AlreadyExists` instead of
+    /// replacing a file that is there, e.g. for backups that must not
+    /// clobber an earlier one.
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        if self.exists(path)? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", display_path(path)),
+            ));
+        }
+
+        self.write(path, contents)
+    }
+
+    /// Every file below the directory `path`, each joined onto `path`, in
+    /// path order. Built on `list_dir`.
+    fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dirs = vec![path.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in self.list_dir(&dir)? {
+                match self.list_dir(&entry) {
+                    Ok(_) => dirs.push(entry),
+                    Err(e) if e.kind() == io::ErrorKind::NotADirectory => files.push(entry),
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+/// The manifest in `dir` or the nearest of its ancestors, as cargo finds the
+/// package it runs in.
+pub fn nearest_manifest<F: FileSystem>(fs: &F, dir: &Path) -> io::Result<Option<PathBuf>> {
+    for dir in dir.ancestors() {
+        let manifest = dir.join("Cargo.toml");
+        if fs.exists(&manifest)? {
+            return Ok(Some(manifest));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The root manifests of the workspaces below `dir`, in path order: every
+/// manifest with a `[workspace]` table, and every package outside of those
+/// that cargo would treat as its own root. `target` and hidden directories
+/// are skipped.
+pub fn find_workspace_roots<F: FileSystem>(fs: &F, dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    let mut roots = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), false)];
+    while let Some((dir, mut in_workspace)) = pending.pop() {
+        let manifest = dir.join("Cargo.toml");
+        if let Some(contents) = read_to_string(fs, &manifest)? {
+            let table: toml::Table = diagnostic::parse_toml(&manifest, &contents)?;
+            if table.contains_key("workspace") {
+                roots.push(manifest);
+                in_workspace = true;
+            } else if !in_workspace && table.contains_key("package") {
+                roots.push(manifest);
+            }
+        }
+
+        let entries = fs
+            .list_dir(&dir)
+            .with_context(|| format!("failed to list {}", display_path(&dir)))?;
+        for entry in entries {
+            let skipped = entry
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name == "target" || name.starts_with('.'));
+            if skipped {
+                continue;
+            }
+            match fs.list_dir(&entry) {
+                Ok(_) => pending.push((entry, in_workspace)),
+                Err(e) if e.kind() == io::ErrorKind::NotADirectory => {}
+                Err(e) => {
+                    Err(e).with_context(|| format!("failed to list {}", display_path(&entry)))?
+                }
+            }
+        }
+    }
+    roots.sort();
+
+    Ok(roots)
+}
+
+/// The contents of `path`, `None` if it doesn't exist.
+fn read_to_string<F: FileSystem>(fs: &F, path: &Path) -> anyhow::Result<Option<String>> {
+    match fs.read(path) {
+        Ok(contents) => {
+            Ok(Some(String::from_utf8(contents).with_context(|| {
+                format!("failed to parse {}", display_path(path))
+            })?))
+        }
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::IsADirectory
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", display_path(path))),
+    }
+}
+
+impl<F: FileSystem + ?Sized> FileSystem for &F {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        (**self).write(path, contents)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        (**self).remove(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        (**self).canonicalize(path)
+    }
+
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        (**self).probe_write(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).list_dir(path)
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        (**self).exists(path)
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        (**self).create(path, contents)
+    }
+
+    fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).walk(path)
+    }
+}
+
+impl<F: FileSystem + ?Sized> FileSystem for Arc<F> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        (**self).write(path, contents)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        (**self).remove(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        (**self).canonicalize(path)
+    }
+
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        (**self).probe_write(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).list_dir(path)
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        (**self).exists(path)
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        (**self).create(path, contents)
+    }
+
+    fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        (**self).walk(path)
+    }
 }
 
+/// The file system of the host, through `std::fs`. Behind the default
+/// `real-fs` feature; targets without one, like `wasm32-unknown-unknown`,
+/// bring their own [`FileSystem`].
+#[cfg(feature = "real-fs")]
+#[derive(Debug, Default, Clone, Copy)]
 pub struct RealFileSystem;
 
+#[cfg(feature = "real-fs")]
 impl FileSystem for RealFileSystem {
     #[inline(always)]
     fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
         std::fs::read(path)
     }
 
+    /// Existing files are replaced by writing a sibling temporary file and
+    /// renaming it over the target, carrying over the target's permissions and
+    /// (on Unix, where allowed) its owner. The rename goes to the resolved
+    /// target, so symlinks are kept. Extended attributes are not copied.
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        let target = match std::fs::canonicalize(path) {
+            Ok(target) => target,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return std::fs::write(path, contents),
+            Err(e) => return Err(e),
+        };
+        let metadata = std::fs::metadata(&target)?;
+        let temp = sibling(&target, "tmp");
+
+        let result = std::fs::write(&temp, contents)
+            .and_then(|()| copy_ownership(&metadata, &temp))
+            .and_then(|()| std::fs::set_permissions(&temp, metadata.permissions()))
+            .and_then(|()| std::fs::rename(&temp, &target));
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+
+        result
+    }
+
+    #[inline(always)]
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_file(path)
+    }
+
     #[inline(always)]
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+
+    /// Creates and removes a file next to the target, which is what `write`
+    /// needs; a read-only mount or directory fails here.
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        let target = match std::fs::canonicalize(path) {
+            Ok(target) => target,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+            Err(e) => return Err(e),
+        };
+
+        let probe = sibling(&target, "probe");
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&probe)?;
+        std::fs::remove_file(&probe)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let dir = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        };
+        let mut entries = std::fs::read_dir(dir)?
+            .map(|entry| Ok(path.join(entry?.file_name())))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+
+        Ok(entries)
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        path.try_exists()
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        use std::io::Write;
+
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+            .write_all(&contents)
+    }
+}
+
+/// A hidden file next to `path`, e.g. `.Cargo.toml.cargo-set.tmp`.
+#[cfg(feature = "real-fs")]
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = std::ffi::OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(".cargo-set.");
+    file_name.push(suffix);
+
+    path.with_file_name(file_name)
+}
+
+/// Wraps another file system and fails operations that don't finish within
+/// a deadline, so a hung network or FUSE mount can't stall the tool forever.
+///
+/// Each operation runs on its own thread while a timeout is configured. A
+/// timed out operation can't be interrupted and keeps its thread until the
+/// underlying call returns; its result is discarded.
+#[derive(Debug, Clone)]
+pub struct TimeoutFileSystem<F> {
+    fs: Arc<F>,
+    timeout: Option<Duration>,
+}
+
+impl<F: FileSystem + 'static> TimeoutFileSystem<F> {
+    /// Without a `timeout`, operations run directly on the calling thread.
+    pub fn new(fs: F, timeout: Option<Duration>) -> Self {
+        Self {
+            fs: Arc::new(fs),
+            timeout,
+        }
+    }
+
+    fn run<T: Send + 'static>(
+        &self,
+        action: &str,
+        path: &Path,
+        op: impl FnOnce(&F, &Path) -> io::Result<T> + Send + 'static,
+    ) -> io::Result<T> {
+        let Some(timeout) = self.timeout else {
+            return op(&self.fs, path);
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let fs = self.fs.clone();
+        let owned = path.to_path_buf();
+        std::thread::spawn(move || {
+            let _ = sender.send(op(&fs, &owned));
+        });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("timed out {action} {}", display_path(path)),
+            )),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(io::Error::other(format!(
+                "panicked while {action} {}",
+                display_path(path)
+            ))),
+        }
+    }
+}
+
+impl<F: FileSystem + 'static> FileSystem for TimeoutFileSystem<F> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.run("reading", path, |fs, path| fs.read(path))
+    }
+
     fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
-        std::fs::write(path, contents)
+        self.run("writing", path, move |fs, path| fs.write(path, contents))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.run("removing", path, |fs, path| fs.remove(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.run("resolving", path, |fs, path| fs.canonicalize(path))
+    }
+
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        self.run("probing", path, |fs, path| fs.probe_write(path))
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.run("listing", path, |fs, path| fs.list_dir(path))
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        self.run("checking", path, |fs, path| fs.exists(path))
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        self.run("creating", path, move |fs, path| fs.create(path, contents))
+    }
+
+    fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.run("walking", path, |fs, path| fs.walk(path))
+    }
+}
+
+/// Wraps another file system and records writes and removals instead of
+/// performing them. Reads see the recorded changes, so a sequence of edits
+/// behaves as it would on disk.
+#[derive(Debug)]
+pub struct RecordingFileSystem<F> {
+    fs: F,
+    changes: Mutex<BTreeMap<PathBuf, Change>>,
+}
+
+/// The original and recorded contents of a changed path; `None` for a file
+/// that doesn't exist.
+#[derive(Debug)]
+struct Change {
+    original: Option<Vec<u8>>,
+    recorded: Option<Vec<u8>>,
+}
+
+impl<F: FileSystem> RecordingFileSystem<F> {
+    pub fn new(fs: F) -> Self {
+        Self {
+            fs,
+            changes: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn record(&self, path: &Path, contents: Option<Vec<u8>>) -> io::Result<()> {
+        let mut changes = self.changes.lock().unwrap();
+        if let Some(change) = changes.get_mut(path) {
+            change.recorded = contents;
+            return Ok(());
+        }
+
+        let original = match self.fs.read(path) {
+            Ok(original) => Some(original),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        changes.insert(
+            path.to_path_buf(),
+            Change {
+                original,
+                recorded: contents,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Every recorded change as one patch for `git apply`, in path order.
+    pub fn patch(&self) -> String {
+        let changes = self.changes.lock().unwrap();
+        changes
+            .iter()
+            .map(|(path, change)| {
+                let old = change.original.as_deref().map(String::from_utf8_lossy);
+                let new = change.recorded.as_deref().map(String::from_utf8_lossy);
+                diff::unified_diff(
+                    &display_path(path).replace('\\', "/"),
+                    old.as_deref(),
+                    new.as_deref(),
+                )
+            })
+            .collect()
     }
 }
 
-#[allow(dead_code)]
+impl<F: FileSystem> FileSystem for RecordingFileSystem<F> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.changes.lock().unwrap().get(path) {
+            Some(Change {
+                recorded: Some(contents),
+                ..
+            }) => return Ok(contents.clone()),
+            Some(Change { recorded: None, .. }) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} was removed", display_path(path)),
+                ))
+            }
+            None => {}
+        }
+
+        self.fs.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        self.record(path, Some(contents))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.read(path)?;
+        self.record(path, None)
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.fs.canonicalize(path)
+    }
+
+    // A recording fails where the write would, so a dry run or a patch
+    // refuses the same updates.
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        self.fs.probe_write(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.list_dir(path)
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        match self.changes.lock().unwrap().get(path) {
+            Some(change) => Ok(change.recorded.is_some()),
+            None => self.fs.exists(path),
+        }
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        if self.exists(path)? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", display_path(path)),
+            ));
+        }
+
+        self.record(path, Some(contents))
+    }
+
+    fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut files = self.fs.walk(path)?;
+        let changes = self.changes.lock().unwrap();
+        files.retain(|file| !changes.contains_key(file));
+        files.extend(
+            changes
+                .iter()
+                .filter(|(file, change)| change.recorded.is_some() && file.starts_with(path))
+                .map(|(file, _)| file.clone()),
+        );
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+/// Wraps another file system and notes the files whose contents change
+/// through it. Writing a file its current contents isn't a change.
+#[derive(Debug)]
+pub struct TrackingFileSystem<F> {
+    fs: F,
+    changed: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl<F: FileSystem> TrackingFileSystem<F> {
+    pub fn new(fs: F) -> Self {
+        Self {
+            fs,
+            changed: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// The files written with new contents, created or removed, in path
+    /// order.
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        self.changed.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl<F: FileSystem> FileSystem for TrackingFileSystem<F> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.fs.read(path)
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        let unchanged = self.fs.read(path).is_ok_and(|current| current == contents);
+        self.fs.write(path, contents)?;
+        if !unchanged {
+            self.changed.lock().unwrap().insert(path.to_path_buf());
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.fs.remove(path)?;
+        self.changed.lock().unwrap().insert(path.to_path_buf());
+
+        Ok(())
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.fs.canonicalize(path)
+    }
+
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        self.fs.probe_write(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.list_dir(path)
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        self.fs.exists(path)
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        self.fs.create(path, contents)?;
+        self.changed.lock().unwrap().insert(path.to_path_buf());
+
+        Ok(())
+    }
+
+    fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.fs.walk(path)
+    }
+}
+
+/// Files held in memory, for working on manifests that don't come from
+/// disk, e.g. one read from stdin:
+///
+/// ```
+/// use std::path::Path;
+///
+/// use cargo_set_lib::{CargoManifestService, FileSystem, MemoryFileSystem};
+///
+/// let fs = MemoryFileSystem::new().with_file(
+///     "Cargo.toml",
+///     "[package]\nname = \"foo\"\nversion = \"0.1.0\"\n",
+/// );
+/// let service = CargoManifestService::new(&fs);
+/// let mut manifest = service.load_manifest(Path::new("Cargo.toml"))?;
+/// service.update_version(&mut manifest, "foo", "0.2.0")?;
+///
+/// assert!(String::from_utf8(fs.read(Path::new("Cargo.toml"))?)?.contains("0.2.0"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file at `path`, replacing any there.
+    pub fn with_file(self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(normalize(path.as_ref()), contents.into());
+        self
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(&normalize(path))
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        self.files.lock().unwrap().insert(normalize(path), contents);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match self.files.lock().unwrap().remove(&normalize(path)) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "File not found")),
+        }
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let dir = normalize(path);
+        let files = self.files.lock().unwrap();
+        if files.contains_key(&dir) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "Not a directory",
+            ));
+        }
+
+        let mut entries = files
+            .keys()
+            .filter_map(|file| file.strip_prefix(&dir).ok()?.components().next())
+            .map(|name| path.join(name))
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Directory not found",
+            ));
+        }
+        entries.dedup();
+
+        Ok(entries)
+    }
+}
+
+#[cfg(all(unix, feature = "real-fs"))]
+fn copy_ownership(metadata: &std::fs::Metadata, path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    match std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid())) {
+        // Only privileged users can give files away; the new file then keeps
+        // the writing user as its owner.
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            tracing::debug!(path = %display_path(path), "cannot preserve file owner");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+#[cfg(all(not(unix), feature = "real-fs"))]
+fn copy_ownership(_: &std::fs::Metadata, _: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// An in-memory file system for tests, with symlinks and read-only paths
+/// to reproduce failures. Enable the `test-util` feature to use it outside
+/// this crate, from [`testing`](crate::testing).
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Default)]
 pub struct MockFileSystem {
     files: Mutex<HashMap<PathBuf, Vec<u8>>>,
+    symlinks: HashMap<PathBuf, PathBuf>,
+    read_only: HashSet<PathBuf>,
 }
 
-#[allow(dead_code)]
+#[cfg(any(test, feature = "test-util"))]
 impl MockFileSystem {
     pub fn new() -> Self {
-        Self {
-            files: Mutex::new(HashMap::new()),
+        Self::default()
+    }
+
+    /// A file system holding a copy of every file below `dir` on disk, at
+    /// its path relative to `dir`, e.g. a fixture workspace checked in next
+    /// to the tests. Symlinks are copied as symlinks, so their targets
+    /// should be relative. Needs the `real-fs` feature.
+    #[cfg(feature = "real-fs")]
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let mut fs = Self::new();
+        let mut pending = vec![PathBuf::new()];
+        while let Some(relative) = pending.pop() {
+            for entry in std::fs::read_dir(dir.join(&relative))? {
+                let entry = entry?;
+                let path = relative.join(entry.file_name());
+                let file_type = entry.file_type()?;
+                if file_type.is_symlink() {
+                    fs.add_symlink(path, std::fs::read_link(entry.path())?);
+                } else if file_type.is_dir() {
+                    pending.push(path);
+                } else {
+                    fs.add_file(path, std::fs::read(entry.path())?);
+                }
+            }
         }
+
+        Ok(fs)
     }
 
     pub fn add_file(&mut self, path: PathBuf, content: Vec<u8>) {
         let mut files = self.files.lock().unwrap();
         files.insert(path, content);
     }
+
+    /// Make `link` point at `target`, which is relative to the link's parent
+    /// directory unless it is absolute.
+    pub fn add_symlink(&mut self, link: PathBuf, target: PathBuf) {
+        self.symlinks.insert(link, target);
+    }
+
+    /// Fail writes to `path`, or to anything below it if it's a directory, as
+    /// a read-only mount would.
+    pub fn set_read_only(&mut self, path: PathBuf) {
+        self.read_only.insert(path);
+    }
+
+    fn check_writable(&self, path: &Path) -> io::Result<()> {
+        if path.ancestors().any(|p| self.read_only.contains(p)) {
+            return Err(io::Error::new(
+                io::ErrorKind::ReadOnlyFilesystem,
+                "read-only file system",
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        let mut path = normalize(path);
+        for _ in 0..40 {
+            let link = path
+                .ancestors()
+                .find_map(|prefix| Some((prefix, self.symlinks.get(prefix)?)));
+            let Some((link, target)) = link else {
+                return Ok(path);
+            };
+
+            let rest = path.strip_prefix(link).unwrap();
+            let base = link.parent().unwrap_or_else(|| Path::new(""));
+            path = normalize(&base.join(target).join(rest));
+        }
+
+        Err(io::Error::other("too many levels of symbolic links"))
+    }
 }
 
+#[cfg(any(test, feature = "test-util"))]
 impl FileSystem for MockFileSystem {
     fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let path = self.resolve(path)?;
         let files = self.files.lock().unwrap();
         files
-            .get(path)
+            .get(&path)
             .cloned()
             .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
     }
 
     fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        let path = self.resolve(path)?;
+        self.check_writable(&path)?;
+        let mut files = self.files.lock().unwrap();
+        files.insert(path, contents);
+
+        Ok(())
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        let path = self.resolve(path)?;
+        self.check_writable(&path)?;
         let mut files = self.files.lock().unwrap();
-        let file = files
-            .get_mut(path)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))?
-            .as_mut();
+        if files.contains_key(&path) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "File already exists",
+            ));
+        }
+        files.insert(path, contents);
+
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let path = self.resolve(path)?;
+        self.check_writable(&path)?;
+        let mut files = self.files.lock().unwrap();
+        files
+            .remove(&path)
+            .map(|_| ())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.resolve(path)
+    }
+
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        self.check_writable(&self.resolve(path)?)
+    }
+
+    /// Directories exist implicitly, as the ancestors of added files.
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let dir = self.resolve(path)?;
+        let files = self.files.lock().unwrap();
+        if files.contains_key(&dir) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "Not a directory",
+            ));
+        }
+
+        let mut entries = files
+            .keys()
+            .filter_map(|file| file.strip_prefix(&dir).ok()?.components().next())
+            .map(|name| path.join(name))
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Directory not found",
+            ));
+        }
+        entries.sort();
+        entries.dedup();
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn slow_operations_time_out() {
+        struct Hanging;
+
+        impl FileSystem for Hanging {
+            fn read(&self, _: &Path) -> io::Result<Vec<u8>> {
+                std::thread::sleep(Duration::from_secs(5));
+                Ok(Vec::new())
+            }
+
+            fn write(&self, _: &Path, _: Vec<u8>) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn remove(&self, _: &Path) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let fs = TimeoutFileSystem::new(Hanging, Some(Duration::from_millis(10)));
+
+        let err = fs.read(Path::new("crates/foo/Cargo.toml")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(err.to_string(), "timed out reading crates/foo/Cargo.toml");
+        assert!(fs.write(Path::new("Cargo.toml"), Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn finds_and_creates_files() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(PathBuf::from("Cargo.toml"), Vec::new());
+        fs.add_file(PathBuf::from("crates/a/Cargo.toml"), Vec::new());
+        fs.add_file(PathBuf::from("crates/a/src/lib.rs"), Vec::new());
+
+        assert!(fs.exists(Path::new("crates/a"))?);
+        assert!(!fs.exists(Path::new("crates/b"))?);
+        assert_eq!(
+            fs.walk(Path::new("crates"))?,
+            [
+                Path::new("crates/a/Cargo.toml"),
+                Path::new("crates/a/src/lib.rs")
+            ]
+        );
+        assert_eq!(
+            nearest_manifest(&fs, Path::new("crates/a/src"))?,
+            Some(PathBuf::from("crates/a/Cargo.toml"))
+        );
+        assert_eq!(
+            nearest_manifest(&fs, Path::new("tools/bin"))?,
+            Some(PathBuf::from("Cargo.toml"))
+        );
+
+        fs.create(Path::new("Cargo.toml.bak"), b"backup".to_vec())?;
+        let err = fs
+            .create(Path::new("Cargo.toml.bak"), Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs.read(Path::new("Cargo.toml.bak"))?, b"backup");
+
+        Ok(())
+    }
+
+    #[test]
+    fn tracks_files_whose_contents_change() -> anyhow::Result<()> {
+        let fs = TrackingFileSystem::new(
+            MemoryFileSystem::new()
+                .with_file("Cargo.toml", "[workspace]\n")
+                .with_file("a/Cargo.toml", "[package]\n"),
+        );
+
+        fs.write(Path::new("Cargo.toml"), b"[workspace]\n".to_vec())?;
+        assert!(fs.changed_files().is_empty());
+
+        fs.write(
+            Path::new("a/Cargo.toml"),
+            b"[package]\nname = 'a'\n".to_vec(),
+        )?;
+        fs.create(Path::new("b/Cargo.toml"), Vec::new())?;
+        assert_eq!(
+            fs.changed_files(),
+            [Path::new("a/Cargo.toml"), Path::new("b/Cargo.toml")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_instead_of_writing() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(PathBuf::from("crates/a/Cargo.toml"), Vec::new());
+        fs.add_file(PathBuf::from("crates/a/src/lib.rs"), Vec::new());
+        let recording = RecordingFileSystem::new(&fs);
+
+        recording.create(Path::new("crates/a/Cargo.toml.bak"), b"backup".to_vec())?;
+        recording.remove(Path::new("crates/a/src/lib.rs"))?;
+        let err = recording
+            .create(Path::new("crates/a/Cargo.toml.bak"), Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            recording.walk(Path::new("crates"))?,
+            [
+                Path::new("crates/a/Cargo.toml"),
+                Path::new("crates/a/Cargo.toml.bak")
+            ]
+        );
+        assert!(!fs.exists(Path::new("crates/a/Cargo.toml.bak"))?);
+        assert!(fs.exists(Path::new("crates/a/src/lib.rs"))?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_the_roots_of_workspaces() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        let workspace = b"[workspace]\nmembers = ['a']\n".to_vec();
+        let package = b"[package]\nname = 'a'\n".to_vec();
+        fs.add_file(PathBuf::from("repo/README.md"), Vec::new());
+        fs.add_file(PathBuf::from("repo/ws/Cargo.toml"), workspace.clone());
+        fs.add_file(PathBuf::from("repo/ws/a/Cargo.toml"), package.clone());
+        fs.add_file(PathBuf::from("repo/ws/nested/Cargo.toml"), workspace);
+        fs.add_file(PathBuf::from("repo/tool/Cargo.toml"), package.clone());
+        fs.add_file(
+            PathBuf::from("repo/tool/target/x/Cargo.toml"),
+            package.clone(),
+        );
+        fs.add_file(PathBuf::from("repo/.cargo/y/Cargo.toml"), package);
+
+        assert_eq!(
+            find_workspace_roots(&fs, Path::new("repo"))?,
+            [
+                Path::new("repo/tool/Cargo.toml"),
+                Path::new("repo/ws/Cargo.toml"),
+                Path::new("repo/ws/nested/Cargo.toml"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "real-fs")]
+    #[test]
+    fn copies_fixture_directories() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cargo-set-fixture-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("crates/a/src"))?;
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = ['crates/*']\n",
+        )?;
+        std::fs::write(dir.join("crates/a/Cargo.toml"), "[package]\nname = 'a'\n")?;
+        std::fs::write(dir.join("crates/a/src/lib.rs"), "")?;
+
+        let fs = MockFileSystem::from_dir(&dir)?;
+        std::fs::remove_dir_all(&dir)?;
+
+        assert_eq!(
+            fs.walk(Path::new(""))?,
+            [
+                Path::new("Cargo.toml"),
+                Path::new("crates/a/Cargo.toml"),
+                Path::new("crates/a/src/lib.rs"),
+            ]
+        );
+        assert_eq!(
+            fs.read(Path::new("crates/a/Cargo.toml"))?,
+            b"[package]\nname = 'a'\n"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(all(unix, feature = "real-fs"))]
+    #[test]
+    fn real_writes_keep_permissions_and_symlinks() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("cargo-set-perms-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let manifest = dir.join("Cargo.toml");
+        let link = dir.join("Linked.toml");
+        std::fs::write(&manifest, "old")?;
+        std::fs::set_permissions(&manifest, std::fs::Permissions::from_mode(0o444))?;
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&manifest, &link)?;
+
+        RealFileSystem.write(&link, b"new".to_vec())?;
 
-        *file = contents;
+        assert_eq!(std::fs::read_to_string(&manifest)?, "new");
+        assert_eq!(
+            std::fs::metadata(&manifest)?.permissions().mode() & 0o777,
+            0o444
+        );
+        assert!(std::fs::symlink_metadata(&link)?.file_type().is_symlink());
 
+        std::fs::remove_dir_all(&dir)?;
         Ok(())
     }
 }
@@ -0,0 +1,270 @@
+use anyhow::Context;
+use toml_edit::{Array, Decor, DocumentMut, Item, Table, Value};
+
+/// How `normalize` formats manifests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatStyle {
+    /// Order the keys of `[package]` and `[workspace.package]` the way the
+    /// cargo reference lists them. Unknown keys keep their order at the end.
+    pub sort_package_keys: bool,
+    /// Order dependency tables alphabetically.
+    pub sort_dependencies: bool,
+    /// Arrays that don't fit on one line of this width are written one
+    /// element per line.
+    pub max_width: usize,
+}
+
+impl Default for FormatStyle {
+    fn default() -> Self {
+        Self {
+            sort_package_keys: true,
+            sort_dependencies: false,
+            max_width: 80,
+        }
+    }
+}
+
+/// `[package]` keys in the order of the cargo reference.
+const PACKAGE_KEYS: &[&str] = &[
+    "name",
+    "version",
+    "authors",
+    "edition",
+    "rust-version",
+    "description",
+    "documentation",
+    "readme",
+    "homepage",
+    "repository",
+    "license",
+    "license-file",
+    "keywords",
+    "categories",
+    "workspace",
+    "build",
+    "links",
+    "exclude",
+    "include",
+    "publish",
+    "default-run",
+    "autobins",
+    "autoexamples",
+    "autotests",
+    "autobenches",
+    "resolver",
+    "metadata",
+];
+
+/// Normalize the spacing, array layout and (per `style`) key order of a
+/// manifest. Comments are kept.
+pub(crate) fn format(source: &str, style: &FormatStyle) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for formatting")?;
+
+    format_table(document.as_table_mut(), &[], style);
+
+    Ok(document.to_string())
+}
+
+/// Order every dependency table of a manifest alphabetically, leaving the
+/// rest of it untouched.
+pub(crate) fn sort_dependencies(source: &str) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for sorting")?;
+
+    sort_dependency_tables(document.as_table_mut(), &[]);
+
+    Ok(document.to_string())
+}
+
+fn is_dependency_table(path: &[&str]) -> bool {
+    let table = match path {
+        [table] | ["workspace", table] | ["target", _, table] => *table,
+        _ => return false,
+    };
+
+    matches!(
+        table.replace('_', "-").as_str(),
+        "dependencies" | "dev-dependencies" | "build-dependencies"
+    ) && (path[0] != "workspace" || table == "dependencies")
+}
+
+fn sort_dependency_tables(table: &mut Table, path: &[&str]) {
+    if is_dependency_table(path) {
+        table.sort_values();
+        return;
+    }
+
+    for (name, item) in table.iter_mut() {
+        if let Item::Table(child) = item {
+            let mut child_path = path.to_vec();
+            child_path.push(name.get());
+            sort_dependency_tables(child, &child_path);
+        }
+    }
+}
+
+fn format_table(table: &mut Table, path: &[&str], style: &FormatStyle) {
+    if style.sort_dependencies && is_dependency_table(path) {
+        table.sort_values();
+    }
+    if style.sort_package_keys
+        && matches!(path, ["package"] | ["project"] | ["workspace", "package"])
+    {
+        let rank = |key: &str| {
+            PACKAGE_KEYS
+                .iter()
+                .position(|k| *k == key)
+                .unwrap_or(PACKAGE_KEYS.len())
+        };
+        table.sort_values_by(|a, _, b, _| rank(a.get()).cmp(&rank(b.get())));
+    }
+
+    let names = table.iter().map(|(k, _)| k.to_string()).collect::<Vec<_>>();
+    for name in names {
+        let Some((mut key, item)) = table.get_key_value_mut(&name) else {
+            continue;
+        };
+
+        match item {
+            Item::Value(value) => {
+                let decor = key.leaf_decor_mut();
+                let prefix = decor
+                    .prefix()
+                    .and_then(|p| p.as_str())
+                    .map(|p| p.trim_end_matches([' ', '\t']).to_string())
+                    .unwrap_or_default();
+                decor.set_prefix(prefix);
+                decor.set_suffix(" ");
+
+                format_value(value, name.len() + 3, style);
+                normalize_value_decor(value.decor_mut());
+            }
+            Item::Table(child) => {
+                let mut child_path = path.to_vec();
+                child_path.push(&name);
+                format_table(child, &child_path, style);
+            }
+            Item::ArrayOfTables(tables) => {
+                let mut child_path = path.to_vec();
+                child_path.push(&name);
+                for child in tables.iter_mut() {
+                    format_table(child, &child_path, style);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+/// One space after `=`; a trailing comment is kept one space after the value.
+fn normalize_value_decor(decor: &mut Decor) {
+    let suffix = decor
+        .suffix()
+        .and_then(|s| s.as_str())
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    decor.set_prefix(" ");
+    if suffix.starts_with('#') {
+        decor.set_suffix(format!(" {suffix}"));
+    } else {
+        decor.set_suffix("");
+    }
+}
+
+/// Format `value`, which starts `offset` columns into its line.
+fn format_value(value: &mut Value, offset: usize, style: &FormatStyle) {
+    match value {
+        Value::Array(array) => format_array(array, offset, style),
+        Value::InlineTable(table) => {
+            table.fmt();
+            for (_, value) in table.iter_mut() {
+                format_value(value, 0, style);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn format_array(array: &mut Array, offset: usize, style: &FormatStyle) {
+    // Arrays with comments are laid out by hand; leave them alone.
+    if array.to_string().contains('#') {
+        return;
+    }
+
+    for value in array.iter_mut() {
+        format_value(value, 0, style);
+    }
+    array.fmt();
+    array.set_trailing_comma(false);
+    array.set_trailing("");
+
+    let single_line = array.to_string();
+    if offset + single_line.trim().len() <= style.max_width {
+        return;
+    }
+
+    for value in array.iter_mut() {
+        value.decor_mut().set_prefix("\n    ");
+        value.decor_mut().set_suffix("");
+    }
+    array.set_trailing_comma(true);
+    array.set_trailing("\n");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalizes_spacing_order_and_arrays() -> anyhow::Result<()> {
+        let source = r#"[package]
+version="0.1.0"
+  edition =   "2021" # pinned
+name = "root"
+keywords=["a","b"]
+categories = ["command-line-utilities", "development-tools::cargo-plugins", "filesystem"]
+
+[dependencies]
+# serialization
+serde={version="1",features=["derive"]}
+"#;
+
+        assert_eq!(
+            format(source, &FormatStyle::default())?,
+            r#"[package]
+name = "root"
+version = "0.1.0"
+edition = "2021" # pinned
+keywords = ["a", "b"]
+categories = [
+    "command-line-utilities",
+    "development-tools::cargo-plugins",
+    "filesystem",
+]
+
+[dependencies]
+# serialization
+serde = { version = "1", features = ["derive"] }
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_only_dependency_tables() -> anyhow::Result<()> {
+        let source = "[package]\nversion = '0.1.0'\nname = 'root'\n\n[dependencies]\ntoml = '0.7'  # config\n# errors\nanyhow = '1'\n\n[target.'cfg(unix)'.dev-dependencies]\nnix = '0.26'\nlibc = '0.2'\n\n[features]\nz = []\na = []\n";
+
+        assert_eq!(
+            sort_dependencies(source)?,
+            "[package]\nversion = '0.1.0'\nname = 'root'\n\n[dependencies]\n# errors\nanyhow = '1'\ntoml = '0.7'  # config\n\n[target.'cfg(unix)'.dev-dependencies]\nlibc = '0.2'\nnix = '0.26'\n\n[features]\nz = []\na = []\n"
+        );
+
+        Ok(())
+    }
+}
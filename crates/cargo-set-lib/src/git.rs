@@ -0,0 +1,369 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+
+use crate::filesystem::FileSystem;
+use crate::paths::{display_path, normalize};
+
+/// Run `git` with `args` in `dir` and return its trimmed stdout.
+fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("failed to run git")?;
+    if !output.status.success() {
+        crate::error::bail!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The commit checked out in the git repository containing `dir`.
+pub fn head_commit(dir: &Path) -> crate::Result<String> {
+    Ok(git(dir, &["rev-parse", "HEAD"])?)
+}
+
+/// `git describe --tags --always` of the commit checked out in the git
+/// repository containing `dir`, e.g. `v0.4.0-3-gabc1234`.
+pub fn describe(dir: &Path) -> crate::Result<String> {
+    Ok(git(dir, &["describe", "--tags", "--always"])?)
+}
+
+/// Every tag of the git repository containing `dir`.
+pub fn tags(dir: &Path) -> crate::Result<Vec<String>> {
+    Ok(git(dir, &["tag", "--list"])?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// The subjects of the commits since `since`, or of every commit without
+/// it, that touch `path`, newest first.
+pub fn commit_subjects(dir: &Path, since: Option<&str>, path: &Path) -> crate::Result<Vec<String>> {
+    let range = since.map(|since| format!("{since}..HEAD"));
+    let path = path.to_string_lossy();
+    let mut args = vec!["log", "--format=%s"];
+    args.extend(range.as_deref());
+    args.extend(["--", &path]);
+
+    Ok(git(dir, &args)?.lines().map(str::to_string).collect())
+}
+
+/// Shallow-clone `url` into `dest`, checking out `branch` or the default
+/// branch.
+pub fn clone(url: &str, branch: Option<&str>, dest: &Path) -> crate::Result<()> {
+    let dest = dest
+        .to_str()
+        .context("the checkout path is not valid UTF-8")?;
+    let mut args = vec!["clone", "--quiet", "--depth", "1"];
+    if let Some(branch) = branch {
+        args.extend(["--branch", branch]);
+    }
+    args.extend(["--", url, dest]);
+
+    git(Path::new("."), &args)?;
+
+    Ok(())
+}
+
+/// The changes to every file of the repository containing `dir`, including
+/// new ones, as a unified diff. Stages everything.
+pub fn diff_all(dir: &Path) -> crate::Result<String> {
+    git(dir, &["add", "--all"])?;
+    Ok(git(dir, &["diff", "--cached"])?)
+}
+
+/// Commit every change of the repository containing `dir` on the new branch
+/// `branch` and push it to `origin`.
+pub fn push_branch(dir: &Path, branch: &str, message: &str) -> crate::Result<()> {
+    git(dir, &["checkout", "--quiet", "-b", branch])?;
+    git(dir, &["add", "--all"])?;
+    git(dir, &["commit", "--quiet", "-m", message])?;
+    git(
+        dir,
+        &["push", "--quiet", "--set-upstream", "origin", branch],
+    )?;
+
+    Ok(())
+}
+
+/// Commit exactly `files` of the repository containing `dir`, new ones
+/// included, leaving any other change alone.
+pub fn commit_files(dir: &Path, files: &[PathBuf], message: &str) -> crate::Result<()> {
+    let files = files
+        .iter()
+        .map(|file| file.to_str().context("a changed path is not valid UTF-8"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    git(dir, &[&["add", "--"], files.as_slice()].concat())?;
+    git(
+        dir,
+        &[
+            &["commit", "--quiet", "-m", message, "--"],
+            files.as_slice(),
+        ]
+        .concat(),
+    )?;
+
+    Ok(())
+}
+
+/// Create the annotated tag `name` at `HEAD` of the repository containing
+/// `dir`.
+pub fn annotated_tag(dir: &Path, name: &str, message: &str) -> crate::Result<()> {
+    git(dir, &["tag", "--annotate", name, "-m", message])?;
+
+    Ok(())
+}
+
+/// The paths with uncommitted changes in the git repository containing
+/// `dir`, untracked files included. Before the first commit that is every
+/// file.
+pub fn changed_files(dir: &Path) -> crate::Result<Vec<String>> {
+    let changed = if git(dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok() {
+        git(dir, &["diff", "--name-only", "HEAD"])?
+    } else {
+        git(dir, &["ls-files"])?
+    };
+    let untracked = git(dir, &["ls-files", "--others", "--exclude-standard"])?;
+
+    Ok(changed
+        .lines()
+        .chain(untracked.lines())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The branch checked out in the git repository containing `dir`.
+pub fn current_branch(dir: &Path) -> crate::Result<String> {
+    Ok(git(dir, &["rev-parse", "--abbrev-ref", "HEAD"])?)
+}
+
+/// The URL of the remote `remote` of the git repository containing `dir`.
+pub fn remote_url(dir: &Path, remote: &str) -> crate::Result<String> {
+    Ok(git(dir, &["remote", "get-url", remote])?)
+}
+
+/// A read-only [`FileSystem`] over the tree of a git revision, e.g. a
+/// release tag, so a workspace can be loaded as it was then without
+/// checking it out, bare repositories included. Paths are taken as on disk,
+/// relative ones from the current directory, and map to where they are in
+/// the repository. Writes fail with `ReadOnlyFilesystem`.
+#[derive(Debug, Clone)]
+pub struct GitFileSystem {
+    dir: PathBuf,
+    commit: String,
+    /// The top of the work tree, or the repository itself if it is bare.
+    root: PathBuf,
+}
+
+impl GitFileSystem {
+    /// The tree of `revision` in the repository containing `dir`.
+    pub fn new(dir: impl Into<PathBuf>, revision: &str) -> crate::Result<Self> {
+        let dir = std::path::absolute(dir.into())?;
+        let commit = git(
+            &dir,
+            &[
+                "rev-parse",
+                "--verify",
+                "--end-of-options",
+                &format!("{revision}^{{commit}}"),
+            ],
+        )
+        .with_context(|| format!("`{revision}` is not a commit of the repository"))?;
+        // Taken from the prefix rather than `--show-toplevel`, which resolves
+        // symlinks the paths given to the file system may not.
+        let prefix = git(&dir, &["rev-parse", "--show-prefix"])?;
+        let mut root = normalize(&dir);
+        for _ in Path::new(&prefix).components() {
+            root.pop();
+        }
+
+        Ok(Self { dir, commit, root })
+    }
+
+    /// The commit the revision resolved to.
+    pub fn commit(&self) -> &str {
+        &self.commit
+    }
+
+    /// The object at `path`, as `git` names it, e.g. `<commit>:crates/a`.
+    fn object(&self, path: &Path) -> io::Result<String> {
+        let path = normalize(&std::path::absolute(path)?);
+        let components = path
+            .strip_prefix(&self.root)
+            .ok()
+            .and_then(|path| {
+                path.components()
+                    .map(|component| match component {
+                        std::path::Component::Normal(name) => name.to_str(),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is outside the repository", display_path(&path)),
+                )
+            })?;
+
+        Ok(format!("{}:{}", self.commit, components.join("/")))
+    }
+
+    /// Run `git` with `args` in `dir`, failing with `NotFound` if the object
+    /// it is about doesn't exist.
+    fn output(&self, args: &[&str]) -> io::Result<Vec<u8>> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.dir)
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+impl FileSystem for GitFileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.output(&["cat-file", "blob", &self.object(path)?])
+    }
+
+    fn write(&self, path: &Path, _: Vec<u8>) -> io::Result<()> {
+        self.probe_write(path)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.probe_write(path)
+    }
+
+    fn probe_write(&self, _: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::ReadOnlyFilesystem,
+            format!("the tree of {} is read-only", self.commit),
+        ))
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let object = self.object(path)?;
+        let kind = self.output(&["cat-file", "-t", &object])?;
+        if kind.trim_ascii() != b"tree" {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                "Not a directory",
+            ));
+        }
+
+        let names = self.output(&["ls-tree", "-z", "--name-only", &object])?;
+        let mut entries = names
+            .split(|byte| *byte == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| path.join(String::from_utf8_lossy(name).as_ref()))
+            .collect::<Vec<_>>();
+        entries.sort();
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cargo::CargoManifestService;
+
+    #[test]
+    fn reads_manifests_from_a_revision() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cargo-set-git-fs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("crates/a"))?;
+        let manifest = |version: &str| format!("[package]\nname = 'a'\nversion = '{version}'\n");
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = ['crates/*']\n",
+        )?;
+        std::fs::write(dir.join("crates/a/Cargo.toml"), manifest("0.1.0"))?;
+        let commit = [
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+        ];
+        git(&dir, &["init", "--quiet"])?;
+        git(&dir, &["add", "--all"])?;
+        git(&dir, &[&commit[..], &["--quiet", "-m", "first"]].concat())?;
+        git(&dir, &["tag", "v0.1.0"])?;
+        std::fs::write(dir.join("crates/a/Cargo.toml"), manifest("0.2.0"))?;
+
+        let fs = GitFileSystem::new(&dir, "v0.1.0")?;
+        let service = CargoManifestService::new(&fs);
+        let mut workspace = service.load_manifest(&dir.join("Cargo.toml"))?;
+        assert_eq!(workspace.package_version("a").as_deref(), Some("0.1.0"));
+        assert_eq!(
+            fs.read(&dir.join("crates/b/Cargo.toml"))
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            fs.read(&dir.join("../Cargo.toml")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(service
+            .update_version(&mut workspace, "a", "0.3.0")
+            .is_err());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("crates/a/Cargo.toml"))?,
+            manifest("0.2.0")
+        );
+
+        let nested = GitFileSystem::new(dir.join("crates"), "HEAD")?;
+        assert_eq!(
+            nested.read(&dir.join("crates/../crates/a/Cargo.toml"))?,
+            manifest("0.1.0").as_bytes()
+        );
+        assert!(GitFileSystem::new(&dir, "v9").is_err());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn lists_changed_files_with_and_without_a_commit() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cargo-set-changed-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        git(&dir, &["init", "--quiet"])?;
+        git(&dir, &["config", "user.name", "test"])?;
+        git(&dir, &["config", "user.email", "test@example.com"])?;
+        std::fs::write(dir.join("Cargo.toml"), "[workspace]\n")?;
+        git(&dir, &["add", "Cargo.toml"])?;
+        std::fs::write(dir.join("new.txt"), "")?;
+
+        assert_eq!(changed_files(&dir)?, ["Cargo.toml", "new.txt"]);
+
+        commit_files(
+            &dir,
+            &[dir.join("Cargo.toml"), dir.join("new.txt")],
+            "first",
+        )?;
+        assert!(changed_files(&dir)?.is_empty());
+        std::fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = []\n")?;
+        assert_eq!(changed_files(&dir)?, ["Cargo.toml"]);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
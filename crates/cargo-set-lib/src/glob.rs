@@ -0,0 +1,176 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::filesystem::FileSystem;
+
+/// Whether `pattern` has glob syntax, as `crates/*` does and `crates/a`
+/// doesn't.
+pub(crate) fn is_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Every path below `dir` matching `pattern`, e.g. `crates/*` or `libs/**`,
+/// in path order. `*` and `?` match within one component, `[abc]` and
+/// `[a-z]` match one character and `**` matches any number of directories.
+pub(crate) fn expand<F: FileSystem>(fs: &F, dir: &Path, pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let components = components(pattern);
+
+    let mut matches = Vec::new();
+    expand_components(fs, dir.to_path_buf(), &components, &mut matches)?;
+    matches.sort();
+    matches.dedup();
+
+    Ok(matches)
+}
+
+fn expand_components<F: FileSystem>(
+    fs: &F,
+    dir: PathBuf,
+    components: &[&str],
+    matches: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let Some((component, rest)) = components.split_first() else {
+        matches.push(dir);
+        return Ok(());
+    };
+
+    if !is_pattern(component) {
+        return expand_components(fs, dir.join(component), rest, matches);
+    }
+
+    let entries = match fs.list_dir(&dir) {
+        Ok(entries) => entries,
+        // Files and vanished directories have nothing to match.
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::NotFound | io::ErrorKind::NotADirectory
+            ) =>
+        {
+            return Ok(())
+        }
+        Err(e) => return Err(e),
+    };
+    if *component == "**" {
+        expand_components(fs, dir, rest, matches)?;
+        for entry in entries {
+            expand_components(fs, entry, components, matches)?;
+        }
+        return Ok(());
+    }
+
+    for entry in entries {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        if matches_component(component, &name) {
+            expand_components(fs, entry, rest, matches)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the relative path `path` matches `pattern`, as [`expand`] would
+/// find it if it existed.
+pub(crate) fn matches(pattern: &str, path: &Path) -> bool {
+    let pattern = components(pattern);
+    let path = path.to_string_lossy().into_owned();
+    matches_components(&pattern, &components(&path))
+}
+
+fn components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect()
+}
+
+fn matches_components(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_components(rest, &path[skip..])),
+        Some((component, rest)) => path.split_first().is_some_and(|(name, path)| {
+            matches_component(component, name) && matches_components(rest, path)
+        }),
+    }
+}
+
+/// Whether the file name `name` matches the single component `pattern`.
+pub(crate) fn matches_component(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    matches_from(&pattern, &name)
+}
+
+fn matches_from(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| matches_from(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && matches_from(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some(end) = rest.iter().skip(1).position(|c| *c == ']').map(|i| i + 1) else {
+                return name.first() == Some(&'[') && matches_from(rest, &name[1..]);
+            };
+            let Some(first) = name.first() else {
+                return false;
+            };
+            let (negated, class) = match rest[..end].split_first() {
+                Some(('!' | '^', class)) => (true, class),
+                _ => (false, &rest[..end]),
+            };
+            let in_class = class
+                .iter()
+                .enumerate()
+                .any(|(i, c)| match class.get(i + 1) {
+                    Some('-') if i + 2 < class.len() => (*c..=class[i + 2]).contains(first),
+                    _ => c == first,
+                });
+            in_class != negated && matches_from(&rest[end + 1..], &name[1..])
+        }
+        Some((c, rest)) => name.first() == Some(c) && matches_from(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::filesystem::MockFileSystem;
+
+    #[test]
+    fn expands_member_patterns() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        for path in [
+            "crates/a/Cargo.toml",
+            "crates/b2/Cargo.toml",
+            "crates/README.md",
+            "libs/x/Cargo.toml",
+            "libs/nested/y/Cargo.toml",
+        ] {
+            fs.add_file(PathBuf::from(path), Vec::new());
+        }
+        let expand = |pattern| -> anyhow::Result<Vec<String>> {
+            Ok(expand(&fs, Path::new(""), pattern)?
+                .iter()
+                .map(|path| crate::paths::display_path(path))
+                .collect())
+        };
+
+        assert_eq!(
+            expand("crates/*")?,
+            ["crates/README.md", "crates/a", "crates/b2"]
+        );
+        assert_eq!(expand("crates/[a-b]?")?, ["crates/b2"]);
+        assert_eq!(expand("crates/[!a]*")?, ["crates/README.md", "crates/b2"]);
+        assert_eq!(
+            expand("libs/**")?,
+            ["libs", "libs/nested", "libs/nested/y", "libs/x"]
+        );
+        assert_eq!(expand("missing/*")?, Vec::<String>::new());
+
+        assert!(matches("crates/*", Path::new("crates/new")));
+        assert!(matches("./crates/[a-c]*/", Path::new("crates/b")));
+        assert!(matches("libs/**", Path::new("libs/nested/z")));
+        assert!(!matches("crates/*", Path::new("crates/nested/z")));
+        assert!(!matches("crates/*", Path::new("libs/x")));
+
+        Ok(())
+    }
+}
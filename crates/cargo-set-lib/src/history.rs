@@ -0,0 +1,228 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::filesystem::FileSystem;
+use crate::paths::display_path;
+
+/// Where the journal of the runs that changed a workspace is kept, relative
+/// to the directory of its root manifest.
+pub const HISTORY_DIR: &str = ".cargo-set/history";
+
+/// How many runs the journal keeps; older ones are dropped as new ones are
+/// recorded.
+const HISTORY_LIMIT: usize = 50;
+
+/// A run that changed files, with what they held before and after it, so
+/// [`CargoManifestService::revert`](crate::CargoManifestService::revert)
+/// can undo it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// When the run started, e.g. `20240601T120000.123Z`, which sorts in
+    /// the order runs happened.
+    pub id: String,
+    pub timestamp: String,
+    pub command: String,
+    pub files: Vec<JournalFile>,
+}
+
+/// A file a run changed, relative to the workspace root if it is inside it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalFile {
+    #[serde(with = "crate::paths::serde_path")]
+    pub path: PathBuf,
+    /// `None` if the run created the file.
+    pub original: Option<String>,
+    /// `None` if the run removed the file.
+    pub contents: Option<String>,
+}
+
+impl JournalEntry {
+    /// An entry for a run at `now` that changed `files`, with paths relative
+    /// to the working directory.
+    pub(crate) fn new(
+        now: SystemTime,
+        command: impl Into<String>,
+        files: Vec<JournalFile>,
+        root_manifest: &Path,
+    ) -> anyhow::Result<Self> {
+        let timestamp = crate::snapshot::rfc3339(now)?;
+        let millis = now
+            .duration_since(UNIX_EPOCH)
+            .context("the clock is set before 1970")?
+            .subsec_millis();
+        let id = format!(
+            "{}.{millis:03}Z",
+            timestamp.trim_end_matches('Z').replace(['-', ':'], "")
+        );
+        let root = workspace_dir(root_manifest);
+
+        Ok(Self {
+            id,
+            timestamp,
+            command: command.into(),
+            files: files
+                .into_iter()
+                .map(|file| JournalFile {
+                    path: match file.path.strip_prefix(root) {
+                        Ok(relative) => relative.to_path_buf(),
+                        Err(_) => file.path,
+                    },
+                    ..file
+                })
+                .collect(),
+        })
+    }
+
+    /// The directory the journal of the workspace rooted at `root_manifest`
+    /// is kept in.
+    pub fn dir_for(root_manifest: &Path) -> PathBuf {
+        workspace_dir(root_manifest).join(HISTORY_DIR)
+    }
+
+    /// Every recorded run of the workspace rooted at `root_manifest`, oldest
+    /// first.
+    pub fn load_all<F: FileSystem>(fs: &F, root_manifest: &Path) -> crate::Result<Vec<Self>> {
+        let dir = Self::dir_for(root_manifest);
+        let paths = match fs.list_dir(&dir) {
+            Ok(paths) => paths,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => Err(e).with_context(|| format!("failed to list {}", display_path(&dir)))?,
+        };
+
+        let mut entries = paths
+            .iter()
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .map(|path| {
+                let contents = fs
+                    .read(path)
+                    .with_context(|| format!("failed to read {}", display_path(path)))?;
+                serde_json::from_slice::<Self>(&contents)
+                    .with_context(|| format!("failed to parse {}", display_path(path)))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(entries)
+    }
+
+    /// Record the entry in the journal of the workspace rooted at
+    /// `root_manifest`, dropping the oldest runs beyond the limit. The
+    /// journal is local state, so a `.gitignore` keeps it out of commits.
+    pub fn save<F: FileSystem>(&self, fs: &F, root_manifest: &Path) -> crate::Result<PathBuf> {
+        let path = self.path(root_manifest);
+        fs.write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write {}", display_path(&path)))?;
+        let ignore = Self::dir_for(root_manifest).with_file_name(".gitignore");
+        match fs.create(&ignore, b"*\n".to_vec()) {
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+                Err(e).with_context(|| format!("failed to write {}", display_path(&ignore)))?
+            }
+            _ => {}
+        }
+
+        let entries = Self::load_all(fs, root_manifest)?;
+        for old in &entries[..entries.len().saturating_sub(HISTORY_LIMIT)] {
+            old.remove(fs, root_manifest)?;
+        }
+
+        Ok(path)
+    }
+
+    /// Take the entry out of the journal of the workspace rooted at
+    /// `root_manifest`.
+    pub fn remove<F: FileSystem>(&self, fs: &F, root_manifest: &Path) -> crate::Result<()> {
+        let path = self.path(root_manifest);
+        Ok(fs
+            .remove(&path)
+            .with_context(|| format!("failed to remove {}", display_path(&path)))?)
+    }
+
+    fn path(&self, root_manifest: &Path) -> PathBuf {
+        Self::dir_for(root_manifest).join(format!("{}.json", self.id))
+    }
+}
+
+/// The directory of `root_manifest`, which the paths of entries are
+/// relative to.
+pub(crate) fn workspace_dir(root_manifest: &Path) -> &Path {
+    root_manifest.parent().unwrap_or_else(|| Path::new(""))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::audit::AuditedFileSystem;
+    use crate::cargo::CargoManifestService;
+    use crate::testing::WorkspaceFixture;
+
+    #[test]
+    fn reverts_recorded_runs() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("a", "0.1.0")
+            .member("b", "0.1.0")
+            .dep("a", "b");
+        let fs = Arc::new(fixture.file_system());
+        let root = fixture.root_path();
+        let run = |version: &str, secs: u64| -> anyhow::Result<JournalEntry> {
+            let audited = AuditedFileSystem::new(fs.clone());
+            let service = CargoManifestService::new(&audited);
+            let mut workspace = service.load_manifest(&root)?;
+            service.update_versions(&mut workspace, &[("a", version)])?;
+            let entry = audited
+                .journal_entry(
+                    UNIX_EPOCH + Duration::from_millis(secs * 1000 + 7),
+                    format!("cargo set set --crate a --set-version {version}"),
+                    &root,
+                )?
+                .unwrap();
+            entry.save(&*fs, &root)?;
+            Ok(entry)
+        };
+
+        let first = run("0.2.0", 1_717_243_200)?;
+        assert_eq!(first.id, "20240601T120000.007Z");
+        assert_eq!(
+            fs.read(&JournalEntry::dir_for(&root).with_file_name(".gitignore"))?,
+            b"*\n"
+        );
+        assert_eq!(
+            first
+                .files
+                .iter()
+                .map(|file| file.path.as_path())
+                .collect::<Vec<_>>(),
+            [Path::new("a/Cargo.toml"), Path::new("b/Cargo.toml")]
+        );
+        let second = run("0.3.0", 1_717_243_260)?;
+        let service = CargoManifestService::new(fs.clone());
+        let error = service.revert(&root, Some(&first.id)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "a/Cargo.toml changed since run {}, nothing was reverted",
+                first.id
+            )
+        );
+
+        let reverted = service.revert(&root, None)?;
+        assert_eq!(reverted.id, second.id);
+        reverted.remove(&*fs, &root)?;
+        fixture.clone().version("a", "0.2.0").assert_files(&*fs);
+        service.revert(&root, None)?.remove(&*fs, &root)?;
+        fixture.assert_files(&*fs);
+        assert!(JournalEntry::load_all(&*fs, &root)?.is_empty());
+        assert!(service.revert(&root, None).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::plan::ChangeSet;
+
+/// Shell commands the `[hooks]` table of `.cargo-set.toml` runs around
+/// every write of manifests, from the workspace root, with the
+/// [`ChangeSet`] being written as JSON on stdin.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    /// Run before writing; one exiting non-zero stops the write, e.g. a
+    /// policy script.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre: Vec<String>,
+    /// Run after writing, e.g. `cargo check`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post: Vec<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre.is_empty() && self.post.is_empty()
+    }
+
+    /// Run the `pre` hooks of the workspace rooted at `root_manifest`,
+    /// failing on the first that fails.
+    pub(crate) fn run_pre(
+        &self,
+        root_manifest: &Path,
+        change_set: &ChangeSet,
+    ) -> anyhow::Result<()> {
+        run_all("pre", &self.pre, root_manifest, change_set)
+    }
+
+    /// Run the `post` hooks of the workspace rooted at `root_manifest`,
+    /// failing on the first that fails.
+    pub(crate) fn run_post(
+        &self,
+        root_manifest: &Path,
+        change_set: &ChangeSet,
+    ) -> anyhow::Result<()> {
+        run_all("post", &self.post, root_manifest, change_set)
+    }
+}
+
+fn run_all(
+    stage: &str,
+    commands: &[String],
+    root_manifest: &Path,
+    change_set: &ChangeSet,
+) -> anyhow::Result<()> {
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    let dir = crate::paths::workspace_dir(root_manifest);
+    let input = serde_json::to_string(change_set)?;
+    for command in commands {
+        tracing::debug!("running the {stage}-hook `{command}`");
+        let status = run(command, dir, &input)
+            .with_context(|| format!("failed to run the {stage}-hook `{command}`"))?;
+        if !status.success() {
+            crate::error::bail!("the {stage}-hook `{command}` failed with {status}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `command` with a shell in `dir`, `input` on its stdin and its output
+/// on stderr, so it doesn't mix with what commands print on stdout.
+fn run(command: &str, dir: &Path, input: &str) -> io::Result<std::process::ExitStatus> {
+    let mut shell = match cfg!(windows) {
+        true => {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        }
+        false => {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        }
+    };
+    let mut child = shell
+        .arg(command)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(io::stderr())
+        .spawn()?;
+
+    let written = child
+        .stdin
+        .take()
+        .map(|mut stdin| stdin.write_all(input.as_bytes()))
+        .transpose();
+    // Hooks that don't read their input close it early.
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            child.wait()?;
+            Err(e)
+        }
+        _ => child.wait(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cargo::CargoManifestService;
+    use crate::testing::WorkspaceFixture;
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_hooks_around_writes() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new().member("a", "0.1.0");
+        let fs = Arc::new(fixture.file_system());
+        let hooks = |pre: &str, post: &str| Hooks {
+            pre: vec![pre.to_string()],
+            post: vec![post.to_string()],
+        };
+
+        let service = CargoManifestService::new(fs.clone()).with_hooks(hooks("exit 1", "true"));
+        let mut cargo_manifest = service.load_manifest(&fixture.root_path())?;
+        let error = service
+            .update_versions(&mut cargo_manifest, &[("a", "0.2.0")])
+            .unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "a pre-hook refused the write, nothing was changed: the pre-hook `exit 1` failed with exit status: 1"
+        );
+        fixture.assert_files(&*fs);
+
+        let service = CargoManifestService::new(fs.clone()).with_hooks(hooks(
+            "grep -q '\"table\":\"package\"'",
+            "grep -q 0.2.0 && exit 3",
+        ));
+        let error = service
+            .update_versions(&mut cargo_manifest, &[("a", "0.2.0")])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the post-hook `grep -q 0.2.0 && exit 3` failed with exit status: 3"
+        );
+        fixture.version("a", "0.2.0").assert_files(&*fs);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::paths::display_path;
+
+/// A rule of [`CargoManifest::lint`](crate::CargoManifest::lint) about how
+/// the members declare their dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintRule {
+    /// A member declares a crate that `[workspace.dependencies]` declares
+    /// too, instead of inheriting it.
+    NotInherited,
+    /// Members require a crate at different versions.
+    DivergentVersions,
+    /// A path dependency of a published member has no `version`, which
+    /// `cargo publish` refuses.
+    PathWithoutVersion,
+}
+
+impl LintRule {
+    /// The name of the rule in reports, e.g. `not-inherited`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::NotInherited => "not-inherited",
+            LintRule::DivergentVersions => "divergent-versions",
+            LintRule::PathWithoutVersion => "path-without-version",
+        }
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How [`CargoManifestService::fix_lints`](crate::CargoManifestService::fix_lints)
+/// resolves a finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintFix {
+    /// Replace the declaration with `workspace = true`, keeping its
+    /// `features` and `optional`.
+    Inherit,
+    /// Add this `version` to the path dependency.
+    SetVersion(String),
+}
+
+/// A declaration breaking a [`LintRule`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub manifest: PathBuf,
+    /// The key of the declaration, e.g. `["dependencies", "serde"]`.
+    pub key: Vec<String>,
+    pub message: String,
+    /// `None` for findings that need a decision, like which version to use.
+    pub fix: Option<LintFix>,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} [{}]",
+            display_path(&self.manifest),
+            self.message,
+            self.rule
+        )
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+use anyhow::Context;
+
+use crate::config::{Config, Versioning};
+
+/// A release tool whose settings `import-config` can translate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// `release.toml` or `[workspace.metadata.release]`.
+    CargoRelease,
+    /// `[workspace.metadata.workspaces]`.
+    CargoWorkspaces,
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImportSource::CargoRelease => "cargo-release",
+            ImportSource::CargoWorkspaces => "cargo-workspaces",
+        })
+    }
+}
+
+/// Settings translated from another tool, and the ones that have no
+/// equivalent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportedConfig {
+    pub config: Config,
+    /// `key = value` lines that were not translated.
+    pub unsupported: Vec<String>,
+}
+
+impl ImportedConfig {
+    /// The `.cargo-set.toml` contents, listing the untranslated settings as
+    /// comments.
+    pub fn render(&self, source: ImportSource) -> crate::Result<String> {
+        let mut output = format!("# cargo-set configuration, imported from {source}.\n");
+        if !self.unsupported.is_empty() {
+            output.push_str("#\n# These settings have no cargo-set equivalent:\n");
+            for line in &self.unsupported {
+                output.push_str(&format!("#   {line}\n"));
+            }
+        }
+        output.push('\n');
+        output.push_str(&self.config.to_toml()?);
+
+        Ok(output)
+    }
+}
+
+/// Translate the settings of a tool's own file, e.g. `release.toml`.
+pub fn import_settings_file(source: ImportSource, contents: &str) -> crate::Result<ImportedConfig> {
+    let settings: toml::Table = toml::from_str(contents)?;
+
+    Ok(import_config(source, &settings))
+}
+
+/// Translate the tool's table in a root manifest, e.g.
+/// `[workspace.metadata.release]`, if it has one.
+pub fn import_manifest(
+    source: ImportSource,
+    manifest: &str,
+) -> crate::Result<Option<ImportedConfig>> {
+    Ok(manifest_settings(source, manifest)?.map(|settings| import_config(source, &settings)))
+}
+
+/// Translate `settings`, the tool's own table.
+fn import_config(source: ImportSource, settings: &toml::Table) -> ImportedConfig {
+    let mut imported = ImportedConfig::default();
+    let string = |key: &str| settings.get(key).and_then(toml::Value::as_str);
+    let flag = |key: &str| settings.get(key).and_then(toml::Value::as_bool);
+
+    let translated: &[&str] = match source {
+        ImportSource::CargoRelease => {
+            imported.config.versioning = flag("shared-version").map(|shared| {
+                if shared {
+                    Versioning::Lockstep
+                } else {
+                    Versioning::Independent
+                }
+            });
+            let prefix = string("tag-prefix").map(release_template);
+            imported.config.tag_format = string("tag-name")
+                .map(|name| {
+                    let name = release_template(name);
+                    match &prefix {
+                        Some(prefix) => name.replace("{prefix}", prefix),
+                        None => name.replace("{prefix}", "{crate}-"),
+                    }
+                })
+                .or_else(|| prefix.map(|prefix| format!("{prefix}v{{version}}")));
+
+            &["shared-version", "tag-name", "tag-prefix"]
+        }
+        ImportSource::CargoWorkspaces => {
+            let independent = flag("independent").unwrap_or(false);
+            imported.config.versioning = Some(if independent {
+                Versioning::Independent
+            } else {
+                Versioning::Lockstep
+            });
+            imported.config.tag_format = Some(if independent {
+                let prefix = string("individual-tag-prefix")
+                    .or(string("individual_tag_prefix"))
+                    .unwrap_or("%n@");
+                format!("{}{{version}}", prefix.replace("%n", "{crate}"))
+            } else {
+                let prefix = string("tag-prefix").or(string("tag_prefix")).unwrap_or("v");
+                format!("{prefix}{{version}}")
+            });
+
+            &[
+                "independent",
+                "individual-tag-prefix",
+                "individual_tag_prefix",
+                "tag-prefix",
+                "tag_prefix",
+            ]
+        }
+    };
+
+    for (key, value) in settings {
+        if !translated.contains(&key.as_str()) {
+            imported.unsupported.push(format!("{key} = {value}"));
+        }
+    }
+
+    imported
+}
+
+/// The table of `source` in a root manifest, if present.
+fn manifest_settings(source: ImportSource, manifest: &str) -> anyhow::Result<Option<toml::Table>> {
+    let manifest: toml::Table = toml::from_str(manifest).context("failed to parse manifest")?;
+    let key = match source {
+        ImportSource::CargoRelease => "release",
+        ImportSource::CargoWorkspaces => "workspaces",
+    };
+
+    Ok(["workspace", "metadata", key]
+        .iter()
+        .try_fold(&manifest, |table, k| table.get(*k)?.as_table())
+        .cloned())
+}
+
+/// Map a cargo-release `{{placeholder}}` template onto cargo-set's
+/// placeholders.
+fn release_template(template: &str) -> String {
+    template
+        .replace("{{crate_name}}", "{crate}")
+        .replace("{{version}}", "{version}")
+        .replace("{{prefix}}", "{prefix}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn translates_cargo_release_settings() -> anyhow::Result<()> {
+        let settings: toml::Table = toml::from_str(
+            "shared-version = true\ntag-name = '{{prefix}}v{{version}}'\npre-release-hook = ['./hook.sh']\n",
+        )?;
+
+        let imported = import_config(ImportSource::CargoRelease, &settings);
+        assert_eq!(imported.config.versioning, Some(Versioning::Lockstep));
+        assert_eq!(
+            imported.config.tag_format.as_deref(),
+            Some("{crate}-v{version}")
+        );
+        assert_eq!(
+            imported.render(ImportSource::CargoRelease)?,
+            "# cargo-set configuration, imported from cargo-release.\n#\n# These settings have no cargo-set equivalent:\n#   pre-release-hook = [\"./hook.sh\"]\n\nversioning = \"lockstep\"\ntag-format = \"{crate}-v{version}\"\n"
+        );
+
+        let manifest = "[workspace]\n[workspace.metadata.workspaces]\nindependent = true\n";
+        let settings = manifest_settings(ImportSource::CargoWorkspaces, manifest)?.unwrap();
+        let imported = import_config(ImportSource::CargoWorkspaces, &settings);
+        assert_eq!(imported.config.versioning, Some(Versioning::Independent));
+        assert_eq!(
+            imported.config.tag_format.as_deref(),
+            Some("{crate}@{version}")
+        );
+
+        Ok(())
+    }
+}
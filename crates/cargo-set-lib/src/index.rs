@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cargo_toml::{Dependency, DepsSet, Manifest};
+
+use crate::paths::normalize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+    Normal,
+    Dev,
+    Build,
+    /// `[workspace.dependencies]`, only present in the root manifest.
+    Workspace,
+}
+
+impl DependencyKind {
+    pub fn table_name(&self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "dependencies",
+            DependencyKind::Dev => "dev-dependencies",
+            DependencyKind::Build => "build-dependencies",
+            DependencyKind::Workspace => "dependencies",
+        }
+    }
+}
+
+/// One dependency table of a manifest, e.g. `[dev-dependencies]` or
+/// `[target.'cfg(unix)'.dependencies]`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DependencyTable {
+    pub kind: DependencyKind,
+    pub target: Option<String>,
+}
+
+impl DependencyTable {
+    pub fn new(kind: DependencyKind) -> Self {
+        Self { kind, target: None }
+    }
+
+    pub fn target(kind: DependencyKind, target: impl Into<String>) -> Self {
+        Self {
+            kind,
+            target: Some(target.into()),
+        }
+    }
+
+    /// Every dependency table present in `manifest`.
+    pub fn all(manifest: &Manifest) -> Vec<DependencyTable> {
+        let mut tables = vec![
+            Self::new(DependencyKind::Normal),
+            Self::new(DependencyKind::Dev),
+            Self::new(DependencyKind::Build),
+        ];
+        if manifest.workspace.is_some() {
+            tables.push(Self::new(DependencyKind::Workspace));
+        }
+        for target in manifest.target.keys() {
+            for kind in [
+                DependencyKind::Normal,
+                DependencyKind::Dev,
+                DependencyKind::Build,
+            ] {
+                tables.push(Self::target(kind, target));
+            }
+        }
+
+        tables
+    }
+
+    /// The TOML key path of this table.
+    pub fn key(&self) -> Vec<String> {
+        let mut key = Vec::new();
+        if let Some(target) = &self.target {
+            key.push("target".to_string());
+            key.push(target.clone());
+        } else if self.kind == DependencyKind::Workspace {
+            key.push("workspace".to_string());
+        }
+        key.push(self.kind.table_name().to_string());
+
+        key
+    }
+
+    pub fn get<'m>(&self, manifest: &'m Manifest) -> Option<&'m DepsSet> {
+        let target = match &self.target {
+            Some(target) => Some(manifest.target.get(target)?),
+            None => None,
+        };
+
+        match (self.kind, target) {
+            (DependencyKind::Workspace, _) => Some(&manifest.workspace.as_ref()?.dependencies),
+            (DependencyKind::Normal, Some(t)) => Some(&t.dependencies),
+            (DependencyKind::Dev, Some(t)) => Some(&t.dev_dependencies),
+            (DependencyKind::Build, Some(t)) => Some(&t.build_dependencies),
+            (DependencyKind::Normal, None) => Some(&manifest.dependencies),
+            (DependencyKind::Dev, None) => Some(&manifest.dev_dependencies),
+            (DependencyKind::Build, None) => Some(&manifest.build_dependencies),
+        }
+    }
+
+    pub fn get_mut<'m>(&self, manifest: &'m mut Manifest) -> Option<&'m mut DepsSet> {
+        let target = match &self.target {
+            Some(target) => Some(manifest.target.get_mut(target)?),
+            None => None,
+        };
+
+        match (self.kind, target) {
+            (DependencyKind::Workspace, _) => Some(&mut manifest.workspace.as_mut()?.dependencies),
+            (DependencyKind::Normal, Some(t)) => Some(&mut t.dependencies),
+            (DependencyKind::Dev, Some(t)) => Some(&mut t.dev_dependencies),
+            (DependencyKind::Build, Some(t)) => Some(&mut t.build_dependencies),
+            (DependencyKind::Normal, None) => Some(&mut manifest.dependencies),
+            (DependencyKind::Dev, None) => Some(&mut manifest.dev_dependencies),
+            (DependencyKind::Build, None) => Some(&mut manifest.build_dependencies),
+        }
+    }
+}
+
+/// Where a dependency is declared: which manifest, which table and under which key.
+///
+/// The index interns the manifest path and key, so the declarations of a
+/// crate across thousands of members share one copy of each.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DependencyLocation {
+    pub manifest: Arc<Path>,
+    pub table: DependencyTable,
+    pub name: Arc<str>,
+}
+
+impl DependencyLocation {
+    /// The TOML key path of the dependency entry.
+    pub fn key(&self) -> Vec<String> {
+        let mut key = self.table.key();
+        key.push(self.name.to_string());
+        key
+    }
+
+    /// The declaration in `manifest`, which must be the manifest at `self.manifest`.
+    pub fn get<'m>(&self, manifest: &'m Manifest) -> Option<&'m Dependency> {
+        self.table.get(manifest)?.get(&*self.name)
+    }
+}
+
+/// The directory of the manifest at `path`, normalized.
+fn manifest_dir(path: &Path) -> PathBuf {
+    normalize(path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Lookup tables from crate names to the manifests that define or reference
+/// them, built once per load so updates don't rescan every manifest.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WorkspaceIndex {
+    packages: BTreeMap<String, Vec<PathBuf>>,
+    dependencies: BTreeMap<Arc<str>, Vec<DependencyLocation>>,
+    /// Dependencies declared under another key than the package they are
+    /// on, by that package.
+    renamed: BTreeMap<String, Vec<DependencyLocation>>,
+    renamed_locations: BTreeSet<DependencyLocation>,
+}
+
+impl WorkspaceIndex {
+    pub fn build<'m>(manifests: impl IntoIterator<Item = (&'m Path, &'m Manifest)>) -> Self {
+        let manifests = manifests.into_iter().collect::<Vec<_>>();
+        let mut index = Self::default();
+
+        let mut package_dirs = BTreeMap::new();
+        for (path, manifest) in &manifests {
+            if let Some(package) = &manifest.package {
+                index
+                    .packages
+                    .entry(package.name.clone())
+                    .or_default()
+                    .push(path.to_path_buf());
+                package_dirs.insert(manifest_dir(path), package.name.as_str());
+            }
+        }
+        // `[workspace.dependencies]` entries renamed with `package`, which
+        // the members inheriting them are renamed by too.
+        let inherited_packages = manifests
+            .iter()
+            .filter_map(|(_, manifest)| manifest.workspace.as_ref())
+            .flat_map(|workspace| &workspace.dependencies)
+            .filter_map(|(name, dependency)| {
+                Some((name.as_str(), dependency.detail()?.package.as_deref()?))
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        for (path, manifest) in &manifests {
+            let manifest_path = Arc::<Path>::from(*path);
+            for table in DependencyTable::all(manifest) {
+                let Some(dependencies) = table.get(manifest) else {
+                    continue;
+                };
+                for (name, dependency) in dependencies {
+                    // The keys of `dependencies` double as the interned
+                    // names.
+                    let interned = match index.dependencies.get_key_value(name.as_str()) {
+                        Some((interned, _)) => interned.clone(),
+                        None => Arc::from(name.as_str()),
+                    };
+                    let location = DependencyLocation {
+                        manifest: manifest_path.clone(),
+                        table: table.clone(),
+                        name: interned,
+                    };
+                    // The package the declaration is on, e.g. `foo` for
+                    // `foo2 = { package = "foo", version = "1" }`, or for a
+                    // path dependency on the member `foo` under another key.
+                    let package = match dependency {
+                        Dependency::Inherited(_) => inherited_packages.get(name.as_str()).copied(),
+                        _ => dependency.detail().and_then(|detail| {
+                            detail.package.as_deref().or_else(|| {
+                                let dir = manifest_dir(path).join(detail.path.as_deref()?);
+                                package_dirs.get(&normalize(&dir)).copied()
+                            })
+                        }),
+                    };
+                    if let Some(package) = package.filter(|package| *package != name.as_str()) {
+                        index.renamed_locations.insert(location.clone());
+                        index
+                            .renamed
+                            .entry(package.to_string())
+                            .or_default()
+                            .push(location.clone());
+                    }
+                    index
+                        .dependencies
+                        .entry(location.name.clone())
+                        .or_default()
+                        .push(location);
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Manifests whose `[package]` is named `name`.
+    pub fn packages(&self, name: &str) -> &[PathBuf] {
+        self.packages
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The names of every package in the workspace, in order.
+    pub fn package_names(&self) -> impl Iterator<Item = &str> {
+        self.packages.keys().map(String::as_str)
+    }
+
+    /// Every dependency declaration in the workspace, ordered by name.
+    pub fn all_dependencies(&self) -> impl Iterator<Item = &DependencyLocation> {
+        self.dependencies.values().flatten()
+    }
+
+    /// Every declaration of a dependency on `name`.
+    pub fn dependencies(&self, name: &str) -> &[DependencyLocation] {
+        self.dependencies
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Every declaration of a dependency on the package `name`, under its
+    /// own name or renamed, leaving out declarations under the key `name`
+    /// that are renamed from another package.
+    pub fn dependencies_on<'i>(
+        &'i self,
+        name: &'i str,
+    ) -> impl Iterator<Item = &'i DependencyLocation> + 'i {
+        let renamed = self.renamed.get(name).map(Vec::as_slice);
+        self.dependencies(name)
+            .iter()
+            .filter(|location| !self.renamed_locations.contains(location))
+            .chain(renamed.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn indexes_every_dependency_table() -> anyhow::Result<()> {
+        let manifest = Manifest::from_str(
+            r#"
+            [package]
+            name = "root"
+            version = "0.1.0"
+
+            [workspace.dependencies]
+            serde = "1"
+
+            [dependencies]
+            serde.workspace = true
+
+            [dev-dependencies]
+            serde = "1"
+
+            [target.'cfg(unix)'.build-dependencies]
+            serde = "1"
+            "#,
+        )?;
+
+        let index = WorkspaceIndex::build([(Path::new("Cargo.toml"), &manifest)]);
+
+        assert_eq!(index.packages("root"), [PathBuf::from("Cargo.toml")]);
+        assert_eq!(
+            index
+                .dependencies("serde")
+                .iter()
+                .map(DependencyLocation::key)
+                .collect::<Vec<_>>(),
+            vec![
+                vec!["dependencies", "serde"],
+                vec!["dev-dependencies", "serde"],
+                vec!["workspace", "dependencies", "serde"],
+                vec!["target", "cfg(unix)", "build-dependencies", "serde"],
+            ]
+        );
+        assert!(index.dependencies("anyhow").is_empty());
+
+        Ok(())
+    }
+}
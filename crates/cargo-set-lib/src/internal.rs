@@ -0,0 +1,94 @@
+use std::fmt;
+use std::path::Path;
+
+use cargo_toml::{Dependency, Manifest};
+
+use crate::index::{DependencyLocation, WorkspaceIndex};
+use crate::paths::display_path;
+
+/// A path dependency on a workspace member whose version requirement isn't
+/// met by the member's current version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequirementDrift {
+    pub location: DependencyLocation,
+    pub requirement: String,
+    /// The member's current version.
+    pub version: String,
+}
+
+impl fmt::Display for RequirementDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: `{}` requires {}, but {} is at {}",
+            display_path(&self.location.manifest),
+            self.location.key().join("."),
+            self.requirement,
+            self.location.name,
+            self.version
+        )
+    }
+}
+
+/// Every internal path dependency in the workspace whose requirement doesn't
+/// match the version of the member it names, ordered by location.
+///
+/// `manifests` are the workspace's manifests and `lookup` finds one of them by
+/// path. Members inheriting their version take it from `workspace_version`.
+pub(crate) fn find_drift<'m>(
+    manifests: impl IntoIterator<Item = &'m Manifest>,
+    lookup: impl Fn(&Path) -> Option<&'m Manifest>,
+    index: &WorkspaceIndex,
+    workspace_version: Option<&str>,
+) -> Vec<RequirementDrift> {
+    let mut drift = Vec::new();
+    for manifest in manifests {
+        let Some(package) = &manifest.package else {
+            continue;
+        };
+        let Some(version) = package
+            .version
+            .get()
+            .ok()
+            .map(String::as_str)
+            .or(workspace_version)
+        else {
+            continue;
+        };
+
+        for location in index.dependencies(&package.name) {
+            let dependency = lookup(&location.manifest)
+                .and_then(|m| location.table.get(m))
+                .and_then(|deps| deps.get(&*location.name));
+            let Some(Dependency::Detailed(detail)) = dependency else {
+                continue;
+            };
+            let (Some(_), Some(requirement)) = (&detail.path, &detail.version) else {
+                continue;
+            };
+
+            if !satisfies(requirement, version) {
+                drift.push(RequirementDrift {
+                    location: location.clone(),
+                    requirement: requirement.clone(),
+                    version: version.to_string(),
+                });
+            }
+        }
+    }
+
+    drift.sort_by(|a, b| a.location.cmp(&b.location));
+    drift
+}
+
+/// Whether `version` meets `requirement`. Unparsable requirements or
+/// versions never do.
+pub(crate) fn satisfies(requirement: &str, version: &str) -> bool {
+    match (
+        semver::VersionReq::parse(requirement),
+        semver::Version::parse(version),
+    ) {
+        (Ok(requirement), Ok(version)) => requirement.matches(&version),
+        _ => false,
+    }
+}
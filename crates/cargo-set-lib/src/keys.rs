@@ -0,0 +1,116 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Context;
+
+/// A dotted TOML key path like `package.repository` or
+/// `target."cfg(unix)".dependencies`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPath(Vec<String>);
+
+impl KeyPath {
+    pub fn parts(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Whether the key is inside `[workspace]`, which only the root manifest
+    /// has.
+    pub fn is_workspace(&self) -> bool {
+        self.0.first().is_some_and(|k| k == "workspace")
+    }
+}
+
+impl FromStr for KeyPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = toml_edit::Key::parse(s)
+            .map_err(|e| anyhow::anyhow!("`{s}` is not a dotted TOML key: {e}"))?;
+
+        Ok(Self(keys.iter().map(|k| k.get().to_string()).collect()))
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys = self
+            .0
+            .iter()
+            .map(|k| toml_edit::Key::new(k.as_str()).display_repr().to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", keys.join("."))
+    }
+}
+
+/// The value to set at a [`KeyPath`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyValue {
+    /// A string, written quoted.
+    String(String),
+    /// A TOML literal, e.g. `true`, `3` or `["a", "b"]`.
+    Literal(String),
+}
+
+impl KeyValue {
+    pub(crate) fn to_value(&self) -> anyhow::Result<toml_edit::Value> {
+        match self {
+            KeyValue::String(value) => Ok(value.as_str().into()),
+            KeyValue::Literal(literal) => literal
+                .parse()
+                .with_context(|| format!("`{literal}` is not a TOML value")),
+        }
+    }
+}
+
+/// Which `metadata` tables [`edit_metadata`] changes.
+///
+/// [`edit_metadata`]: crate::CargoManifestService::edit_metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataScope {
+    /// `[package.metadata.<tool>]` of the root and every member package.
+    Packages,
+    /// `[workspace.metadata.<tool>]` of the root.
+    Workspace,
+}
+
+/// A change to the `metadata` table of a tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataEdit {
+    /// Set `key`, below the tool's table, to a value.
+    Set(KeyPath, KeyValue),
+    /// Merge a TOML document into the tool's table: tables in both are
+    /// merged key by key and other values replaced, keeping every key the
+    /// document doesn't mention.
+    Merge(String),
+    /// Remove `key` below the tool's table, or the whole table without one.
+    Delete(Option<KeyPath>),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_dotted_keys_and_values() -> anyhow::Result<()> {
+        let key = "target.'cfg(unix)'.dependencies".parse::<KeyPath>()?;
+        assert_eq!(key.parts(), ["target", "cfg(unix)", "dependencies"]);
+        assert_eq!(key.to_string(), "target.\"cfg(unix)\".dependencies");
+        assert!("package..name".parse::<KeyPath>().is_err());
+
+        assert_eq!(
+            KeyValue::Literal("[\"a\", 'b']".to_string())
+                .to_value()?
+                .to_string(),
+            "[\"a\", 'b']"
+        );
+        assert_eq!(
+            KeyValue::String("1.70".to_string()).to_value()?.to_string(),
+            "\"1.70\""
+        );
+        assert!(KeyValue::Literal("not toml".to_string())
+            .to_value()
+            .is_err());
+
+        Ok(())
+    }
+}
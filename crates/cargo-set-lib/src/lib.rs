@@ -1,6 +1,207 @@
+//! Load a cargo workspace and change the versions, dependencies and keys of
+//! its manifests while keeping their formatting.
+//!
+//! [`CargoManifestService`] does the work: configure it with its `with_*`
+//! methods, load a workspace with [`CargoManifestService::load_manifest`]
+//! and pass the [`CargoManifest`] to its mutations, like
+//! [`update_version`](CargoManifestService::update_version),
+//! [`bump_workspace`](CargoManifestService::bump_workspace),
+//! [`add_dependency`](CargoManifestService::add_dependency) or
+//! [`set_key`](CargoManifestService::set_key). Each one writes the manifests
+//! it changes and keeps the loaded workspace up to date, so mutations can
+//! follow each other; [`ManifestEdit`] combines several into one write per
+//! file, and [`plan`](CargoManifestService::plan) lists what a set of
+//! [`Operation`]s would change as a [`ChangeSet`] to review before
+//! [`apply`](CargoManifestService::apply) writes it. Storage goes through a
+//! [`FileSystem`], the disk with `RealFileSystem` of the `real-fs` feature
+//! or memory with [`MemoryFileSystem`].
+//!
+//! ```
+//! use std::path::Path;
+//!
+//! use cargo_set_lib::{CargoManifestService, MemoryFileSystem};
+//!
+//! let fs = MemoryFileSystem::new()
+//!     .with_file("Cargo.toml", "[workspace]\nmembers = [\"my-crate\"]\n")
+//!     .with_file(
+//!         "my-crate/Cargo.toml",
+//!         "[package]\nname = \"my-crate\"\nversion = \"1.0.0\"\n",
+//!     );
+//! let service = CargoManifestService::new(fs);
+//! let mut workspace = service.load_manifest(Path::new("Cargo.toml"))?;
+//! service.update_version(&mut workspace, "my-crate", "1.2.3")?;
+//! for (path, manifest) in workspace.members() {
+//!     println!("{}: {:?}", path.display(), manifest.package.as_ref().map(|p| &p.name));
+//! }
+//! # Ok::<(), cargo_set_lib::Error>(())
+//! ```
+//!
+//! # Errors
+//!
+//! Operations return [`Result`], whose [`Error`] has a variant for each
+//! failure callers handle differently:
+//!
+//! - [`Error::Parse`] when a manifest or settings file doesn't parse, with
+//!   the span of the offending part for
+//!   [miette](https://docs.rs/miette) to show;
+//! - [`Error::Policy`] when a change breaks the rules of the workspace's
+//!   [`Policy`], listing every violation;
+//! - [`Error::PartialWrite`] when an update failed after writing some
+//!   manifests, with the state to [`resume`](CargoManifestService::resume)
+//!   it;
+//! - [`Error::Cancelled`] when the [`CancellationToken`] of the service
+//!   fired.
+//!
+//! Anything else is [`Error::Other`], an [`anyhow::Error`] whose context
+//! names the file and value involved.
+//!
+//! # Features
+//!
+//! - `real-fs`, on by default: [`RealFileSystem`] and everything else that
+//!   touches files through `std::fs`. Turn it off to build for targets
+//!   without a file system, like `wasm32-unknown-unknown`, and pass the
+//!   service a [`FileSystem`] of your own, e.g. a [`MemoryFileSystem`]
+//!   filled by the caller.
+//! - `tokio`: `AsyncCargoManifestService` and `AsyncFileSystem`, for async
+//!   callers.
+//! - `git-fs`: everything that runs the `git` command: `GitFileSystem`, to
+//!   load a workspace as a git revision has it, e.g. to compare versions
+//!   with a release tag, helpers like `tags` and `commit_files`, and the
+//!   git placeholders of [`expand_template`].
+//! - `test-util`: the `testing` module, with builders for in-memory
+//!   workspaces and the `MockFileSystem` they live in.
+
+mod actions;
+mod annotation;
+mod artifacts;
+mod audit;
+mod banned;
+mod build_info;
+mod bump;
+mod cancellation;
 mod cargo;
+mod changelog;
+mod check;
+mod config;
+mod dependencies;
+mod diagnostic;
+mod diff;
+mod duplicates;
+mod edit;
+mod error;
+mod explain;
+mod features;
 mod filesystem;
+mod format;
+#[cfg(feature = "git-fs")]
+mod git;
+mod glob;
+mod history;
+mod hooks;
+mod hygiene;
+mod import;
+mod index;
+mod internal;
+mod keys;
+mod lints;
+mod lockfile;
+mod matcher;
+mod metadata;
+#[cfg(feature = "tokio")]
+mod nonblocking;
+mod occurrences;
+mod order;
+mod outdated;
+mod parallel;
+mod patch;
+mod paths;
+mod plan;
+mod policy;
+mod profile;
+mod registry;
+mod repair;
+mod report;
+mod requirements;
+mod resume;
+mod schema;
+mod snapshot;
+mod stats;
+mod tags;
+mod targets;
+mod template;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+mod transform;
+mod version;
+mod versions_file;
+mod why;
 
-pub use cargo::{CargoManifest, CargoManifestService};
-pub use filesystem::{FileSystem, RealFileSystem};
-
+pub use actions::{CodeAction, TextEdit};
+pub use annotation::{Annotation, AnnotationLevel};
+pub use artifacts::Artifacts;
+pub use audit::{AuditConfig, AuditRecord, AuditedFile, AuditedFileSystem};
+pub use banned::{BannedDependency, BannedVersions};
+pub use build_info::{BuildInfo, BuildInfoConfig, BuildInfoFormat};
+pub use bump::{BumpLevel, CalverFormat};
+pub use cancellation::{CancellationToken, Cancelled};
+pub use cargo::{CargoManifest, CargoManifestService, LoadFailure};
+pub use changelog::{changelog_section, previous_tag, ChangelogFormat, CHANGELOG_FILE};
+pub use check::CheckViolation;
+pub use config::{Config, OutputFormat, Versioning, CONFIG_FILE};
+pub use dependencies::{
+    FeatureEdit, GitReference, HoistedDependency, NewDependency, PatchEntry, PatchSource,
+};
+pub use diagnostic::ParseError;
+pub use duplicates::{DeclaredRequirement, DuplicateDependency};
+pub use edit::{ManifestEdit, Operation};
+pub use error::{Error, Result};
+pub use explain::{ExplainedEdit, Explanation};
+pub use features::{FeatureInconsistency, MemberFeatures};
+#[cfg(feature = "real-fs")]
+pub use filesystem::RealFileSystem;
+pub use filesystem::{
+    find_workspace_roots, nearest_manifest, FileSystem, MemoryFileSystem, RecordingFileSystem,
+    TimeoutFileSystem, TrackingFileSystem,
+};
+pub use format::FormatStyle;
+#[cfg(feature = "git-fs")]
+pub use git::{
+    annotated_tag, changed_files, clone, commit_files, commit_subjects, current_branch, describe,
+    diff_all, head_commit, push_branch, remote_url, tags, GitFileSystem,
+};
+pub use history::{JournalEntry, JournalFile, HISTORY_DIR};
+pub use hooks::Hooks;
+pub use hygiene::{LintFinding, LintFix, LintRule};
+pub use import::{import_manifest, import_settings_file, ImportSource, ImportedConfig};
+pub use index::{DependencyKind, DependencyLocation, DependencyTable};
+pub use internal::RequirementDrift;
+pub use keys::{KeyPath, KeyValue, MetadataEdit, MetadataScope};
+pub use lints::{LintInheritance, LintLevel, LintSetting};
+pub use matcher::CrateMatcher;
+pub use metadata::Discovery;
+#[cfg(feature = "tokio")]
+pub use nonblocking::{
+    AsyncCargoManifestService, AsyncFileSystem, BlockingFileSystem, TokioFileSystem,
+};
+pub use occurrences::Occurrence;
+pub use order::OrderedMember;
+pub use outdated::{OutdatedDependency, OutdatedMember, OutdatedReport};
+pub use paths::{display_path, workspace_dir};
+pub use plan::{ChangeSet, PlannedEdit};
+pub use policy::{Policy, PolicyContext, PolicyError, PolicyViolation};
+pub use registry::{Latest, PublishedVersion, Registry, SparseIndex, CRATES_IO_INDEX};
+pub use repair::Repair;
+pub use report::{Change, ChangeReport, ChangedFile};
+pub use requirements::{RequirementChange, RequirementStyle, UnfreezeStyle};
+pub use resume::{
+    FileWrite, PartialWriteError, ResumeMode, ResumeState, ResumeSummary, RESUME_FILE,
+};
+pub use schema::Document;
+pub use snapshot::snapshot_pre;
+pub use stats::Stats;
+pub use tags::{tag_name, TagCheck, TagStatus, DEFAULT_TAG_FORMAT};
+pub use targets::TargetEdit;
+pub use template::expand_template;
+pub use transform::Transform;
+pub use versions_file::{VersionTarget, VersionsFile};
+pub use why::DependencyPath;
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::Context;
+use toml_edit::{InlineTable, Value};
+
+/// How the compiler treats a lint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+    Forbid,
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+            LintLevel::Forbid => "forbid",
+        })
+    }
+}
+
+impl FromStr for LintLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "deny" => Ok(LintLevel::Deny),
+            "forbid" => Ok(LintLevel::Forbid),
+            _ => crate::error::bail!("`{s}` is not a lint level, use allow, warn, deny or forbid"),
+        }
+    }
+}
+
+/// The level of one lint or lint group in `[workspace.lints]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintSetting {
+    /// The table the lint belongs in: `rust`, `clippy` or `rustdoc`.
+    pub tool: String,
+    pub lint: String,
+    pub level: LintLevel,
+    /// Orders groups before the lints they contain, e.g. `-1` for
+    /// `clippy::pedantic`.
+    pub priority: Option<i64>,
+}
+
+impl LintSetting {
+    pub(crate) fn to_value(&self) -> Value {
+        let level = Value::from(self.level.to_string());
+        let Some(priority) = self.priority else {
+            return level;
+        };
+
+        let mut table = InlineTable::new();
+        table.insert("level", level);
+        table.insert("priority", priority.into());
+        table.fmt();
+
+        Value::InlineTable(table)
+    }
+}
+
+impl FromStr for LintSetting {
+    type Err = anyhow::Error;
+
+    /// A lint as rustc names it and its level, with an optional priority,
+    /// e.g. `unsafe_code=deny` or `clippy::pedantic=warn:-1`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, level) = s
+            .split_once('=')
+            .with_context(|| format!("`{s}` is not LINT=LEVEL, e.g. `unsafe_code=deny`"))?;
+        let (tool, lint) = match name.trim().split_once("::") {
+            Some((tool, lint)) => (tool, lint),
+            None => ("rust", name.trim()),
+        };
+        if !["rust", "clippy", "rustdoc"].contains(&tool) {
+            crate::error::bail!(
+                "`{tool}` is not a lint tool, use clippy:: or rustdoc:: or nothing"
+            );
+        }
+        let valid = !lint.is_empty()
+            && lint
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+        if !valid {
+            crate::error::bail!("`{lint}` is not a lint name");
+        }
+
+        let (level, priority) = match level.trim().split_once(':') {
+            Some((level, priority)) => (
+                level,
+                Some(
+                    priority
+                        .parse()
+                        .with_context(|| format!("`{priority}` is not a lint priority"))?,
+                ),
+            ),
+            None => (level.trim(), None),
+        };
+
+        Ok(Self {
+            tool: tool.to_string(),
+            lint: lint.to_string(),
+            level: level.parse()?,
+            priority,
+        })
+    }
+}
+
+/// What [`inherit_workspace_lints`] did to the members.
+///
+/// [`inherit_workspace_lints`]: crate::CargoManifestService::inherit_workspace_lints
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintInheritance {
+    /// Members that now inherit the workspace's lints.
+    pub inherited: Vec<PathBuf>,
+    /// Members left alone because they configure lints of their own, which
+    /// cargo doesn't allow next to `workspace = true`.
+    pub own_lints: Vec<PathBuf>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_lint_settings() -> anyhow::Result<()> {
+        let setting = "clippy::pedantic=warn:-1".parse::<LintSetting>()?;
+        assert_eq!(
+            setting,
+            LintSetting {
+                tool: "clippy".to_string(),
+                lint: "pedantic".to_string(),
+                level: LintLevel::Warn,
+                priority: Some(-1),
+            }
+        );
+        assert_eq!(
+            setting.to_value().to_string(),
+            "{ level = \"warn\", priority = -1 }"
+        );
+
+        let setting = "unsafe_code=deny".parse::<LintSetting>()?;
+        assert_eq!(setting.tool, "rust");
+        assert_eq!(setting.to_value().to_string(), "\"deny\"");
+
+        assert!("unsafe_code=loud".parse::<LintSetting>().is_err());
+        assert!("miri::x=deny".parse::<LintSetting>().is_err());
+
+        Ok(())
+    }
+}
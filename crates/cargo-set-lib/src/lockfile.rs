@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+use toml_edit::{DocumentMut, Item, Value};
+
+use crate::diagnostic;
+use crate::filesystem::FileSystem;
+use crate::internal::satisfies;
+use crate::paths::display_path;
+
+/// The packages resolved in a workspace's `Cargo.lock`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct Lockfile {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// Entries of the form `name`, `name version` or `name version (source)`.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// The names of the crates this package depends on.
+    pub fn dependency_names(&self) -> impl Iterator<Item = &str> {
+        self.dependencies
+            .iter()
+            .filter_map(|dependency| dependency.split_whitespace().next())
+    }
+}
+
+impl Lockfile {
+    /// Where the lockfile of the workspace rooted at `root_manifest` lives.
+    pub fn path_for(root_manifest: &Path) -> PathBuf {
+        root_manifest
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join("Cargo.lock")
+    }
+
+    pub fn load<F: FileSystem>(fs: &F, root_manifest: &Path) -> crate::Result<Self> {
+        let path = Self::path_for(root_manifest);
+        let contents = fs.read(&path).with_context(|| {
+            format!(
+                "failed to read {}, run `cargo generate-lockfile` to create it",
+                display_path(&path)
+            )
+        })?;
+        let contents = String::from_utf8(contents)
+            .with_context(|| format!("failed to parse {}", display_path(&path)))?;
+
+        Ok(diagnostic::parse_toml(&path, &contents)?)
+    }
+
+    /// The highest locked version of `name` that meets `requirement`; a crate
+    /// can be locked at several versions.
+    pub fn resolve(&self, name: &str, requirement: &str) -> Option<&str> {
+        self.packages
+            .iter()
+            .filter(|p| p.name == name && satisfies(requirement, &p.version))
+            .filter_map(|p| Some((semver::Version::parse(&p.version).ok()?, p)))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, p)| p.version.as_str())
+    }
+}
+
+/// Rewrite the `[[package]]` entries of the workspace members in `contents`,
+/// a `Cargo.lock`, to the versions in `versions`, along with the
+/// `name version` references other entries make to them. Members are the
+/// entries without a `source`.
+///
+/// Returns `None` when every entry is already up to date.
+pub(crate) fn set_member_versions(
+    contents: &str,
+    versions: &BTreeMap<String, String>,
+) -> anyhow::Result<Option<String>> {
+    let mut document = contents.parse::<DocumentMut>()?;
+    let Some(packages) = document
+        .get_mut("package")
+        .and_then(Item::as_array_of_tables_mut)
+    else {
+        return Ok(None);
+    };
+
+    let mut renamed = BTreeMap::new();
+    for package in packages.iter_mut() {
+        if package.contains_key("source") {
+            continue;
+        }
+        let Some(name) = package
+            .get("name")
+            .and_then(Item::as_str)
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let Some(new) = versions.get(&name) else {
+            continue;
+        };
+        let Some(version) = package.get_mut("version").and_then(Item::as_value_mut) else {
+            continue;
+        };
+        if let Some(old) = version.as_str().filter(|old| old != new) {
+            renamed.insert(format!("{name} {old}"), format!("{name} {new}"));
+            replace_string(version, new);
+        }
+    }
+    if renamed.is_empty() {
+        return Ok(None);
+    }
+
+    for package in packages.iter_mut() {
+        let Some(dependencies) = package.get_mut("dependencies").and_then(Item::as_array_mut)
+        else {
+            continue;
+        };
+        for dependency in dependencies.iter_mut() {
+            if let Some(new) = dependency.as_str().and_then(|d| renamed.get(d)).cloned() {
+                replace_string(dependency, &new);
+            }
+        }
+    }
+
+    Ok(Some(document.to_string()))
+}
+
+/// Replace the string `value` keeping the whitespace around it.
+fn replace_string(value: &mut Value, new: &str) {
+    let decor = value.decor().clone();
+    *value = new.into();
+    *value.decor_mut() = decor;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolves_highest_matching_version() -> anyhow::Result<()> {
+        let lockfile: Lockfile = toml::from_str(
+            r#"
+            version = 3
+
+            [[package]]
+            name = "syn"
+            version = "1.0.109"
+
+            [[package]]
+            name = "syn"
+            version = "2.0.18"
+
+            [[package]]
+            name = "syn"
+            version = "2.0.3"
+            "#,
+        )?;
+
+        assert_eq!(lockfile.resolve("syn", "1"), Some("1.0.109"));
+        assert_eq!(lockfile.resolve("syn", "^2.0.1"), Some("2.0.18"));
+        assert_eq!(lockfile.resolve("syn", "3"), None);
+        assert_eq!(lockfile.resolve("quote", "1"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sets_the_versions_of_members() -> anyhow::Result<()> {
+        let contents = r#"version = 3
+
+[[package]]
+name = "a"
+version = "0.1.0"
+dependencies = [
+ "b 0.1.0",
+ "serde",
+]
+
+[[package]]
+name = "b"
+version = "0.1.0"
+
+[[package]]
+name = "b"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        let versions = BTreeMap::from([
+            ("a".to_string(), "0.1.0".to_string()),
+            ("b".to_string(), "0.2.0".to_string()),
+            ("serde".to_string(), "2.0.0".to_string()),
+        ]);
+
+        assert_eq!(
+            set_member_versions(contents, &versions)?.as_deref(),
+            Some(
+                contents
+                    .replacen(" \"b 0.1.0\"", " \"b 0.2.0\"", 1)
+                    .replacen(
+                        "name = \"b\"\nversion = \"0.1.0\"\n\n",
+                        "name = \"b\"\nversion = \"0.2.0\"\n\n",
+                        1
+                    )
+                    .as_str()
+            )
+        );
+        assert_eq!(
+            set_member_versions(contents, &BTreeMap::from([("a".into(), "0.1.0".into())]))?,
+            None
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use crate::glob;
+
+/// The crates an update applies to: names and glob patterns like
+/// `my-org-*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateMatcher {
+    patterns: Vec<String>,
+}
+
+impl CrateMatcher {
+    pub fn new<P: Into<String>>(patterns: impl IntoIterator<Item = P>) -> Self {
+        Self {
+            patterns: patterns.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| glob::matches_component(pattern, name))
+    }
+
+    /// The crates out of `packages` to update, in order. Plain names are
+    /// kept even if they aren't a package, since dependencies on them still
+    /// get updated, but every pattern has to match a package.
+    pub(crate) fn select<'p>(
+        &self,
+        packages: impl IntoIterator<Item = &'p str>,
+    ) -> anyhow::Result<Vec<String>> {
+        let packages = packages.into_iter().collect::<Vec<_>>();
+
+        let mut selected = Vec::new();
+        for pattern in &self.patterns {
+            if !glob::is_pattern(pattern) {
+                selected.push(pattern.clone());
+                continue;
+            }
+            let matched = packages
+                .iter()
+                .filter(|name| glob::matches_component(pattern, name))
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            if matched.is_empty() {
+                crate::error::bail!("`{pattern}` matches no package of the workspace");
+            }
+            selected.extend(matched);
+        }
+        selected.sort();
+        selected.dedup();
+
+        Ok(selected)
+    }
+}
+
+/// The name out of `candidates` closest to `name`, if one is close enough to
+/// be a likely typo of it.
+pub(crate) fn suggest<'c>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'c str>,
+) -> Option<&'c str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= (name.chars().count() / 3).max(1))
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between `a` and `b`, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+impl From<&str> for CrateMatcher {
+    fn from(name: &str) -> Self {
+        Self::new([name])
+    }
+}
+
+impl From<String> for CrateMatcher {
+    fn from(name: String) -> Self {
+        Self::new([name])
+    }
+}
+
+impl From<&String> for CrateMatcher {
+    fn from(name: &String) -> Self {
+        Self::new([name.as_str()])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn selects_names_and_pattern_matches() -> anyhow::Result<()> {
+        let packages = ["my-org-core", "my-org-cli", "other"];
+
+        let matcher = CrateMatcher::new(["my-org-*", "other", "serde"]);
+        assert_eq!(
+            matcher.select(packages)?,
+            ["my-org-cli", "my-org-core", "other", "serde"]
+        );
+        assert!(matcher.matches("my-org-web"));
+        assert!(!matcher.matches("your-org-web"));
+        assert!(CrateMatcher::from("nope-*").select(packages).is_err());
+
+        assert_eq!(suggest("my-org-cil", packages), Some("my-org-cli"));
+        assert_eq!(suggest("tpyo", packages), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::paths::{display_path, normalize};
+
+/// How the members of a workspace are found.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Discovery {
+    /// Expand the `members` of the root manifest, and of member manifests
+    /// that are workspaces themselves, through the file system.
+    #[default]
+    Manifest,
+    /// Ask `cargo metadata`, so the members are exactly the ones cargo
+    /// builds, including path dependencies outside `members`. Cargo reads the
+    /// disk, whatever the file system of the service.
+    Cargo,
+}
+
+/// The members `cargo metadata` reports for a workspace.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct CargoMembers {
+    pub members: Vec<PathBuf>,
+    /// `None` with a cargo too old to report default members.
+    pub default_members: Option<BTreeSet<PathBuf>>,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    workspace_default_members: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    id: String,
+    manifest_path: PathBuf,
+}
+
+/// The members of the workspace rooted at `root_path`, as cargo resolves
+/// them, under paths relative to the directory of `root_path` like the
+/// members found through the manifest.
+pub(crate) fn cargo_members(root_path: &Path) -> anyhow::Result<CargoMembers> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--no-deps",
+            "--manifest-path",
+        ])
+        .arg(root_path)
+        .output()
+        .context("failed to run cargo metadata")?;
+    if !output.status.success() {
+        crate::error::bail!(
+            "`cargo metadata` failed for {}: {}",
+            display_path(root_path),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let root_dir = std::path::absolute(root_path)
+        .with_context(|| format!("failed to resolve {}", display_path(root_path)))?;
+    parse_members(
+        &output.stdout,
+        root_path,
+        root_dir.parent().unwrap_or(&root_dir),
+    )
+    .context("failed to parse the output of cargo metadata")
+}
+
+/// The members of `metadata`, a `cargo metadata` document, with the absolute
+/// paths cargo reports under `root_dir` made relative to the directory of
+/// `root_path`.
+fn parse_members(
+    metadata: &[u8],
+    root_path: &Path,
+    root_dir: &Path,
+) -> anyhow::Result<CargoMembers> {
+    let metadata: Metadata = serde_json::from_slice(metadata)?;
+    let base = root_path.parent().unwrap_or_else(|| Path::new(""));
+    let manifests = metadata
+        .packages
+        .iter()
+        .map(|package| {
+            let relative = relative_to(&normalize(&package.manifest_path), root_dir);
+            (package.id.as_str(), normalize(&base.join(relative)))
+        })
+        .collect::<BTreeMap<_, _>>();
+    let paths = |ids: &[String]| {
+        ids.iter()
+            .filter_map(|id| manifests.get(id.as_str()).cloned())
+            .filter(|path| path != &normalize(root_path))
+            .collect::<Vec<_>>()
+    };
+
+    Ok(CargoMembers {
+        members: paths(&metadata.workspace_members),
+        default_members: metadata
+            .workspace_default_members
+            .map(|ids| paths(&ids).into_iter().collect()),
+    })
+}
+
+/// `path` relative to `base`, both absolute, stepping out of `base` with
+/// `..` where they diverge.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let base = normalize(base);
+    let path_components = path.components().collect::<Vec<_>>();
+    let base_components = base.components().collect::<Vec<_>>();
+    let shared = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    base_components[shared..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .chain(path_components[shared..].iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolves_the_members_cargo_reports() -> anyhow::Result<()> {
+        let metadata = br#"{
+            "packages": [
+                { "id": "path+file:///ws#root@0.1.0", "manifest_path": "/ws/Cargo.toml" },
+                { "id": "path+file:///ws/crates/a#0.1.0", "manifest_path": "/ws/crates/a/Cargo.toml" },
+                { "id": "path+file:///ws/vendor/b#0.1.0", "manifest_path": "/ws/vendor/b/Cargo.toml" },
+                { "id": "path+file:///shared/c#0.1.0", "manifest_path": "/shared/c/Cargo.toml" }
+            ],
+            "workspace_members": [
+                "path+file:///ws#root@0.1.0",
+                "path+file:///ws/crates/a#0.1.0",
+                "path+file:///ws/vendor/b#0.1.0",
+                "path+file:///shared/c#0.1.0"
+            ],
+            "workspace_default_members": ["path+file:///ws/crates/a#0.1.0"],
+            "workspace_root": "/ws"
+        }"#;
+
+        let members = parse_members(metadata, Path::new("Cargo.toml"), Path::new("/ws"))?;
+        assert_eq!(
+            members,
+            CargoMembers {
+                members: vec![
+                    PathBuf::from("crates/a/Cargo.toml"),
+                    PathBuf::from("vendor/b/Cargo.toml"),
+                    PathBuf::from("../shared/c/Cargo.toml"),
+                ],
+                default_members: Some(BTreeSet::from([PathBuf::from("crates/a/Cargo.toml")])),
+            }
+        );
+
+        let members = parse_members(metadata, Path::new("ws/Cargo.toml"), Path::new("/ws"))?;
+        assert_eq!(members.members[0], Path::new("ws/crates/a/Cargo.toml"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+//! Async access to workspaces, for callers on a tokio runtime.
+//!
+//! [`AsyncCargoManifestService`] runs the blocking service on tokio's
+//! blocking pool, so async callers can `.await` loads and updates without
+//! wrapping every call in `spawn_blocking` themselves. Its storage is either
+//! a [`FileSystem`] or an [`AsyncFileSystem`] bridged with
+//! [`BlockingFileSystem`]. For timeouts, wrap that in a
+//! [`TimeoutFileSystem`](crate::TimeoutFileSystem) before handing it to the
+//! service, as for any other [`FileSystem`].
+
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use tokio::runtime::Handle;
+
+use crate::cargo::{CargoManifest, CargoManifestService};
+use crate::filesystem::{FileSystem, RealFileSystem};
+use crate::paths::display_path;
+
+/// The async counterpart of [`FileSystem`], with the same semantics for
+/// every operation.
+pub trait AsyncFileSystem: Send + Sync + 'static {
+    fn read(&self, path: &Path) -> impl Future<Output = io::Result<Vec<u8>>> + Send;
+    fn write(&self, path: &Path, contents: Vec<u8>) -> impl Future<Output = io::Result<()>> + Send;
+    fn remove(&self, path: &Path) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Resolve symlinks in `path`. File systems without symlinks return the
+    /// path unchanged.
+    fn canonicalize(&self, path: &Path) -> impl Future<Output = io::Result<PathBuf>> + Send {
+        let path = path.to_path_buf();
+        async move { Ok(path) }
+    }
+
+    /// Check, without changing anything, that `write(path, ..)` can succeed.
+    fn probe_write(&self, _path: &Path) -> impl Future<Output = io::Result<()>> + Send {
+        async { Ok(()) }
+    }
+
+    /// The entries of the directory `path`, each joined onto `path`, in path
+    /// order.
+    fn list_dir(&self, _path: &Path) -> impl Future<Output = io::Result<Vec<PathBuf>>> + Send {
+        async {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "listing directories is not supported",
+            ))
+        }
+    }
+
+    /// Whether a file or directory exists at `path`.
+    fn exists(&self, path: &Path) -> impl Future<Output = io::Result<bool>> + Send {
+        async move {
+            match self.read(path).await {
+                Ok(_) => return Ok(true),
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                Err(_) => {}
+            }
+            match self.list_dir(path).await {
+                Ok(_) => Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    /// Write `contents` to `path`, failing with `AlreadyExists` instead of
+    /// replacing a file that is there.
+    fn create(
+        &self,
+        path: &Path,
+        contents: Vec<u8>,
+    ) -> impl Future<Output = io::Result<()>> + Send {
+        async move {
+            if self.exists(path).await? {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists", display_path(path)),
+                ));
+            }
+
+            self.write(path, contents).await
+        }
+    }
+
+    /// Every file below the directory `path`, each joined onto `path`, in
+    /// path order. Built on `list_dir`.
+    fn walk(&self, path: &Path) -> impl Future<Output = io::Result<Vec<PathBuf>>> + Send {
+        async move {
+            let mut files = Vec::new();
+            let mut dirs = vec![path.to_path_buf()];
+            while let Some(dir) = dirs.pop() {
+                for entry in self.list_dir(&dir).await? {
+                    match self.list_dir(&entry).await {
+                        Ok(_) => dirs.push(entry),
+                        Err(e) if e.kind() == io::ErrorKind::NotADirectory => files.push(entry),
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+            files.sort();
+
+            Ok(files)
+        }
+    }
+}
+
+/// The disk through tokio's blocking pool, with the semantics of
+/// [`RealFileSystem`], e.g. replacing files atomically.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioFileSystem;
+
+impl TokioFileSystem {
+    async fn run<T: Send + 'static>(
+        path: &Path,
+        op: impl FnOnce(&Path) -> io::Result<T> + Send + 'static,
+    ) -> io::Result<T> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || op(&path))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+impl AsyncFileSystem for TokioFileSystem {
+    async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        Self::run(path, |path| RealFileSystem.read(path)).await
+    }
+
+    async fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        Self::run(path, move |path| RealFileSystem.write(path, contents)).await
+    }
+
+    async fn remove(&self, path: &Path) -> io::Result<()> {
+        Self::run(path, |path| RealFileSystem.remove(path)).await
+    }
+
+    async fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        Self::run(path, |path| RealFileSystem.canonicalize(path)).await
+    }
+
+    async fn probe_write(&self, path: &Path) -> io::Result<()> {
+        Self::run(path, |path| RealFileSystem.probe_write(path)).await
+    }
+
+    async fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        Self::run(path, |path| RealFileSystem.list_dir(path)).await
+    }
+
+    async fn exists(&self, path: &Path) -> io::Result<bool> {
+        Self::run(path, |path| RealFileSystem.exists(path)).await
+    }
+
+    async fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        Self::run(path, move |path| RealFileSystem.create(path, contents)).await
+    }
+
+    async fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        Self::run(path, |path| RealFileSystem.walk(path)).await
+    }
+}
+
+/// An [`AsyncFileSystem`] as a blocking [`FileSystem`], for the service run
+/// by [`AsyncCargoManifestService`]. Its operations block on the runtime
+/// they were created in, so they must not run on an async task.
+pub struct BlockingFileSystem<A> {
+    fs: A,
+    handle: Handle,
+}
+
+impl<A: AsyncFileSystem> BlockingFileSystem<A> {
+    /// Bridges `fs` on the current runtime.
+    ///
+    /// # Panics
+    ///
+    /// Outside of a tokio runtime.
+    pub fn new(fs: A) -> Self {
+        Self {
+            fs,
+            handle: Handle::current(),
+        }
+    }
+}
+
+impl<A: AsyncFileSystem> FileSystem for BlockingFileSystem<A> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.handle.block_on(self.fs.read(path))
+    }
+
+    fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        self.handle.block_on(self.fs.write(path, contents))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.handle.block_on(self.fs.remove(path))
+    }
+
+    fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+        self.handle.block_on(self.fs.canonicalize(path))
+    }
+
+    fn probe_write(&self, path: &Path) -> io::Result<()> {
+        self.handle.block_on(self.fs.probe_write(path))
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.handle.block_on(self.fs.list_dir(path))
+    }
+
+    fn exists(&self, path: &Path) -> io::Result<bool> {
+        self.handle.block_on(self.fs.exists(path))
+    }
+
+    fn create(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+        self.handle.block_on(self.fs.create(path, contents))
+    }
+
+    fn walk(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.handle.block_on(self.fs.walk(path))
+    }
+}
+
+/// A [`CargoManifestService`] for async callers. Every operation runs on
+/// tokio's blocking pool; configure the service with its `with_*` methods
+/// before converting it with `From`.
+pub struct AsyncCargoManifestService<F: FileSystem> {
+    service: Arc<CargoManifestService<F>>,
+}
+
+impl<F: FileSystem> Clone for AsyncCargoManifestService<F> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+        }
+    }
+}
+
+impl<A: AsyncFileSystem> AsyncCargoManifestService<BlockingFileSystem<A>> {
+    /// A service with the default options over `fs`, on the current runtime.
+    ///
+    /// # Panics
+    ///
+    /// Outside of a tokio runtime.
+    pub fn new(fs: A) -> Self {
+        CargoManifestService::new(BlockingFileSystem::new(fs)).into()
+    }
+}
+
+impl<F: FileSystem> From<CargoManifestService<F>> for AsyncCargoManifestService<F> {
+    fn from(service: CargoManifestService<F>) -> Self {
+        Self {
+            service: Arc::new(service),
+        }
+    }
+}
+
+impl<F: FileSystem + 'static> AsyncCargoManifestService<F> {
+    /// Run `op` with the blocking service on tokio's blocking pool, for
+    /// operations without an async method of their own.
+    pub async fn run<T: Send + 'static>(
+        &self,
+        op: impl FnOnce(&CargoManifestService<F>) -> crate::Result<T> + Send + 'static,
+    ) -> crate::Result<T> {
+        let service = self.service.clone();
+        tokio::task::spawn_blocking(move || op(&service))
+            .await
+            .context("the blocking task of the update failed")?
+    }
+
+    pub async fn load_manifest(&self, path: impl Into<PathBuf>) -> crate::Result<CargoManifest> {
+        let path = path.into();
+        self.run(move |service| service.load_manifest(&path)).await
+    }
+
+    /// Set several crates to new versions, as
+    /// [`CargoManifestService::update_versions`] does, returning the updated
+    /// workspace. On failure the workspace is dropped; load it again to see
+    /// what was written.
+    pub async fn update_versions(
+        &self,
+        mut s: CargoManifest,
+        updates: Vec<(String, String)>,
+    ) -> crate::Result<CargoManifest> {
+        self.run(move |service| {
+            service.update_versions(&mut s, &updates)?;
+            Ok(s)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::filesystem::MockFileSystem;
+
+    struct AsyncMock(Arc<MockFileSystem>);
+
+    impl AsyncFileSystem for AsyncMock {
+        async fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            self.0.read(path)
+        }
+
+        async fn write(&self, path: &Path, contents: Vec<u8>) -> io::Result<()> {
+            self.0.write(path, contents)
+        }
+
+        async fn remove(&self, path: &Path) -> io::Result<()> {
+            self.0.remove(path)
+        }
+
+        async fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            self.0.list_dir(path)
+        }
+    }
+
+    /// An [`AsyncFileSystem`] whose reads never finish.
+    struct Hanging;
+
+    impl AsyncFileSystem for Hanging {
+        async fn read(&self, _path: &Path) -> io::Result<Vec<u8>> {
+            std::future::pending().await
+        }
+
+        async fn write(&self, _path: &Path, _contents: Vec<u8>) -> io::Result<()> {
+            Ok(())
+        }
+
+        async fn remove(&self, _path: &Path) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn finds_and_creates_files_through_the_defaults() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(PathBuf::from("a/Cargo.toml"), Vec::new());
+        fs.add_file(PathBuf::from("a/src/lib.rs"), Vec::new());
+        let fs = Arc::new(fs);
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+
+        runtime.block_on(async {
+            let fs = AsyncMock(fs.clone());
+            assert!(fs.exists(Path::new("a/Cargo.toml")).await?);
+            assert!(fs.exists(Path::new("a/src")).await?);
+            assert!(!fs.exists(Path::new("b")).await?);
+            let err = fs
+                .create(Path::new("a/Cargo.toml"), b"new".to_vec())
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+            fs.create(Path::new("a/README.md"), b"new".to_vec()).await?;
+            assert_eq!(
+                fs.walk(Path::new("a")).await?,
+                [
+                    PathBuf::from("a/Cargo.toml"),
+                    PathBuf::from("a/README.md"),
+                    PathBuf::from("a/src/lib.rs"),
+                ]
+            );
+
+            anyhow::Ok(())
+        })
+    }
+
+    #[test]
+    fn times_out_through_a_timeout_file_system() -> anyhow::Result<()> {
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+
+        let err = runtime.block_on(async {
+            let fs = crate::TimeoutFileSystem::new(
+                BlockingFileSystem::new(Hanging),
+                Some(std::time::Duration::from_millis(10)),
+            );
+            let service = AsyncCargoManifestService::from(CargoManifestService::new(fs));
+            service.load_manifest("Cargo.toml").await.unwrap_err()
+        });
+
+        assert!(format!("{err:#}").contains("timed out reading Cargo.toml"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn updates_versions_through_an_async_file_system() -> anyhow::Result<()> {
+        let mut fs = MockFileSystem::new();
+        fs.add_file(
+            PathBuf::from("Cargo.toml"),
+            b"[workspace]\nmembers = ['a', 'b']\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("a/Cargo.toml"),
+            b"[package]\nname = 'a'\nversion = '0.1.0'\n".to_vec(),
+        );
+        fs.add_file(
+            PathBuf::from("b/Cargo.toml"),
+            b"[package]\nname = 'b'\nversion = '0.1.0'\n\n[dependencies]\na = { path = '../a', version = '0.1.0' }\n".to_vec(),
+        );
+        let fs = Arc::new(fs);
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+
+        let manifest = runtime.block_on(async {
+            let service = AsyncCargoManifestService::new(AsyncMock(fs.clone()));
+            let manifest = service.load_manifest("Cargo.toml").await?;
+            let updates = vec![("a".to_string(), "0.2.0".to_string())];
+            service.update_versions(manifest, updates).await
+        })?;
+
+        assert!(manifest
+            .package_versions()
+            .contains(&("a".to_string(), Some("0.2.0".to_string()))));
+        assert!(String::from_utf8(fs.read(Path::new("b/Cargo.toml"))?)?
+            .contains("a = { path = '../a', version = '0.2.0' }"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::paths::display_path;
+
+/// One place a crate appears in the workspace: its own version or a
+/// dependency declaration naming it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Occurrence {
+    #[serde(with = "crate::paths::serde_path")]
+    pub manifest: PathBuf,
+    /// The TOML key path of the version, or of the declaration when it has
+    /// no version of its own.
+    pub key: Vec<String>,
+    /// `None` for declarations without a requirement, like path-only or
+    /// inherited ones.
+    pub version: Option<String>,
+}
+
+impl fmt::Display for Occurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: `{}`",
+            display_path(&self.manifest),
+            self.key.join(".")
+        )?;
+        match &self.version {
+            Some(version) => write!(f, " = {version}"),
+            None => write!(f, " (no version)"),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// A member in publish order, with the members that have to be published
+/// before it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrderedMember {
+    pub name: String,
+    pub version: Option<String>,
+    #[serde(with = "crate::paths::serde_path")]
+    pub manifest: PathBuf,
+    /// The members it depends on, in name order.
+    pub dependencies: Vec<String>,
+    /// `false` for members with `publish = false` or an empty list of
+    /// registries.
+    pub publish: bool,
+}
+
+/// The crates of `graph`, which maps every crate to the crates it depends
+/// on, with each one after its dependencies. Crates free to go at the same
+/// time are in name order.
+///
+/// A cycle is returned as the crates on it, starting and ending with the
+/// same crate.
+pub(crate) fn topological_order(
+    graph: &BTreeMap<String, BTreeSet<String>>,
+) -> Result<Vec<String>, Vec<String>> {
+    let mut remaining = graph
+        .iter()
+        .map(|(name, dependencies)| {
+            let dependencies = dependencies
+                .iter()
+                .filter(|dependency| graph.contains_key(*dependency))
+                .map(String::as_str)
+                .collect::<BTreeSet<_>>();
+            (name.as_str(), dependencies)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut order = Vec::new();
+    while !remaining.is_empty() {
+        let ready = remaining
+            .iter()
+            .filter(|(_, dependencies)| dependencies.is_empty())
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        if ready.is_empty() {
+            return Err(cycle(&remaining));
+        }
+
+        for name in ready {
+            remaining.remove(name);
+            for dependencies in remaining.values_mut() {
+                dependencies.remove(name);
+            }
+            order.push(name.to_string());
+        }
+    }
+
+    Ok(order)
+}
+
+/// A cycle in `remaining`, where every crate still waits for another.
+fn cycle(remaining: &BTreeMap<&str, BTreeSet<&str>>) -> Vec<String> {
+    let mut path: Vec<&str> = Vec::new();
+    let mut at = *remaining.keys().next().expect("a cycle has crates");
+    while !path.contains(&at) {
+        path.push(at);
+        at = remaining[at]
+            .iter()
+            .next()
+            .expect("every remaining crate waits for another");
+    }
+
+    let start = path.iter().position(|name| *name == at).unwrap_or(0);
+    path[start..]
+        .iter()
+        .chain([&at])
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph(edges: &[(&str, &[&str])]) -> BTreeMap<String, BTreeSet<String>> {
+        edges
+            .iter()
+            .map(|(name, dependencies)| {
+                (
+                    name.to_string(),
+                    dependencies.iter().map(|d| d.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_crates_after_their_dependencies() {
+        let order = topological_order(&graph(&[
+            ("app", &["cli", "core", "serde"]),
+            ("cli", &["core"]),
+            ("core", &[]),
+            ("macros", &[]),
+        ]));
+        assert_eq!(
+            order,
+            Ok(["core", "macros", "cli", "app"].map(String::from).to_vec())
+        );
+
+        let cycle = topological_order(&graph(&[
+            ("a", &["b"]),
+            ("b", &["c"]),
+            ("c", &["b"]),
+            ("d", &[]),
+        ]));
+        assert_eq!(cycle, Err(["b", "c", "b"].map(String::from).to_vec()));
+    }
+}
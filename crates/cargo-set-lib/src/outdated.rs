@@ -0,0 +1,114 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::paths::display_path;
+
+/// How the workspace compares to the registry: members whose local version
+/// differs from their latest release, and requirements the latest release
+/// of a dependency doesn't satisfy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct OutdatedReport {
+    pub members: Vec<OutdatedMember>,
+    pub dependencies: Vec<OutdatedDependency>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutdatedMember {
+    pub name: String,
+    pub local: String,
+    /// The latest release, or `None` if the member was never published.
+    pub published: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutdatedDependency {
+    #[serde(with = "crate::paths::serde_path")]
+    pub manifest: PathBuf,
+    pub key: Vec<String>,
+    pub package: String,
+    pub requirement: String,
+    pub latest: String,
+}
+
+impl OutdatedReport {
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty() && self.dependencies.is_empty()
+    }
+}
+
+impl fmt::Display for OutdatedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.members.is_empty() {
+            let rows = self
+                .members
+                .iter()
+                .map(|m| {
+                    [
+                        m.name.clone(),
+                        m.local.clone(),
+                        m.published.clone().unwrap_or_else(|| "-".to_string()),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            table(f, ["member", "local", "published"], &rows)?;
+        }
+
+        if !self.dependencies.is_empty() {
+            if !self.members.is_empty() {
+                writeln!(f)?;
+            }
+            let rows = self
+                .dependencies
+                .iter()
+                .map(|d| {
+                    [
+                        d.package.clone(),
+                        d.requirement.clone(),
+                        d.latest.clone(),
+                        format!("{} `{}`", display_path(&d.manifest), d.key.join(".")),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            table(
+                f,
+                ["dependency", "requirement", "latest", "declared in"],
+                &rows,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write `rows` under `header` with every column but the last padded to
+/// its widest cell.
+fn table<const N: usize>(
+    f: &mut fmt::Formatter<'_>,
+    header: [&str; N],
+    rows: &[[String; N]],
+) -> fmt::Result {
+    let widths: [usize; N] = std::array::from_fn(|column| {
+        rows.iter()
+            .map(|row| row[column].len())
+            .chain([header[column].len()])
+            .max()
+            .unwrap_or_default()
+    });
+
+    let header = header.map(str::to_string);
+    for row in std::iter::once(&header).chain(rows) {
+        let mut line = String::new();
+        for (column, cell) in row.iter().enumerate() {
+            if column + 1 < N {
+                line.push_str(&format!("{cell:<width$}  ", width = widths[column]));
+            } else {
+                line.push_str(cell);
+            }
+        }
+        writeln!(f, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1037 @@
+use std::ops::Range;
+
+use anyhow::Context;
+use toml_edit::{Document, DocumentMut, Item, TableLike, Value};
+
+use crate::dependencies::GitReference;
+
+/// Set the string value at `key` (a path of table keys) to `value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ValueEdit {
+    pub key: Vec<String>,
+    pub value: String,
+}
+
+impl ValueEdit {
+    pub fn new<K: Into<String>>(
+        key: impl IntoIterator<Item = K>,
+        value: impl Into<String>,
+    ) -> Self {
+        Self {
+            key: key.into_iter().map(Into::into).collect(),
+            value: value.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Patch {
+    range: Range<usize>,
+    replacement: String,
+}
+
+/// Apply `edits` to `source`, touching only the bytes of the changed values.
+///
+/// Values that already exist are replaced in place by splicing the new literal
+/// over the old one, keeping its quoting style. Keys that don't exist yet (for
+/// example a `version` added to a path dependency) are inserted through
+/// `toml_edit`, which still preserves the surrounding formatting.
+pub(crate) fn apply_edits(source: &str, edits: &[ValueEdit]) -> anyhow::Result<String> {
+    let document = Document::parse(source).context("failed to parse manifest for editing")?;
+
+    let mut patches = Vec::new();
+    let mut inserts = Vec::new();
+    for edit in edits {
+        let key = resolve_key(document.as_table(), &edit.key);
+        match locate(document.as_item(), &key) {
+            Some(range) => {
+                let replacement = string_literal(&source[range.clone()], &edit.value);
+                patches.push(Patch { range, replacement });
+            }
+            None => inserts.push((key, &edit.value)),
+        }
+    }
+
+    let patched = splice(source, patches)?;
+    if inserts.is_empty() {
+        return Ok(patched);
+    }
+
+    let mut document: DocumentMut = patched
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    for (key, value) in inserts {
+        insert(&mut document, &key, value)?;
+    }
+
+    Ok(document.to_string())
+}
+
+/// Map `key` onto the spelling used by `document`: `package.*` keys onto
+/// manifests that predate the `[package]` header (which cargo still accepts as
+/// `[project]` or as top-level keys), and dependency tables onto their legacy
+/// underscore names.
+fn resolve_key(document: &toml_edit::Table, key: &[String]) -> Vec<String> {
+    let mut key = match key.split_first() {
+        Some((first, rest)) if first == "package" && !document.contains_key("package") => {
+            if document.contains_key("project") {
+                std::iter::once("project".to_string())
+                    .chain(rest.iter().cloned())
+                    .collect()
+            } else {
+                rest.to_vec()
+            }
+        }
+        _ => key.to_vec(),
+    };
+
+    let table_index = match key.first().map(String::as_str) {
+        Some("target") => 2,
+        _ => 0,
+    };
+    let parent = key[..table_index]
+        .iter()
+        .try_fold(document as &dyn TableLike, |table, k| {
+            table.get(k)?.as_table_like()
+        });
+    if let (Some(parent), Some(table)) = (parent, key.get(table_index)) {
+        let alias = table.replace('-', "_");
+        if !parent.contains_key(table) && parent.contains_key(&alias) {
+            key[table_index] = alias;
+        }
+    }
+
+    key
+}
+
+fn locate(item: &Item, key: &[String]) -> Option<Range<usize>> {
+    let mut item = item;
+    for k in key {
+        item = item.as_table_like()?.get(k)?;
+    }
+
+    item.as_str()?;
+    item.span()
+}
+
+fn insert(document: &mut DocumentMut, key: &[String], value: &str) -> anyhow::Result<()> {
+    let (last, parents) = key.split_last().context("cannot edit an empty key")?;
+
+    let mut item = document.as_item_mut();
+    for k in parents {
+        item = item
+            .as_table_like_mut()
+            .and_then(|table| table.get_mut(k))
+            .with_context(|| format!("`{}` is not a table", key.join(".")))?;
+    }
+
+    match item {
+        Item::Value(Value::InlineTable(table)) => {
+            // Keep the padding before the closing brace after the new entry
+            // instead of between the old last entry and its comma.
+            let suffix = table
+                .iter_mut()
+                .last()
+                .and_then(|(_, last)| {
+                    let suffix = last.decor().suffix()?.as_str()?.to_string();
+                    last.decor_mut().set_suffix("");
+                    Some(suffix)
+                })
+                .unwrap_or_default();
+            table.insert(last, Value::from(value).decorated(" ", suffix));
+        }
+        item => {
+            item.as_table_like_mut()
+                .with_context(|| format!("`{}` is not a table", key.join(".")))?
+                .insert(last, toml_edit::value(value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `source` has a value at `key`.
+pub(crate) fn contains_key(source: &str, key: &[String]) -> anyhow::Result<bool> {
+    let document = Document::parse(source).context("failed to parse manifest for editing")?;
+
+    Ok(get(&document, key).is_some())
+}
+
+/// Whether the value at `key` inherits from the workspace, as
+/// `edition.workspace = true` does.
+pub(crate) fn is_inherited(source: &str, key: &[String]) -> anyhow::Result<bool> {
+    let document = Document::parse(source).context("failed to parse manifest for editing")?;
+
+    Ok(get(&document, key)
+        .and_then(Item::as_table_like)
+        .is_some_and(|table| table.contains_key("workspace")))
+}
+
+/// The string at `key` in `source`, if there is one.
+pub(crate) fn get_str(source: &str, key: &[String]) -> anyhow::Result<Option<String>> {
+    let document = Document::parse(source).context("failed to parse manifest for editing")?;
+
+    Ok(get(&document, key)
+        .and_then(Item::as_str)
+        .map(str::to_string))
+}
+
+fn get<'d>(document: &'d Document<&str>, key: &[String]) -> Option<&'d Item> {
+    resolve_key(document.as_table(), key)
+        .iter()
+        .try_fold(document.as_item(), |item, k| item.as_table_like()?.get(k))
+}
+
+/// Remove the entries at `keys` (paths of table keys) from `source`. Keys
+/// that don't exist are ignored.
+pub(crate) fn remove_keys(source: &str, keys: &[Vec<String>]) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+
+    for key in keys {
+        let key = resolve_key(document.as_table(), key);
+        let Some((last, parents)) = key.split_last() else {
+            continue;
+        };
+        let table = parents
+            .iter()
+            .try_fold(document.as_item_mut(), |item, k| {
+                item.as_table_like_mut()?.get_mut(k)
+            })
+            .and_then(Item::as_table_like_mut);
+        if let Some(table) = table {
+            table.remove(last);
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Remove each of the tables at `keys` that is empty, in order, so removing
+/// a table's last entry can take its parents with it.
+pub(crate) fn remove_empty_tables(source: &str, keys: &[Vec<String>]) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+
+    for key in keys {
+        let Some((last, parents)) = key.split_last() else {
+            continue;
+        };
+        let Some(table) = parents
+            .iter()
+            .try_fold(document.as_item_mut(), |item, k| {
+                item.as_table_like_mut()?.get_mut(k)
+            })
+            .and_then(Item::as_table_like_mut)
+        else {
+            continue;
+        };
+        if table
+            .get(last)
+            .and_then(Item::as_table_like)
+            .is_some_and(|table| table.is_empty())
+        {
+            table.remove(last);
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Merge `entries` into the table at `key`, creating it and its parents as
+/// needed. Tables in both are merged key by key; any other entry of
+/// `entries` replaces the one in `source`, and entries only in `source` are
+/// kept.
+pub(crate) fn merge_table(
+    source: &str,
+    key: &[String],
+    entries: &toml_edit::Table,
+) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+
+    let mut item = document.as_item_mut();
+    for k in key {
+        item = item
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` is not a table", key.join(".")))?
+            .entry(k)
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            });
+    }
+    let table = item
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a table", key.join(".")))?;
+    merge_into(table, entries);
+
+    Ok(document.to_string())
+}
+
+fn merge_into(table: &mut dyn TableLike, entries: &dyn TableLike) {
+    for (k, entry) in entries.iter() {
+        match (table.get_mut(k), entry.as_table_like()) {
+            (Some(existing), Some(entries)) if existing.is_table_like() => {
+                if let Some(existing) = existing.as_table_like_mut() {
+                    merge_into(existing, entries);
+                }
+            }
+            (Some(Item::Value(existing)), None) => {
+                if let Some(value) = entry.as_value() {
+                    let decor = existing.decor().clone();
+                    *existing = value.clone();
+                    *existing.decor_mut() = decor;
+                }
+            }
+            _ => {
+                table.insert(k, entry.clone());
+            }
+        }
+    }
+}
+
+/// Set the string entries of the table at `table`, creating it and its
+/// parents as needed.
+pub(crate) fn set_table_strings(
+    source: &str,
+    table: &[&str],
+    entries: &[(&str, &str)],
+) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let key = resolve_key(
+        document.as_table(),
+        &table.iter().map(|k| k.to_string()).collect::<Vec<_>>(),
+    );
+
+    let mut item = document.as_item_mut();
+    for k in &key {
+        let parent = item
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` is not a table", key.join(".")))?;
+        item = parent.entry(k).or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+    }
+    let target = item
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a table", key.join(".")))?;
+    for (k, v) in entries {
+        match target.get_mut(k) {
+            Some(Item::Value(value)) => {
+                let decor = value.decor().clone();
+                *value = Value::from(*v);
+                *value.decor_mut() = decor;
+            }
+            _ => {
+                target.insert(k, toml_edit::value(*v));
+            }
+        }
+    }
+    if let Some(table) = item.as_table_mut() {
+        table.set_implicit(false);
+    }
+
+    Ok(document.to_string())
+}
+
+/// Set the value at `key` to `value`, creating the tables above it as needed
+/// and keeping the comments around an existing value.
+pub(crate) fn set_value(source: &str, key: &[String], value: Value) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let key = resolve_key(document.as_table(), key);
+    let (last, parents) = key.split_last().context("cannot edit an empty key")?;
+
+    let mut item = document.as_item_mut();
+    for k in parents {
+        item = item
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` is not a table", key.join(".")))?
+            .entry(k)
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            });
+    }
+    let table = item
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a table", key.join(".")))?;
+    match table.get_mut(last) {
+        Some(Item::Value(existing)) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        Some(Item::None) | None => {
+            table.insert(last, toml_edit::value(value));
+        }
+        Some(_) => crate::error::bail!("`{}` is a table, not a value", key.join(".")),
+    }
+
+    Ok(document.to_string())
+}
+
+/// Set `key` of the `[[array]]` table named `name`, e.g. the `path` of a
+/// `[[bin]]`, keeping the comments around an existing value, or remove it
+/// for `None`. Returns `None` if no such table exists.
+pub(crate) fn set_named_table_value(
+    source: &str,
+    array: &str,
+    name: &str,
+    key: &str,
+    value: Option<Value>,
+) -> anyhow::Result<Option<String>> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let table = document
+        .get_mut(array)
+        .and_then(Item::as_array_of_tables_mut)
+        .and_then(|tables| {
+            tables
+                .iter_mut()
+                .find(|table| table.get("name").and_then(Item::as_str) == Some(name))
+        });
+    let Some(table) = table else {
+        return Ok(None);
+    };
+
+    match (table.get_mut(key), value) {
+        (Some(Item::Value(existing)), Some(value)) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        (_, Some(value)) => {
+            table.insert(key, toml_edit::value(value));
+        }
+        (_, None) => {
+            table.remove(key);
+        }
+    }
+
+    Ok(Some(document.to_string()))
+}
+
+/// Make the dependency at `key` inherit from `[workspace.dependencies]`,
+/// keeping its `features` and `optional`. Returns the new source and the
+/// declaration to add to the workspace, without those two keys.
+pub(crate) fn inherit_dependency(source: &str, key: &[String]) -> anyhow::Result<(String, Value)> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let key = resolve_key(document.as_table(), key);
+    let (name, parents) = key.split_last().context("cannot edit an empty key")?;
+    let table = parents
+        .iter()
+        .try_fold(document.as_item_mut(), |item, k| {
+            item.as_table_like_mut()?.get_mut(k)
+        })
+        .and_then(Item::as_table_like_mut)
+        .with_context(|| format!("`{}` is not a table", parents.join(".")))?;
+    let item = table
+        .get_mut(name)
+        .with_context(|| format!("`{}` is not set", key.join(".")))?;
+
+    let mut inherited = toml_edit::InlineTable::new();
+    inherited.insert("workspace", true.into());
+    let workspace = match item.as_table_like() {
+        Some(declaration) => {
+            let mut workspace = toml_edit::InlineTable::new();
+            for (k, v) in declaration.iter() {
+                let Some(v) = v.as_value() else {
+                    continue;
+                };
+                let v = v.clone().decorated(" ", " ");
+                if matches!(k, "features" | "optional") {
+                    inherited.insert(k, v);
+                } else {
+                    workspace.insert(k, v);
+                }
+            }
+            anyhow::ensure!(
+                !workspace.contains_key("path"),
+                "`{}` is a path dependency",
+                key.join(".")
+            );
+            match (workspace.len(), workspace.get("version")) {
+                (1, Some(version)) => version.clone(),
+                _ => Value::InlineTable(workspace),
+            }
+        }
+        None => item
+            .as_value()
+            .with_context(|| format!("`{}` is not a dependency", key.join(".")))?
+            .clone(),
+    };
+    inherited.fmt();
+
+    match item {
+        Item::Value(value) => {
+            let decor = value.decor().clone();
+            *value = Value::InlineTable(inherited);
+            *value.decor_mut() = decor;
+        }
+        item => *item = toml_edit::value(inherited),
+    }
+
+    Ok((document.to_string(), workspace.decorated(" ", "")))
+}
+
+/// Add `name = value` to `[workspace.dependencies]`, creating the table if
+/// needed. An existing entry is kept.
+pub(crate) fn add_workspace_dependency(
+    source: &str,
+    name: &str,
+    value: Value,
+) -> anyhow::Result<String> {
+    let key = ["workspace".to_string(), "dependencies".to_string()];
+    set_dependency(source, &key, name, value, false)
+}
+
+/// Set `name = value` in the dependency table at `table`, creating the table
+/// if needed. An existing entry is replaced only if `replace` is set.
+pub(crate) fn set_dependency(
+    source: &str,
+    table: &[String],
+    name: &str,
+    value: Value,
+    replace: bool,
+) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let table = resolve_key(document.as_table(), table);
+
+    let (last, parents) = table.split_last().context("cannot edit an empty key")?;
+    let mut item = document.as_item_mut();
+    for k in parents {
+        item = item
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` is not a table", table.join(".")))?
+            .entry(k)
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            });
+    }
+    let dependencies = item
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a table", table.join(".")))?
+        .entry(last)
+        .or_insert_with(toml_edit::table)
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a table", table.join(".")))?;
+
+    match dependencies.get_mut(name) {
+        Some(existing) if replace => *existing = Item::Value(value),
+        Some(_) => {}
+        None => {
+            dependencies.insert(name, Item::Value(value));
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Enable `add` and disable `remove` in the `features` of the dependency at
+/// `key`, and set its `default-features`. A plain requirement string becomes
+/// an inline table if it needs any of those; an emptied `features` is
+/// removed.
+pub(crate) fn edit_features(
+    source: &str,
+    key: &[String],
+    add: &[String],
+    remove: &[String],
+    default_features: Option<bool>,
+) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let key = resolve_key(document.as_table(), key);
+    let item = key
+        .iter()
+        .try_fold(document.as_item_mut(), |item, k| {
+            item.as_table_like_mut()?.get_mut(k)
+        })
+        .with_context(|| format!("`{}` doesn't exist", key.join(".")))?;
+
+    if let Some(requirement) = item.as_str() {
+        if add.is_empty() && default_features.is_none() {
+            return Ok(document.to_string());
+        }
+        let mut table = toml_edit::InlineTable::new();
+        table.insert("version", requirement.into());
+        let decor = item
+            .as_value()
+            .map(|v| v.decor().clone())
+            .unwrap_or_default();
+        let mut value = Value::InlineTable(table);
+        *value.decor_mut() = decor;
+        *item = Item::Value(value);
+    }
+    let dependency = item
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a dependency", key.join(".")))?;
+
+    let mut features = dependency
+        .get("features")
+        .and_then(Item::as_array)
+        .cloned()
+        .unwrap_or_default();
+    features.retain(|f| !f.as_str().is_some_and(|f| remove.iter().any(|r| r == f)));
+    for feature in add {
+        if !features.iter().any(|f| f.as_str() == Some(feature)) {
+            features.push(feature.as_str());
+        }
+    }
+    features.fmt();
+    if features.is_empty() {
+        dependency.remove("features");
+    } else if let Some(Item::Value(Value::Array(existing))) = dependency.get_mut("features") {
+        let decor = existing.decor().clone();
+        *existing = features;
+        *existing.decor_mut() = decor;
+    } else {
+        dependency.insert("features", toml_edit::value(features));
+    }
+
+    // Inherited declarations can't change their default features.
+    if let (Some(enabled), false) = (default_features, dependency.contains_key("workspace")) {
+        let key = if dependency.contains_key("default_features") {
+            "default_features"
+        } else {
+            "default-features"
+        };
+        match dependency.get_mut(key) {
+            Some(Item::Value(value)) => {
+                let decor = value.decor().clone();
+                *value = enabled.into();
+                *value.decor_mut() = decor;
+            }
+            _ => {
+                dependency.insert(key, toml_edit::value(enabled));
+            }
+        }
+    }
+    if let Some(table) = item.as_inline_table_mut() {
+        table.fmt();
+    }
+
+    Ok(document.to_string())
+}
+
+/// Point the git dependency at `key` at `reference`, removing the other of
+/// `rev`, `branch` and `tag`, and keeping the comments around a reference of
+/// the same kind.
+pub(crate) fn set_git_reference(
+    source: &str,
+    key: &[String],
+    reference: &GitReference,
+) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let key = resolve_key(document.as_table(), key);
+    let item = key
+        .iter()
+        .try_fold(document.as_item_mut(), |item, k| {
+            item.as_table_like_mut()?.get_mut(k)
+        })
+        .filter(|item| item.get("git").is_some())
+        .with_context(|| format!("`{}` is not a git dependency", key.join(".")))?;
+    let dependency = item
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a git dependency", key.join(".")))?;
+
+    for kind in ["rev", "branch", "tag"] {
+        if kind != reference.key() {
+            dependency.remove(kind);
+            continue;
+        }
+        match dependency.get_mut(kind) {
+            Some(Item::Value(existing)) => {
+                let decor = existing.decor().clone();
+                *existing = reference.value().into();
+                *existing.decor_mut() = decor;
+            }
+            _ => {
+                dependency.insert(kind, toml_edit::value(reference.value()));
+            }
+        }
+    }
+    if let Some(table) = item.as_inline_table_mut() {
+        table.fmt();
+    }
+
+    Ok(document.to_string())
+}
+
+/// Add the strings `add` to the array at `key`, creating it and the tables
+/// above it as needed, and take the strings `remove` out of it. New items
+/// are laid out like the last one, so a multi-line array keeps one item per
+/// line.
+pub(crate) fn edit_string_array(
+    source: &str,
+    key: &[String],
+    add: &[String],
+    remove: &[String],
+) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let key = resolve_key(document.as_table(), key);
+    let (last, parents) = key.split_last().context("cannot edit an empty key")?;
+
+    let mut item = document.as_item_mut();
+    for k in parents {
+        item = item
+            .as_table_like_mut()
+            .with_context(|| format!("`{}` is not a table", key.join(".")))?
+            .entry(k)
+            .or_insert_with(|| {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            });
+    }
+    let array = item
+        .as_table_like_mut()
+        .with_context(|| format!("`{}` is not a table", key.join(".")))?
+        .entry(last)
+        .or_insert_with(|| toml_edit::value(toml_edit::Array::new()))
+        .as_array_mut()
+        .with_context(|| format!("`{}` is not an array", key.join(".")))?;
+    let multi_line = array.iter().any(|value| {
+        value
+            .decor()
+            .prefix()
+            .and_then(|prefix| prefix.as_str())
+            .is_some_and(|prefix| prefix.contains('\n'))
+    });
+
+    array.retain(|value| {
+        !value
+            .as_str()
+            .is_some_and(|v| remove.iter().any(|r| r == v))
+    });
+    for value in add {
+        if array
+            .iter()
+            .any(|existing| existing.as_str() == Some(value))
+        {
+            continue;
+        }
+        // The indentation of the last item, without the comments above it.
+        let prefix = array
+            .iter()
+            .last()
+            .and_then(|last| last.decor().prefix()?.as_str())
+            .and_then(|prefix| Some(format!("\n{}", &prefix[prefix.rfind('\n')? + 1..])));
+        match prefix {
+            Some(prefix) => array.push_formatted(Value::from(value.as_str()).decorated(prefix, "")),
+            None => array.push(value.as_str()),
+        }
+    }
+    if !multi_line {
+        array.fmt();
+    }
+
+    Ok(document.to_string())
+}
+
+/// Rename the entries at `keys` (paths of table keys) to the paired names,
+/// touching only the bytes of the keys. Keys that don't exist are ignored.
+///
+/// An entry declared with dotted keys (`a.path = ".."`, `a.version = ".."`)
+/// spells its key once per line, and each of them is renamed.
+pub(crate) fn rename_keys(source: &str, keys: &[(Vec<String>, String)]) -> anyhow::Result<String> {
+    let document = Document::parse(source).context("failed to parse manifest for editing")?;
+
+    let mut patches = Vec::new();
+    for (key, name) in keys {
+        let key = resolve_key(document.as_table(), key);
+        let Some((last, parents)) = key.split_last() else {
+            continue;
+        };
+        let Some((key, item)) = parents
+            .iter()
+            .try_fold(document.as_item(), |item, k| item.as_table_like()?.get(k))
+            .and_then(Item::as_table_like)
+            .and_then(|table| table.get_key_value(last))
+        else {
+            continue;
+        };
+        let replacement = toml_edit::Key::new(name.as_str())
+            .display_repr()
+            .to_string();
+        let mut ranges: Vec<_> = key.span().into_iter().collect();
+        dotted_key_spans(source, item, 1, &mut ranges);
+        patches.extend(ranges.into_iter().map(|range| Patch {
+            range,
+            replacement: replacement.clone(),
+        }));
+    }
+
+    splice(source, patches)
+}
+
+/// Collect the spans of the key of `item`, `depth` segments before the keys
+/// of its entries, if it's a dotted table: the parser keeps the span of its
+/// first spelling only.
+fn dotted_key_spans(source: &str, item: &Item, depth: usize, ranges: &mut Vec<Range<usize>>) {
+    let Some(table) = item.as_table_like().filter(|table| table.is_dotted()) else {
+        return;
+    };
+    for (key, _) in table.iter() {
+        let Some((key, item)) = table.get_key_value(key) else {
+            continue;
+        };
+        if item.as_table_like().is_some_and(TableLike::is_dotted) {
+            dotted_key_spans(source, item, depth + 1, ranges);
+        } else if let Some(span) = key.span() {
+            ranges.extend(
+                (0..depth)
+                    .try_fold(span, |span, _| previous_key_segment(source, span.start))
+                    .filter(|range| !ranges.contains(range)),
+            );
+        }
+    }
+}
+
+/// The span of the key segment before the `.` ahead of byte `end`.
+fn previous_key_segment(source: &str, end: usize) -> Option<Range<usize>> {
+    let before = source[..end].trim_end_matches([' ', '\t']);
+    let before = before.strip_suffix('.')?.trim_end_matches([' ', '\t']);
+    let start = match before.chars().last()? {
+        quote @ ('"' | '\'') => {
+            let inner = &before[..before.len() - 1];
+            let mut start = inner.rfind(quote)?;
+            while quote == '"' && inner[..start].ends_with('\\') {
+                start = inner[..start].rfind(quote)?;
+            }
+            start
+        }
+        _ => before
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .map_or(0, |i| i + 1),
+    };
+    Some(start..before.len())
+}
+
+/// Point the `[features]` of `source` at the dependency key `new` instead of
+/// `old`: `old/feature`, `old?/feature` and `dep:old`.
+pub(crate) fn rename_feature_references(
+    source: &str,
+    old: &str,
+    new: &str,
+) -> anyhow::Result<String> {
+    let mut document: DocumentMut = source
+        .parse()
+        .context("failed to parse manifest for editing")?;
+    let Some(features) = document
+        .get_mut("features")
+        .and_then(Item::as_table_like_mut)
+    else {
+        return Ok(source.to_string());
+    };
+
+    let mut changed = false;
+    for (_, enables) in features.iter_mut() {
+        let Some(enables) = enables.as_array_mut() else {
+            continue;
+        };
+        for value in enables.iter_mut() {
+            let Some(feature) = value.as_str() else {
+                continue;
+            };
+            let renamed = if feature == format!("dep:{old}") {
+                format!("dep:{new}")
+            } else if let Some(rest) = feature
+                .strip_prefix(old)
+                .filter(|rest| rest.starts_with('/') || rest.starts_with("?/"))
+            {
+                format!("{new}{rest}")
+            } else {
+                continue;
+            };
+            let decor = value.decor().clone();
+            *value = renamed.into();
+            *value.decor_mut() = decor;
+            changed = true;
+        }
+    }
+
+    Ok(if changed {
+        document.to_string()
+    } else {
+        source.to_string()
+    })
+}
+
+/// Render `value` as a TOML string, reusing the quote style of `original`.
+fn string_literal(original: &str, value: &str) -> String {
+    let literal_safe = !value.contains(['\'', '\n', '\r']);
+    if original.starts_with('\'') && !original.starts_with("'''") && literal_safe {
+        return format!("'{value}'");
+    }
+
+    toml_edit::Value::from(value).decorated("", "").to_string()
+}
+
+fn splice(source: &str, mut patches: Vec<Patch>) -> anyhow::Result<String> {
+    patches.sort_by_key(|p| p.range.start);
+    patches.dedup_by(|later, earlier| later.range == earlier.range);
+
+    let mut output = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for patch in patches {
+        anyhow::ensure!(
+            patch.range.start >= cursor,
+            "overlapping edits at byte {}",
+            patch.range.start
+        );
+        output.push_str(&source[cursor..patch.range.start]);
+        output.push_str(&patch.replacement);
+        cursor = patch.range.end;
+    }
+    output.push_str(&source[cursor..]);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn patches_only_the_changed_values() -> anyhow::Result<()> {
+        let source = r#"# root manifest
+[package]
+name = "root"   # the name
+version = "0.1.0" # bumped by cargo-set
+
+[dependencies]
+child = { path = "child", version = '0.2.0' }
+other.version = "0.1.0"
+"#;
+
+        let patched = apply_edits(
+            source,
+            &[
+                ValueEdit::new(["package", "version"], "0.2.0"),
+                ValueEdit::new(["dependencies", "child", "version"], "0.3.0"),
+                ValueEdit::new(["dependencies", "other", "version"], "0.4.0"),
+            ],
+        )?;
+
+        assert_eq!(
+            patched,
+            r#"# root manifest
+[package]
+name = "root"   # the name
+version = "0.2.0" # bumped by cargo-set
+
+[dependencies]
+child = { path = "child", version = '0.3.0' }
+other.version = "0.4.0"
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn patches_headerless_package() -> anyhow::Result<()> {
+        let patched = apply_edits(
+            "name = 'child'\nversion = '0.2.0'",
+            &[ValueEdit::new(["package", "version"], "0.3.0")],
+        )?;
+
+        assert_eq!(patched, "name = 'child'\nversion = '0.3.0'");
+
+        Ok(())
+    }
+
+    #[test]
+    fn patches_legacy_table_names() -> anyhow::Result<()> {
+        let patched = apply_edits(
+            "[dev_dependencies]\nchild = '0.2.0'\n[target.'cfg(unix)'.build_dependencies]\nchild = '0.2.0'",
+            &[
+                ValueEdit::new(["dev-dependencies", "child"], "0.3.0"),
+                ValueEdit::new(["target", "cfg(unix)", "build-dependencies", "child"], "0.3.0"),
+            ],
+        )?;
+
+        assert_eq!(
+            patched,
+            "[dev_dependencies]\nchild = '0.3.0'\n[target.'cfg(unix)'.build_dependencies]\nchild = '0.3.0'"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn inserts_missing_values() -> anyhow::Result<()> {
+        let source = "[dependencies]\nchild = { path = \"child\" } # local\n";
+
+        let patched = apply_edits(
+            source,
+            &[ValueEdit::new(
+                ["dependencies", "child", "version"],
+                "0.3.0",
+            )],
+        )?;
+
+        assert_eq!(
+            patched,
+            "[dependencies]\nchild = { path = \"child\", version = \"0.3.0\" } # local\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn renames_every_spelling_of_a_dotted_key() -> anyhow::Result<()> {
+        let source = r#"[dependencies]
+a.path = "../a"
+a . version = "0.1" # pinned
+"a".features.default = false
+other = "1"
+dependencies.a.optional = true
+"#;
+
+        let renamed = rename_keys(
+            source,
+            &[(
+                vec!["dependencies".to_string(), "a".to_string()],
+                "zz".to_string(),
+            )],
+        )?;
+
+        assert_eq!(
+            renamed,
+            r#"[dependencies]
+zz.path = "../a"
+zz . version = "0.1" # pinned
+zz.features.default = false
+other = "1"
+dependencies.a.optional = true
+"#
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf, Prefix};
+
+/// Render `path` for reports and error messages with `/` separators on every
+/// platform, so output is identical on Windows, macOS and Linux.
+///
+/// Parts that aren't valid Unicode are escaped (`\xFF` for bytes on Unix,
+/// `\u{D800}` for unpaired surrogates on Windows) rather than replaced, so no
+/// information is lost.
+pub fn display_path(path: &Path) -> String {
+    let display = escape(path.as_os_str());
+    if std::path::MAIN_SEPARATOR == '/' {
+        display
+    } else {
+        display.replace(std::path::MAIN_SEPARATOR, "/")
+    }
+}
+
+/// The directory of the workspace rooted at `root_manifest`: its parent, or
+/// `.` for a bare `Cargo.toml`.
+pub fn workspace_dir(root_manifest: &Path) -> &Path {
+    match root_manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+#[cfg(unix)]
+fn escape(s: &OsStr) -> String {
+    use std::fmt::Write;
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut escaped = String::new();
+    for chunk in s.as_bytes().utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            write!(escaped, "\\x{byte:02X}").unwrap();
+        }
+    }
+
+    escaped
+}
+
+#[cfg(windows)]
+fn escape(s: &OsStr) -> String {
+    use std::os::windows::ffi::OsStrExt;
+
+    char::decode_utf16(s.encode_wide())
+        .map(|c| match c {
+            Ok(c) => c.to_string(),
+            Err(e) => format!("\\u{{{:04X}}}", e.unpaired_surrogate()),
+        })
+        .collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn escape(s: &OsStr) -> String {
+    s.to_string_lossy().into_owned()
+}
+
+/// Lossless serde representation of paths: a plain string when the path is
+/// valid Unicode, otherwise its raw bytes (Unix) or UTF-16 units (Windows).
+pub(crate) mod serde_path {
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Encoded {
+        Unicode(String),
+        Bytes { bytes: Vec<u8> },
+        Wide { wide: Vec<u16> },
+    }
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        let encoded = match path.to_str() {
+            Some(s) => Encoded::Unicode(s.to_string()),
+            None => encode(path.as_os_str()).map_err(serde::ser::Error::custom)?,
+        };
+
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        let os_string = match Encoded::deserialize(deserializer)? {
+            Encoded::Unicode(s) => OsString::from(s),
+            encoded => decode(encoded).map_err(serde::de::Error::custom)?,
+        };
+
+        Ok(PathBuf::from(os_string))
+    }
+
+    #[cfg(unix)]
+    fn encode(s: &std::ffi::OsStr) -> Result<Encoded, &'static str> {
+        use std::os::unix::ffi::OsStrExt;
+
+        Ok(Encoded::Bytes {
+            bytes: s.as_bytes().to_vec(),
+        })
+    }
+
+    #[cfg(windows)]
+    fn encode(s: &std::ffi::OsStr) -> Result<Encoded, &'static str> {
+        use std::os::windows::ffi::OsStrExt;
+
+        Ok(Encoded::Wide {
+            wide: s.encode_wide().collect(),
+        })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn encode(_: &std::ffi::OsStr) -> Result<Encoded, &'static str> {
+        Err("path is not valid unicode")
+    }
+
+    fn decode(encoded: Encoded) -> Result<OsString, &'static str> {
+        match encoded {
+            Encoded::Unicode(s) => Ok(OsString::from(s)),
+            #[cfg(unix)]
+            Encoded::Bytes { bytes } => {
+                use std::os::unix::ffi::OsStringExt;
+                Ok(OsString::from_vec(bytes))
+            }
+            #[cfg(windows)]
+            Encoded::Wide { wide } => {
+                use std::os::windows::ffi::OsStringExt;
+                Ok(OsString::from_wide(&wide))
+            }
+            _ => Err("path was written on a different platform"),
+        }
+    }
+}
+
+/// Lexically clean up `path` so equal locations compare and print equally.
+///
+/// Separators become native, `.` components are dropped and `..` components
+/// cancel the preceding directory where there is one. On Windows, verbatim
+/// prefixes as returned by `canonicalize` (`\\?\C:\`, `\\?\UNC\server\share`)
+/// are reduced to their plain forms and drive letters are upper-cased.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Disk(drive) | Prefix::VerbatimDisk(drive) => {
+                    normalized.push(format!("{}:", drive.to_ascii_uppercase() as char));
+                }
+                Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => {
+                    normalized.push(format!(
+                        r"\\{}\{}",
+                        server.to_string_lossy(),
+                        share.to_string_lossy()
+                    ));
+                }
+                _ => normalized.push(prefix.as_os_str()),
+            },
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Whether the resolved `path` lies inside the resolved directory `root`.
+pub(crate) fn is_within(path: &Path, root: &Path) -> bool {
+    path.starts_with(root) && !path.components().any(|c| c == Component::ParentDir)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn uses_forward_slashes() {
+        let path = PathBuf::from("crates").join("foo").join("Cargo.toml");
+
+        assert_eq!(display_path(&path), "crates/foo/Cargo.toml");
+    }
+
+    #[test]
+    fn finds_the_workspace_directory() {
+        assert_eq!(workspace_dir(Path::new("Cargo.toml")), Path::new("."));
+        assert_eq!(workspace_dir(Path::new("a/Cargo.toml")), Path::new("a"));
+    }
+
+    #[test]
+    fn normalizes_dots() {
+        assert_eq!(
+            display_path(&normalize(Path::new("./crates/./foo/../bar/Cargo.toml"))),
+            "crates/bar/Cargo.toml"
+        );
+        assert_eq!(
+            display_path(&normalize(Path::new("../x/../../y"))),
+            "../../y"
+        );
+        assert_eq!(display_path(&normalize(Path::new("/../x"))), "/x");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn handles_non_utf8_paths() -> anyhow::Result<()> {
+        use std::os::unix::ffi::OsStrExt;
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Wrapper(#[serde(with = "serde_path")] PathBuf);
+
+        let path = PathBuf::from(OsStr::from_bytes(b"caf\xE9/Cargo.toml"));
+        assert_eq!(display_path(&path), "caf\\xE9/Cargo.toml");
+
+        let json = serde_json::to_string(&Wrapper(path.clone()))?;
+        assert_eq!(serde_json::from_str::<Wrapper>(&json)?, Wrapper(path));
+        assert_eq!(
+            serde_json::to_string(&Wrapper(PathBuf::from("crates/foo")))?,
+            "\"crates/foo\""
+        );
+
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn normalizes_windows_prefixes() {
+        assert_eq!(
+            normalize(Path::new(r"\\?\c:\ws\crates/foo\Cargo.toml")),
+            PathBuf::from(r"C:\ws\crates\foo\Cargo.toml")
+        );
+        assert_eq!(
+            normalize(Path::new(r"\\?\UNC\server\share\ws\Cargo.toml")),
+            PathBuf::from(r"\\server\share\ws\Cargo.toml")
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item, Value};
+
+use crate::paths::display_path;
+use crate::resume::FileWrite;
+
+/// The edits a set of operations would make, from
+/// [`CargoManifestService::plan`](crate::CargoManifestService::plan), to
+/// review before [`CargoManifestService::apply`](crate::CargoManifestService::apply)
+/// writes them. Serializes to JSON, e.g. for a pull request comment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeSet {
+    /// The changed values, file by file in path order.
+    pub edits: Vec<PlannedEdit>,
+    /// The manifests to write, with the contents they were planned from.
+    pub writes: Vec<FileWrite>,
+}
+
+/// A value the change set adds, changes or removes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedEdit {
+    #[serde(with = "crate::paths::serde_path")]
+    pub file: PathBuf,
+    /// The dotted name of the table the value is in, e.g. `dependencies`;
+    /// empty for the top level.
+    pub table: String,
+    /// The dotted key of the value within the table, e.g. `serde.version`.
+    pub key: String,
+    /// The value as TOML, `None` if the key is added.
+    pub old: Option<String>,
+    /// The value as TOML, `None` if the key is removed.
+    pub new: Option<String>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+}
+
+impl fmt::Display for ChangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.edits.is_empty() {
+            return write!(f, "nothing to change");
+        }
+        for (i, edit) in self.edits.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", display_path(&edit.file))?;
+            if !edit.table.is_empty() {
+                write!(f, " [{}]", edit.table)?;
+            }
+            write!(
+                f,
+                " {}: {} -> {}",
+                edit.key,
+                edit.old.as_deref().unwrap_or("(none)"),
+                edit.new.as_deref().unwrap_or("(none)")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The values that differ between `old` and `new`, the contents of `file`
+/// before and after a change; `old` is `None` for a new file.
+pub(crate) fn value_changes(
+    file: &Path,
+    old: Option<&str>,
+    new: &str,
+) -> anyhow::Result<Vec<PlannedEdit>> {
+    let parse = |source: &str| {
+        source
+            .parse::<DocumentMut>()
+            .with_context(|| format!("failed to parse {}", display_path(file)))
+    };
+    let mut old_values = BTreeMap::new();
+    if let Some(old) = old {
+        collect(parse(old)?.as_item(), &mut Vec::new(), 0, &mut old_values);
+    }
+    let mut new_values = BTreeMap::new();
+    collect(parse(new)?.as_item(), &mut Vec::new(), 0, &mut new_values);
+
+    let mut keys = old_values
+        .keys()
+        .chain(new_values.keys())
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+
+    Ok(keys
+        .into_iter()
+        .filter_map(|path| {
+            let old = old_values.get(path);
+            let new = new_values.get(path);
+            if old.map(|(_, value)| value) == new.map(|(_, value)| value) {
+                return None;
+            }
+            let (header, _) = new.or(old)?;
+            Some(PlannedEdit {
+                file: file.to_path_buf(),
+                table: path[..*header].join("."),
+                key: path[*header..].join("."),
+                old: old.map(|(_, value)| value.clone()),
+                new: new.map(|(_, value)| value.clone()),
+            })
+        })
+        .collect())
+}
+
+/// Every value under `item` by its key path, with how many segments of the
+/// path name the table its header declares.
+fn collect(
+    item: &Item,
+    path: &mut Vec<String>,
+    header: usize,
+    values: &mut BTreeMap<Vec<String>, (usize, String)>,
+) {
+    match item {
+        Item::Table(table) => {
+            let header = if table.is_dotted() {
+                header
+            } else {
+                path.len()
+            };
+            for (key, item) in table.iter() {
+                path.push(key.to_string());
+                collect(item, path, header, values);
+                path.pop();
+            }
+        }
+        Item::ArrayOfTables(tables) => {
+            for (i, table) in tables.iter().enumerate() {
+                path.push(i.to_string());
+                for (key, item) in table.iter() {
+                    path.push(key.to_string());
+                    collect(item, path, path.len() - 1, values);
+                    path.pop();
+                }
+                path.pop();
+            }
+        }
+        Item::Value(Value::InlineTable(table)) => {
+            for (key, value) in table.iter() {
+                path.push(key.to_string());
+                collect(&Item::Value(value.clone()), path, header, values);
+                path.pop();
+            }
+        }
+        Item::Value(value) => {
+            let mut value = value.clone();
+            value.decor_mut().clear();
+            values.insert(path.clone(), (header, value.to_string()));
+        }
+        Item::None => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lists_the_values_that_change() -> anyhow::Result<()> {
+        let old = "[package]\nname = 'b'\nversion = '0.1.0' # released\n\n[dependencies]\na = { path = '../a', version = '0.1' }\nlog = '0.4'\n\n[[bin]]\nname = 'b'\n";
+        let new = "[package]\nname = 'b'\nversion = '0.2.0' # released\n\n[dependencies]\na = { path = '../a', version = '0.2.0' }\n\n[[bin]]\nname = 'tool'\n";
+        let file = Path::new("b/Cargo.toml");
+
+        let edits = value_changes(file, Some(old), new)?;
+        let rows = edits
+            .iter()
+            .map(|edit| {
+                (
+                    edit.table.as_str(),
+                    edit.key.as_str(),
+                    edit.old.as_deref(),
+                    edit.new.as_deref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                ("bin.0", "name", Some("'b'"), Some("'tool'")),
+                ("dependencies", "a.version", Some("'0.1'"), Some("'0.2.0'")),
+                ("dependencies", "log", Some("'0.4'"), None),
+                ("package", "version", Some("'0.1.0'"), Some("'0.2.0'")),
+            ]
+        );
+        assert_eq!(
+            ChangeSet {
+                edits: edits[1..2].to_vec(),
+                writes: Vec::new(),
+            }
+            .to_string(),
+            "b/Cargo.toml [dependencies] a.version: '0.1' -> '0.2.0'"
+        );
+        assert_eq!(value_changes(file, Some(old), old)?, []);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::glob;
+use crate::paths::display_path;
+
+/// Rules version changes must follow, from the `[policy]` table of
+/// `.cargo-set.toml`. Every change of an update is checked before anything
+/// is written.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Policy {
+    /// Refuse major version bumps unless they are explicitly allowed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub major_requires_flag: bool,
+    /// Branches on which no crate may be set to a pre-release version.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub release_branches: Vec<String>,
+    /// Crates whose version may only change while the given file, relative
+    /// to the workspace root, exists.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub approval_files: BTreeMap<String, PathBuf>,
+    /// Crates owned elsewhere, by name or glob pattern, whose version,
+    /// manifest and declarations in other manifests must not change unless
+    /// protection is overridden.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected: Vec<String>,
+}
+
+/// What a policy is checked against besides the changes themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicyContext {
+    /// Major bumps were explicitly allowed, e.g. with `--allow-major`.
+    pub allow_major: bool,
+    /// The checked out git branch, if known.
+    pub branch: Option<String>,
+    /// Protected crates may change, e.g. with `--override-protection`.
+    pub override_protection: bool,
+}
+
+/// A version change the policy refuses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    pub package: String,
+    /// What was about to change, e.g. `1.2.0 -> 2.0.0`.
+    pub change: String,
+    pub reason: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.package, self.change, self.reason)
+    }
+}
+
+/// Returned when an update violates the policy; nothing was written.
+#[derive(Debug)]
+pub struct PolicyError {
+    pub violations: Vec<PolicyViolation>,
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} change(s) violate the policy, nothing was changed:",
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n  {violation}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+const PROTECTED: &str = "the crate is protected, pass `--override-protection` to change it";
+
+impl Policy {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The rules setting `package` from `from` to `to` breaks. `approved`
+    /// tells whether an approval file exists.
+    pub(crate) fn check(
+        &self,
+        context: &PolicyContext,
+        package: &str,
+        from: Option<&str>,
+        to: &str,
+        approved: impl Fn(&Path) -> bool,
+    ) -> Vec<PolicyViolation> {
+        let mut reasons = Vec::new();
+        let new = semver::Version::parse(to).ok();
+        let old = from.and_then(|from| semver::Version::parse(from).ok());
+
+        if let (Some(old), Some(new)) = (&old, &new) {
+            if self.major_requires_flag && !context.allow_major && new.major > old.major {
+                reasons.push("major bumps need `--allow-major`".to_string());
+            }
+        }
+        if let (Some(new), Some(branch)) = (&new, &context.branch) {
+            if !new.pre.is_empty() && self.release_branches.contains(branch) {
+                reasons.push(format!("pre-releases aren't allowed on `{branch}`"));
+            }
+        }
+        if self.is_protected(context, package) {
+            reasons.push(PROTECTED.to_string());
+        }
+        if let Some(file) = self.approval_files.get(package) {
+            if !approved(file) {
+                reasons.push(format!("needs the approval file {}", display_path(file)));
+            }
+        }
+
+        reasons
+            .into_iter()
+            .map(|reason| PolicyViolation {
+                package: package.to_string(),
+                change: format!("{} -> {to}", from.unwrap_or("(none)")),
+                reason,
+            })
+            .collect()
+    }
+
+    /// The violation of writing `manifest`, the manifest of `package`, if
+    /// the crate is protected.
+    pub(crate) fn check_write(
+        &self,
+        context: &PolicyContext,
+        package: &str,
+        manifest: &Path,
+    ) -> Option<PolicyViolation> {
+        self.is_protected(context, package)
+            .then(|| PolicyViolation {
+                package: package.to_string(),
+                change: format!("editing {}", display_path(manifest)),
+                reason: PROTECTED.to_string(),
+            })
+    }
+
+    /// The violation of editing the declaration of the dependency
+    /// `dependency` in `manifest`, if the crate is protected.
+    pub(crate) fn check_dependency_write(
+        &self,
+        context: &PolicyContext,
+        dependency: &str,
+        manifest: &Path,
+    ) -> Option<PolicyViolation> {
+        self.is_protected(context, dependency)
+            .then(|| PolicyViolation {
+                package: dependency.to_string(),
+                change: format!("editing its declaration in {}", display_path(manifest)),
+                reason: PROTECTED.to_string(),
+            })
+    }
+
+    fn is_protected(&self, context: &PolicyContext, package: &str) -> bool {
+        !context.override_protection
+            && self
+                .protected
+                .iter()
+                .any(|pattern| glob::matches_component(pattern, package))
+    }
+}
@@ -0,0 +1,186 @@
+use toml_edit::Value;
+
+/// The profiles cargo defines; every other profile needs `inherits`.
+pub(crate) const BUILTIN_PROFILES: [&str; 5] = ["dev", "release", "test", "bench", "doc"];
+
+/// What a profile setting may be set to.
+enum Kind {
+    Bool,
+    /// A positive integer.
+    Count,
+    /// A string.
+    Name,
+    /// Booleans and integers from `integers` as they are, or one of the
+    /// strings.
+    OneOf {
+        bool: bool,
+        integers: &'static [i64],
+        strings: &'static [&'static str],
+    },
+}
+
+const SETTINGS: [(&str, Kind); 13] = [
+    (
+        "opt-level",
+        Kind::OneOf {
+            bool: false,
+            integers: &[0, 1, 2, 3],
+            strings: &["s", "z"],
+        },
+    ),
+    (
+        "debug",
+        Kind::OneOf {
+            bool: true,
+            integers: &[0, 1, 2],
+            strings: &[
+                "none",
+                "line-tables-only",
+                "line-directives-only",
+                "limited",
+                "full",
+            ],
+        },
+    ),
+    (
+        "split-debuginfo",
+        Kind::OneOf {
+            bool: false,
+            integers: &[],
+            strings: &["off", "packed", "unpacked"],
+        },
+    ),
+    (
+        "strip",
+        Kind::OneOf {
+            bool: true,
+            integers: &[],
+            strings: &["none", "debuginfo", "symbols"],
+        },
+    ),
+    ("debug-assertions", Kind::Bool),
+    ("overflow-checks", Kind::Bool),
+    (
+        "lto",
+        Kind::OneOf {
+            bool: true,
+            integers: &[],
+            strings: &["fat", "thin", "off"],
+        },
+    ),
+    (
+        "panic",
+        Kind::OneOf {
+            bool: false,
+            integers: &[],
+            strings: &["unwind", "abort"],
+        },
+    ),
+    ("incremental", Kind::Bool),
+    ("codegen-units", Kind::Count),
+    ("rpath", Kind::Bool),
+    ("inherits", Kind::Name),
+    ("codegen-backend", Kind::Name),
+];
+
+/// The value of the profile setting `key` written as `value` on the command
+/// line, e.g. `lto` as `thin` or `opt-level` as `3`, checked against what
+/// cargo accepts for it.
+pub(crate) fn setting_value(key: &str, value: &str) -> anyhow::Result<Value> {
+    let Some((_, kind)) = SETTINGS.iter().find(|(name, _)| *name == key) else {
+        let known = SETTINGS.map(|(name, _)| format!("`{name}`")).join(", ");
+        crate::error::bail!("`{key}` is not a profile setting, use one of {known}");
+    };
+
+    let bool = value.parse::<bool>().ok();
+    let integer = value.parse::<i64>().ok();
+    let parsed = match kind {
+        Kind::Bool => bool.map(Value::from),
+        Kind::Count => integer.filter(|n| *n > 0).map(Value::from),
+        Kind::Name => (!value.is_empty()).then(|| Value::from(value)),
+        Kind::OneOf {
+            bool: takes_bool,
+            integers,
+            strings,
+        } => match (bool, integer) {
+            (Some(b), _) if *takes_bool => Some(Value::from(b)),
+            (_, Some(n)) if integers.contains(&n) => Some(Value::from(n)),
+            _ => strings.contains(&value).then(|| Value::from(value)),
+        },
+    };
+
+    parsed.ok_or_else(|| {
+        let expected = match kind {
+            Kind::Bool => "`true` or `false`".to_string(),
+            Kind::Count => "a positive number".to_string(),
+            Kind::Name => "a name".to_string(),
+            Kind::OneOf {
+                bool,
+                integers,
+                strings,
+            } => {
+                let bools: &[&str] = if *bool { &["true", "false"] } else { &[] };
+                let choices = bools
+                    .iter()
+                    .map(|b| format!("`{b}`"))
+                    .chain(integers.iter().map(|n| format!("`{n}`")))
+                    .chain(strings.iter().map(|s| format!("`{s}`")))
+                    .collect::<Vec<_>>();
+                match choices.split_last() {
+                    Some((last, [])) => last.clone(),
+                    Some((last, rest)) => format!("{} or {last}", rest.join(", ")),
+                    None => String::new(),
+                }
+            }
+        };
+        anyhow::anyhow!("`{key}` must be {expected}, not `{value}`")
+    })
+}
+
+/// Check that `name` can name a profile.
+pub(crate) fn check_name(name: &str) -> anyhow::Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+    if !valid {
+        crate::error::bail!("`{name}` is not a profile name, use letters, digits, `-` and `_`");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checks_settings_against_what_cargo_accepts() -> anyhow::Result<()> {
+        assert_eq!(setting_value("lto", "thin")?.to_string(), "\"thin\"");
+        assert_eq!(setting_value("lto", "true")?.to_string(), "true");
+        assert_eq!(setting_value("opt-level", "3")?.to_string(), "3");
+        assert_eq!(setting_value("opt-level", "z")?.to_string(), "\"z\"");
+        assert_eq!(setting_value("codegen-units", "1")?.to_string(), "1");
+        assert_eq!(
+            setting_value("strip", "symbols")?.to_string(),
+            "\"symbols\""
+        );
+
+        assert_eq!(
+            setting_value("opt-level", "4").unwrap_err().to_string(),
+            "`opt-level` must be `0`, `1`, `2`, `3`, `s` or `z`, not `4`"
+        );
+        assert_eq!(
+            setting_value("codegen-units", "0").unwrap_err().to_string(),
+            "`codegen-units` must be a positive number, not `0`"
+        );
+        assert!(setting_value("opt", "3")
+            .unwrap_err()
+            .to_string()
+            .starts_with("`opt` is not a profile setting"));
+        assert!(check_name("release-lto").is_ok());
+        assert!(check_name("release lto").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// The sparse index of crates.io.
+pub const CRATES_IO_INDEX: &str = "https://index.crates.io";
+
+/// A source of published crate versions.
+pub trait Registry {
+    /// Every published version of `name`; empty if it was never published.
+    fn versions(&self, name: &str) -> anyhow::Result<Vec<PublishedVersion>>;
+
+    /// Whether `version` of `name` was published, yanked or not; a yanked
+    /// version can't be published again either.
+    fn has_version(&self, name: &str, version: &str) -> anyhow::Result<bool> {
+        Ok(self
+            .versions(name)?
+            .iter()
+            .any(|published| published.version == version))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PublishedVersion {
+    #[serde(rename = "vers")]
+    pub version: String,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+/// The newest unyanked release among `versions`, preferring stable ones.
+pub fn latest(versions: &[PublishedVersion]) -> Option<semver::Version> {
+    let releases = versions
+        .iter()
+        .filter(|v| !v.yanked)
+        .filter_map(|v| semver::Version::parse(&v.version).ok())
+        .collect::<Vec<_>>();
+
+    releases
+        .iter()
+        .filter(|v| v.pre.is_empty())
+        .max()
+        .or_else(|| releases.iter().max())
+        .cloned()
+}
+
+/// Which release `--set-version latest` and its variants resolve to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Latest {
+    /// The newest release, `latest`.
+    Any,
+    /// The newest release with the current major version, `latest-minor`.
+    Minor,
+    /// The newest release with the current major and minor version,
+    /// `latest-patch`.
+    Patch,
+}
+
+impl Latest {
+    /// The newest unyanked release among `versions` that `self` allows
+    /// coming from `current`.
+    pub fn select(
+        self,
+        current: &semver::Version,
+        versions: &[PublishedVersion],
+    ) -> Option<semver::Version> {
+        let allowed = versions
+            .iter()
+            .filter(|v| {
+                let Ok(version) = semver::Version::parse(&v.version) else {
+                    return false;
+                };
+                match self {
+                    Latest::Any => true,
+                    Latest::Minor => version.major == current.major,
+                    Latest::Patch => {
+                        version.major == current.major && version.minor == current.minor
+                    }
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        latest(&allowed)
+    }
+}
+
+impl FromStr for Latest {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(Latest::Any),
+            "latest-minor" => Ok(Latest::Minor),
+            "latest-patch" => Ok(Latest::Patch),
+            _ => crate::error::bail!(
+                "expected `latest`, `latest-minor` or `latest-patch`, got `{s}`"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Latest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Latest::Any => "latest",
+            Latest::Minor => "latest-minor",
+            Latest::Patch => "latest-patch",
+        })
+    }
+}
+
+/// A registry's sparse index, e.g. [`CRATES_IO_INDEX`], fetched with curl.
+/// `file://` URLs work too, for mirrors on disk.
+#[derive(Debug, Clone)]
+pub struct SparseIndex {
+    url: String,
+}
+
+impl SparseIndex {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+
+    /// The index path of `name`, e.g. `se/rd/serde`.
+    fn path(name: &str) -> String {
+        let name = name.to_lowercase();
+        match name.len() {
+            1 => format!("1/{name}"),
+            2 => format!("2/{name}"),
+            3 => format!("3/{}/{name}", &name[..1]),
+            _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+        }
+    }
+}
+
+impl Registry for SparseIndex {
+    fn versions(&self, name: &str) -> anyhow::Result<Vec<PublishedVersion>> {
+        let url = format!("{}/{}", self.url.trim_end_matches('/'), Self::path(name));
+        let output = Command::new("curl")
+            .args(["--silent", "--show-error", "--location"])
+            .args(["--write-out", "\n%{http_code}", "--", &url])
+            .output()
+            .context("failed to run curl")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+
+        match (output.status.code(), status) {
+            // 37: a `file://` URL that doesn't exist.
+            (Some(37), _) | (_, "404" | "410") => return Ok(Vec::new()),
+            (Some(0), "200" | "000") => {}
+            _ => crate::error::bail!(
+                "failed to fetch {url}: {} {}",
+                status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+
+        body.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("unexpected index entry for {name} at {url}"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn picks_latest_stable_release() {
+        let published = |version: &str, yanked| PublishedVersion {
+            version: version.to_string(),
+            yanked,
+        };
+
+        assert_eq!(SparseIndex::path("a"), "1/a");
+        assert_eq!(SparseIndex::path("syn"), "3/s/syn");
+        assert_eq!(SparseIndex::path("Serde"), "se/rd/serde");
+
+        let versions = [
+            published("1.0.0", false),
+            published("1.2.0", true),
+            published("1.1.0", false),
+            published("2.0.0-rc.1", false),
+        ];
+        assert_eq!(latest(&versions), Some(semver::Version::new(1, 1, 0)));
+        assert_eq!(
+            latest(&versions[3..]),
+            Some(semver::Version::parse("2.0.0-rc.1").unwrap())
+        );
+        assert_eq!(latest(&[]), None);
+    }
+
+    #[test]
+    fn selects_the_latest_compatible_release() -> anyhow::Result<()> {
+        let versions =
+            ["1.2.3", "1.2.9", "1.4.0", "1.5.0-rc.1", "2.0.0"].map(|version| PublishedVersion {
+                version: version.to_string(),
+                yanked: false,
+            });
+        let current = semver::Version::new(1, 2, 3);
+        let select = |latest: &str| -> anyhow::Result<Option<String>> {
+            Ok(latest
+                .parse::<Latest>()?
+                .select(&current, &versions)
+                .map(|v| v.to_string()))
+        };
+
+        assert_eq!(select("latest")?.as_deref(), Some("2.0.0"));
+        assert_eq!(select("latest-minor")?.as_deref(), Some("1.4.0"));
+        assert_eq!(select("latest-patch")?.as_deref(), Some("1.2.9"));
+        assert!(select("newest").is_err());
+        assert_eq!(
+            Latest::Patch.select(&semver::Version::new(3, 0, 0), &versions),
+            None
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+/// A suggested fix for a common hand-editing mistake in a manifest.
+///
+/// Manifests with these mistakes usually don't parse, so detection works on
+/// the text line by line rather than on the TOML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+    /// 1-based line the repair applies to.
+    pub line: usize,
+    pub message: String,
+    /// The new text of the line, or `None` to delete it.
+    pub replacement: Option<String>,
+}
+
+/// Find repairable mistakes in `source`: misspelled dependency table headers,
+/// unquoted versions and other string values, and keys set twice in the same
+/// table.
+pub(crate) fn diagnose(source: &str) -> Vec<Repair> {
+    let mut repairs = Vec::new();
+    let mut section = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let number = index + 1;
+        let trimmed = line.trim();
+
+        if let Some(header) = parse_header(trimmed) {
+            section = split_dotted(header);
+            if let Some(fixed) = fix_header(&section) {
+                repairs.push(Repair {
+                    line: number,
+                    message: format!("`[{header}]` is not a manifest table, use `[{fixed}]`"),
+                    replacement: Some(line.replacen(header, &fixed, 1)),
+                });
+                section = split_dotted(&fixed);
+            }
+            seen.clear();
+            continue;
+        }
+
+        let Some((key, value)) = split_key_value(line) else {
+            continue;
+        };
+
+        if let Some(previous) = seen.insert(key.to_string(), number) {
+            repairs.push(Repair {
+                line: previous,
+                message: format!("`{key}` is set again on line {number}, which is kept"),
+                replacement: None,
+            });
+        }
+
+        if let Some(fixed) = quote_value(&section, key, &line[value.clone()]) {
+            repairs.push(Repair {
+                line: number,
+                message: format!("`{key}` must be a string, quote it"),
+                replacement: Some(format!(
+                    "{}{}{}",
+                    &line[..value.start],
+                    fixed,
+                    &line[value.end..]
+                )),
+            });
+        }
+    }
+
+    repairs
+}
+
+/// Apply `repairs` to `source`, keeping line endings. A line that is both
+/// deleted and replaced is deleted.
+pub(crate) fn apply(source: &str, repairs: &[Repair]) -> String {
+    let mut output = String::with_capacity(source.len());
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let number = index + 1;
+        let ending_len = line.len() - line.trim_end_matches(['\r', '\n']).len();
+        let (content, ending) = line.split_at(line.len() - ending_len);
+
+        let mut repaired = Some(content.to_string());
+        for repair in repairs.iter().filter(|r| r.line == number) {
+            repaired = match (&repaired, &repair.replacement) {
+                (Some(_), Some(replacement)) => Some(replacement.clone()),
+                _ => None,
+            };
+        }
+
+        if let Some(repaired) = repaired {
+            output.push_str(&repaired);
+            output.push_str(ending);
+        }
+    }
+
+    output
+}
+
+fn parse_header(line: &str) -> Option<&str> {
+    let line = strip_comment(line).trim();
+    if line.starts_with("[[") {
+        return None;
+    }
+
+    Some(line.strip_prefix('[')?.strip_suffix(']')?.trim())
+}
+
+/// Split a dotted key on the dots outside quotes.
+fn split_dotted(key: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quote = None;
+    for c in key.chars() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('.', None) => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+
+    parts.iter().map(|p| p.trim().to_string()).collect()
+}
+
+/// The dependency table named by `section`, e.g. `dev-dependencies` for
+/// `[target.'cfg(unix)'.dev-dependencies]`.
+fn table_index(section: &[String]) -> usize {
+    match section.first().map(String::as_str) {
+        Some("target") => 2,
+        Some("workspace") => 1,
+        _ => 0,
+    }
+}
+
+fn fix_header(section: &[String]) -> Option<String> {
+    let index = table_index(section);
+    let table = section.get(index)?;
+
+    let canonical = table.to_lowercase().replace(['-', '_'], "");
+    let (prefix, rest) = ["dev", "build"]
+        .iter()
+        .find_map(|prefix| Some((*prefix, canonical.strip_prefix(prefix)?)))
+        .unwrap_or(("", canonical.as_str()));
+    if !matches!(
+        rest,
+        "dependencies" | "dependency" | "deps" | "dependancies" | "dependecies" | "dependences"
+    ) {
+        return None;
+    }
+
+    let fixed = match prefix {
+        "" => "dependencies".to_string(),
+        prefix => format!("{prefix}-dependencies"),
+    };
+    // Cargo still accepts the underscore spellings.
+    if *table == fixed || *table == fixed.replace('-', "_") {
+        return None;
+    }
+
+    let mut fixed_section = section.to_vec();
+    fixed_section[index] = fixed;
+    Some(fixed_section.join("."))
+}
+
+/// Split `key = value` into the key and the byte range of the value, ignoring
+/// a trailing comment.
+fn split_key_value(line: &str) -> Option<(&str, std::ops::Range<usize>)> {
+    let equals = find_unquoted(line, '=')?;
+    let key = line[..equals].trim();
+    if key.is_empty() || key.starts_with('#') {
+        return None;
+    }
+
+    let rest = strip_comment(&line[equals + 1..]);
+    let start = equals + 1 + (rest.len() - rest.trim_start().len());
+    let end = equals + 1 + rest.trim_end().len();
+
+    Some((key, start..end.max(start)))
+}
+
+fn find_unquoted(text: &str, needle: char) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (c, None) if c == needle => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn strip_comment(text: &str) -> &str {
+    match find_unquoted(text, '#') {
+        Some(i) => &text[..i],
+        None => text,
+    }
+}
+
+/// String fields of `[package]` that are commonly left unquoted.
+const PACKAGE_STRING_KEYS: &[&str] = &["name", "version", "edition", "rust-version", "license"];
+
+/// The quoted form of `value` if it contains bare tokens where cargo expects
+/// strings: versions in dependency declarations, or string fields of
+/// `[package]`.
+fn quote_value(section: &[String], key: &str, value: &str) -> Option<String> {
+    let package = matches!(
+        section
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .as_slice(),
+        [] | ["package"] | ["project"] | ["workspace", "package"]
+    );
+    if package && PACKAGE_STRING_KEYS.contains(&key) && is_bare(value) {
+        return Some(quote(value));
+    }
+
+    let index = table_index(section);
+    if !section
+        .get(index)
+        .is_some_and(|table| table.ends_with("dependencies"))
+    {
+        return None;
+    }
+
+    let version_key = key == "version" || key.ends_with(".version");
+    match section.len() - index {
+        // `[dependencies]`: `name = 1.0`, `name.version = 1.0` or an inline table.
+        1 if value.starts_with('{') => quote_inline_version(value),
+        1 if (version_key || !key.contains('.')) && is_bare_version(value) => Some(quote(value)),
+        // `[dependencies.name]`
+        2 if version_key && is_bare_version(value) => Some(quote(value)),
+        _ => None,
+    }
+}
+
+fn quote_inline_version(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('{')?.strip_suffix('}')?;
+
+    let mut fixed = String::from("{");
+    let mut changed = false;
+    for entry in split_entries(inner) {
+        let text = &inner[entry.clone()];
+        match split_key_value(text) {
+            Some((key, range)) if key == "version" && is_bare_version(&text[range.clone()]) => {
+                fixed.push_str(&text[..range.start]);
+                fixed.push_str(&quote(&text[range.clone()]));
+                fixed.push_str(&text[range.end..]);
+                changed = true;
+            }
+            _ => fixed.push_str(text),
+        }
+    }
+    fixed.push('}');
+
+    changed.then_some(fixed)
+}
+
+/// Split the inside of an inline table on its top-level commas, keeping the
+/// commas with the preceding entry.
+fn split_entries(text: &str) -> Vec<std::ops::Range<usize>> {
+    let mut entries = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('[' | '{', None) => depth += 1,
+            (']' | '}', None) => depth = depth.saturating_sub(1),
+            (',', None) if depth == 0 => {
+                entries.push(start..i + 1);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(start..text.len());
+
+    entries
+}
+
+fn is_bare(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with(['"', '\'', '[', '{'])
+        && !matches!(value, "true" | "false")
+}
+
+fn is_bare_version(value: &str) -> bool {
+    is_bare(value)
+        && value.starts_with(|c: char| c.is_ascii_digit() || "^~<>=*".contains(c))
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".+-*^~<>=, ".contains(c))
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{value}\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn repairs_common_mistakes() {
+        let source = "[package]\nname = root\nversion = 0.1.0 # bumped\nedition = 2021\n\n[dependency]\nserde = 1.0\nchild = { path = \"child\", version = 0.2 }\nserde = \"1.0.100\"\n\n[target.'cfg(unix)'.dev_dependency]\nlibc.version = 0.2\n[dev_dependencies]\n";
+
+        let repairs = diagnose(source);
+        assert_eq!(
+            repairs
+                .iter()
+                .map(|r| (r.line, r.message.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (2, "`name` must be a string, quote it"),
+                (3, "`version` must be a string, quote it"),
+                (4, "`edition` must be a string, quote it"),
+                (6, "`[dependency]` is not a manifest table, use `[dependencies]`"),
+                (7, "`serde` must be a string, quote it"),
+                (8, "`child` must be a string, quote it"),
+                (7, "`serde` is set again on line 9, which is kept"),
+                (
+                    11,
+                    "`[target.'cfg(unix)'.dev_dependency]` is not a manifest table, use `[target.'cfg(unix)'.dev-dependencies]`"
+                ),
+                (12, "`libc.version` must be a string, quote it"),
+            ]
+        );
+
+        assert_eq!(
+            apply(source, &repairs),
+            "[package]\nname = \"root\"\nversion = \"0.1.0\" # bumped\nedition = \"2021\"\n\n[dependencies]\nchild = { path = \"child\", version = \"0.2\" }\nserde = \"1.0.100\"\n\n[target.'cfg(unix)'.dev-dependencies]\nlibc.version = \"0.2\"\n[dev_dependencies]\n"
+        );
+    }
+}
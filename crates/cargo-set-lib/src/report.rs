@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// What a version change edited, file by file, for release tooling to turn
+/// into changelog entries or PR descriptions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ChangeReport {
+    /// The root manifest of the workspace, which tells the reports of
+    /// several workspaces apart.
+    #[serde(with = "crate::paths::serde_path")]
+    pub root: PathBuf,
+    pub files: Vec<ChangedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedFile {
+    #[serde(with = "crate::paths::serde_path")]
+    pub path: PathBuf,
+    pub changes: Vec<Change>,
+}
+
+/// One edited value: the version of a package, or a requirement on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    /// The crate whose version changed.
+    pub package: String,
+    pub key: Vec<String>,
+    pub from: Option<String>,
+    pub to: String,
+    /// The members inheriting the changed `[workspace.dependencies]` entry,
+    /// which follow it without being edited.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inherited_by: Vec<String>,
+}
+
+impl ChangeReport {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
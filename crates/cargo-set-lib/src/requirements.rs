@@ -0,0 +1,258 @@
+use std::fmt;
+
+use crate::index::DependencyLocation;
+use crate::paths::display_path;
+
+/// A dependency requirement rewritten by `freeze` or `unfreeze`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequirementChange {
+    pub location: DependencyLocation,
+    pub from: String,
+    pub to: String,
+}
+
+impl fmt::Display for RequirementChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: `{}` {} -> {}",
+            display_path(&self.location.manifest),
+            self.location.key().join("."),
+            self.from,
+            self.to
+        )
+    }
+}
+
+/// How `unfreeze` writes the requirement for a pinned version, shown for
+/// `=1.2.3`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnfreezeStyle {
+    /// `1.2`
+    #[default]
+    Minor,
+    /// `1.2.3`
+    Bare,
+    /// `^1.2.3`
+    Caret,
+}
+
+/// How requirements on a crate are written when its version changes, shown
+/// for setting a crate required as `~1.2` to 1.3.0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RequirementStyle {
+    /// `~1.3.0`: every requirement keeps its operators, and ranges like
+    /// `>=1.2, <2` keep their upper bound while it still admits the version.
+    #[default]
+    Preserve,
+    /// `=1.3.0`
+    Exact,
+    /// `^1.3.0`
+    Caret,
+    /// `~1.3.0`
+    Tilde,
+}
+
+/// `requirement` rewritten to require `version` in `style`. Requirements
+/// whose operators can't be kept become `version` as it is.
+pub(crate) fn rewrite(requirement: &str, version: &str, style: RequirementStyle) -> String {
+    match style {
+        RequirementStyle::Preserve => {
+            preserve(requirement, version).unwrap_or_else(|| version.to_string())
+        }
+        RequirementStyle::Exact => format!("={version}"),
+        RequirementStyle::Caret => format!("^{version}"),
+        RequirementStyle::Tilde => format!("~{version}"),
+    }
+}
+
+/// `requirement` with the same operators, moved up to `version`.
+fn preserve(requirement: &str, version: &str) -> Option<String> {
+    let new = semver::Version::parse(version).ok()?;
+    let parts = requirement
+        .split(',')
+        .map(|part| {
+            let part = part.trim();
+            let start = part.find(|c: char| c.is_ascii_digit() || c == '*')?;
+            let (operator, bound) = (part[..start].trim(), &part[start..]);
+            let admits = || semver::VersionReq::parse(part).is_ok_and(|req| req.matches(&new));
+
+            match operator {
+                // Already at the version, e.g. `0.1` for 0.1.0.
+                "" | "^" | "~" | "=" | ">=" if lower_bound(bound).as_ref() == Some(&new) => {
+                    Some(part.to_string())
+                }
+                // `>1.2` would leave out the version it is set to.
+                ">" => Some(format!(">={version}")),
+                "" | "^" | "~" | "=" | ">=" if !bound.contains('*') => {
+                    Some(format!("{operator}{version}"))
+                }
+                "" | "=" if admits() => Some(part.to_string()),
+                "" | "=" => {
+                    let fixed = bound.split('.').take_while(|c| *c != "*").count();
+                    let components = [new.major, new.minor, new.patch].map(|c| c.to_string());
+                    Some(format!("{operator}{}.*", components[..fixed].join(".")))
+                }
+                "<" | "<=" if admits() => Some(part.to_string()),
+                "<=" => Some(format!("<={version}")),
+                "<" => Some(match bound.split('.').count() {
+                    1 => format!("<{}", new.major + 1),
+                    2 => format!("<{}.{}", new.major, new.minor + 1),
+                    _ => format!("<{}.{}.{}", new.major, new.minor, new.patch + 1),
+                }),
+                _ => None,
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(parts.join(", "))
+}
+
+/// The version `bound` of a requirement starts at, with its missing
+/// components as zeros, e.g. 1.2.0 for `1.2`.
+fn lower_bound(bound: &str) -> Option<semver::Version> {
+    let components = bound.split('.').count();
+    if bound.contains(['-', '+']) || components >= 3 {
+        return semver::Version::parse(bound).ok();
+    }
+    semver::Version::parse(&format!("{bound}{}", ".0".repeat(3 - components))).ok()
+}
+
+/// Whether `requirement` is already met by `version`, e.g. `^1.4` by 1.4.7
+/// but not `=1.4.2` or `~1.3`.
+pub(crate) fn admits(requirement: &str, version: &str) -> bool {
+    match (
+        semver::VersionReq::parse(requirement),
+        semver::Version::parse(version),
+    ) {
+        (Ok(req), Ok(version)) => req.matches(&version),
+        _ => false,
+    }
+}
+
+/// Whether `requirement` already pins a single version, e.g. `=1.2.3`.
+pub(crate) fn is_pinned(requirement: &str) -> bool {
+    match semver::VersionReq::parse(requirement) {
+        Ok(req) => {
+            req.comparators.len() == 1
+                && req.comparators[0].op == semver::Op::Exact
+                && req.comparators[0].patch.is_some()
+        }
+        Err(_) => false,
+    }
+}
+
+/// The pin of `requirement` to `locked`, unless it is pinned already.
+pub(crate) fn freeze(requirement: &str, locked: &str) -> Option<String> {
+    (!is_pinned(requirement)).then(|| format!("={locked}"))
+}
+
+/// `requirement` raised to the locked version `locked`, keeping its `^` or
+/// `~` operator, e.g. `^1.2` to `^1.2.7`. Pins, wildcards and multi-part
+/// requirements are left alone.
+pub(crate) fn upgrade(requirement: &str, locked: &str) -> Option<String> {
+    let req = semver::VersionReq::parse(requirement).ok()?;
+    if is_pinned(requirement) || req.comparators.len() != 1 {
+        return None;
+    }
+    let operator = match requirement.trim_start().chars().next()? {
+        '^' => "^",
+        '~' => "~",
+        c if c.is_ascii_digit() => "",
+        _ => return None,
+    };
+
+    Some(format!("{operator}{locked}"))
+}
+
+/// The range requirement for the pinned `requirement`, or `None` if it isn't
+/// pinned. Pre-release versions always keep every component, since shorter
+/// requirements wouldn't match them.
+pub(crate) fn unfreeze(requirement: &str, style: UnfreezeStyle) -> Option<String> {
+    if !is_pinned(requirement) {
+        return None;
+    }
+    let version = requirement.trim().trim_start_matches('=').trim();
+    let version = semver::Version::parse(version).ok()?;
+
+    let unfrozen = match style {
+        UnfreezeStyle::Minor if version.pre.is_empty() => {
+            format!("{}.{}", version.major, version.minor)
+        }
+        UnfreezeStyle::Minor | UnfreezeStyle::Bare => semver::Version {
+            build: semver::BuildMetadata::EMPTY,
+            ..version
+        }
+        .to_string(),
+        UnfreezeStyle::Caret => format!(
+            "^{}",
+            semver::Version {
+                build: semver::BuildMetadata::EMPTY,
+                ..version
+            }
+        ),
+    };
+
+    Some(unfrozen)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn freezes_and_unfreezes() {
+        assert_eq!(freeze("1.2", "1.2.7"), Some("=1.2.7".to_string()));
+        assert_eq!(freeze("= 1.2.7", "1.2.7"), None);
+        assert_eq!(freeze("=1.2", "1.2.7"), Some("=1.2.7".to_string()));
+
+        assert_eq!(
+            unfreeze("=1.2.3", UnfreezeStyle::Minor),
+            Some("1.2".to_string())
+        );
+        assert_eq!(
+            unfreeze("=1.2.3", UnfreezeStyle::Bare),
+            Some("1.2.3".to_string())
+        );
+        assert_eq!(
+            unfreeze("=1.2.3+build", UnfreezeStyle::Caret),
+            Some("^1.2.3".to_string())
+        );
+        assert_eq!(
+            unfreeze("=1.2.3-rc.1", UnfreezeStyle::Minor),
+            Some("1.2.3-rc.1".to_string())
+        );
+        assert_eq!(unfreeze("1.2.3", UnfreezeStyle::Minor), None);
+    }
+
+    #[test]
+    fn rewrites_requirements_in_style() {
+        let preserve = |requirement| rewrite(requirement, "1.3.0", RequirementStyle::Preserve);
+        assert_eq!(preserve("1.2"), "1.3.0");
+        assert_eq!(preserve("~1.2"), "~1.3.0");
+        assert_eq!(preserve("^1.2.4"), "^1.3.0");
+        assert_eq!(preserve("=1.2.4"), "=1.3.0");
+        assert_eq!(preserve(">=1, <2"), ">=1.3.0, <2");
+        assert_eq!(preserve(">= 1.2, < 1.3"), ">=1.3.0, <1.4");
+        assert_eq!(preserve(">1.2, <=1.2.9"), ">=1.3.0, <=1.3.0");
+        assert_eq!(preserve("1.*"), "1.*");
+        assert_eq!(preserve("0.*"), "1.*");
+        assert_eq!(preserve("*"), "*");
+        assert_eq!(preserve("workspace"), "1.3.0");
+        assert_eq!(preserve("1.3"), "1.3");
+        assert_eq!(preserve("~ 1.3.0"), "~ 1.3.0");
+        assert_eq!(preserve(">=1.3, <2"), ">=1.3, <2");
+
+        assert_eq!(rewrite("~1.2", "1.3.0", RequirementStyle::Exact), "=1.3.0");
+        assert_eq!(rewrite("~1.2", "1.3.0", RequirementStyle::Caret), "^1.3.0");
+        assert_eq!(
+            rewrite(">=1, <2", "1.3.0", RequirementStyle::Tilde),
+            "~1.3.0"
+        );
+
+        assert!(admits("^1.4", "1.4.7"));
+        assert!(!admits("=1.4.2", "1.4.7"));
+        assert!(!admits("~1.3", "1.4.7"));
+        assert!(!admits("^1.4", "1.5.0-rc.1"));
+    }
+}
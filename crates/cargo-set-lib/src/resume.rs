@@ -0,0 +1,121 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::filesystem::FileSystem;
+use crate::paths::display_path;
+
+/// File name of the resume state, stored next to the root manifest.
+pub const RESUME_FILE: &str = ".cargo-set-resume.json";
+
+/// A single manifest write, with enough information to redo or undo it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileWrite {
+    #[serde(with = "crate::paths::serde_path")]
+    pub path: PathBuf,
+    /// Contents before the write, if the manifest was loaded from disk.
+    pub original: Option<String>,
+    pub contents: String,
+}
+
+/// Writes of an interrupted multi-file operation, split into the ones that
+/// reached disk and the ones that didn't.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeState {
+    pub completed: Vec<FileWrite>,
+    pub pending: Vec<FileWrite>,
+}
+
+impl ResumeState {
+    /// Where the resume state for the workspace rooted at `root_manifest` lives.
+    pub fn path_for(root_manifest: &Path) -> PathBuf {
+        root_manifest
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(RESUME_FILE)
+    }
+
+    pub fn load<F: FileSystem>(fs: &F, root_manifest: &Path) -> crate::Result<Option<Self>> {
+        let path = Self::path_for(root_manifest);
+        let contents = match fs.read(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", display_path(&path)))?,
+        };
+
+        let state = serde_json::from_slice(&contents)
+            .with_context(|| format!("failed to parse {}", display_path(&path)))?;
+
+        Ok(Some(state))
+    }
+
+    pub(crate) fn save<F: FileSystem>(
+        &self,
+        fs: &F,
+        root_manifest: &Path,
+    ) -> anyhow::Result<PathBuf> {
+        let path = Self::path_for(root_manifest);
+        fs.write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write {}", display_path(&path)))?;
+
+        Ok(path)
+    }
+
+    /// Paths only, as reported to operators.
+    pub fn summary(&self) -> ResumeSummary {
+        ResumeSummary {
+            completed: self
+                .completed
+                .iter()
+                .map(|w| display_path(&w.path))
+                .collect(),
+            pending: self.pending.iter().map(|w| display_path(&w.path)).collect(),
+        }
+    }
+}
+
+/// Machine-readable report of which files an interrupted operation changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeSummary {
+    pub completed: Vec<String>,
+    pub pending: Vec<String>,
+}
+
+/// How `CargoManifestService::resume` settles an interrupted operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeMode {
+    /// Perform the pending writes.
+    Finish,
+    /// Restore the original contents of the completed writes.
+    Rollback,
+}
+
+/// Returned when some, but not all, manifests of an update were written.
+#[derive(Debug)]
+pub struct PartialWriteError {
+    pub state: ResumeState,
+    /// Where the resume state was saved, if saving it succeeded.
+    pub state_path: Option<PathBuf>,
+    pub source: anyhow::Error,
+}
+
+impl fmt::Display for PartialWriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.state.completed.len() + self.state.pending.len();
+        write!(
+            f,
+            "wrote {} of {} manifests: {:#}",
+            self.state.completed.len(),
+            total,
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for PartialWriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
@@ -0,0 +1,339 @@
+use serde_json::{json, Value};
+
+/// The documents cargo-set reads or writes that have a JSON Schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Document {
+    /// `.cargo-set.toml`.
+    Config,
+    /// The JSON object of crate versions written by the `json-file` artifact.
+    Versions,
+    /// The file `apply-versions` applies, mapping crates to versions or bump
+    /// levels.
+    VersionsFile,
+    /// One line of the audit log.
+    AuditRecord,
+    /// The report of `set --output json`.
+    ChangeReport,
+}
+
+/// A semantic version, pre-release and build metadata included.
+const VERSION_PATTERN: &str = r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(-[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?(\+[0-9A-Za-z-]+(\.[0-9A-Za-z-]+)*)?$";
+
+impl Document {
+    /// The JSON Schema (draft 2020-12) of the document.
+    pub fn schema(self) -> Value {
+        let mut schema = match self {
+            Document::Config => config(),
+            Document::Versions => json!({
+                "title": "cargo-set versions",
+                "description": "Every crate of the workspace with its version.",
+                "type": "object",
+                "additionalProperties": version(),
+            }),
+            Document::VersionsFile => json!({
+                "title": "cargo-set versions file",
+                "description": "Crates to set with `apply-versions`, each to a version or by a bump level.",
+                "type": "object",
+                "additionalProperties": {
+                    "anyOf": [version(), bump_level()],
+                },
+            }),
+            Document::AuditRecord => audit_record(),
+            Document::ChangeReport => change_report(),
+        };
+        schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+
+        schema
+    }
+}
+
+fn version() -> Value {
+    json!({ "type": "string", "pattern": VERSION_PATTERN })
+}
+
+fn bump_level() -> Value {
+    json!({
+        "anyOf": [
+            { "enum": ["major", "minor", "patch", "pre", "release", "calver"] },
+            { "type": "string", "pattern": "^calver:(YYYY|YY)\\.MM\\.(MICRO|DD)$" },
+        ]
+    })
+}
+
+fn strings() -> Value {
+    json!({ "type": "array", "items": { "type": "string" } })
+}
+
+/// An object with exactly the given properties, all optional.
+fn table(description: &str, properties: Value) -> Value {
+    json!({
+        "description": description,
+        "type": "object",
+        "additionalProperties": false,
+        "properties": properties,
+    })
+}
+
+fn config() -> Value {
+    let mut config = table(
+        "Project defaults and rules for cargo-set, stored next to the root manifest.",
+        json!({
+            "manifest-path": {
+                "description": "The root manifest used without `--path`, relative to the configuration.",
+                "type": "string",
+            },
+            "versioning": {
+                "description": "Whether the crates of the workspace share one version.",
+                "enum": ["lockstep", "independent"],
+            },
+            "tag-format": {
+                "description": "Format of release tags; `{crate}` and `{version}` are replaced.",
+                "type": "string",
+            },
+            "bump": bump_level(),
+            "update-dev-dependencies": {
+                "description": "Whether version changes update `[dev-dependencies]` too.",
+                "type": "boolean",
+            },
+            "output": {
+                "description": "How commands with a choice of output print their results.",
+                "enum": ["plain", "json"],
+            },
+            "artifacts": table(
+                "Files describing the new versions, written after every version change.",
+                json!({
+                    "version-file": { "type": "string" },
+                    "env-file": { "type": "string" },
+                    "json-file": { "type": "string" },
+                }),
+            ),
+            "build-info": table(
+                "Crates that embed their version, git commit and build time.",
+                json!({
+                    "crates": strings(),
+                    "format": { "enum": ["rust", "metadata"] },
+                    "path": {
+                        "description": "Where the Rust source goes, relative to each crate's directory.",
+                        "type": "string",
+                    },
+                }),
+            ),
+            "policy": table(
+                "Rules every version change is checked against before writing.",
+                json!({
+                    "major-requires-flag": { "type": "boolean" },
+                    "release-branches": strings(),
+                    "approval-files": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                    },
+                    "protected": strings(),
+                }),
+            ),
+            "audit": table(
+                "Where records of every command that changed files are sent.",
+                json!({
+                    "file": { "type": "string" },
+                    "syslog": { "type": "boolean" },
+                }),
+            ),
+            "banned-versions": {
+                "description": "Dependency versions no manifest may use, as version ranges per crate.",
+                "type": "object",
+                "additionalProperties": strings(),
+            },
+            "hooks": table(
+                "Shell commands run before and after every write of manifests, with the changes as JSON on stdin.",
+                json!({
+                    "pre": strings(),
+                    "post": strings(),
+                }),
+            ),
+        }),
+    );
+    config["title"] = json!(".cargo-set.toml");
+
+    config
+}
+
+fn audit_record() -> Value {
+    let nullable_string = json!({ "type": ["string", "null"] });
+    json!({
+        "title": "cargo-set audit record",
+        "description": "Who changed which files, when and how.",
+        "type": "object",
+        "required": ["timestamp", "user", "command", "git_commit", "files"],
+        "additionalProperties": false,
+        "properties": {
+            "timestamp": { "type": "string", "format": "date-time" },
+            "user": nullable_string,
+            "command": { "type": "string" },
+            "git_commit": nullable_string,
+            "error": { "type": "string" },
+            "files": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["path", "diff"],
+                    "additionalProperties": false,
+                    "properties": {
+                        "path": { "type": "string" },
+                        "diff": { "type": "string" },
+                    },
+                },
+            },
+        },
+    })
+}
+
+fn change_report() -> Value {
+    json!({
+        "title": "cargo-set change report",
+        "description": "The files a version change edited, with each changed value.",
+        "type": "object",
+        "required": ["root", "files"],
+        "additionalProperties": false,
+        "properties": {
+            "root": {
+                "type": "string",
+                "description": "The root manifest of the workspace."
+            },
+            "files": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["path", "changes"],
+                    "additionalProperties": false,
+                    "properties": {
+                        "path": { "type": "string" },
+                        "changes": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "required": ["package", "key", "from", "to"],
+                                "additionalProperties": false,
+                                "properties": {
+                                    "package": { "type": "string" },
+                                    "key": strings(),
+                                    "from": { "type": ["string", "null"] },
+                                    "to": { "type": "string" },
+                                    "inherited_by": strings(),
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::artifacts::Artifacts;
+    use crate::audit::{AuditConfig, AuditRecord, AuditedFile};
+    use crate::build_info::{BuildInfoConfig, BuildInfoFormat};
+    use crate::bump::BumpLevel;
+    use crate::config::{Config, OutputFormat, Versioning};
+    use crate::hooks::Hooks;
+    use crate::policy::Policy;
+    use crate::report::{Change, ChangeReport, ChangedFile};
+
+    /// Fail if `value` has an object key `schema` doesn't describe.
+    fn assert_described(value: &Value, schema: &Value, at: &str) {
+        let Some(object) = value.as_object() else {
+            return;
+        };
+        for (key, value) in object {
+            let property = schema["properties"]
+                .get(key)
+                .or_else(|| schema.get("additionalProperties").filter(|p| p.is_object()))
+                .unwrap_or_else(|| panic!("`{at}{key}` is missing from the schema"));
+            assert_described(value, property, &format!("{at}{key}."));
+        }
+    }
+
+    #[test]
+    fn schemas_describe_every_field() -> anyhow::Result<()> {
+        let config = Config {
+            manifest_path: Some(PathBuf::from("Cargo.toml")),
+            versioning: Some(Versioning::Lockstep),
+            tag_format: Some("v{version}".to_string()),
+            bump: Some(BumpLevel::Minor),
+            update_dev_dependencies: Some(false),
+            output: Some(OutputFormat::Json),
+            artifacts: Artifacts {
+                version_file: Some(PathBuf::from("VERSION")),
+                env_file: Some(PathBuf::from("versions.env")),
+                json_file: Some(PathBuf::from("versions.json")),
+            },
+            build_info: BuildInfoConfig {
+                crates: vec!["a".to_string()],
+                format: BuildInfoFormat::Metadata,
+                path: PathBuf::from("src/info.rs"),
+            },
+            policy: Policy {
+                major_requires_flag: true,
+                release_branches: vec!["main".to_string()],
+                approval_files: BTreeMap::from([("a".to_string(), PathBuf::from("APPROVED"))]),
+                protected: vec!["a".to_string()],
+            },
+            audit: AuditConfig {
+                file: Some(PathBuf::from("audit.jsonl")),
+                syslog: true,
+            },
+            banned_versions: toml::from_str("openssl = ['=0.10.45']")?,
+            hooks: Hooks {
+                pre: vec!["./check.sh".to_string()],
+                post: vec!["cargo check".to_string()],
+            },
+        };
+        assert_described(
+            &serde_json::to_value(&config)?,
+            &Document::Config.schema(),
+            "",
+        );
+
+        let record = AuditRecord {
+            timestamp: "2024-06-01T12:00:00Z".to_string(),
+            user: Some("ci".to_string()),
+            command: "cargo set bump".to_string(),
+            git_commit: None,
+            error: Some("failed".to_string()),
+            files: vec![AuditedFile {
+                path: "Cargo.toml".to_string(),
+                diff: String::new(),
+            }],
+        };
+        assert_described(
+            &serde_json::to_value(&record)?,
+            &Document::AuditRecord.schema(),
+            "",
+        );
+
+        let report = ChangeReport {
+            root: PathBuf::from("Cargo.toml"),
+            files: vec![ChangedFile {
+                path: PathBuf::from("a/Cargo.toml"),
+                changes: vec![Change {
+                    package: "a".to_string(),
+                    key: vec!["package".to_string(), "version".to_string()],
+                    from: Some("0.1.0".to_string()),
+                    to: "0.2.0".to_string(),
+                    inherited_by: vec!["b".to_string()],
+                }],
+            }],
+        };
+        assert_described(
+            &serde_json::to_value(&report)?,
+            &Document::ChangeReport.schema(),
+            "",
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+/// The pre-release of a snapshot build made at `now` from commit `commit`,
+/// e.g. `snapshot.20240601.abc1234`.
+pub fn snapshot_pre(now: SystemTime, commit: &str) -> crate::Result<String> {
+    let days = now
+        .duration_since(UNIX_EPOCH)
+        .context("the clock is set before 1970")?
+        .as_secs()
+        / 86_400;
+    let (year, month, day) = civil_from_days(days as i64);
+
+    let commit: String = commit.chars().take(7).collect();
+    // A purely numeric identifier with a leading zero is not valid semver.
+    let commit = if commit.chars().all(|c| c.is_ascii_digit()) {
+        format!("g{commit}")
+    } else {
+        commit
+    };
+
+    let pre = format!("snapshot.{year:04}{month:02}{day:02}.{commit}");
+    semver::Prerelease::new(&pre).with_context(|| format!("`{pre}` is not a valid pre-release"))?;
+
+    Ok(pre)
+}
+
+/// The UTC date of `time` as year, month and day.
+pub(crate) fn date(time: SystemTime) -> anyhow::Result<(u64, u32, u32)> {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .context("the clock is set before 1970")?
+        .as_secs()
+        / 86_400;
+    let (year, month, day) = civil_from_days(days as i64);
+
+    Ok((year as u64, month, day))
+}
+
+/// `time` as an RFC 3339 UTC timestamp, e.g. `2024-06-01T12:00:00Z`.
+pub(crate) fn rfc3339(time: SystemTime) -> anyhow::Result<String> {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .context("the clock is set before 1970")?
+        .as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let (hour, minute, second) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+
+    Ok(format!(
+        "{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z"
+    ))
+}
+
+/// The proleptic Gregorian date `days` after 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn builds_snapshot_pre_releases() -> anyhow::Result<()> {
+        // 2024-06-01T12:00:00Z
+        let now = UNIX_EPOCH + Duration::from_secs(1_717_243_200);
+
+        assert_eq!(
+            snapshot_pre(now, "abc1234def")?,
+            "snapshot.20240601.abc1234"
+        );
+        assert_eq!(
+            snapshot_pre(now, "0123456789")?,
+            "snapshot.20240601.g0123456"
+        );
+        assert_eq!(rfc3339(now)?, "2024-06-01T12:00:00Z");
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) use std::time::Instant;
+
+/// The clock phases are timed with. `wasm32-unknown-unknown` has none, so
+/// there every phase takes no time.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+impl Instant {
+    pub(crate) fn now() -> Self {
+        Instant
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// Phase timings and I/O counts collected while operating on a workspace.
+///
+/// Stats accumulate over every operation performed on the same
+/// `CargoManifest`, so a load followed by two updates reports the sum.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Reading and parsing manifests.
+    pub load: Duration,
+    /// Resolving workspace member paths.
+    pub resolve: Duration,
+    /// Applying edits and serializing the new manifests.
+    pub compute: Duration,
+    /// Writing manifests back to the file system.
+    pub write: Duration,
+    pub files_read: usize,
+    pub files_written: usize,
+    pub members: usize,
+}
+
+impl Stats {
+    pub fn total(&self) -> Duration {
+        self.load + self.resolve + self.compute + self.write
+    }
+
+    pub(crate) fn time<T>(phase: &mut Duration, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let output = f();
+        *phase += start.elapsed();
+        output
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "load:          {:?}", self.load)?;
+        writeln!(f, "resolve:       {:?}", self.resolve)?;
+        writeln!(f, "compute:       {:?}", self.compute)?;
+        writeln!(f, "write:         {:?}", self.write)?;
+        writeln!(f, "total:         {:?}", self.total())?;
+        writeln!(f, "files read:    {}", self.files_read)?;
+        writeln!(f, "files written: {}", self.files_written)?;
+        write!(f, "members:       {}", self.members)
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// The tag format of a workspace without a `tag-format`, naming tags like
+/// `foo-v1.2.0`.
+pub const DEFAULT_TAG_FORMAT: &str = "{crate}-v{version}";
+
+/// How the version of a member stands against the git tags, from
+/// [`CargoManifest::verify_tags`](crate::CargoManifest::verify_tags).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagCheck {
+    pub package: String,
+    pub version: String,
+    /// The tag the version is released under, e.g. `foo-v1.2.0`.
+    pub tag: String,
+    pub status: TagStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TagStatus {
+    Tagged,
+    /// The version has no tag yet.
+    Missing,
+    /// This tag is of a newer version than the manifest's, e.g. of a
+    /// release whose bump was lost.
+    Behind(String),
+}
+
+impl TagCheck {
+    pub fn is_tagged(&self) -> bool {
+        self.status == TagStatus::Tagged
+    }
+}
+
+impl fmt::Display for TagCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: ", self.package, self.version)?;
+        match &self.status {
+            TagStatus::Tagged => write!(f, "tagged {}", self.tag),
+            TagStatus::Missing => write!(f, "no tag {}", self.tag),
+            TagStatus::Behind(newer) => write!(f, "{newer} is of a newer version"),
+        }
+    }
+}
+
+/// The tag `format`, with `{crate}` and `{version}`, names for `version` of
+/// `package`.
+pub fn tag_name(format: &str, package: &str, version: &str) -> String {
+    format
+        .replace("{crate}", package)
+        .replace("{version}", version)
+}
+
+/// The tag `format` names for `version` of `package`, and how `tags` stand
+/// against it.
+pub(crate) fn check(
+    tags: &[String],
+    format: &str,
+    package: &str,
+    version: &str,
+) -> anyhow::Result<TagCheck> {
+    let named = format.replace("{crate}", package);
+    let Some((prefix, suffix)) = named.split_once("{version}") else {
+        crate::error::bail!("the tag format `{format}` has no `{{version}}`");
+    };
+    let tag = tag_name(format, package, version);
+
+    let status = if tags.contains(&tag) {
+        TagStatus::Tagged
+    } else {
+        let current = semver::Version::parse(version).ok();
+        let newest = tags
+            .iter()
+            .filter_map(|tag| {
+                let released = tag.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                let released = semver::Version::parse(released).ok()?;
+                current
+                    .as_ref()
+                    .is_some_and(|current| released > *current)
+                    .then_some((released, tag))
+            })
+            .max();
+        match newest {
+            Some((_, newer)) => TagStatus::Behind(newer.clone()),
+            None => TagStatus::Missing,
+        }
+    };
+
+    Ok(TagCheck {
+        package: package.to_string(),
+        version: version.to_string(),
+        tag,
+        status,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cargo::CargoManifestService;
+    use crate::testing::WorkspaceFixture;
+
+    #[test]
+    fn checks_member_versions_against_tags() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .virtual_manifest()
+            .member("a", "0.1.0")
+            .member("b", "0.2.0")
+            .member("c", "1.0.0");
+        let service = CargoManifestService::new(fixture.file_system());
+        let workspace = service.load_manifest(&fixture.root_path())?;
+        let tags = [
+            "a-v0.1.0",
+            "b-v0.1.0",
+            "b-v0.3.0",
+            "b-v0.2.1-rc.1",
+            "c-v0.9.0",
+        ]
+        .map(String::from);
+
+        let checks = workspace.verify_tags(&tags, "{crate}-v{version}")?;
+        assert_eq!(
+            checks.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "a 0.1.0: tagged a-v0.1.0",
+                "b 0.2.0: b-v0.3.0 is of a newer version",
+                "c 1.0.0: no tag c-v1.0.0",
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&checks[1].status)?,
+            r#"{"behind":"b-v0.3.0"}"#
+        );
+        assert!(workspace.verify_tags(&tags, "{crate}").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+
+use toml_edit::{Array, Value};
+
+/// The crate types cargo accepts in `[lib] crate-type`.
+const CRATE_TYPES: [&str; 7] = [
+    "bin",
+    "lib",
+    "rlib",
+    "dylib",
+    "cdylib",
+    "staticlib",
+    "proc-macro",
+];
+
+/// The boolean settings of `[lib]`.
+const LIB_SETTINGS: [&str; 6] = ["test", "doctest", "bench", "doc", "harness", "proc-macro"];
+
+/// A change to the `[[bin]]` or `[lib]` target of members.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetEdit {
+    /// Rename the binary `from` to `to`. A binary whose `path` cargo infers
+    /// from its name gets the file it is built from pinned as its `path`.
+    RenameBin { from: String, to: String },
+    /// Set the `required-features` of the binary `bin`, or remove them if
+    /// `features` is empty.
+    RequiredFeatures { bin: String, features: Vec<String> },
+    /// Set `[lib] crate-type`.
+    CrateType(Vec<String>),
+    /// Set a boolean setting of `[lib]`, e.g. `doctest = false`.
+    Lib { setting: String, value: bool },
+}
+
+impl TargetEdit {
+    /// The binary the edit applies to, `None` for the library.
+    pub(crate) fn bin(&self) -> Option<&str> {
+        match self {
+            TargetEdit::RenameBin { from, .. } => Some(from),
+            TargetEdit::RequiredFeatures { bin, .. } => Some(bin),
+            TargetEdit::CrateType(_) | TargetEdit::Lib { .. } => None,
+        }
+    }
+
+    /// Fail for values cargo wouldn't accept.
+    pub(crate) fn check(&self) -> anyhow::Result<()> {
+        match self {
+            TargetEdit::RenameBin { to, .. } => {
+                let valid = !to.is_empty()
+                    && to
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+                if !valid {
+                    crate::error::bail!("`{to}` is not a binary name");
+                }
+            }
+            TargetEdit::RequiredFeatures { .. } => {}
+            TargetEdit::CrateType(types) => {
+                if types.is_empty() {
+                    crate::error::bail!("a library needs at least one crate type");
+                }
+                if let Some(unknown) = types.iter().find(|t| !CRATE_TYPES.contains(&t.as_str())) {
+                    crate::error::bail!(
+                        "`{unknown}` is not a crate type, use {}",
+                        CRATE_TYPES.join(", ")
+                    );
+                }
+            }
+            TargetEdit::Lib { setting, .. } => {
+                if !LIB_SETTINGS.contains(&setting.as_str()) {
+                    crate::error::bail!(
+                        "`{setting}` is not a `[lib]` setting, use {}",
+                        LIB_SETTINGS.join(", ")
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The `[lib]` key the edit sets and its value, for library edits.
+    pub(crate) fn lib_value(&self) -> Option<(&str, Value)> {
+        match self {
+            TargetEdit::CrateType(types) => Some((
+                "crate-type",
+                Value::Array(types.iter().map(String::as_str).collect::<Array>()),
+            )),
+            TargetEdit::Lib { setting, value } => Some((setting, Value::from(*value))),
+            TargetEdit::RenameBin { .. } | TargetEdit::RequiredFeatures { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for TargetEdit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetEdit::RenameBin { from, to } => write!(f, "renamed the binary {from} to {to}"),
+            TargetEdit::RequiredFeatures { bin, features } if features.is_empty() => {
+                write!(f, "removed the required features of the binary {bin}")
+            }
+            TargetEdit::RequiredFeatures { bin, features } => write!(
+                f,
+                "set the required features of the binary {bin} to {}",
+                features.join(", ")
+            ),
+            TargetEdit::CrateType(types) => {
+                write!(f, "set the library crate type to {}", types.join(", "))
+            }
+            TargetEdit::Lib { setting, value } => {
+                write!(f, "set `{setting} = {value}` on the library")
+            }
+        }
+    }
+}
+
+/// The file cargo builds the binary `bin` of `package` from when its
+/// `[[bin]]` has no `path`, out of the files `exists` finds, relative to the
+/// member's directory.
+pub(crate) fn inferred_bin_path(
+    package: &str,
+    bin: &str,
+    exists: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let main = (bin == package).then(|| "src/main.rs".to_string());
+    main.into_iter()
+        .chain([
+            format!("src/bin/{bin}.rs"),
+            format!("src/bin/{bin}/main.rs"),
+        ])
+        .find(|path| exists(path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checks_and_describes_target_edits() {
+        let edit = TargetEdit::CrateType(vec!["cdylib".to_string(), "rlib".to_string()]);
+        assert!(edit.check().is_ok());
+        assert_eq!(
+            edit.lib_value()
+                .map(|(key, value)| (key, value.to_string())),
+            Some(("crate-type", "[\"cdylib\", \"rlib\"]".to_string()))
+        );
+        assert!(TargetEdit::CrateType(vec!["exe".to_string()])
+            .check()
+            .is_err());
+        assert!(TargetEdit::Lib {
+            setting: "fast".to_string(),
+            value: true
+        }
+        .check()
+        .is_err());
+        assert!(TargetEdit::RenameBin {
+            from: "a".to_string(),
+            to: "b c".to_string()
+        }
+        .check()
+        .is_err());
+
+        let files = ["src/main.rs", "src/bin/tool/main.rs"];
+        let exists = |path: &str| files.contains(&path);
+        assert_eq!(
+            inferred_bin_path("app", "app", exists).as_deref(),
+            Some("src/main.rs")
+        );
+        assert_eq!(
+            inferred_bin_path("app", "tool", exists).as_deref(),
+            Some("src/bin/tool/main.rs")
+        );
+        assert_eq!(inferred_bin_path("app", "other", exists), None);
+    }
+}
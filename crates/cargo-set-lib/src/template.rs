@@ -0,0 +1,154 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+#[cfg(feature = "git-fs")]
+use crate::git;
+
+/// `template` with its placeholders expanded, for versions derived from the
+/// build, e.g. `0.5.0-nightly.{date:%Y%m%d}+{git_sha_short}`:
+///
+/// - `{git_describe}`: `git describe --tags --always` of the repository
+///   containing `dir`;
+/// - `{git_sha}` and `{git_sha_short}`: the commit checked out, whole or
+///   its first 7 characters;
+/// - `{date:FORMAT}`: `now` in UTC, with `%Y`, `%m`, `%d`, `%H`, `%M` and
+///   `%S`; `{date}` is `{date:%Y%m%d}`;
+/// - `{env:NAME}`: the environment variable `NAME`, which has to be set.
+///
+/// `{{` and `}}` stand for literal braces. Git only runs for templates
+/// that use it, and needs the `git-fs` feature.
+#[cfg_attr(not(feature = "git-fs"), allow(unused_variables))]
+pub fn expand_template(template: &str, dir: &Path, now: SystemTime) -> crate::Result<String> {
+    Ok(expand(template, |name, argument| match (name, argument) {
+        #[cfg(feature = "git-fs")]
+        ("git_describe", None) => Ok(git::describe(dir)?),
+        #[cfg(feature = "git-fs")]
+        ("git_sha", None) => Ok(git::head_commit(dir)?),
+        #[cfg(feature = "git-fs")]
+        ("git_sha_short", None) => Ok(git::head_commit(dir)?.chars().take(7).collect()),
+        #[cfg(not(feature = "git-fs"))]
+        ("git_describe" | "git_sha" | "git_sha_short", None) => {
+            crate::error::bail!("`{{{name}}}` needs the `git-fs` feature")
+        }
+        ("date", format) => format_date(now, format.unwrap_or("%Y%m%d")),
+        ("env", Some(variable)) => std::env::var(variable)
+            .with_context(|| format!("the environment variable `{variable}` is not set")),
+        _ => crate::error::bail!(
+            "`{{{name}}}` is not a placeholder, use git_describe, git_sha, git_sha_short, \
+             date:FORMAT or env:NAME"
+        ),
+    })?)
+}
+
+/// `template` with every `{name}` or `{name:argument}` replaced by what
+/// `resolve` returns for it.
+fn expand(
+    template: &str,
+    mut resolve: impl FnMut(&str, Option<&str>) -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        expanded.push_str(&rest[..start]);
+        let brace = &rest[start..start + 1];
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix(brace) {
+            expanded.push_str(brace);
+            rest = after;
+            continue;
+        }
+        if brace == "}" {
+            crate::error::bail!("`{template}` has a `}}` without a `{{`, write `}}}}` for a brace");
+        }
+
+        let end = rest
+            .find('}')
+            .with_context(|| format!("`{template}` has a `{{` without a `}}`"))?;
+        let (name, argument) = match rest[..end].split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (&rest[..end], None),
+        };
+        expanded.push_str(&resolve(name, argument)?);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+/// `time` in UTC as `format` lays it out, with `%Y`, `%m`, `%d`, `%H`, `%M`,
+/// `%S` and `%%`.
+fn format_date(time: SystemTime, format: &str) -> anyhow::Result<String> {
+    let (year, month, day) = crate::snapshot::date(time)?;
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .context("the clock is set before 1970")?
+        .as_secs();
+
+    let mut formatted = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            formatted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => formatted.push_str(&format!("{year:04}")),
+            Some('m') => formatted.push_str(&format!("{month:02}")),
+            Some('d') => formatted.push_str(&format!("{day:02}")),
+            Some('H') => formatted.push_str(&format!("{:02}", secs / 3600 % 24)),
+            Some('M') => formatted.push_str(&format!("{:02}", secs / 60 % 60)),
+            Some('S') => formatted.push_str(&format!("{:02}", secs % 60)),
+            Some('%') => formatted.push('%'),
+            other => crate::error::bail!(
+                "`%{}` in `{format}` is not a date field, use %Y, %m, %d, %H, %M or %S",
+                other.map(String::from).unwrap_or_default()
+            ),
+        }
+    }
+
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn expands_version_templates() -> anyhow::Result<()> {
+        // 2024-06-10T08:30:00Z
+        let now = UNIX_EPOCH + Duration::from_secs(1_718_008_200);
+        std::env::set_var("CARGO_SET_TEMPLATE_TEST_BUILD", "42");
+
+        assert_eq!(
+            expand_template(
+                "0.5.0-nightly.{date:%Y%m%d}.{env:CARGO_SET_TEMPLATE_TEST_BUILD}",
+                Path::new("."),
+                now
+            )?,
+            "0.5.0-nightly.20240610.42"
+        );
+        assert_eq!(
+            expand_template("{date:%H%M}{{x}}", Path::new("."), now)?,
+            "0830{x}"
+        );
+        let git = |name: &str, _: Option<&str>| Ok(format!("<{name}>"));
+        assert_eq!(
+            expand("1.0.0-{git_describe}+{git_sha_short}", git)?,
+            "1.0.0-<git_describe>+<git_sha_short>"
+        );
+
+        assert!(expand_template("1.0.{nope}", Path::new("."), now).is_err());
+        assert!(expand_template("1.0.{date", Path::new("."), now).is_err());
+        assert!(
+            expand_template("{env:CARGO_SET_TEMPLATE_TEST_UNSET}", Path::new("."), now).is_err()
+        );
+        assert!(format_date(now, "%Q").is_err());
+
+        Ok(())
+    }
+}
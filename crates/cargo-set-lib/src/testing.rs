@@ -0,0 +1,325 @@
+//! Builders for in-memory workspaces, for tests of code driving
+//! [`CargoManifestService`](crate::CargoManifestService). Enable the
+//! `test-util` feature to use them outside this crate.
+//!
+//! [`WorkspaceFixture`] describes a workspace crate by crate;
+//! [`MockFileSystem::from_dir`] loads one checked in as a directory tree.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use cargo_set_lib::testing::WorkspaceFixture;
+//! use cargo_set_lib::CargoManifestService;
+//!
+//! let fixture = WorkspaceFixture::new()
+//!     .member("child", "0.2.0")
+//!     .dep("child", "root");
+//! let fs = Arc::new(fixture.file_system());
+//! let service = CargoManifestService::new(fs.clone());
+//! let mut workspace = service.load_manifest(&fixture.root_path())?;
+//! service.update_version(&mut workspace, "child", "0.3.0")?;
+//!
+//! fixture.version("child", "0.3.0").assert_files(&fs);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::filesystem::FileSystem;
+pub use crate::filesystem::MockFileSystem;
+
+/// A workspace described crate by crate, rendered to manifests on demand.
+///
+/// The root manifest is a package named `root` at version `0.1.0` unless
+/// changed with [`root`](Self::root) or
+/// [`virtual_manifest`](Self::virtual_manifest); every member lives in a
+/// directory named after it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceFixture {
+    root: Option<FixtureCrate>,
+    members: Vec<FixtureCrate>,
+    files: BTreeMap<PathBuf, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FixtureCrate {
+    name: String,
+    version: String,
+    /// Dependency names with their requirement, or `None` for members,
+    /// which are declared by path and current version.
+    dependencies: Vec<(String, Option<String>)>,
+}
+
+impl FixtureCrate {
+    fn new(name: &str, version: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies: Vec::new(),
+        }
+    }
+}
+
+impl Default for WorkspaceFixture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkspaceFixture {
+    pub fn new() -> Self {
+        Self {
+            root: Some(FixtureCrate::new("root", "0.1.0")),
+            members: Vec::new(),
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Name and version the package of the root manifest.
+    pub fn root(mut self, name: &str, version: &str) -> Self {
+        let dependencies = self.root.take().map(|r| r.dependencies).unwrap_or_default();
+        self.root = Some(FixtureCrate {
+            dependencies,
+            ..FixtureCrate::new(name, version)
+        });
+        self
+    }
+
+    /// Make the root manifest a virtual manifest, without a package.
+    pub fn virtual_manifest(mut self) -> Self {
+        self.root = None;
+        self
+    }
+
+    /// Add the member `name` at `version`, in the directory `name`.
+    pub fn member(mut self, name: &str, version: &str) -> Self {
+        self.members.push(FixtureCrate::new(name, version));
+        self
+    }
+
+    /// Make `dependent` depend on the workspace crate `name` by path and its
+    /// current version.
+    ///
+    /// # Panics
+    ///
+    /// If `dependent` isn't a crate of the fixture.
+    pub fn dep(mut self, name: &str, dependent: &str) -> Self {
+        self.crate_mut(dependent)
+            .dependencies
+            .push((name.to_string(), None));
+        self
+    }
+
+    /// Make `dependent` depend on the third-party crate `name`.
+    ///
+    /// # Panics
+    ///
+    /// If `dependent` isn't a crate of the fixture.
+    pub fn requires(mut self, dependent: &str, name: &str, requirement: &str) -> Self {
+        self.crate_mut(dependent)
+            .dependencies
+            .push((name.to_string(), Some(requirement.to_string())));
+        self
+    }
+
+    /// Change the version of the crate `name`, and with it every dependency
+    /// on it; the manifests a version change is expected to produce.
+    ///
+    /// # Panics
+    ///
+    /// If `name` isn't a crate of the fixture.
+    pub fn version(mut self, name: &str, version: &str) -> Self {
+        self.crate_mut(name).version = version.to_string();
+        self
+    }
+
+    /// Add a file other than a manifest, e.g. `Cargo.lock` or
+    /// `.cargo-set.toml`, relative to the workspace root.
+    pub fn file(mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        self.files.insert(path.into(), contents.into());
+        self
+    }
+
+    /// The path of the root manifest.
+    pub fn root_path(&self) -> PathBuf {
+        PathBuf::from("Cargo.toml")
+    }
+
+    /// The path of the manifest of the crate `name`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` isn't a crate of the fixture.
+    pub fn manifest_path(&self, name: &str) -> PathBuf {
+        self.directory(name).join("Cargo.toml")
+    }
+
+    /// The contents of the manifest of the crate `name`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` isn't a crate of the fixture.
+    pub fn manifest(&self, name: &str) -> String {
+        let path = self.manifest_path(name);
+        self.files().remove(&path).unwrap()
+    }
+
+    /// Every file of the workspace, manifests included.
+    pub fn files(&self) -> BTreeMap<PathBuf, String> {
+        let mut files = self.files.clone();
+
+        let mut root = self
+            .root
+            .as_ref()
+            .map(|r| self.render(r))
+            .unwrap_or_default();
+        if !root.is_empty() {
+            root.push('\n');
+        }
+        let members = self
+            .members
+            .iter()
+            .map(|m| format!("\"{}\"", m.name))
+            .collect::<Vec<_>>();
+        root.push_str(&format!(
+            "[workspace]\nmembers = [{}]\n",
+            members.join(", ")
+        ));
+        files.insert(self.root_path(), root);
+
+        for member in &self.members {
+            files.insert(self.manifest_path(&member.name), self.render(member));
+        }
+
+        files
+    }
+
+    /// An in-memory file system holding [`files`](Self::files).
+    pub fn file_system(&self) -> impl FileSystem {
+        let mut fs = MockFileSystem::new();
+        for (path, contents) in self.files() {
+            fs.add_file(path, contents.into_bytes());
+        }
+
+        fs
+    }
+
+    /// Panic unless every file of the fixture is in `fs` with these exact
+    /// contents.
+    pub fn assert_files(&self, fs: &impl FileSystem) {
+        for (path, expected) in self.files() {
+            let actual = fs
+                .read(&path)
+                .map(|contents| String::from_utf8_lossy(&contents).into_owned())
+                .unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display()));
+            assert_eq!(actual, expected, "{} differs", path.display());
+        }
+    }
+
+    fn crates(&self) -> impl Iterator<Item = &FixtureCrate> {
+        self.root.iter().chain(&self.members)
+    }
+
+    fn crate_mut(&mut self, name: &str) -> &mut FixtureCrate {
+        self.root
+            .iter_mut()
+            .chain(&mut self.members)
+            .find(|c| c.name == name)
+            .unwrap_or_else(|| panic!("the fixture has no crate `{name}`"))
+    }
+
+    fn directory(&self, name: &str) -> PathBuf {
+        match &self.root {
+            Some(root) if root.name == name => PathBuf::new(),
+            _ if self.members.iter().any(|m| m.name == name) => PathBuf::from(name),
+            _ => panic!("the fixture has no crate `{name}`"),
+        }
+    }
+
+    /// The path of the crate `to` as written in the manifest of `from`.
+    fn relative_path(&self, from: &str, to: &str) -> String {
+        let (from, to) = (self.directory(from), self.directory(to));
+        let up = from.components().map(|_| "..");
+        let path = up
+            .chain(to.to_str())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+
+        if path.is_empty() {
+            ".".to_string()
+        } else {
+            path.join("/")
+        }
+    }
+
+    fn render(&self, package: &FixtureCrate) -> String {
+        let mut manifest = format!(
+            "[package]\nname = \"{}\"\nversion = \"{}\"\n",
+            package.name, package.version
+        );
+
+        if !package.dependencies.is_empty() {
+            manifest.push_str("\n[dependencies]\n");
+        }
+        for (name, requirement) in &package.dependencies {
+            let line = match requirement {
+                Some(requirement) => format!("{name} = \"{requirement}\"\n"),
+                None => {
+                    let dependency = self
+                        .crates()
+                        .find(|c| &c.name == name)
+                        .unwrap_or_else(|| panic!("the fixture has no crate `{name}`"));
+                    format!(
+                        "{name} = {{ path = \"{}\", version = \"{}\" }}\n",
+                        self.relative_path(&package.name, name),
+                        dependency.version
+                    )
+                }
+            };
+            manifest.push_str(&line);
+        }
+
+        manifest
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::CargoManifestService;
+
+    #[test]
+    fn renders_workspaces_and_expected_changes() -> anyhow::Result<()> {
+        let fixture = WorkspaceFixture::new()
+            .member("child", "0.2.0")
+            .member("other", "1.0.0")
+            .dep("child", "root")
+            .dep("root", "child")
+            .dep("child", "other")
+            .requires("other", "serde", "1.0");
+
+        assert_eq!(
+            fixture.manifest("root"),
+            "[package]\nname = \"root\"\nversion = \"0.1.0\"\n\n[dependencies]\nchild = { path = \"child\", version = \"0.2.0\" }\n\n[workspace]\nmembers = [\"child\", \"other\"]\n"
+        );
+        assert_eq!(
+            fixture.manifest("other"),
+            "[package]\nname = \"other\"\nversion = \"1.0.0\"\n\n[dependencies]\nchild = { path = \"../child\", version = \"0.2.0\" }\nserde = \"1.0\"\n"
+        );
+        assert_eq!(
+            fixture.manifest("child"),
+            "[package]\nname = \"child\"\nversion = \"0.2.0\"\n\n[dependencies]\nroot = { path = \"..\", version = \"0.1.0\" }\n"
+        );
+
+        let fs = Arc::new(fixture.file_system());
+        let service = CargoManifestService::new(fs.clone());
+        let mut workspace = service.load_manifest(&fixture.root_path())?;
+        service.update_version(&mut workspace, "child", "0.3.0")?;
+        fixture.version("child", "0.3.0").assert_files(&fs);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Context;
+
+/// A rewrite applied to a computed version before it is written, so a
+/// pipeline can e.g. go from a released `1.4.0` to `1.5.0-dev` in one step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transform {
+    /// `append:<text>`: add `text` to the end, e.g. `append:-dev`.
+    Append(String),
+    /// `strip-prefix:<text>`: drop a leading `text`, e.g. `strip-prefix:v`
+    /// for versions taken from tags.
+    StripPrefix(String),
+    /// `strip-pre`: drop the pre-release.
+    StripPre,
+    /// `strip-build`: drop the build metadata.
+    StripBuild,
+}
+
+impl Transform {
+    /// Apply `transforms` to `version` in order; a result they changed must
+    /// be a valid version. One they left alone is returned as it is, for the
+    /// update to validate like any other version.
+    pub fn apply_all(transforms: &[Transform], version: &str) -> crate::Result<String> {
+        let transformed = transforms
+            .iter()
+            .try_fold(version.to_string(), |version, transform| {
+                transform.apply(&version)
+            })?;
+        if transformed == version {
+            return Ok(transformed);
+        }
+
+        semver::Version::parse(&transformed).with_context(|| {
+            format!("transforming {version} produced {transformed}, which is not a valid version")
+        })?;
+
+        Ok(transformed)
+    }
+
+    fn apply(&self, version: &str) -> anyhow::Result<String> {
+        let parse = || {
+            semver::Version::parse(version)
+                .with_context(|| format!("can't apply `{self}` to {version}, it is not a version"))
+        };
+
+        Ok(match self {
+            Transform::Append(suffix) => format!("{version}{suffix}"),
+            Transform::StripPrefix(prefix) => version
+                .strip_prefix(prefix.as_str())
+                .unwrap_or(version)
+                .to_string(),
+            Transform::StripPre => {
+                let mut version = parse()?;
+                version.pre = semver::Prerelease::EMPTY;
+                version.to_string()
+            }
+            Transform::StripBuild => {
+                let mut version = parse()?;
+                version.build = semver::BuildMetadata::EMPTY;
+                version.to_string()
+            }
+        })
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transform::Append(suffix) => write!(f, "append:{suffix}"),
+            Transform::StripPrefix(prefix) => write!(f, "strip-prefix:{prefix}"),
+            Transform::StripPre => f.write_str("strip-pre"),
+            Transform::StripBuild => f.write_str("strip-build"),
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("append", suffix)) => Ok(Transform::Append(suffix.to_string())),
+            Some(("strip-prefix", prefix)) => Ok(Transform::StripPrefix(prefix.to_string())),
+            None if s == "strip-pre" => Ok(Transform::StripPre),
+            None if s == "strip-build" => Ok(Transform::StripBuild),
+            _ => crate::error::bail!(
+                "`{s}` is not a transform, use append:<text>, strip-prefix:<text>, strip-pre or strip-build"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn applies_transforms_in_order() -> anyhow::Result<()> {
+        let transforms = ["strip-prefix:v", "strip-pre", "strip-build", "append:-dev"]
+            .iter()
+            .map(|t| t.parse())
+            .collect::<anyhow::Result<Vec<Transform>>>()?;
+
+        assert_eq!(
+            Transform::apply_all(&transforms, "v1.5.0-rc.1+abc")?,
+            "1.5.0-dev"
+        );
+        assert_eq!(
+            Transform::apply_all(&[Transform::Append(" dev".to_string())], "1.5.0")
+                .unwrap_err()
+                .to_string(),
+            "transforming 1.5.0 produced 1.5.0 dev, which is not a valid version"
+        );
+        assert_eq!(Transform::apply_all(&[], "banana")?, "banana");
+        assert_eq!(Transform::apply_all(&transforms[..1], "banana")?, "banana");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+
+/// A version change that goes backwards, e.g. `1.2.0` to `1.1.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Downgrade {
+    pub package: String,
+    pub from: semver::Version,
+    pub to: semver::Version,
+}
+
+impl fmt::Display for Downgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} would go down from {} to {}",
+            self.package, self.from, self.to
+        )
+    }
+}
+
+/// Parse `version`, the new version of `package`, as a semantic version.
+pub(crate) fn validate(package: &str, version: &str) -> anyhow::Result<semver::Version> {
+    semver::Version::parse(version).map_err(|e| {
+        anyhow::anyhow!("`{version}` is not a valid version for {package}: {e}, use e.g. `1.2.3`")
+    })
+}
+
+/// The downgrade setting `package` from `current` to `new` would be.
+/// Current versions that don't parse can't be compared and never are one.
+pub(crate) fn downgrade(package: &str, current: &str, new: &semver::Version) -> Option<Downgrade> {
+    let current = semver::Version::parse(current).ok()?;
+
+    (new < &current).then(|| Downgrade {
+        package: package.to_string(),
+        from: current,
+        to: new.clone(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_versions_and_finds_downgrades() -> anyhow::Result<()> {
+        assert_eq!(
+            validate("a", "banana").unwrap_err().to_string(),
+            "`banana` is not a valid version for a: unexpected character 'b' while parsing major version number, use e.g. `1.2.3`"
+        );
+        assert!(validate("a", "1.2").is_err());
+
+        let new = validate("a", "1.2.0-rc.1")?;
+        assert_eq!(
+            downgrade("a", "1.2.0", &new)
+                .map(|d| d.to_string())
+                .as_deref(),
+            Some("a would go down from 1.2.0 to 1.2.0-rc.1")
+        );
+        assert_eq!(downgrade("a", "1.1.9", &new), None);
+        assert_eq!(downgrade("a", "not a version", &new), None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+
+use crate::bump::BumpLevel;
+use crate::filesystem::FileSystem;
+use crate::paths::display_path;
+
+/// What `apply-versions` sets one crate to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionTarget {
+    Exact(semver::Version),
+    Bump(BumpLevel),
+}
+
+impl FromStr for VersionTarget {
+    type Err = anyhow::Error;
+
+    /// A bump level, e.g. `minor`, or else a version.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<BumpLevel>() {
+            Ok(level) => Ok(VersionTarget::Bump(level)),
+            Err(_) => Ok(VersionTarget::Exact(
+                semver::Version::parse(s).with_context(|| {
+                    format!("`{s}` is neither a version nor major, minor or patch")
+                })?,
+            )),
+        }
+    }
+}
+
+/// A mapping from crate names to target versions, as generated by release
+/// tooling:
+///
+/// ```toml
+/// cargo-set = "0.2.0"
+/// cargo-set-lib = "minor"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionsFile {
+    pub targets: BTreeMap<String, VersionTarget>,
+}
+
+impl VersionsFile {
+    pub fn parse(source: &str) -> crate::Result<Self> {
+        let entries: BTreeMap<String, String> = toml::from_str(source)?;
+
+        let targets = entries
+            .into_iter()
+            .map(|(name, value)| {
+                let target = value
+                    .parse::<VersionTarget>()
+                    .map_err(|e| anyhow::anyhow!("`{name}`: {e}"))?;
+                Ok((name, target))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { targets })
+    }
+
+    pub fn load<F: FileSystem>(fs: &F, path: &Path) -> crate::Result<Self> {
+        let contents = fs
+            .read(path)
+            .with_context(|| format!("failed to read {}", display_path(path)))?;
+        let contents = String::from_utf8(contents)
+            .with_context(|| format!("failed to parse {}", display_path(path)))?;
+
+        Ok(Self::parse(&contents)
+            .with_context(|| format!("failed to parse {}", display_path(path)))?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_versions_and_bump_levels() -> anyhow::Result<()> {
+        let file = VersionsFile::parse("a = '1.2.3-rc.1'\nb = 'minor'\n")?;
+        assert_eq!(
+            file.targets,
+            BTreeMap::from([
+                (
+                    "a".to_string(),
+                    VersionTarget::Exact(semver::Version::parse("1.2.3-rc.1")?)
+                ),
+                ("b".to_string(), VersionTarget::Bump(BumpLevel::Minor)),
+            ])
+        );
+
+        let error = VersionsFile::parse("a = 'next'").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`a`: `next` is neither a version nor major, minor or patch"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+
+use crate::index::DependencyLocation;
+use crate::paths::display_path;
+
+/// How a member comes to depend on a crate: the shortest chain of
+/// dependencies from the member to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyPath {
+    pub member: String,
+    /// Crate names from the member to the dependency, both included.
+    pub path: Vec<String>,
+    /// The declaration of the last workspace member on the path, where the
+    /// dependency chain leaves the workspace. Pins and removals go there.
+    pub declaration: DependencyLocation,
+}
+
+impl fmt::Display for DependencyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} (via `{}` in {})",
+            self.member,
+            self.path.join(" -> "),
+            self.declaration.key().join("."),
+            display_path(&self.declaration.manifest)
+        )
+    }
+}
+
+/// The shortest path through `graph`, which maps crate names to the crates
+/// they depend on, from `from` to `to`.
+pub(crate) fn shortest_path(
+    graph: &BTreeMap<String, Vec<String>>,
+    from: &str,
+    to: &str,
+) -> Option<Vec<String>> {
+    let mut previous: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(name) = queue.pop_front() {
+        if name == to {
+            let mut path = vec![to.to_string()];
+            let mut at = to;
+            while let Some(&before) = previous.get(at) {
+                path.push(before.to_string());
+                at = before;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for next in graph.get(name).into_iter().flatten() {
+            if next != from && !previous.contains_key(next.as_str()) {
+                previous.insert(next, name);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_the_shortest_dependency_path() {
+        let graph = BTreeMap::from([
+            (
+                "app".to_string(),
+                vec!["core".to_string(), "reqwest".to_string()],
+            ),
+            ("core".to_string(), vec!["tokio".to_string()]),
+            ("reqwest".to_string(), vec!["hyper".to_string()]),
+            (
+                "hyper".to_string(),
+                vec!["tokio".to_string(), "app".to_string()],
+            ),
+        ]);
+
+        assert_eq!(
+            shortest_path(&graph, "app", "tokio"),
+            Some(vec![
+                "app".to_string(),
+                "core".to_string(),
+                "tokio".to_string()
+            ])
+        );
+        assert_eq!(
+            shortest_path(&graph, "reqwest", "tokio"),
+            Some(vec![
+                "reqwest".to_string(),
+                "hyper".to_string(),
+                "tokio".to_string()
+            ])
+        );
+        assert_eq!(shortest_path(&graph, "core", "hyper"), None);
+    }
+}
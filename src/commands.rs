@@ -1,113 +1,3489 @@
-use std::env::Args;
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::env;
+use std::io::{Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
 
-use cargo_set_lib::{CargoManifestService, RealFileSystem};
-use clap::{Parser, Subcommand, ValueEnum};
+use anyhow::Context;
+use cargo_set_lib::{
+    annotated_tag, changed_files, changelog_section, commit_files, commit_subjects, current_branch,
+    display_path, expand_template, head_commit, import_manifest, import_settings_file,
+    previous_tag, push_branch, remote_url, snapshot_pre, tag_name, tags, workspace_dir,
+    AnnotationLevel, Artifacts, AuditedFileSystem, BuildInfo, BuildInfoFormat, BumpLevel,
+    CargoManifest, CargoManifestService, ChangelogFormat, Config, CrateMatcher, DependencyKind,
+    DependencyTable, Discovery, Document, FeatureEdit, FileSystem, FormatStyle, GitFileSystem,
+    GitReference, Hooks, ImportSource, JournalEntry, KeyPath, KeyValue, Latest, LintSetting,
+    MemoryFileSystem, MetadataEdit, MetadataScope, NewDependency, OutputFormat, PatchEntry,
+    PatchSource, PolicyContext, RealFileSystem, RecordingFileSystem, Registry, RequirementStyle,
+    ResumeMode, SparseIndex, TargetEdit, TimeoutFileSystem, TrackingFileSystem, Transform,
+    UnfreezeStyle, VersionTarget, VersionsFile, CRATES_IO_INDEX, DEFAULT_TAG_FORMAT,
+};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 
-pub fn cli_execute(args: Args) -> anyhow::Result<()> {
-    let cli = Cli::parse_from(args);
-    let cargo_manifest_service = CargoManifestService::new(RealFileSystem);
+use crate::compat::{self, CompatCommand};
+use crate::daemon::Daemon;
+use crate::interactive;
+use crate::pr::{self, Forge, PullRequest};
+use crate::remote::{self, Outcome};
+use crate::roots;
+use crate::watch::{watch, Check};
 
-    match &cli.log_level {
-        Some(level) => {
-            tracing_subscriber::fmt()
-                .with_max_level(level)
-                .pretty()
-                .init();
+/// Exit status when `--fail-if-unchanged` finds nothing changed; errors exit
+/// with 1.
+const EXIT_UNCHANGED: u8 = 2;
+
+pub fn cli_execute(args: env::Args) -> anyhow::Result<ExitCode> {
+    let cli = parse_cli(args)?;
+
+    if let Some(level) = &cli.log_level {
+        tracing_subscriber::fmt()
+            .with_max_level(level)
+            .pretty()
+            .init();
+    }
+
+    let Some(command) = &cli.command else {
+        return Ok(ExitCode::SUCCESS);
+    };
+    let changed = Cell::new(0);
+    let execute = |command: &Commands| {
+        changed.set(changed.get() + execute(&cli, command)?);
+        anyhow::Ok(())
+    };
+    if let Some(url) = &cli.repo {
+        let outcome = match (&cli.push, cli.keep) {
+            (Some(branch), _) => Outcome::Push(branch.clone()),
+            (None, true) => Outcome::Keep,
+            (None, false) => Outcome::Diff,
+        };
+        remote::run(url, cli.branch.as_deref(), &outcome, command, execute)?;
+    } else {
+        let roots = roots::collect(&cli.roots, cli.roots_file.as_deref(), cli.scan.as_deref())?;
+        if roots.is_empty() {
+            execute(command)?;
+        } else {
+            let runs = roots
+                .iter()
+                .map(|root| Ok((root.as_path(), command.with_root(root)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            roots::run_each(&runs, execute)?;
+        }
+    }
+
+    match changed.get() {
+        0 if cli.fail_if_unchanged => {
+            eprintln!("no files changed");
+            Ok(ExitCode::from(EXIT_UNCHANGED))
+        }
+        0 => Ok(ExitCode::SUCCESS),
+        changed => {
+            // Patches, dry runs and streamed manifests leave the files as
+            // they are.
+            let written = cli.emit_patch.is_none()
+                && !cli.stdin
+                && !cli.stdout
+                && !matches!(command, Commands::Set { dry_run: true, .. });
+            match written {
+                true => eprintln!("{changed} file(s) changed"),
+                false => eprintln!("{changed} file(s) would change"),
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// Run `command`, writing its changes as a patch instead if `cli` asks for
+/// one or printing them for `set --dry-run`, and recording them in the
+/// workspace's audit log if it has one.
+///
+/// Returns the number of files changed, or that would be for a patch.
+fn execute(cli: &Cli, command: &Commands) -> anyhow::Result<usize> {
+    if let Some(revision) = &cli.revision {
+        let dir = command.root().map_or_else(
+            |_| PathBuf::from("."),
+            |root| workspace_dir(&root).to_path_buf(),
+        );
+        return run_tracked(cli, command, GitFileSystem::new(dir, revision)?);
+    }
+    let fs = TimeoutFileSystem::new(RealFileSystem, cli.io_timeout.map(Duration::from_secs));
+    if cli.stdin || cli.stdout {
+        return stream(cli, command, fs);
+    }
+
+    let dry_run = matches!(command, Commands::Set { dry_run: true, .. });
+    if cli.emit_patch.is_some() || dry_run {
+        if command.artifacts().is_some_and(|args| args.git_commit) {
+            anyhow::bail!("`--git-commit` needs the changes written, not a patch or `--dry-run`");
+        }
+        if command
+            .artifacts()
+            .is_some_and(|args| args.update_lockfile == Some(LockfileUpdate::Cargo))
+        {
+            anyhow::bail!(
+                "`--update-lockfile cargo` needs the changes written, not a patch or `--dry-run`"
+            );
+        }
+        let recording = RecordingFileSystem::new(fs);
+        let changed = run_tracked(cli, command, &recording)?;
+        if let Some(patch_path) = &cli.emit_patch {
+            std::fs::write(patch_path, recording.patch())
+                .with_context(|| format!("failed to write {}", display_path(patch_path)))?;
+            eprintln!("wrote {}", display_path(patch_path));
+        }
+        if dry_run {
+            print!("{}", recording.patch());
+        }
+
+        return Ok(changed);
+    }
+
+    // Commands without a single workspace, like `daemon`, aren't audited or
+    // recorded in the history.
+    let Ok(root) = command.root() else {
+        return run_tracked(cli, command, fs);
+    };
+    let audit = match cli.untrusted() {
+        false => {
+            Config::load(&RealFileSystem, &root)?
+                .unwrap_or_default()
+                .audit
+        }
+        true => Default::default(),
+    };
+
+    let audited = AuditedFileSystem::new(fs);
+    let now = SystemTime::now();
+    let result = run_tracked(cli, command, &audited);
+    let command_line = env::args().collect::<Vec<_>>().join(" ");
+    if !audit.is_empty() {
+        let record = audited.record(
+            now,
+            &command_line,
+            head_commit(workspace_dir(&root)).ok(),
+            result.as_ref().err(),
+        )?;
+        if let Some(record) = record {
+            audit.append(&root, &record)?;
+        }
+    }
+    // Undoing a run isn't a run to undo, and the runs in a checkout of
+    // `--repo` or for a `pr` are undone with git; their journal would only
+    // end up in the diff.
+    let journaled = !matches!(command, Commands::Revert { .. })
+        && cli.repo.is_none()
+        && !matches!(cli.command, Some(Commands::Pr { .. }));
+    if journaled {
+        if let Err(e) = journal(&audited, now, &command_line, &root) {
+            eprintln!("warning: the run wasn't recorded for `revert`: {e:#}");
+        }
+    }
+
+    result
+}
+
+/// Record what the run seen by `audited` changed in the journal of the
+/// workspace rooted at `root`.
+fn journal<F: FileSystem>(
+    audited: &AuditedFileSystem<F>,
+    now: SystemTime,
+    command_line: &str,
+    root: &Path,
+) -> anyhow::Result<()> {
+    let Some(entry) = audited.journal_entry(now, command_line, root)? else {
+        return Ok(());
+    };
+    let dir = JournalEntry::dir_for(root);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("failed to create {}", display_path(&dir)))?;
+    entry.save(&RealFileSystem, root)?;
+
+    Ok(())
+}
+
+/// Run `command` over `fs`, returning the number of files it changed.
+fn run_tracked<F: FileSystem>(cli: &Cli, command: &Commands, fs: F) -> anyhow::Result<usize> {
+    let tracking = TrackingFileSystem::new(fs);
+    run(cli, service(cli, command, &tracking)?, command)?;
+
+    Ok(tracking.changed_files().len())
+}
+
+/// Run `command` as a filter for `--stdin` and `--stdout`: on the root
+/// manifest read from stdin, or from `fs` with the changes kept in memory,
+/// printing the changed manifest on stdout.
+fn stream<F: FileSystem>(cli: &Cli, command: &Commands, fs: F) -> anyhow::Result<usize> {
+    if command
+        .artifacts()
+        .is_some_and(|args| args.git_commit || args.update_lockfile == Some(LockfileUpdate::Cargo))
+    {
+        anyhow::bail!(
+            "`--git-commit` and `--update-lockfile cargo` need the changes written, not streamed"
+        );
+    }
+
+    let root = command.root()?;
+    let (changed, output) = if cli.stdin {
+        let mut contents = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut contents)
+            .context("failed to read the manifest from stdin")?;
+        let memory = MemoryFileSystem::new().with_file(&root, contents);
+        let changed = run_tracked(cli, command, &memory)?;
+        (changed, memory.read(&root))
+    } else {
+        let recording = RecordingFileSystem::new(fs);
+        let changed = run_tracked(cli, command, &recording)?;
+        (changed, recording.read(&root))
+    };
+    let output = output.with_context(|| format!("failed to read {}", display_path(&root)))?;
+
+    std::io::stdout()
+        .write_all(&output)
+        .context("failed to write the manifest to stdout")?;
+
+    Ok(changed)
+}
+
+/// Parses the command line with the defaults of the project configuration
+/// in the current directory, which flags given on the command line override.
+fn parse_cli<I, T>(args: I) -> anyhow::Result<Cli>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let args = args
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<std::ffi::OsString>>();
+    // Commands that set up or repair a workspace, or don't touch one, run
+    // without its configuration, which may be what is broken.
+    let unconfigured = Cli::command()
+        .try_get_matches_from(&args)
+        .ok()
+        .and_then(|matches| matches.subcommand_name().map(str::to_string))
+        .is_some_and(|name| matches!(name.as_str(), "fix" | "init" | "completions" | "schema"));
+    let config = if unconfigured {
+        Config::default()
+    } else {
+        Config::load(&RealFileSystem, Path::new("Cargo.toml"))?.unwrap_or_default()
+    };
+    let matches = with_config_defaults(Cli::command(), &config).get_matches_from(args);
+
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    cli.config = config;
+
+    Ok(cli)
+}
+
+/// `command` and its subcommands with their defaults taken from `config`.
+fn with_config_defaults(command: clap::Command, config: &Config) -> clap::Command {
+    // Clap only keeps `'static` defaults; these live for the whole run.
+    fn leak(value: String) -> &'static str {
+        Box::leak(value.into_boxed_str())
+    }
+    let path = config
+        .manifest_path
+        .as_ref()
+        .map(|path| leak(path.display().to_string()));
+    let output = config.output.map(|output| match output {
+        OutputFormat::Plain => "plain",
+        OutputFormat::Json => "json",
+    });
+    let level = config.bump.map(|level| leak(level.to_string()));
+    let tag_format = config.tag_format.clone().map(leak);
+
+    command
+        .mut_args(|arg| match arg.get_id().as_str() {
+            "path" if arg.get_default_values() == ["Cargo.toml"] => match path {
+                Some(path) => arg.default_value(path),
+                None => arg,
+            },
+            "output" => match output {
+                Some(output) => arg.default_value(output),
+                None => arg,
+            },
+            "git_tag" => match tag_format {
+                Some(format) => arg.default_missing_value(format),
+                None => arg,
+            },
+            _ => arg,
+        })
+        .mut_subcommands(|subcommand| {
+            let subcommand = match (subcommand.get_name(), level) {
+                ("bump", Some(level)) => {
+                    subcommand.mut_arg("level", |arg| arg.default_value(level))
+                }
+                _ => subcommand,
+            };
+            with_config_defaults(subcommand, config)
+        })
+}
+
+/// A service over `fs`, configured by the global flags of `cli` and the
+/// policy of the workspace `command` works on.
+fn service<F: FileSystem>(
+    cli: &Cli,
+    command: &Commands,
+    fs: F,
+) -> anyhow::Result<CargoManifestService<F>> {
+    let mut cargo_manifest_service = CargoManifestService::new(fs)
+        .with_write_concurrency(cli.jobs)
+        .with_load_concurrency(cli.jobs)
+        .with_lenient(cli.lenient)
+        .with_sorted_dependencies(cli.sort_deps)
+        .with_allow_downgrade(cli.allow_downgrade)
+        .with_atomic_writes(!cli.no_rollback)
+        .with_default_members_only(cli.default_members_only)
+        .with_requirement_style(match cli.exact {
+            true => RequirementStyle::Exact,
+            false => cli.req_style.into(),
+        })
+        .with_if_needed(cli.if_needed)
+        .with_discovery(cli.discovery.into());
+    if cli.normalize {
+        cargo_manifest_service = cargo_manifest_service.with_format(FormatStyle::default());
+    }
+
+    let mut hooks = Hooks::default();
+    // Commands without a single workspace, like `daemon`, run unchecked.
+    if let Ok(root) = command.root() {
+        let config = Config::load(cargo_manifest_service.file_system(), &root)?
+            .unwrap_or_else(|| cli.config.clone());
+        let dev_dependencies = cli
+            .update_dev_deps
+            .or(config.update_dev_dependencies)
+            .unwrap_or(true);
+        cargo_manifest_service = cargo_manifest_service
+            .with_banned_versions(config.banned_versions)
+            .with_dev_dependencies(dev_dependencies);
+        if !cli.untrusted() {
+            hooks = config.hooks;
+        } else if !config.hooks.is_empty() {
+            eprintln!(
+                "warning: not running the `[hooks]` of the clone, pass `--trust-repo` to run them"
+            );
+        }
+        let mut policy = config.policy;
+        policy.protected.extend(cli.protect.iter().cloned());
+        if !policy.is_empty() {
+            let context = PolicyContext {
+                allow_major: cli.allow_major,
+                branch: current_branch(workspace_dir(&root)).ok(),
+                override_protection: cli.override_protection,
+            };
+            cargo_manifest_service = cargo_manifest_service.with_policy(policy, context);
         }
-        None => {}
     }
 
-    match &cli.command {
-        Some(Commands::Set {
+    // Changes that aren't written in place don't run the hooks.
+    let in_place = cli.emit_patch.is_none()
+        && !cli.stdin
+        && !cli.stdout
+        && !matches!(command, Commands::Set { dry_run: true, .. });
+    if in_place {
+        hooks.pre.extend(cli.pre_hook.iter().cloned());
+        hooks.post.extend(cli.post_hook.iter().cloned());
+        cargo_manifest_service = cargo_manifest_service.with_hooks(hooks);
+    }
+
+    Ok(cargo_manifest_service)
+}
+
+fn run<F: FileSystem>(
+    cli: &Cli,
+    cargo_manifest_service: CargoManifestService<F>,
+    command: &Commands,
+) -> anyhow::Result<()> {
+    match command {
+        Commands::Set {
             workspace,
-            _crate,
+            crates,
+            workspace_version,
             path,
             set_version,
+            rev,
+            branch,
+            tag,
             bump,
-        }) => {
+            pre,
+            build,
+            index_url,
+            check_registry,
+            allow_yanked,
+            cascade,
+            transform,
+            artifacts,
+            dry_run: _,
+            output,
+        } => {
+            let cargo_manifest_service = match check_registry {
+                true => cargo_manifest_service
+                    .with_registry_check(SparseIndex::new(index_url.clone()), *allow_yanked),
+                false => cargo_manifest_service,
+            };
             tracing::trace!(
                 workspace = workspace,
-                crate = _crate,
-                path = path.as_ref().unwrap().display().to_string(),
+                crates = ?crates,
+                path = display_path(path.as_ref().unwrap()),
                 set_version = set_version.as_ref(),
                 "command - set"
             );
 
             let mut manifest = cargo_manifest_service.load_manifest(path.as_ref().unwrap())?;
+            let matcher = CrateMatcher::new(crates);
+            let reference = match (rev, branch, tag) {
+                (Some(rev), _, _) => Some(GitReference::Rev(rev.clone())),
+                (_, Some(branch), _) => Some(GitReference::Branch(branch.clone())),
+                (_, _, Some(tag)) => Some(GitReference::Tag(tag.clone())),
+                _ => None,
+            };
+            if let Some(reference) = reference {
+                let changed = cargo_manifest_service
+                    .update_git_reference(&mut manifest, &matcher, &reference)
+                    .inspect_err(report_partial_write)?;
+                for location in &changed {
+                    eprintln!(
+                        "set the {} of `{}` in {} to {}",
+                        reference.key(),
+                        location.key().join("."),
+                        display_path(&location.manifest),
+                        reference.value()
+                    );
+                }
+                return Ok(());
+            }
+            let matched = match workspace_version {
+                true => manifest.version_inheritors(),
+                false => manifest.matching_packages(&matcher)?,
+            };
 
-            if let Some(set_version) = set_version {
-                cargo_manifest_service.update_version(&mut manifest, _crate, set_version)?;
-            } else if let Some(_bump_level) = bump {
-                todo!("haven't implemented bump yet")
+            let (mut given, mut reasons) = match (set_version, bump) {
+                (Some(given), _) if given.starts_with("latest") && *workspace_version => {
+                    anyhow::bail!("`--set-version {given}` needs a `--crate` to look up");
+                }
+                (Some(given), _) if given.starts_with("latest") => {
+                    let latest = given.parse::<Latest>()?;
+                    let [package] = matched.as_slice() else {
+                        anyhow::bail!(
+                            "`--set-version {latest}` needs exactly one crate, `--crate` matched {}",
+                            matched.join(", ")
+                        );
+                    };
+                    (
+                        cargo_manifest_service.latest_version(
+                            &manifest,
+                            package,
+                            latest,
+                            &SparseIndex::new(index_url.clone()),
+                        )?,
+                        vec![format!("the `{latest}` release on {index_url}")],
+                    )
+                }
+                (Some(template), _) => {
+                    let root = path.as_ref().unwrap();
+                    let given = expand_template(template, workspace_dir(root), SystemTime::now())?;
+                    let reason = match given == *template {
+                        true => format!("given with `--set-version {given}`"),
+                        false => format!("expanded from `--set-version {template}`"),
+                    };
+                    (given, vec![reason])
+                }
+                (None, Some(level)) if *workspace_version => (
+                    cargo_manifest_service.bumped_workspace_version(
+                        &manifest,
+                        *level,
+                        pre.as_deref(),
+                    )?,
+                    vec![format!("a `{level}` bump of `workspace.package.version`")],
+                ),
+                (None, Some(level)) => {
+                    let [package] = matched.as_slice() else {
+                        anyhow::bail!(
+                            "`--bump` needs exactly one crate, `--crate` matched {}",
+                            matched.join(", ")
+                        );
+                    };
+                    let level = *level;
+                    (
+                        cargo_manifest_service.bumped_version(
+                            &manifest,
+                            package,
+                            level,
+                            pre.as_deref(),
+                        )?,
+                        vec![format!("a `{level}` bump (`--bump`)")],
+                    )
+                }
+                (None, None) => unreachable!("clap requires --set-version or --bump"),
+            };
+            if let Some(build) = build {
+                let mut version = semver::Version::parse(&given)
+                    .with_context(|| format!("can't attach build metadata to `{given}`"))?;
+                version.build = semver::BuildMetadata::new(build)
+                    .map_err(|e| anyhow::anyhow!("`{build}` is not valid build metadata: {e}"))?;
+                given = version.to_string();
+                reasons.push(format!("with build metadata from `--build {build}`"));
+            }
+            let set_version = Transform::apply_all(transform, &given)?;
+            let cascade = cascade
+                .as_deref()
+                .map(str::parse::<BumpLevel>)
+                .transpose()?;
+            let cascaded = match cascade {
+                Some(level) => {
+                    let updates = matched
+                        .iter()
+                        .map(|package| (package.as_str(), set_version.as_str()))
+                        .collect::<Vec<_>>();
+                    cargo_manifest_service.cascaded_versions(&manifest, &updates, level)?
+                }
+                None => Vec::new(),
+            };
+            let version_of = |package: &str| {
+                cascaded
+                    .iter()
+                    .find(|(cascaded, _)| cascaded == package)
+                    .map_or(set_version.clone(), |(_, version)| version.clone())
+            };
+            if cli.explain {
+                reasons.extend(
+                    transform
+                        .iter()
+                        .map(|transform| format!("then rewritten by `--transform {transform}`")),
+                );
+                let updates = match cascade {
+                    Some(_) => cascaded.clone(),
+                    None => matched
+                        .iter()
+                        .map(|package| (package.clone(), set_version.clone()))
+                        .collect::<Vec<_>>(),
+                };
+                explain(
+                    &cargo_manifest_service,
+                    &manifest,
+                    &updates,
+                    |package| match (cascade, matched.iter().any(|m| m == package)) {
+                        (Some(level), false) => vec![format!(
+                            "a `{level}` bump (`--cascade`), it depends on a crate that was set"
+                        )],
+                        _ => reasons.clone(),
+                    },
+                );
+            }
+            let before = (*output != Output::Plain).then(|| manifest.clone());
+            let updated = match workspace_version {
+                true => {
+                    cargo_manifest_service.update_workspace_version(&mut manifest, &set_version)
+                }
+                false if cascade.is_some() => cargo_manifest_service
+                    .update_versions(&mut manifest, &cascaded)
+                    .map(|_| {
+                        cascaded
+                            .iter()
+                            .map(|(package, _)| package.clone())
+                            .collect()
+                    }),
+                false => {
+                    cargo_manifest_service.update_version(&mut manifest, matcher, &set_version)
+                }
+            }
+            .inspect_err(report_partial_write)?;
+            // Members sharing `workspace.package.version` with a crate that
+            // was set moved along with it.
+            let mut updated = updated;
+            if updated
+                .iter()
+                .any(|package| manifest.version_inheritors().contains(package))
+            {
+                let along = manifest
+                    .version_inheritors()
+                    .into_iter()
+                    .filter(|inheritor| !updated.contains(inheritor))
+                    .collect::<Vec<_>>();
+                if !along.is_empty() {
+                    eprintln!(
+                        "{} {} `workspace.package.version` and moved to {set_version} too",
+                        along.join(", "),
+                        inherit(along.len()),
+                    );
+                }
+                updated.extend(along);
+                updated.sort();
+            }
+            if updated.len() > 1 {
+                for package in &updated {
+                    eprintln!("set {package} to {}", version_of(package));
+                }
+            }
+            for package in &updated {
+                let inheriting = manifest.inheriting_members(package);
+                if !inheriting.is_empty() {
+                    eprintln!(
+                        "{} {} {package} from `[workspace.dependencies]`",
+                        inheriting.join(", "),
+                        inherit(inheriting.len()),
+                    );
+                }
+            }
+            if let Some(before) = &before {
+                let report = cargo_manifest_service.change_report(before, &manifest, &updated)?;
+                match output {
+                    Output::Github => {
+                        for annotation in manifest.change_annotations(&report) {
+                            println!("{annotation}");
+                        }
+                    }
+                    _ => println!("{}", serde_json::to_string_pretty(&report)?),
+                }
+            }
+            let updates = updated
+                .into_iter()
+                .map(|package| {
+                    let version = version_of(&package);
+                    (package, version)
+                })
+                .collect::<Vec<_>>();
+            after_version_change(
+                &cargo_manifest_service,
+                &mut manifest,
+                path.as_ref().unwrap(),
+                artifacts,
+                &updates,
+            )?;
+
+            if cli.stats {
+                eprintln!("{}", manifest.stats());
+            }
+
+            let failures = manifest.load_failures();
+            if !failures.is_empty() {
+                for failure in failures {
+                    eprintln!("skipped {failure}");
+                }
+                anyhow::bail!(
+                    "{} member manifest(s) could not be loaded and were left untouched",
+                    failures.len()
+                );
             }
         }
-        None => {}
-    }
+        Commands::Resume { path, rollback } => {
+            let mode = if *rollback {
+                ResumeMode::Rollback
+            } else {
+                ResumeMode::Finish
+            };
+            let state = cargo_manifest_service.resume(path, mode)?;
 
-    Ok(())
-}
+            let files = match mode {
+                ResumeMode::Finish => state.summary().completed,
+                ResumeMode::Rollback => state.summary().pending,
+            };
+            for file in files {
+                eprintln!("restored {file}");
+            }
+        }
+        Commands::Revert {
+            path,
+            last: _,
+            id,
+            list,
+        } => {
+            if *list {
+                for entry in JournalEntry::load_all(&RealFileSystem, path)? {
+                    println!("{}  {}", entry.id, entry.command);
+                }
+                return Ok(());
+            }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-pub enum LogLevel {
-    Off,
-    Trace,
-    Debug,
-    Info,
-    Warn,
-    Error,
-}
+            let entry = cargo_manifest_service.revert(path, id.as_deref())?;
+            entry.remove(&RealFileSystem, path)?;
+            for file in &entry.files {
+                eprintln!("restored {}", display_path(&file.path));
+            }
+            eprintln!("reverted run {}: {}", entry.id, entry.command);
+        }
+        Commands::Get {
+            _crate,
+            path,
+            output,
+        } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let occurrences = manifest.occurrences(_crate);
+            if occurrences.is_empty() {
+                anyhow::bail!("`{_crate}` doesn't appear in the workspace");
+            }
+            match output {
+                Output::Plain => {
+                    for occurrence in &occurrences {
+                        println!("{occurrence}");
+                    }
+                }
+                Output::Json => println!("{}", serde_json::to_string_pretty(&occurrences)?),
+                Output::Github => {
+                    for occurrence in &occurrences {
+                        let message = match &occurrence.version {
+                            Some(version) => format!("{_crate} is at {version} here"),
+                            None => format!("{_crate} is declared here without a version"),
+                        };
+                        let annotation = manifest.annotate(
+                            AnnotationLevel::Notice,
+                            &occurrence.manifest,
+                            &occurrence.key,
+                            message,
+                        );
+                        println!("{annotation}");
+                    }
+                }
+            }
+        }
+        Commands::Why { package, path } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let paths = cargo_manifest_service.why(&manifest, package);
+            if paths.is_empty() {
+                anyhow::bail!("no member depends on `{package}`");
+            }
+            for path in &paths {
+                println!("{path}");
+            }
+        }
+        Commands::Outdated {
+            path,
+            output,
+            index_url,
+        } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let registry = SparseIndex::new(index_url.clone());
+            let report = cargo_manifest_service.outdated(&manifest, &registry)?;
+            match output {
+                Output::Plain if report.is_empty() => println!("everything is up to date"),
+                Output::Plain => print!("{report}"),
+                Output::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                Output::Github => {
+                    for annotation in manifest.outdated_annotations(&report) {
+                        println!("{annotation}");
+                    }
+                }
+            }
+        }
+        Commands::SyncVersions {
+            path,
+            to,
+            to_max: _,
+            artifacts,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let updates = cargo_manifest_service
+                .align_versions(&mut manifest, to.as_deref())
+                .inspect_err(report_partial_write)?;
+            for (name, version) in &updates {
+                eprintln!("set {name} to {version}");
+            }
+            after_version_change(
+                &cargo_manifest_service,
+                &mut manifest,
+                path,
+                artifacts,
+                &updates,
+            )?;
+        }
+        Commands::Edition {
+            path,
+            edition,
+            workspace,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changed = cargo_manifest_service
+                .set_edition(&mut manifest, edition, *workspace)
+                .inspect_err(report_partial_write)?;
+            for path in &changed {
+                eprintln!("set the edition of {} to {edition}", display_path(path));
+            }
+        }
+        Commands::RustVersion {
+            path,
+            rust_version,
+            workspace,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changed = cargo_manifest_service
+                .set_rust_version(&mut manifest, rust_version, *workspace)
+                .inspect_err(report_partial_write)?;
+            for path in &changed {
+                eprintln!(
+                    "set the rust-version of {} to {rust_version}",
+                    display_path(path)
+                );
+            }
+        }
+        Commands::Add {
+            path,
+            _crate,
+            version,
+            features,
+            dev,
+            build,
+            member,
+            workspace,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let target = match member {
+                Some(member) => manifest
+                    .package_manifest(member)
+                    .map(Path::to_path_buf)
+                    .with_context(|| format!("`{member}` is not a package of the workspace"))?,
+                None => manifest.root_path().to_path_buf(),
+            };
+            let kind = match (workspace, dev, build) {
+                (true, _, _) => DependencyKind::Workspace,
+                (_, true, _) => DependencyKind::Dev,
+                (_, _, true) => DependencyKind::Build,
+                _ => DependencyKind::Normal,
+            };
+            let dependency = NewDependency {
+                table: DependencyTable::new(kind),
+                features: features.clone(),
+                ..NewDependency::new(_crate, version)
+            };
+            cargo_manifest_service
+                .add_dependency(&mut manifest, &target, &dependency)
+                .inspect_err(report_partial_write)?;
+            eprintln!("added {_crate} = {version} to {}", display_path(&target));
+        }
+        Commands::Remove {
+            path,
+            _crate,
+            workspace,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let removed = cargo_manifest_service
+                .remove_dependency_everywhere(&mut manifest, _crate, *workspace)
+                .inspect_err(report_partial_write)?;
+            for location in &removed {
+                eprintln!(
+                    "removed `{}` from {}",
+                    location.key().join("."),
+                    display_path(&location.manifest)
+                );
+            }
+        }
+        Commands::Features {
+            path,
+            _crate,
+            add,
+            remove,
+            default_features,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let edit = FeatureEdit {
+                add: add.clone(),
+                remove: remove.clone(),
+                default_features: *default_features,
+            };
+            let changed = cargo_manifest_service
+                .edit_features(&mut manifest, _crate, &edit)
+                .inspect_err(report_partial_write)?;
+            for location in &changed {
+                eprintln!(
+                    "updated `{}` in {}",
+                    location.key().join("."),
+                    display_path(&location.manifest)
+                );
+            }
+        }
+        Commands::Rename { path, _crate, to } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changed = cargo_manifest_service
+                .rename_package(&mut manifest, _crate, to)
+                .inspect_err(report_partial_write)?;
+            for path in &changed {
+                eprintln!("renamed {_crate} to {to} in {}", display_path(path));
+            }
+        }
+        Commands::Key {
+            path,
+            key,
+            value,
+            literal,
+            workspace,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let value = if *literal {
+                KeyValue::Literal(value.clone())
+            } else {
+                KeyValue::String(value.clone())
+            };
+            let changed = cargo_manifest_service
+                .set_key(&mut manifest, key, &value, *workspace)
+                .inspect_err(report_partial_write)?;
+            for path in &changed {
+                eprintln!("set `{key}` in {}", display_path(path));
+            }
+        }
+        Commands::Metadata {
+            path,
+            tool,
+            workspace,
+            set,
+            value,
+            literal,
+            merge,
+            delete,
+        } => {
+            let edit = match (set, value, merge, delete) {
+                (Some(key), Some(value), _, _) => {
+                    let value = if *literal {
+                        KeyValue::Literal(value.clone())
+                    } else {
+                        KeyValue::String(value.clone())
+                    };
+                    MetadataEdit::Set(key.clone(), value)
+                }
+                (_, _, Some(document), _) => MetadataEdit::Merge(document.clone()),
+                (_, _, _, Some(key)) => MetadataEdit::Delete(key.clone()),
+                _ => anyhow::bail!("give one of `--set`, `--merge` or `--delete`"),
+            };
+            let scope = if *workspace {
+                MetadataScope::Workspace
+            } else {
+                MetadataScope::Packages
+            };
 
-impl Into<tracing::metadata::LevelFilter> for &LogLevel {
-    fn into(self) -> tracing::metadata::LevelFilter {
-        match self {
-            LogLevel::Trace => tracing::metadata::LevelFilter::TRACE,
-            LogLevel::Debug => tracing::metadata::LevelFilter::DEBUG,
-            LogLevel::Info => tracing::metadata::LevelFilter::INFO,
-            LogLevel::Warn => tracing::metadata::LevelFilter::WARN,
-            LogLevel::Error => tracing::metadata::LevelFilter::ERROR,
-            LogLevel::Off => tracing::metadata::LevelFilter::OFF,
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changed = cargo_manifest_service
+                .edit_metadata(&mut manifest, scope, tool, &edit)
+                .inspect_err(report_partial_write)?;
+            for path in &changed {
+                eprintln!("edited the `{tool}` metadata of {}", display_path(path));
+            }
         }
-    }
-}
+        Commands::Patch {
+            path,
+            krate,
+            registry,
+            git,
+            rev,
+            branch,
+            tag,
+            patch_path,
+            remove,
+            command,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            if let Some(PatchCommand::List) = command {
+                for entry in manifest.patches() {
+                    println!("{entry}");
+                }
+                return Ok(());
+            }
 
-#[derive(Parser)]
-#[command(author, version, about, long_about = None)]
-#[command(propagate_version = true)]
-#[command(subcommand_required = true)]
-pub struct Cli {
-    name: Option<String>,
+            let Some(name) = krate else {
+                anyhow::bail!("give the crate to patch with `--crate`");
+            };
+            if *remove {
+                cargo_manifest_service.remove_patch(&mut manifest, registry, name)?;
+                eprintln!("removed the patch of {name} from [patch.{registry}]");
+                return Ok(());
+            }
 
-    #[arg(global = true, help_heading = "Globals", long, default_value = "info")]
-    log_level: Option<LogLevel>,
+            let source = match (git, patch_path) {
+                (Some(url), _) => PatchSource::Git {
+                    url: url.clone(),
+                    reference: match (rev, branch, tag) {
+                        (Some(rev), _, _) => Some(GitReference::Rev(rev.clone())),
+                        (_, Some(branch), _) => Some(GitReference::Branch(branch.clone())),
+                        (_, _, Some(tag)) => Some(GitReference::Tag(tag.clone())),
+                        _ => None,
+                    },
+                },
+                (None, Some(dir)) => PatchSource::Path(dir.clone()),
+                (None, None) => anyhow::bail!("give `--git`, `--patch-path` or `--remove`"),
+            };
+            let entry = PatchEntry {
+                registry: registry.clone(),
+                name: name.clone(),
+                source,
+            };
+            cargo_manifest_service.set_patch(&mut manifest, &entry)?;
+            eprintln!("patched {entry}");
+        }
+        Commands::Member { path, command } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            match command {
+                MemberCommand::Add { dir, create } => {
+                    if *create {
+                        let member_dir = workspace_dir(path).join(dir).join("src");
+                        std::fs::create_dir_all(&member_dir).with_context(|| {
+                            format!("failed to create {}", display_path(&member_dir))
+                        })?;
+                    }
+                    let added = cargo_manifest_service
+                        .add_member(&mut manifest, dir, *create)
+                        .inspect_err(report_partial_write)?;
+                    eprintln!("added {} to `workspace.members`", display_path(&added));
+                }
+                MemberCommand::Remove {
+                    dir,
+                    remove_dependencies,
+                } => {
+                    let removed = cargo_manifest_service
+                        .remove_member(&mut manifest, dir, *remove_dependencies)
+                        .inspect_err(report_partial_write)?;
+                    eprintln!("took {dir} out of the workspace");
+                    for name in removed {
+                        eprintln!("removed `{name}` from `[workspace.dependencies]`");
+                    }
+                }
+            }
+        }
+        Commands::Profile {
+            name,
+            path,
+            settings,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changed = cargo_manifest_service
+                .set_profile(&mut manifest, name, settings)
+                .inspect_err(report_partial_write)?;
+            if changed {
+                eprintln!("set [profile.{name}] in {}", display_path(path));
+            } else {
+                eprintln!("[profile.{name}] is already up to date");
+            }
+        }
+        Commands::Lints {
+            path,
+            settings,
+            inherit,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            if !settings.is_empty() {
+                let changed = cargo_manifest_service
+                    .set_workspace_lints(&mut manifest, settings)
+                    .inspect_err(report_partial_write)?;
+                if changed {
+                    eprintln!("set [workspace.lints] in {}", display_path(path));
+                }
+            }
+            if *inherit {
+                let inheritance = cargo_manifest_service
+                    .inherit_workspace_lints(&mut manifest)
+                    .inspect_err(report_partial_write)?;
+                for path in &inheritance.inherited {
+                    eprintln!("{} inherits the workspace lints", display_path(path));
+                }
+                for path in &inheritance.own_lints {
+                    eprintln!(
+                        "warning: {} has lints of its own, left alone",
+                        display_path(path)
+                    );
+                }
+            }
+        }
+        Commands::Order { path, output } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let order = manifest.publish_order()?;
+            match output {
+                Output::Plain => {
+                    for member in &order {
+                        println!("{}", member.name);
+                    }
+                }
+                Output::Json => println!("{}", serde_json::to_string_pretty(&order)?),
+                Output::Github => {
+                    for (i, member) in order.iter().enumerate() {
+                        let annotation = manifest.annotate(
+                            AnnotationLevel::Notice,
+                            &member.manifest,
+                            &["package".to_string(), "name".to_string()],
+                            format!(
+                                "{} is number {} of {} to publish",
+                                member.name,
+                                i + 1,
+                                order.len()
+                            ),
+                        );
+                        println!("{annotation}");
+                    }
+                }
+            }
+        }
+        Commands::Publish {
+            path,
+            dry_run,
+            index_url,
+            wait_timeout,
+        } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let order = manifest.publish_order()?;
+            let registry = SparseIndex::new(index_url.clone());
+            for (i, member) in order.iter().enumerate() {
+                let Some(version) = member.version.as_deref().filter(|_| member.publish) else {
+                    eprintln!("skipping {}: not published", member.name);
+                    continue;
+                };
+                if registry.has_version(&member.name, version)? {
+                    eprintln!("skipping {} {version}: already published", member.name);
+                    continue;
+                }
+                if *dry_run {
+                    println!("would publish {} {version}", member.name);
+                    continue;
+                }
 
-    #[command(subcommand)]
-    command: Option<Commands>,
-}
+                cargo_publish(manifest.root_path(), &member.name)?;
+                eprintln!("published {} {version}", member.name);
+                let needed = order[i + 1..]
+                    .iter()
+                    .any(|later| later.dependencies.contains(&member.name));
+                if needed {
+                    wait_for_release(
+                        &registry,
+                        &member.name,
+                        version,
+                        Duration::from_secs(*wait_timeout),
+                    )?;
+                }
+            }
+        }
+        Commands::Target {
+            path,
+            crates,
+            rename_bin,
+            bin,
+            required_features,
+            crate_type,
+            lib,
+        } => {
+            let edit = match (rename_bin, required_features, crate_type, lib) {
+                (Some((from, to)), _, _, _) => TargetEdit::RenameBin {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+                (_, Some(features), _, _) => TargetEdit::RequiredFeatures {
+                    bin: bin.clone().unwrap_or_default(),
+                    features: features.iter().filter(|f| !f.is_empty()).cloned().collect(),
+                },
+                (_, _, Some(types), _) => TargetEdit::CrateType(types.clone()),
+                (_, _, _, Some((setting, value))) => TargetEdit::Lib {
+                    setting: setting.clone(),
+                    value: value
+                        .parse()
+                        .with_context(|| format!("`{value}` is not `true` or `false`"))?,
+                },
+                _ => anyhow::bail!(
+                    "give one of `--rename-bin`, `--required-features`, `--crate-type` or `--lib`"
+                ),
+            };
+            let matcher = match crates.is_empty() {
+                true => CrateMatcher::new(["*"]),
+                false => CrateMatcher::new(crates),
+            };
 
-#[derive(Subcommand)]
-pub enum Commands {
-    Set {
-        #[arg(long, default_missing_value = "true", default_value = "false")]
-        workspace: bool,
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changed = cargo_manifest_service
+                .edit_targets(&mut manifest, &matcher, &edit)
+                .inspect_err(report_partial_write)?;
+            for path in &changed {
+                eprintln!("{edit} in {}", display_path(path));
+            }
+            if changed.is_empty() {
+                eprintln!("the targets are already up to date");
+            }
+        }
+        Commands::Lint { path, fix, output } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            if *fix {
+                let fixed = cargo_manifest_service
+                    .fix_lints(&mut manifest)
+                    .inspect_err(report_partial_write)?;
+                for finding in &fixed {
+                    eprintln!("fixed {finding}");
+                }
+            }
 
-        #[arg(long, name = "crate")]
-        _crate: String,
+            let findings = manifest.lint();
+            let annotations = findings
+                .iter()
+                .map(|finding| manifest.lint_annotation(finding))
+                .collect::<Vec<_>>();
+            match output {
+                Output::Plain => {
+                    for (finding, annotation) in findings.iter().zip(&annotations) {
+                        let line = annotation
+                            .line
+                            .map(|line| format!(":{line}"))
+                            .unwrap_or_default();
+                        let fixable = match finding.fix {
+                            Some(_) => " (fixable with --fix)",
+                            None => "",
+                        };
+                        println!(
+                            "{}{line}: {} [{}]{fixable}",
+                            display_path(&finding.manifest),
+                            finding.message,
+                            finding.rule
+                        );
+                    }
+                }
+                Output::Json => println!("{}", serde_json::to_string_pretty(&annotations)?),
+                Output::Github => {
+                    for annotation in &annotations {
+                        println!("{annotation}");
+                    }
+                }
+            }
+            if !findings.is_empty() {
+                anyhow::bail!("{} lint finding(s)", findings.len());
+            }
+        }
+        Commands::VerifyTags {
+            path,
+            format,
+            output,
+        } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let format = match format {
+                Some(format) => format.clone(),
+                None => Config::load(cargo_manifest_service.file_system(), path)?
+                    .and_then(|config| config.tag_format)
+                    .unwrap_or_else(|| DEFAULT_TAG_FORMAT.to_string()),
+            };
+            let checks = manifest.verify_tags(&tags(workspace_dir(path))?, &format)?;
+            match output {
+                Output::Plain => {
+                    for check in &checks {
+                        println!("{check}");
+                    }
+                }
+                Output::Json => println!("{}", serde_json::to_string_pretty(&checks)?),
+                Output::Github => {
+                    for check in checks.iter().filter(|check| !check.is_tagged()) {
+                        println!("{}", manifest.tag_annotation(check));
+                    }
+                }
+            }
+            let untagged = checks.iter().filter(|check| !check.is_tagged()).count();
+            if untagged > 0 {
+                anyhow::bail!("{untagged} member(s) don't have a tag for their version");
+            }
+        }
+        Commands::WorkspaceIfy {
+            path,
+            min_manifests,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let hoisted = cargo_manifest_service
+                .hoist_dependencies(&mut manifest, *min_manifests)
+                .inspect_err(report_partial_write)?;
+            if hoisted.is_empty() {
+                eprintln!("no dependency is declared by {min_manifests} manifests");
+            }
+            for dependency in &hoisted {
+                eprintln!("moved {dependency}");
+            }
+        }
+        Commands::Check {
+            path,
+            crates,
+            expect,
+            output,
+        } => {
+            if crates.len() != expect.len() {
+                anyhow::bail!(
+                    "every `--crate` needs an `--expect`, got {} crate(s) and {} version(s)",
+                    crates.len(),
+                    expect.len()
+                );
+            }
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let versioning = Config::load(cargo_manifest_service.file_system(), path)?
+                .unwrap_or_default()
+                .versioning;
+            let expected = crates
+                .iter()
+                .cloned()
+                .zip(expect.iter().cloned())
+                .collect::<Vec<_>>();
 
-        #[arg(long, default_value = "Cargo.toml")]
-        path: Option<PathBuf>,
+            let violations = manifest.check(&expected, versioning);
+            let annotations = violations
+                .iter()
+                .map(|violation| manifest.violation_annotation(violation))
+                .collect::<Vec<_>>();
+            match output {
+                Output::Plain => {
+                    for violation in &violations {
+                        println!("{violation}");
+                    }
+                }
+                Output::Json => println!("{}", serde_json::to_string_pretty(&annotations)?),
+                Output::Github => {
+                    for annotation in &annotations {
+                        println!("{annotation}");
+                    }
+                }
+            }
+            if !violations.is_empty() {
+                anyhow::bail!("{} version check(s) failed", violations.len());
+            }
+        }
+        Commands::Audit { path } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let banned = cargo_manifest_service.banned_dependencies(&manifest);
+            for banned in &banned {
+                println!("{banned}");
+            }
+            let inconsistencies = manifest.feature_inconsistencies();
+            for inconsistency in &inconsistencies {
+                println!("{inconsistency}");
+            }
+            if !banned.is_empty() || !inconsistencies.is_empty() {
+                anyhow::bail!(
+                    "{} banned version(s) in use, {} dependency(ies) with inconsistent features",
+                    banned.len(),
+                    inconsistencies.len()
+                );
+            }
+        }
+        Commands::Completions { shell } => {
+            clap_complete::generate(
+                *shell,
+                &mut Cli::command(),
+                "cargo-set",
+                &mut std::io::stdout(),
+            );
+        }
+        Commands::Schema { document } => {
+            let schema = Document::from(*document).schema();
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+        Commands::Fix {
+            path,
+            dry_run,
+            interactive,
+        } => {
+            let fixed = cargo_manifest_service.fix(path, |manifest, repair| {
+                eprintln!(
+                    "{}:{}: {}",
+                    display_path(manifest),
+                    repair.line,
+                    repair.message
+                );
+                if *dry_run {
+                    false
+                } else if *interactive {
+                    confirm("apply this fix?")
+                } else {
+                    true
+                }
+            })?;
 
-        #[arg(long, conflicts_with = "bump", required_unless_present = "bump")]
-        set_version: Option<String>,
+            let count: usize = fixed.iter().map(|(_, repairs)| repairs.len()).sum();
+            if !*dry_run {
+                eprintln!("applied {count} fix(es) to {} manifest(s)", fixed.len());
+            }
+        }
+        Commands::Sync { path } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let synced = cargo_manifest_service
+                .sync_versions(&mut manifest)
+                .inspect_err(report_partial_write)?;
 
-        #[arg(long, required_unless_present = "set_version")]
-        bump: Option<BumpLevel>,
-    },
-}
+            for drift in &synced {
+                eprintln!("synced {drift}");
+            }
+            if cli.stats {
+                eprintln!("{}", manifest.stats());
+            }
+        }
+        Commands::CheckInternal { path } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-pub enum BumpLevel {
-    Patch,
-    Minor,
-    Major,
+            let drift = manifest.requirement_drift();
+            if !drift.is_empty() {
+                for finding in &drift {
+                    println!("{finding}");
+                }
+                anyhow::bail!(
+                    "{} internal requirement(s) are out of date, run `cargo set sync` to fix them",
+                    drift.len()
+                );
+            }
+        }
+        Commands::Freeze { path, crates } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changes = cargo_manifest_service
+                .freeze(&mut manifest, crates)
+                .inspect_err(report_partial_write)?;
+
+            for change in &changes {
+                eprintln!("pinned {change}");
+            }
+        }
+        Commands::Unfreeze {
+            path,
+            crates,
+            style,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let changes = cargo_manifest_service
+                .unfreeze(&mut manifest, crates, style.into())
+                .inspect_err(report_partial_write)?;
+
+            for change in &changes {
+                eprintln!("unpinned {change}");
+            }
+        }
+        Commands::Normalize { path, max_width } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let cargo_manifest_service = cargo_manifest_service.with_format(FormatStyle {
+                max_width: *max_width,
+                sort_dependencies: cli.sort_deps,
+                ..FormatStyle::default()
+            });
+            let formatted = cargo_manifest_service
+                .normalize(&mut manifest)
+                .inspect_err(report_partial_write)?;
+
+            for path in &formatted {
+                eprintln!("formatted {}", display_path(path));
+            }
+        }
+        Commands::UnusedDeps { path, fix } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+
+            if *fix {
+                let removed = cargo_manifest_service
+                    .remove_unused_workspace_dependencies(&mut manifest)
+                    .inspect_err(report_partial_write)?;
+                for name in &removed {
+                    eprintln!("removed workspace.dependencies.{name}");
+                }
+            } else {
+                let unused = manifest.unused_workspace_dependencies();
+                if !unused.is_empty() {
+                    for name in &unused {
+                        println!("workspace.dependencies.{name} is not used by any member");
+                    }
+                    anyhow::bail!(
+                        "{} unused workspace dependenc(ies), run with `--fix` to remove them",
+                        unused.len()
+                    );
+                }
+            }
+        }
+        Commands::BuildInfo {
+            path,
+            crates,
+            format,
+            out,
+        } => {
+            let mut config = Config::load(cargo_manifest_service.file_system(), path)?
+                .unwrap_or_default()
+                .build_info;
+            if !crates.is_empty() {
+                config.crates = crates.clone();
+            }
+            if let Some(format) = format {
+                config.format = format.into();
+            }
+            if let Some(out) = out {
+                config.path = out.clone();
+            }
+            if config.crates.is_empty() {
+                anyhow::bail!("no crates selected, pass `--crate` or set `build-info.crates` in .cargo-set.toml");
+            }
+
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let info = BuildInfo::new(head_commit(workspace_dir(path))?, SystemTime::now())?;
+            let written = cargo_manifest_service
+                .write_build_info(&mut manifest, &config, &info)
+                .inspect_err(report_partial_write)?;
+            for path in &written {
+                eprintln!("wrote {}", display_path(path));
+            }
+        }
+        Commands::Watch {
+            path,
+            check_internal,
+            duplicates,
+            unused_deps,
+        } => {
+            let mut checks = [
+                (*check_internal, Check::Internal),
+                (*duplicates, Check::Duplicates),
+                (*unused_deps, Check::UnusedDeps),
+            ]
+            .into_iter()
+            .filter_map(|(selected, check)| selected.then_some(check))
+            .collect::<Vec<_>>();
+            if checks.is_empty() {
+                checks = vec![Check::Internal, Check::Duplicates, Check::UnusedDeps];
+            }
+
+            watch(&cargo_manifest_service, path, &checks)?;
+        }
+        Commands::Daemon { socket } => {
+            Daemon::new(cargo_manifest_service).serve(socket)?;
+        }
+        Commands::Init { path, force } => {
+            let manifest = cargo_manifest_service.load_manifest(path)?;
+            let config_path = Config::path_for(path);
+            if config_path.exists() && !*force {
+                anyhow::bail!(
+                    "{} already exists, pass `--force` to overwrite it",
+                    display_path(&config_path)
+                );
+            }
+
+            let tags = tags(workspace_dir(path)).unwrap_or_else(|e| {
+                tracing::debug!(error = %e, "no git tags to infer the tag format from");
+                Vec::new()
+            });
+            std::fs::write(&config_path, manifest.starter_config(&tags))
+                .with_context(|| format!("failed to write {}", display_path(&config_path)))?;
+            eprintln!("wrote {}", display_path(&config_path));
+        }
+        Commands::ImportConfig { path, from, force } => {
+            let source = ImportSource::from(from);
+            let config_path = Config::path_for(path);
+            if config_path.exists() && !*force {
+                anyhow::bail!(
+                    "{} already exists, pass `--force` to overwrite it",
+                    display_path(&config_path)
+                );
+            }
+
+            let release_toml = workspace_dir(path).join("release.toml");
+            let imported = if source == ImportSource::CargoRelease && release_toml.exists() {
+                let contents = std::fs::read_to_string(&release_toml)
+                    .with_context(|| format!("failed to read {}", display_path(&release_toml)))?;
+                import_settings_file(source, &contents)
+                    .with_context(|| format!("failed to parse {}", display_path(&release_toml)))?
+            } else {
+                let manifest = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read {}", display_path(path)))?;
+                import_manifest(source, &manifest)
+                    .with_context(|| format!("failed to parse {}", display_path(path)))?
+                    .with_context(|| format!("found no {source} settings to import"))?
+            };
+
+            for line in &imported.unsupported {
+                eprintln!("not imported: {line}");
+            }
+            std::fs::write(&config_path, imported.render(source)?)
+                .with_context(|| format!("failed to write {}", display_path(&config_path)))?;
+            eprintln!("wrote {}", display_path(&config_path));
+        }
+        Commands::Bump {
+            path,
+            level,
+            next_dev,
+            pre,
+            snapshot,
+            artifacts,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let pre = if *snapshot {
+                let commit = head_commit(workspace_dir(path))?;
+                Some(snapshot_pre(SystemTime::now(), &commit)?)
+            } else {
+                next_dev.then(|| pre.clone())
+            };
+            if cli.explain {
+                let level = *level;
+                let updates =
+                    cargo_manifest_service.bumped_versions(&manifest, level, pre.as_deref())?;
+                explain(&cargo_manifest_service, &manifest, &updates, |_| {
+                    let mut reasons = vec![format!("a `{level}` bump (`--level`)")];
+                    match (&pre, *snapshot) {
+                        (Some(pre), true) => reasons.push(format!(
+                            "with the snapshot pre-release `{pre}` from today's date and HEAD (`--snapshot`)"
+                        )),
+                        (Some(pre), false) => {
+                            reasons.push(format!("with the pre-release `{pre}` (`--next-dev`)"))
+                        }
+                        (None, _) => {}
+                    }
+                    reasons
+                });
+            }
+            let bumped = cargo_manifest_service
+                .bump_workspace(&mut manifest, *level, pre.as_deref())
+                .inspect_err(report_partial_write)?;
+
+            for (name, version) in &bumped {
+                eprintln!("set {name} to {version}");
+            }
+            after_version_change(
+                &cargo_manifest_service,
+                &mut manifest,
+                path,
+                artifacts,
+                &bumped,
+            )?;
+            if cli.stats {
+                eprintln!("{}", manifest.stats());
+            }
+        }
+        Commands::ApplyVersions {
+            file: VersionsFileArg(file),
+            path,
+            artifacts,
+        } => {
+            let versions = if file == Path::new("-") {
+                let mut contents = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut contents)
+                    .context("failed to read the versions from stdin")?;
+                VersionsFile::parse(&contents).context("failed to parse the versions from stdin")?
+            } else {
+                VersionsFile::load(&RealFileSystem, file)?
+            };
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            if cli.explain {
+                let updates = cargo_manifest_service.resolve_versions(&manifest, &versions)?;
+                explain(&cargo_manifest_service, &manifest, &updates, |name| {
+                    let target = match &versions.targets[name] {
+                        VersionTarget::Exact(version) => format!("`{version}`"),
+                        VersionTarget::Bump(level) => format!("a `{level}` bump"),
+                    };
+                    vec![format!("listed in {} as {target}", display_path(file))]
+                });
+            }
+            let applied = cargo_manifest_service
+                .apply_versions(&mut manifest, &versions)
+                .inspect_err(report_partial_write)?;
+
+            for (name, version) in &applied {
+                eprintln!("set {name} to {version}");
+            }
+            after_version_change(
+                &cargo_manifest_service,
+                &mut manifest,
+                path,
+                artifacts,
+                &applied,
+            )?;
+            if cli.stats {
+                eprintln!("{}", manifest.stats());
+            }
+        }
+        Commands::Interactive {
+            path,
+            level,
+            artifacts,
+        } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+            let versions = interactive::choose_targets(
+                &manifest,
+                *level,
+                &mut std::io::stdin().lock(),
+                &mut std::io::stderr(),
+            )?;
+            if versions.targets.is_empty() {
+                eprintln!("nothing to change");
+                return Ok(());
+            }
+
+            let recording = RecordingFileSystem::new(RealFileSystem);
+            let preview = service(cli, command, &recording)?;
+            preview.apply_versions(&mut preview.load_manifest(path)?, &versions)?;
+            print!("{}", recording.patch());
+            if !confirm("apply these changes?") {
+                eprintln!("nothing was changed");
+                return Ok(());
+            }
+
+            let applied = cargo_manifest_service
+                .apply_versions(&mut manifest, &versions)
+                .inspect_err(report_partial_write)?;
+            for (name, version) in &applied {
+                eprintln!("set {name} to {version}");
+            }
+            after_version_change(
+                &cargo_manifest_service,
+                &mut manifest,
+                path,
+                artifacts,
+                &applied,
+            )?;
+        }
+        Commands::Duplicates { path, fix } => {
+            let mut manifest = cargo_manifest_service.load_manifest(path)?;
+
+            if *fix {
+                let changes = cargo_manifest_service
+                    .unify_duplicate_dependencies(&mut manifest)
+                    .inspect_err(report_partial_write)?;
+                for change in &changes {
+                    eprintln!("{change}");
+                }
+            } else {
+                let duplicates = manifest.duplicate_dependencies();
+                if !duplicates.is_empty() {
+                    for duplicate in &duplicates {
+                        println!("{duplicate}");
+                    }
+                    anyhow::bail!(
+                        "{} crate(s) required at different versions, run with `--fix` to unify them",
+                        duplicates.len()
+                    );
+                }
+            }
+        }
+        Commands::Pr {
+            branch,
+            base,
+            title,
+            forge,
+            command,
+        } => {
+            let command =
+                parse_cli(std::iter::once("cargo-set").chain(command.iter().map(String::as_str)))?
+                    .command
+                    .context("no command given to run")?;
+            if matches!(command, Commands::Pr { .. }) {
+                anyhow::bail!("`pr` can't run another `pr`");
+            }
+            let root = command.root()?;
+            let dir = workspace_dir(&root);
+            pr::check_clean(dir)?;
+            let base = match base {
+                Some(base) => base.clone(),
+                None => current_branch(dir)?,
+            };
+
+            let before = cargo_manifest_service
+                .load_manifest(&root)?
+                .package_versions();
+            execute(cli, &command)?;
+            let after = cargo_manifest_service
+                .load_manifest(&root)?
+                .package_versions();
+            let files = changed_files(dir)?;
+            if files.is_empty() {
+                anyhow::bail!("the command changed nothing, no pull request was opened");
+            }
+
+            // Invoked by cargo as `cargo-set set ...`, or directly as `cargo-set ...`.
+            let args = env::args().skip(1).collect::<Vec<_>>();
+            let args = args.strip_prefix(&["set".to_string()]).unwrap_or(&args);
+            let invocation = format!("cargo set {}", args.join(" "));
+            let body = pr::summary(&invocation, &before, &after, &files);
+            push_branch(dir, branch, title)?;
+            eprintln!("pushed {branch}");
+
+            let url = pr::open(
+                *forge,
+                &remote_url(dir, "origin")?,
+                &PullRequest {
+                    title,
+                    body: &body,
+                    head: branch,
+                    base: &base,
+                },
+            )?;
+            println!("{url}");
+        }
+        Commands::Compat { command } => {
+            compat::execute(&cargo_manifest_service, command)
+                .map_err(cargo_set_lib::Error::from)
+                .inspect_err(report_partial_write)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the version artifacts selected by `args` or the workspace's
+/// configuration, regenerate its configured build info, then commit and tag
+/// the `updates` if `args` asks to.
+pub(crate) fn after_version_change<F: FileSystem>(
+    service: &CargoManifestService<F>,
+    manifest: &mut CargoManifest,
+    root_manifest: &Path,
+    args: &ArtifactArgs,
+    updates: &[(String, String)],
+) -> anyhow::Result<()> {
+    let config = Config::load(service.file_system(), root_manifest)?.unwrap_or_default();
+    let artifacts = Artifacts {
+        version_file: args.version_file.clone(),
+        env_file: args.env_file.clone(),
+        json_file: args.json_file.clone(),
+    }
+    .or(config.artifacts);
+    let mut written = manifest
+        .written_files()
+        .map(Path::to_path_buf)
+        .collect::<Vec<_>>();
+    if !artifacts.is_empty() {
+        for path in service.write_artifacts(manifest, &artifacts)? {
+            eprintln!("wrote {}", display_path(&path));
+            written.push(path);
+        }
+    }
+
+    if !config.build_info.crates.is_empty() {
+        let info = BuildInfo::new(
+            head_commit(workspace_dir(root_manifest))?,
+            SystemTime::now(),
+        )?;
+        for path in service.write_build_info(manifest, &config.build_info, &info)? {
+            eprintln!("wrote {}", display_path(&path));
+            written.push(path);
+        }
+    }
+
+    match args.update_lockfile {
+        Some(LockfileUpdate::Patch) => {
+            if let Some(path) = service.update_lockfile(manifest)? {
+                eprintln!("wrote {}", display_path(&path));
+                written.push(path);
+            }
+        }
+        Some(LockfileUpdate::Cargo) if !updates.is_empty() => {
+            cargo_update(root_manifest, updates)?;
+            written.push(workspace_dir(root_manifest).join("Cargo.lock"));
+        }
+        _ => {}
+    }
+
+    if let Some(style) = args.changelog {
+        let dir = workspace_dir(root_manifest);
+        let tags = tags(dir)?;
+        let tag_format = args
+            .git_tag
+            .clone()
+            .or(config.tag_format.clone())
+            .unwrap_or_else(|| DEFAULT_TAG_FORMAT.to_string());
+        for (name, version) in updates {
+            let Some(package_manifest) = manifest.package_manifest(name) else {
+                continue;
+            };
+            let crate_dir = std::path::absolute(workspace_dir(package_manifest))
+                .with_context(|| format!("failed to resolve {}", display_path(package_manifest)))?;
+            let since = previous_tag(&tags, &tag_format, name, version);
+            let subjects = commit_subjects(dir, since.as_deref(), &crate_dir)?;
+            let section = changelog_section(style.into(), version, SystemTime::now(), &subjects)?;
+            let path = service.write_changelog(manifest, name, &section)?;
+            eprintln!("wrote {}", display_path(&path));
+            written.push(path);
+        }
+    }
+
+    if args.git_commit && !written.is_empty() {
+        commit_and_tag(workspace_dir(root_manifest), &written, args, updates)?;
+    }
+
+    Ok(())
+}
+
+/// Run `cargo update` for each of `updates` to lock it at its new version.
+fn cargo_update(root_manifest: &Path, updates: &[(String, String)]) -> anyhow::Result<()> {
+    for (name, version) in updates {
+        let status =
+            std::process::Command::new(std::env::var_os("CARGO").unwrap_or("cargo".into()))
+                .arg("update")
+                .arg("--manifest-path")
+                .arg(root_manifest)
+                .args(["-p", name, "--precise", version])
+                .status()
+                .context("failed to run cargo")?;
+        if !status.success() {
+            anyhow::bail!("`cargo update -p {name} --precise {version}` failed");
+        }
+    }
+
+    Ok(())
+}
+
+/// Run `cargo publish` for the member `package`.
+fn cargo_publish(root_manifest: &Path, package: &str) -> anyhow::Result<()> {
+    let status = std::process::Command::new(std::env::var_os("CARGO").unwrap_or("cargo".into()))
+        .arg("publish")
+        .arg("--manifest-path")
+        .arg(root_manifest)
+        .args(["-p", package])
+        .status()
+        .context("failed to run cargo")?;
+    if !status.success() {
+        anyhow::bail!("`cargo publish -p {package}` failed");
+    }
+
+    Ok(())
+}
+
+/// Poll `registry` until it has `version` of `package`, for up to `timeout`.
+fn wait_for_release(
+    registry: &impl Registry,
+    package: &str,
+    version: &str,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let start = std::time::Instant::now();
+    while !registry.has_version(package, version)? {
+        if start.elapsed() >= timeout {
+            anyhow::bail!(
+                "{package} {version} didn't appear on the registry within {}s",
+                timeout.as_secs()
+            );
+        }
+        eprintln!("waiting for {package} {version} to appear on the registry");
+        std::thread::sleep(Duration::from_secs(5));
+    }
+
+    Ok(())
+}
+
+fn commit_and_tag(
+    dir: &Path,
+    written: &[PathBuf],
+    args: &ArtifactArgs,
+    updates: &[(String, String)],
+) -> anyhow::Result<()> {
+    let written = written
+        .iter()
+        .map(std::path::absolute)
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to resolve the written files")?;
+    let versions = updates
+        .iter()
+        .map(|(name, version)| format!("{name} {version}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (first_crate, first_version) = updates
+        .first()
+        .map(|(name, version)| (name.as_str(), version.as_str()))
+        .unwrap_or_default();
+    let message = args
+        .git_message
+        .replace("{versions}", &versions)
+        .replace("{crate}", first_crate)
+        .replace("{version}", first_version);
+    commit_files(dir, &written, &message)?;
+    eprintln!("committed {} file(s): {message}", written.len());
+
+    if let Some(format) = &args.git_tag {
+        for (name, version) in updates {
+            let tag = tag_name(format, name, version);
+            annotated_tag(dir, &tag, &format!("{name} {version}"))?;
+            eprintln!("tagged {tag}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Print why each of `updates` is made; `reasons` tells how the version of
+/// a crate was chosen.
+fn explain<F: FileSystem>(
+    cargo_manifest_service: &CargoManifestService<F>,
+    manifest: &CargoManifest,
+    updates: &[(String, String)],
+    reasons: impl Fn(&str) -> Vec<String>,
+) {
+    for (name, version) in updates {
+        let explanation = cargo_manifest_service.explain(manifest, name, version, reasons(name));
+        eprintln!("{explanation}");
+    }
+}
+
+/// A `KEY=VALUE` setting of `profile --set`.
+fn parse_setting(setting: &str) -> Result<(String, String), String> {
+    match setting.split_once('=') {
+        Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+        None => Err(format!("`{setting}` is not KEY=VALUE")),
+    }
+}
+
+/// The verb for `count` members inheriting something.
+fn inherit(count: usize) -> &'static str {
+    match count {
+        1 => "inherits",
+        _ => "inherit",
+    }
+}
+
+/// Ask a yes/no question on stderr and read the answer from stdin.
+fn confirm(question: &str) -> bool {
+    eprint!("{question} [y/N] ");
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Print which manifests an interrupted update did and didn't write, as JSON
+/// on stdout, and how to settle it.
+fn report_partial_write(error: &cargo_set_lib::Error) {
+    let cargo_set_lib::Error::PartialWrite(partial) = error else {
+        return;
+    };
+
+    match serde_json::to_string_pretty(&partial.state.summary()) {
+        Ok(summary) => println!("{summary}"),
+        Err(e) => tracing::warn!(error = %e, "failed to serialize partial write report"),
+    }
+    if partial.state_path.is_some() {
+        eprintln!("run `cargo set resume` to finish or `cargo set resume --rollback` to undo");
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LogLevel {
+    Off,
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<&LogLevel> for tracing::metadata::LevelFilter {
+    fn from(level: &LogLevel) -> Self {
+        match level {
+            LogLevel::Trace => tracing::metadata::LevelFilter::TRACE,
+            LogLevel::Debug => tracing::metadata::LevelFilter::DEBUG,
+            LogLevel::Info => tracing::metadata::LevelFilter::INFO,
+            LogLevel::Warn => tracing::metadata::LevelFilter::WARN,
+            LogLevel::Error => tracing::metadata::LevelFilter::ERROR,
+            LogLevel::Off => tracing::metadata::LevelFilter::OFF,
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+#[command(after_help = "Exit status:\n  \
+    0  success, whether files changed or not\n  \
+    1  an error\n  \
+    2  no files changed, with `--fail-if-unchanged`")]
+#[command(propagate_version = true)]
+#[command(subcommand_required = true)]
+pub struct Cli {
+    name: Option<String>,
+
+    #[arg(global = true, help_heading = "Globals", long, default_value = "info")]
+    log_level: Option<LogLevel>,
+
+    /// Print phase timings and file counts when the command finishes
+    #[arg(global = true, help_heading = "Globals", long)]
+    stats: bool,
+
+    /// Exit with status 2 when the command changes no files, e.g. a version
+    /// that is already set
+    #[arg(global = true, help_heading = "Globals", long)]
+    fail_if_unchanged: bool,
+
+    /// Only work on the `default-members` of the workspace, if it declares
+    /// them
+    #[arg(global = true, help_heading = "Globals", long)]
+    default_members_only: bool,
+
+    /// How to find the members of the workspace: from the `members` of its
+    /// manifest, or as `cargo metadata` reports them
+    #[arg(
+        global = true,
+        help_heading = "Globals",
+        long,
+        value_enum,
+        default_value = "manifest"
+    )]
+    discovery: MemberDiscovery,
+
+    /// Skip member manifests that fail to load instead of aborting
+    #[arg(global = true, help_heading = "Globals", long)]
+    lenient: bool,
+
+    /// When writing a manifest fails, keep the ones already written and save
+    /// a resume state for `resume` instead of restoring them
+    #[arg(global = true, help_heading = "Globals", long)]
+    no_rollback: bool,
+
+    /// Maximum number of manifests read or written at the same time
+    #[arg(global = true, help_heading = "Globals", long, default_value = "1")]
+    jobs: NonZeroUsize,
+
+    /// Normalize the formatting of every manifest an update writes
+    #[arg(global = true, help_heading = "Globals", long)]
+    normalize: bool,
+
+    /// Keep dependency tables alphabetically sorted in every manifest written
+    #[arg(global = true, help_heading = "Globals", long)]
+    sort_deps: bool,
+
+    /// Whether version changes update `[dev-dependencies]` requirements too,
+    /// overriding `update-dev-dependencies` of the configuration
+    #[arg(global = true, help_heading = "Globals", long, value_name = "BOOL")]
+    update_dev_deps: Option<bool>,
+
+    /// How requirements on a crate are rewritten when its version changes:
+    /// `preserve` keeps their operators, e.g. `~1.2` to `~1.3.0`
+    #[arg(
+        global = true,
+        help_heading = "Globals",
+        long,
+        value_enum,
+        value_name = "STYLE",
+        default_value = "preserve"
+    )]
+    req_style: ReqStyle,
+
+    /// Pin requirements on changed crates to their exact new version, like
+    /// `--req-style exact`
+    #[arg(global = true, help_heading = "Globals", long)]
+    exact: bool,
+
+    /// Only rewrite requirements the new version doesn't already meet, e.g.
+    /// leave `^1.4` alone for 1.4.7
+    #[arg(global = true, help_heading = "Globals", long)]
+    if_needed: bool,
+
+    /// A shell command to run before writing manifests, with the changes as
+    /// JSON on stdin; the write is refused if it fails. Runs after the
+    /// `pre` hooks of `.cargo-set.toml`
+    #[arg(global = true, help_heading = "Globals", long, value_name = "COMMAND")]
+    pre_hook: Vec<String>,
+
+    /// A shell command to run after writing manifests, with the changes as
+    /// JSON on stdin, e.g. `cargo check`
+    #[arg(global = true, help_heading = "Globals", long, value_name = "COMMAND")]
+    post_hook: Vec<String>,
+
+    /// Fail file operations that take longer than this many seconds
+    #[arg(global = true, help_heading = "Globals", long, value_name = "SECONDS")]
+    io_timeout: Option<u64>,
+
+    /// Read the workspace as this git revision has it, e.g. a release tag,
+    /// instead of the working tree, without checking it out. Commands that
+    /// write fail
+    #[arg(
+        global = true,
+        help_heading = "Globals",
+        long,
+        value_name = "REV",
+        conflicts_with_all = ["repo", "emit_patch", "stdin", "stdout"]
+    )]
+    revision: Option<String>,
+
+    /// Run the command in each of these workspaces, each as its own
+    /// operation, instead of at `--path`; may be repeated
+    #[arg(
+        global = true,
+        help_heading = "Globals",
+        long = "root",
+        value_name = "PATH"
+    )]
+    roots: Vec<PathBuf>,
+
+    /// Also run in the workspaces listed in this file, one root manifest or
+    /// directory per line
+    #[arg(global = true, help_heading = "Globals", long, value_name = "FILE")]
+    roots_file: Option<PathBuf>,
+
+    /// Also run in every workspace found below this directory: each manifest
+    /// with a `[workspace]` table and each package outside of one
+    #[arg(global = true, help_heading = "Globals", long, value_name = "DIR")]
+    scan: Option<PathBuf>,
+
+    /// Clone this git repository to a temporary directory and run the
+    /// command there, printing the changes as a diff
+    ///
+    /// The `[hooks]` and `[audit]` of the clone's `.cargo-set.toml` or
+    /// `workspace.metadata.cargo-set` are ignored unless `--trust-repo` is
+    /// given: they would run commands and write files from a repository that
+    /// may not be yours.
+    #[arg(
+        global = true,
+        help_heading = "Remote",
+        long,
+        value_name = "URL",
+        conflicts_with_all = ["roots", "roots_file", "scan"]
+    )]
+    repo: Option<String>,
+
+    /// The branch of `--repo` to check out instead of its default branch
+    #[arg(global = true, help_heading = "Remote", long, requires = "repo")]
+    branch: Option<String>,
+
+    /// Commit the changes to this new branch and push it instead of printing
+    /// them
+    #[arg(
+        global = true,
+        help_heading = "Remote",
+        long,
+        value_name = "BRANCH",
+        requires = "repo"
+    )]
+    push: Option<String>,
+
+    /// Leave the checkout in place for inspection instead of printing the
+    /// changes
+    #[arg(
+        global = true,
+        help_heading = "Remote",
+        long,
+        requires = "repo",
+        conflicts_with = "push"
+    )]
+    keep: bool,
+
+    /// Run the `[hooks]` and keep the `[audit]` log of the `--repo` clone's
+    /// configuration
+    #[arg(global = true, help_heading = "Remote", long, requires = "repo")]
+    trust_repo: bool,
+
+    /// Allow major version bumps the workspace's policy would refuse
+    #[arg(global = true, help_heading = "Globals", long)]
+    allow_major: bool,
+
+    /// Before changing versions, print for every crate why its new version
+    /// was chosen and which files are edited because of it
+    #[arg(global = true, help_heading = "Globals", long)]
+    explain: bool,
+
+    /// Don't warn when an update sets a crate to a lower version
+    #[arg(global = true, help_heading = "Globals", long)]
+    allow_downgrade: bool,
+
+    /// Allow changes to crates the workspace's policy marks as protected
+    #[arg(global = true, help_heading = "Globals", long)]
+    override_protection: bool,
+
+    /// Refuse any edit of this crate's manifest, version or declarations,
+    /// as if the policy protected it; a name or glob pattern. Repeatable
+    #[arg(global = true, help_heading = "Globals", long, value_name = "CRATE")]
+    protect: Vec<String>,
+
+    /// Write every edit to this file as a patch for `git apply` instead of
+    /// changing any file
+    #[arg(
+        global = true,
+        help_heading = "Globals",
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["roots", "roots_file", "scan", "repo"]
+    )]
+    emit_patch: Option<PathBuf>,
+
+    /// Read the root manifest from stdin instead of `--path` and print it,
+    /// changed, on stdout; `--path` only names it. Its members aren't read
+    #[arg(
+        global = true,
+        help_heading = "Globals",
+        long,
+        conflicts_with_all = ["roots", "roots_file", "scan", "repo", "emit_patch"]
+    )]
+    stdin: bool,
+
+    /// Print the changed root manifest on stdout instead of writing any file
+    #[arg(
+        global = true,
+        help_heading = "Globals",
+        long,
+        conflicts_with_all = ["roots", "roots_file", "scan", "repo", "emit_patch"]
+    )]
+    stdout: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// The configuration of the current directory, used by workspaces without
+    /// their own.
+    #[arg(skip)]
+    config: Config,
+}
+
+impl Cli {
+    /// Whether this runs in a clone of `--repo` whose configuration may not
+    /// run commands or write outside the checkout.
+    fn untrusted(&self) -> bool {
+        self.repo.is_some() && !self.trust_repo
+    }
+}
+
+#[derive(Subcommand, Clone)]
+pub enum Commands {
+    /// Set or bump the versions of members and update the requirements of
+    /// the crates depending on them
+    Set {
+        #[arg(long, default_missing_value = "true", default_value = "false")]
+        workspace: bool,
+
+        /// The crates to set, by name or glob pattern like `my-org-*`.
+        /// Repeatable
+        #[arg(
+            long = "crate",
+            name = "crate",
+            value_name = "CRATE",
+            required_unless_present = "workspace_version"
+        )]
+        crates: Vec<String>,
+
+        /// Set `[workspace.package] version` instead of `--crate`, and with
+        /// it every member that inherits it
+        #[arg(long, conflicts_with = "crate")]
+        workspace_version: bool,
+
+        #[arg(long, default_value = "Cargo.toml")]
+        path: Option<PathBuf>,
+
+        /// The new version, or `latest`, `latest-minor` or `latest-patch` for
+        /// the newest release of the crate on `--index-url`. Placeholders
+        /// are expanded first: `{git_describe}`, `{git_sha}`,
+        /// `{git_sha_short}`, `{date:%Y%m%d}` and `{env:NAME}`, e.g.
+        /// `0.5.0-nightly.{date:%Y%m%d}+{git_sha_short}`
+        #[arg(
+            long,
+            conflicts_with_all = ["bump", "git_reference"],
+            required_unless_present_any = ["bump", "git_reference"]
+        )]
+        set_version: Option<String>,
+
+        /// Point the git dependencies on `--crate` at this commit instead of
+        /// setting a version, replacing their `branch` or `tag`
+        #[arg(
+            long,
+            group = "git_reference",
+            conflicts_with_all = ["workspace_version", "bump", "cascade"]
+        )]
+        rev: Option<String>,
+
+        /// Point the git dependencies on `--crate` at this branch instead
+        #[arg(
+            long,
+            group = "git_reference",
+            conflicts_with_all = ["workspace_version", "bump", "cascade"]
+        )]
+        branch: Option<String>,
+
+        /// Point the git dependencies on `--crate` at this tag instead; not
+        /// to be confused with `--git-tag`, which tags the commit of a release
+        #[arg(
+            long,
+            group = "git_reference",
+            conflicts_with_all = ["workspace_version", "bump", "cascade"]
+        )]
+        tag: Option<String>,
+
+        /// The sparse index `--set-version latest` and `--check-registry`
+        /// look crates up in
+        #[arg(long, value_name = "URL", default_value = CRATES_IO_INDEX)]
+        index_url: String,
+
+        /// Before setting a dependency that isn't a member, refuse versions
+        /// of it that `--index-url` doesn't have or has yanked, e.g. the
+        /// typo `1.0.1999`
+        #[arg(long)]
+        check_registry: bool,
+
+        /// Only warn about yanked versions with `--check-registry`
+        #[arg(long, requires = "check_registry")]
+        allow_yanked: bool,
+
+        /// The bump: patch, minor, major, pre, release or calver, which
+        /// takes a format, e.g. `calver:YY.MM.MICRO`
+        #[arg(
+            long,
+            value_name = "LEVEL",
+            required_unless_present_any = ["set_version", "git_reference"]
+        )]
+        bump: Option<BumpLevel>,
+
+        /// The pre-release label of the bump, e.g. `rc` to go from
+        /// 1.4.0-rc.1 to 1.4.0-rc.2 with `--bump pre`, or from 1.4.0 to
+        /// 1.5.0-rc.1 with `--bump minor`
+        #[arg(long, value_name = "LABEL", requires = "bump")]
+        pre: Option<String>,
+
+        /// Build metadata to attach to the new version, e.g. `sha.abc1234`
+        #[arg(long, value_name = "META")]
+        build: Option<String>,
+
+        /// Also bump every member depending on the crate, directly or
+        /// through other members, by `patch` or `minor`, so they can be
+        /// published with it
+        #[arg(
+            long,
+            value_name = "LEVEL",
+            value_parser = ["patch", "minor"],
+            conflicts_with = "workspace_version"
+        )]
+        cascade: Option<String>,
+
+        #[command(flatten)]
+        artifacts: ArtifactArgs,
+
+        /// Rewrite the new version before writing it: append:<text>,
+        /// strip-prefix:<text>, strip-pre or strip-build. Repeatable, applied
+        /// in order
+        #[arg(long, value_name = "TRANSFORM")]
+        transform: Vec<Transform>,
+
+        /// Print the changes as a unified diff instead of writing them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Print a report of every file edited and the values changed in it
+        /// with `json`, or annotate the values with `github`
+        #[arg(long, value_enum, default_value = "plain")]
+        output: Output,
+    },
+    /// Undo a run that changed files, from the history kept in
+    /// `.cargo-set/history/`
+    Revert {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Undo the last run, the default
+        #[arg(long, conflicts_with_all = ["id", "list"])]
+        last: bool,
+
+        /// Undo the run with this id, as shown by `--list`
+        #[arg(long, value_name = "RUN", conflicts_with = "list")]
+        id: Option<String>,
+
+        /// List the recorded runs, oldest first
+        #[arg(long)]
+        list: bool,
+    },
+    /// Finish or roll back an update that failed after writing some manifests,
+    /// with `--no-rollback` or when restoring them failed
+    Resume {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Restore the manifests that were already written instead
+        #[arg(long)]
+        rollback: bool,
+    },
+    /// Align the version requirements of internal path dependencies with the
+    /// current versions of the members they point at
+    Sync {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+    },
+    /// Fail if any internal path dependency requires a version its member
+    /// doesn't have
+    CheckInternal {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+    },
+    /// Pin dependency requirements to the exact versions in Cargo.lock
+    Freeze {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Only pin dependencies on this crate; may be repeated
+        #[arg(long = "crate", value_name = "CRATE")]
+        crates: Vec<String>,
+    },
+    /// Turn exact `=x.y.z` pins back into range requirements
+    Unfreeze {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Only unpin dependencies on this crate; may be repeated
+        #[arg(long = "crate", value_name = "CRATE")]
+        crates: Vec<String>,
+
+        /// How to write the range: `1.2` (minor), `1.2.3` (bare) or `^1.2.3` (caret)
+        #[arg(long, default_value = "minor")]
+        style: RangeStyle,
+    },
+    /// Normalize spacing, key order and array layout of every manifest
+    Normalize {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Width after which arrays are written one element per line
+        #[arg(long, default_value = "80")]
+        max_width: usize,
+    },
+    /// Report `[workspace.dependencies]` entries that no member inherits
+    UnusedDeps {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Remove the unused entries
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Record the version, git commit and build time of crates, as a
+    /// generated Rust source or in `[package.metadata.build-info]`
+    BuildInfo {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Crates to record, instead of `build-info.crates` from the config
+        #[arg(long = "crate", value_name = "CRATE")]
+        crates: Vec<String>,
+
+        #[arg(long)]
+        format: Option<BuildInfoOutput>,
+
+        /// Where the Rust source goes, relative to each crate
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Re-run checks whenever a manifest of the workspace changes. Runs every
+    /// check unless some are selected
+    Watch {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Check internal requirements, like `check-internal`
+        #[arg(long)]
+        check_internal: bool,
+
+        /// Check for conflicting declarations, like `duplicates`
+        #[arg(long)]
+        duplicates: bool,
+
+        /// Check for unused workspace dependencies, like `unused-deps`
+        #[arg(long)]
+        unused_deps: bool,
+    },
+    /// Serve load, query and edit requests as newline-delimited JSON-RPC on a
+    /// unix socket, keeping loaded workspaces between requests
+    Daemon {
+        #[arg(long, default_value = ".cargo-set.sock")]
+        socket: PathBuf,
+    },
+    /// Write a starter `.cargo-set.toml` with defaults inferred from the
+    /// workspace and its git tags
+    Init {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Overwrite an existing configuration
+        #[arg(long)]
+        force: bool,
+    },
+    /// Translate cargo-release or cargo-workspaces settings into a
+    /// `.cargo-set.toml`
+    ImportConfig {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[arg(long)]
+        from: ImportTool,
+
+        /// Overwrite an existing configuration
+        #[arg(long)]
+        force: bool,
+    },
+    /// Bump every package of the workspace
+    Bump {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The bump: patch, minor, major, pre, release or calver, which
+        /// takes a format, e.g. `calver:YY.MM.MICRO`
+        #[arg(long, default_value = "minor")]
+        level: BumpLevel,
+
+        /// Start the next development cycle: bump, then add the `--pre`
+        /// pre-release, e.g. 1.4.0 -> 1.5.0-dev
+        #[arg(long)]
+        next_dev: bool,
+
+        /// The pre-release used by `--next-dev`, e.g. `dev` or `alpha.0`
+        #[arg(long, default_value = "dev", requires = "next_dev")]
+        pre: String,
+
+        /// Make a throwaway snapshot version from the date and git HEAD,
+        /// e.g. 1.4.0 -> 1.5.0-snapshot.20240601.abc1234
+        #[arg(long, conflicts_with = "next_dev")]
+        snapshot: bool,
+
+        #[command(flatten)]
+        artifacts: ArtifactArgs,
+    },
+    /// Set several crates at once from a file mapping crate names to
+    /// versions or bump levels, e.g. `foo = "1.2.0"` or `bar = "minor"`
+    #[command(visible_alias = "apply")]
+    ApplyVersions {
+        #[command(flatten)]
+        file: VersionsFileArg,
+
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[command(flatten)]
+        artifacts: ArtifactArgs,
+    },
+    /// Pick members and a bump level or version for each at prompts, preview
+    /// the changes and apply them once confirmed
+    Interactive {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The bump of crates left without an answer
+        #[arg(long, default_value = "patch")]
+        level: BumpLevel,
+
+        #[command(flatten)]
+        artifacts: ArtifactArgs,
+    },
+    /// List crates a member requires at different versions in different
+    /// dependency tables
+    Duplicates {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Require the highest of the versions everywhere
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Run a command on a new branch, push it and open a GitHub or GitLab
+    /// pull request describing the changes, e.g. `cargo set pr --branch
+    /// bump bump --level minor`. Tokens come from `GITHUB_TOKEN` or
+    /// `GITLAB_TOKEN`
+    Pr {
+        /// The branch to create and push
+        #[arg(long)]
+        branch: String,
+
+        /// The branch to merge into, instead of the checked out one
+        #[arg(long)]
+        base: Option<String>,
+
+        #[arg(long, default_value = "Update crate versions")]
+        title: String,
+
+        /// The kind of forge `origin` is on, if its URL doesn't tell
+        #[arg(long)]
+        forge: Option<Forge>,
+
+        /// The command to run, with its arguments
+        #[arg(
+            value_name = "COMMAND",
+            required = true,
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+    /// `add`, `rm`, `upgrade` and `set-version` with the flags and output of
+    /// cargo-edit, so scripts can switch by changing the binary name
+    Compat {
+        #[command(subcommand)]
+        command: CompatCommand,
+    },
+    /// Repair common manifest mistakes such as unquoted versions, misspelled
+    /// dependency tables and duplicate keys
+    Fix {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Only list the problems found
+        #[arg(long, conflicts_with = "interactive")]
+        dry_run: bool,
+
+        /// Ask before applying each fix
+        #[arg(long)]
+        interactive: bool,
+    },
+    /// Report dependency declarations and `Cargo.lock` entries using a
+    /// version in the `[banned-versions]` of `.cargo-set.toml`, and
+    /// dependencies members enable with inconsistent features
+    Audit {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+    },
+    /// Show which members depend on a crate and through which crates, from
+    /// the manifests and `Cargo.lock`. Pins and removals go in the
+    /// declaration shown
+    Why {
+        /// The crate to trace, e.g. `tokio`
+        package: String,
+
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+    },
+    /// Print everywhere a crate appears, its own version and every
+    /// declaration depending on it, with the current version
+    Get {
+        #[arg(long, name = "crate")]
+        _crate: String,
+
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[arg(long, value_enum, default_value = "plain")]
+        output: Output,
+    },
+    /// Compare members with their latest release and third-party requirements
+    /// with the latest release of each dependency, to plan upgrades
+    Outdated {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Print the report as JSON or as `github` annotations
+        #[arg(long, value_enum, default_value = "plain")]
+        output: Output,
+
+        /// The sparse index to compare against
+        #[arg(long, value_name = "URL", default_value = CRATES_IO_INDEX)]
+        index_url: String,
+    },
+    /// Set every crate of the workspace to one version, for lockstep releases
+    SyncVersions {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The version, e.g. `1.2.3`
+        #[arg(long, value_name = "VERSION", required_unless_present = "to_max")]
+        to: Option<String>,
+
+        /// Use the highest version any crate is at
+        #[arg(long, conflicts_with = "to")]
+        to_max: bool,
+
+        #[command(flatten)]
+        artifacts: ArtifactArgs,
+    },
+    /// Set the Rust edition of the root manifest, or of every manifest with
+    /// `--workspace`
+    Edition {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The edition, e.g. `2021`
+        #[arg(long)]
+        edition: String,
+
+        /// Also set it in every member
+        #[arg(long)]
+        workspace: bool,
+    },
+    /// Set the minimum supported Rust version, `rust-version`, of the root
+    /// manifest, or of every manifest with `--workspace`
+    RustVersion {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The version, e.g. `1.70`
+        #[arg(long, value_name = "VERSION")]
+        rust_version: String,
+
+        /// Also set it in every member
+        #[arg(long)]
+        workspace: bool,
+    },
+    /// Declare a new dependency in the root manifest, a member or
+    /// `[workspace.dependencies]`
+    #[command(disable_version_flag = true)]
+    Add {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[arg(long, name = "crate")]
+        _crate: String,
+
+        /// The version requirement, e.g. `1.0`
+        #[arg(long)]
+        version: String,
+
+        /// Features to enable, comma separated or repeated
+        #[arg(long, value_delimiter = ',')]
+        features: Vec<String>,
+
+        /// Add it to `[dev-dependencies]`
+        #[arg(long, conflicts_with = "build")]
+        dev: bool,
+
+        /// Add it to `[build-dependencies]`
+        #[arg(long)]
+        build: bool,
+
+        /// The member to add it to, instead of the root manifest
+        #[arg(long, value_name = "CRATE")]
+        member: Option<String>,
+
+        /// Add it to `[workspace.dependencies]`
+        #[arg(long, conflicts_with_all = ["member", "dev", "build"])]
+        workspace: bool,
+    },
+    /// Remove a dependency from every dependency table of the root manifest,
+    /// or of every manifest with `--workspace`
+    Remove {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[arg(long, name = "crate")]
+        _crate: String,
+
+        /// Also remove it from every member
+        #[arg(long)]
+        workspace: bool,
+    },
+    /// Enable or disable features of a dependency everywhere it's declared
+    Features {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[arg(long, name = "crate")]
+        _crate: String,
+
+        /// Features to enable, comma separated or repeated
+        #[arg(long, value_delimiter = ',', value_name = "FEATURE")]
+        add: Vec<String>,
+
+        /// Features to disable, comma separated or repeated
+        #[arg(long, value_delimiter = ',', value_name = "FEATURE")]
+        remove: Vec<String>,
+
+        /// Set `default-features`, except on inherited declarations
+        #[arg(long, value_name = "BOOL")]
+        default_features: Option<bool>,
+    },
+    /// Rename a crate of the workspace and every dependency on it. Its
+    /// directory is left where it is
+    Rename {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[arg(long, name = "crate")]
+        _crate: String,
+
+        /// The new name
+        #[arg(long, value_name = "NAME")]
+        to: String,
+    },
+    /// Set any value by its dotted key, for fields without a command of
+    /// their own
+    Key {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The dotted key, e.g. `package.repository`
+        #[arg(long)]
+        key: KeyPath,
+
+        /// The value, written as a string unless `--literal` is given
+        #[arg(long)]
+        value: String,
+
+        /// Read the value as TOML, e.g. `true` or `["a", "b"]`
+        #[arg(long)]
+        literal: bool,
+
+        /// Also set it in every member
+        #[arg(long)]
+        workspace: bool,
+    },
+    /// Set, merge or delete keys of a tool's `[package.metadata.<tool>]` in
+    /// every package, or of `[workspace.metadata.<tool>]` with `--workspace`
+    #[command(group(clap::ArgGroup::new("edit").required(true)))]
+    Metadata {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The tool's dotted key below `metadata`, e.g. `docs.rs` or `dist`
+        #[arg(long)]
+        tool: KeyPath,
+
+        /// Edit `[workspace.metadata.<tool>]` of the root instead
+        #[arg(long)]
+        workspace: bool,
+
+        /// Set this dotted key below the tool's table to `--value`
+        #[arg(long, value_name = "KEY", group = "edit", requires = "value")]
+        set: Option<KeyPath>,
+
+        /// The value for `--set`, written as a string unless `--literal` is
+        /// given
+        #[arg(long, requires = "set")]
+        value: Option<String>,
+
+        /// Read `--value` as TOML, e.g. `true` or `["a", "b"]`
+        #[arg(long, requires = "set")]
+        literal: bool,
+
+        /// Merge this TOML document into the tool's table, e.g.
+        /// `all-features = true`
+        #[arg(long, value_name = "TOML", group = "edit")]
+        merge: Option<String>,
+
+        /// Delete this dotted key below the tool's table, or the whole table
+        /// without one
+        #[arg(long, value_name = "KEY", group = "edit", num_args = 0..=1)]
+        delete: Option<Option<KeyPath>>,
+    },
+    /// Add or remove a `[patch]` entry of the root manifest, e.g. `cargo set
+    /// patch --crate foo --git https://github.com/org/foo --rev abc123`, or
+    /// list them with `patch list`
+    #[command(
+        args_conflicts_with_subcommands = true,
+        subcommand_negates_reqs = true,
+        group(clap::ArgGroup::new("source").required(true))
+    )]
+    Patch {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The crate to patch
+        #[arg(long = "crate", value_name = "CRATE", required = true)]
+        krate: Option<String>,
+
+        /// The registry whose crate is patched
+        #[arg(long, default_value = "crates-io")]
+        registry: String,
+
+        /// Take the crate from this git repository
+        #[arg(long, value_name = "URL", group = "source")]
+        git: Option<String>,
+
+        /// The commit of `--git` to use
+        #[arg(long, requires = "git", conflicts_with_all = ["branch", "tag"])]
+        rev: Option<String>,
+
+        /// The branch of `--git` to use
+        #[arg(long, requires = "git", conflicts_with = "tag")]
+        branch: Option<String>,
+
+        /// The tag of `--git` to use
+        #[arg(long, requires = "git")]
+        tag: Option<String>,
+
+        /// Take the crate from this directory
+        #[arg(long, value_name = "DIR", group = "source")]
+        patch_path: Option<String>,
+
+        /// Remove the crate's patch instead
+        #[arg(long, group = "source")]
+        remove: bool,
+
+        #[command(subcommand)]
+        command: Option<PatchCommand>,
+    },
+    /// Add a directory to `workspace.members`, or take a member out of the
+    /// workspace
+    Member {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[command(subcommand)]
+        command: MemberCommand,
+    },
+    /// Set keys of `[profile.<name>]` in the root manifest, e.g. `cargo set
+    /// profile release --set lto=thin --set codegen-units=1`, checking them
+    /// against what cargo accepts
+    Profile {
+        /// The profile, e.g. `release`; custom profiles need `inherits`
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// A setting as KEY=VALUE, e.g. `opt-level=3` or `inherits=release`;
+        /// may be repeated
+        #[arg(long = "set", value_name = "KEY=VALUE", required = true, value_parser = parse_setting)]
+        settings: Vec<(String, String)>,
+    },
+    /// Set lint levels in `[workspace.lints]` and make members inherit them,
+    /// e.g. `cargo set lints --set unsafe_code=deny --set
+    /// clippy::pedantic=warn:-1 --inherit`
+    #[command(group(clap::ArgGroup::new("edit").multiple(true).required(true)))]
+    Lints {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// A lint as rustc names it and its level, with an optional priority
+        /// after a colon, e.g. `unsafe_code=deny`; may be repeated
+        #[arg(long = "set", value_name = "LINT=LEVEL", group = "edit")]
+        settings: Vec<LintSetting>,
+
+        /// Make every member without lints of its own use `lints.workspace =
+        /// true`
+        #[arg(long, group = "edit")]
+        inherit: bool,
+    },
+    /// Print the members in the order to publish them, each after the
+    /// members it depends on. Fails on a dependency cycle
+    Order {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        #[arg(long, value_enum, default_value = "plain")]
+        output: Output,
+    },
+    /// Publish the members in dependency order with `cargo publish`, waiting
+    /// for each to appear on the registry before publishing the members
+    /// that depend on it. Versions already on the registry and members with
+    /// `publish = false` are skipped
+    Publish {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Print what would be published without publishing it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// The sparse index of the registry the members are published to
+        #[arg(long, value_name = "URL", default_value = CRATES_IO_INDEX)]
+        index_url: String,
+
+        /// Fail when a published member doesn't appear on the index within
+        /// this many seconds
+        #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+        wait_timeout: u64,
+    },
+    /// Edit the `[[bin]]` and `[lib]` targets of members: rename a binary,
+    /// set its `required-features`, or set `[lib]` settings like
+    /// `crate-type`, e.g. `cargo set target --lib doctest=false`
+    #[command(group(clap::ArgGroup::new("edit").required(true)))]
+    Target {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The members to edit, by name or glob pattern like `my-org-*`;
+        /// every member without one. Repeatable
+        #[arg(long = "crate", value_name = "CRATE")]
+        crates: Vec<String>,
+
+        /// Rename a binary, e.g. `old-name=new-name`
+        #[arg(long, value_name = "OLD=NEW", group = "edit", value_parser = parse_setting)]
+        rename_bin: Option<(String, String)>,
+
+        /// The binary `--required-features` is set on
+        #[arg(long, value_name = "NAME", requires = "required_features")]
+        bin: Option<String>,
+
+        /// Set the `required-features` of `--bin`, comma separated, or
+        /// remove them when empty
+        #[arg(
+            long,
+            value_name = "FEATURES",
+            value_delimiter = ',',
+            num_args = 0..=1,
+            group = "edit",
+            requires = "bin"
+        )]
+        required_features: Option<Vec<String>>,
+
+        /// Set `[lib] crate-type`, comma separated, e.g. `cdylib,rlib`
+        #[arg(long, value_name = "TYPES", value_delimiter = ',', group = "edit")]
+        crate_type: Option<Vec<String>>,
+
+        /// Set a boolean `[lib]` setting, e.g. `doctest=false`
+        #[arg(long, value_name = "SETTING=BOOL", group = "edit", value_parser = parse_setting)]
+        lib: Option<(String, String)>,
+    },
+    /// Check how members declare their dependencies: crates they declare
+    /// instead of inheriting from `[workspace.dependencies]`, crates required
+    /// at different versions and path dependencies without a `version`.
+    /// Fails with a line per finding
+    Lint {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// Fix the findings that don't need a decision first, like adding
+        /// the member's version to a path dependency
+        #[arg(long)]
+        fix: bool,
+
+        /// Print the findings as JSON or as `github` annotations
+        #[arg(long, value_enum, default_value = "plain")]
+        output: Output,
+    },
+    /// Check that every published member has a git tag for its version, and
+    /// fail with a line per member that doesn't or whose tags are of a
+    /// newer version
+    VerifyTags {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// The tags' names, with `{crate}` and `{version}`; defaults to
+        /// `tag-format` of `.cargo-set.toml`, else `{crate}-v{version}`
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<String>,
+
+        /// Print the checks as JSON or the missing tags as `github`
+        /// annotations
+        #[arg(long, value_enum, default_value = "plain")]
+        output: Output,
+    },
+    /// Move dependencies several manifests declare into
+    /// `[workspace.dependencies]` and inherit them with `workspace = true`
+    WorkspaceIfy {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// How many manifests must declare a dependency for it to move
+        #[arg(long, value_name = "N", default_value = "2")]
+        min_manifests: usize,
+    },
+    /// Verify the workspace's versions without writing anything: internal
+    /// requirements, lockstep versioning and expected versions. Fails with a
+    /// line per violation
+    Check {
+        #[arg(long, default_value = "Cargo.toml")]
+        path: PathBuf,
+
+        /// A crate to expect at the version of the matching `--expect`;
+        /// repeatable
+        #[arg(long = "crate", value_name = "CRATE", requires = "expect")]
+        crates: Vec<String>,
+
+        /// The version the matching `--crate` must be at
+        #[arg(long, value_name = "VERSION", requires = "crates")]
+        expect: Vec<String>,
+
+        /// Print the violations as JSON or as `github` annotations
+        #[arg(long, value_enum, default_value = "plain")]
+        output: Output,
+    },
+    /// Print the completion script of a shell, e.g.
+    /// `cargo set completions bash > /etc/bash_completion.d/cargo-set`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the JSON Schema of a file cargo-set reads or writes, for editor
+    /// validation and generating types
+    Schema {
+        #[arg(value_enum)]
+        document: SchemaDocument,
+    },
+}
+
+impl Commands {
+    /// The artifact flags of commands that change versions.
+    fn artifacts(&self) -> Option<&ArtifactArgs> {
+        match self {
+            Commands::Set { artifacts, .. }
+            | Commands::SyncVersions { artifacts, .. }
+            | Commands::Bump { artifacts, .. }
+            | Commands::ApplyVersions { artifacts, .. }
+            | Commands::Interactive { artifacts, .. } => Some(artifacts),
+            _ => None,
+        }
+    }
+}
+
+// Version files to write after a version change, overriding the
+// `[artifacts]` of `.cargo-set.toml`. Relative paths are taken from the
+// workspace root. A plain comment, as clap would take a doc comment for the
+// about of the commands flattening these.
+#[derive(Args, Clone, Default)]
+#[command(next_help_heading = "Artifacts")]
+pub struct ArtifactArgs {
+    /// Write the shared workspace version to this file
+    #[arg(long, value_name = "PATH")]
+    version_file: Option<PathBuf>,
+
+    /// Write a `.env` fragment with every crate's version to this file
+    #[arg(long, value_name = "PATH")]
+    env_file: Option<PathBuf>,
+
+    /// Write a JSON object of crate versions to this file
+    #[arg(long, value_name = "PATH")]
+    json_file: Option<PathBuf>,
+
+    /// Bring the members' entries in `Cargo.lock` up to date, by patching
+    /// the lockfile or by running `cargo update -p <crate> --precise <version>`
+    #[arg(
+        long,
+        value_name = "HOW",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "patch"
+    )]
+    update_lockfile: Option<LockfileUpdate>,
+
+    /// Add a section with the commits since the previous release to each
+    /// changed crate's `CHANGELOG.md`
+    #[arg(long, value_name = "FORMAT", value_enum)]
+    changelog: Option<ChangelogStyle>,
+
+    /// Commit exactly the files that were written
+    #[arg(long)]
+    git_commit: bool,
+
+    /// The commit message; `{versions}` becomes e.g. `a 1.2.3, b 1.2.3`, and
+    /// `{crate}` and `{version}` the first crate and its version
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        default_value = "Release {versions}",
+        requires = "git_commit"
+    )]
+    git_message: String,
+
+    /// Create an annotated tag for every changed crate after committing, named
+    /// by FORMAT with `{crate}` and `{version}` replaced
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_TAG_FORMAT,
+        requires = "git_commit"
+    )]
+    git_tag: Option<String>,
+}
+
+/// The versions file of `apply-versions`, `-` for stdin, given as FILE or
+/// with `--from`. Clap has no argument that is both, so the two are read
+/// into the one path here.
+#[derive(Clone)]
+pub struct VersionsFileArg(PathBuf);
+
+impl FromArgMatches for VersionsFileArg {
+    fn from_arg_matches(matches: &clap::ArgMatches) -> Result<Self, clap::Error> {
+        matches
+            .get_one::<PathBuf>("file")
+            .or(matches.get_one("from"))
+            .cloned()
+            .map(Self)
+            .ok_or_else(|| clap::Error::new(clap::error::ErrorKind::MissingRequiredArgument))
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &clap::ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+
+        Ok(())
+    }
+}
+
+impl Args for VersionsFileArg {
+    fn augment_args(command: clap::Command) -> clap::Command {
+        command
+            .arg(
+                clap::Arg::new("file")
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required_unless_present("from")
+                    .help("The versions file, `-` for stdin"),
+            )
+            .arg(
+                clap::Arg::new("from")
+                    .long("from")
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .conflicts_with("file")
+                    .help("The versions file, `-` for stdin, as an alternative to FILE"),
+            )
+    }
+
+    fn augment_args_for_update(command: clap::Command) -> clap::Command {
+        Self::augment_args(command)
+    }
+}
+
+#[derive(Subcommand, Clone)]
+pub enum PatchCommand {
+    /// List the `[patch]` entries of the root manifest
+    List,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum MemberCommand {
+    /// Add a directory to `workspace.members`
+    Add {
+        /// The member's directory, relative to the workspace root
+        #[arg(value_name = "DIR")]
+        dir: String,
+
+        /// Write a minimal manifest for a package named after the directory
+        /// if it has none
+        #[arg(long)]
+        create: bool,
+    },
+    /// Take a member out of `workspace.members` and `default-members`, or
+    /// into `workspace.exclude` if a glob matches it. Its files stay
+    Remove {
+        /// The member's directory, relative to the workspace root
+        #[arg(value_name = "DIR")]
+        dir: String,
+
+        /// Also remove the `[workspace.dependencies]` entries with a path to
+        /// it
+        #[arg(long)]
+        remove_dependencies: bool,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum LockfileUpdate {
+    /// Rewrite the members' `[[package]]` entries in place
+    Patch,
+    /// Let cargo update the lockfile
+    Cargo,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ChangelogStyle {
+    /// Added, Fixed and Changed sections, as on keepachangelog.com
+    KeepAChangelog,
+    /// Features, bug fixes and breaking changes from conventional commits
+    Conventional,
+}
+
+impl From<ChangelogStyle> for ChangelogFormat {
+    fn from(style: ChangelogStyle) -> Self {
+        match style {
+            ChangelogStyle::KeepAChangelog => ChangelogFormat::KeepAChangelog,
+            ChangelogStyle::Conventional => ChangelogFormat::Conventional,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    Plain,
+    Json,
+    /// GitHub Actions workflow commands, annotating the manifest lines
+    Github,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum MemberDiscovery {
+    Manifest,
+    Cargo,
+}
+
+impl From<MemberDiscovery> for Discovery {
+    fn from(discovery: MemberDiscovery) -> Self {
+        match discovery {
+            MemberDiscovery::Manifest => Discovery::Manifest,
+            MemberDiscovery::Cargo => Discovery::Cargo,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ImportTool {
+    CargoRelease,
+    CargoWorkspaces,
+}
+
+impl From<&ImportTool> for ImportSource {
+    fn from(tool: &ImportTool) -> Self {
+        match tool {
+            ImportTool::CargoRelease => ImportSource::CargoRelease,
+            ImportTool::CargoWorkspaces => ImportSource::CargoWorkspaces,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum BuildInfoOutput {
+    Rust,
+    Metadata,
+}
+
+impl From<&BuildInfoOutput> for BuildInfoFormat {
+    fn from(format: &BuildInfoOutput) -> Self {
+        match format {
+            BuildInfoOutput::Rust => BuildInfoFormat::Rust,
+            BuildInfoOutput::Metadata => BuildInfoFormat::Metadata,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ReqStyle {
+    Preserve,
+    Exact,
+    Caret,
+    Tilde,
+}
+
+impl From<ReqStyle> for RequirementStyle {
+    fn from(style: ReqStyle) -> Self {
+        match style {
+            ReqStyle::Preserve => RequirementStyle::Preserve,
+            ReqStyle::Exact => RequirementStyle::Exact,
+            ReqStyle::Caret => RequirementStyle::Caret,
+            ReqStyle::Tilde => RequirementStyle::Tilde,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum RangeStyle {
+    Minor,
+    Bare,
+    Caret,
+}
+
+impl From<&RangeStyle> for UnfreezeStyle {
+    fn from(style: &RangeStyle) -> Self {
+        match style {
+            RangeStyle::Minor => UnfreezeStyle::Minor,
+            RangeStyle::Bare => UnfreezeStyle::Bare,
+            RangeStyle::Caret => UnfreezeStyle::Caret,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum SchemaDocument {
+    /// `.cargo-set.toml`
+    Config,
+    /// The JSON object of the `--json-file` artifact
+    Versions,
+    /// The input of `apply-versions`
+    VersionsFile,
+    /// A line of the `[audit]` log file
+    AuditRecord,
+    /// The report of `set --output json`
+    ChangeReport,
+}
+
+impl From<SchemaDocument> for Document {
+    fn from(document: SchemaDocument) -> Self {
+        match document {
+            SchemaDocument::Config => Document::Config,
+            SchemaDocument::Versions => Document::Versions,
+            SchemaDocument::VersionsFile => Document::VersionsFile,
+            SchemaDocument::AuditRecord => Document::AuditRecord,
+            SchemaDocument::ChangeReport => Document::ChangeReport,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fixes_a_root_manifest_that_does_not_parse() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cargo-set-fix-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("a"))?;
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"a\"]\n\n[workspace.package]\nversion = 0.1.0\n",
+        )?;
+        std::fs::write(
+            dir.join("a/Cargo.toml"),
+            "[package]\nname = \"a\"\nversion.workspace = true\n",
+        )?;
+        let root = dir.join("Cargo.toml");
+
+        let cli = parse_cli([
+            "cargo-set".as_ref(),
+            "fix".as_ref(),
+            "--path".as_ref(),
+            root.as_os_str(),
+        ])?;
+        let changed = execute(&cli, cli.command.as_ref().expect("fix was given"))?;
+
+        assert_eq!(changed, 1);
+        assert!(std::fs::read_to_string(&root)?.contains("version = \"0.1.0\""));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
 }
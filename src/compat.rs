@@ -0,0 +1,328 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use cargo_set_lib::{
+    BumpLevel, CargoManifest, CargoManifestService, DependencyKind, DependencyTable, FileSystem,
+    NewDependency,
+};
+use clap::{Args, Subcommand};
+
+use crate::commands::{after_version_change, ArtifactArgs};
+
+/// Drop-in replacements for `cargo add`, `cargo rm`, `cargo upgrade` and
+/// `cargo set-version`, taking the same flags and printing the same status
+/// lines. Everything is resolved offline.
+#[derive(Subcommand, Clone)]
+pub enum CompatCommand {
+    /// Add dependencies to a manifest, like `cargo add`
+    Add {
+        /// Dependencies to add, as `name@version`
+        #[arg(value_name = "DEP_ID", required = true)]
+        dependencies: Vec<String>,
+
+        #[command(flatten)]
+        section: Section,
+
+        /// Features to enable, separated by commas or spaces; may be repeated
+        #[arg(short = 'F', long)]
+        features: Vec<String>,
+
+        /// Mark the dependency as optional
+        #[arg(long)]
+        optional: bool,
+
+        /// Disable the default features
+        #[arg(long)]
+        no_default_features: bool,
+
+        /// Declare the dependency under this name instead
+        #[arg(long)]
+        rename: Option<String>,
+
+        #[command(flatten)]
+        manifest: ManifestArgs,
+    },
+    /// Remove dependencies from a manifest, like `cargo rm`
+    Rm {
+        /// Dependencies to remove
+        #[arg(value_name = "DEP_ID", required = true)]
+        dependencies: Vec<String>,
+
+        #[command(flatten)]
+        section: Section,
+
+        #[command(flatten)]
+        manifest: ManifestArgs,
+    },
+    /// Raise requirements to the versions in Cargo.lock, like
+    /// `cargo upgrade --offline`
+    Upgrade {
+        /// Only upgrade this dependency; may be repeated
+        #[arg(short = 'p', long = "package", value_name = "PKGID")]
+        packages: Vec<String>,
+
+        /// Accepted for compatibility, upgrades never use the network
+        #[arg(long)]
+        offline: bool,
+
+        #[arg(long, default_value = "Cargo.toml")]
+        manifest_path: PathBuf,
+    },
+    /// Change the version of packages, like `cargo set-version`
+    SetVersion {
+        /// The version to set
+        #[arg(value_name = "TARGET", required_unless_present = "bump")]
+        target: Option<String>,
+
+        /// Bump the current version instead
+        #[arg(long, conflicts_with = "target")]
+        bump: Option<BumpLevel>,
+
+        /// Package to change; may be repeated
+        #[arg(short = 'p', long = "package", value_name = "PKGID")]
+        packages: Vec<String>,
+
+        /// Change every package of the workspace
+        #[arg(long, alias = "all", conflicts_with = "packages")]
+        workspace: bool,
+
+        /// Package to leave out with `--workspace`; may be repeated
+        #[arg(long, value_name = "PKGID", requires = "workspace")]
+        exclude: Vec<String>,
+
+        #[arg(long, default_value = "Cargo.toml")]
+        manifest_path: PathBuf,
+    },
+}
+
+impl CompatCommand {
+    /// Replace the command's `--manifest-path` with `map(path)`.
+    pub fn map_manifest_path(&mut self, map: impl FnOnce(&Path) -> PathBuf) {
+        let path = match self {
+            CompatCommand::Add { manifest, .. } | CompatCommand::Rm { manifest, .. } => {
+                &mut manifest.manifest_path
+            }
+            CompatCommand::Upgrade { manifest_path, .. }
+            | CompatCommand::SetVersion { manifest_path, .. } => manifest_path,
+        };
+        *path = map(path);
+    }
+}
+
+/// The dependency table `add` and `rm` edit.
+#[derive(Args, Clone)]
+pub struct Section {
+    /// Use `[dev-dependencies]`
+    #[arg(short = 'D', long, conflicts_with = "build")]
+    dev: bool,
+
+    /// Use `[build-dependencies]`
+    #[arg(short = 'B', long)]
+    build: bool,
+
+    /// Use the table of this target, e.g. `cfg(unix)`
+    #[arg(long)]
+    target: Option<String>,
+}
+
+impl Section {
+    fn table(&self) -> DependencyTable {
+        let kind = if self.dev {
+            DependencyKind::Dev
+        } else if self.build {
+            DependencyKind::Build
+        } else {
+            DependencyKind::Normal
+        };
+
+        match &self.target {
+            Some(target) => DependencyTable::target(kind, target),
+            None => DependencyTable::new(kind),
+        }
+    }
+}
+
+/// Which manifest `add` and `rm` edit.
+#[derive(Args, Clone)]
+pub struct ManifestArgs {
+    /// Edit the manifest of this package instead of the root
+    #[arg(short = 'p', long = "package", value_name = "PKGID")]
+    package: Option<String>,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    manifest_path: PathBuf,
+}
+
+impl ManifestArgs {
+    fn select(&self, manifest: &CargoManifest) -> anyhow::Result<PathBuf> {
+        match &self.package {
+            Some(package) => manifest
+                .package_manifest(package)
+                .map(Path::to_path_buf)
+                .with_context(|| format!("package `{package}` not found in the workspace")),
+            None => Ok(manifest.root_path().to_path_buf()),
+        }
+    }
+}
+
+pub fn execute<F: FileSystem>(
+    service: &CargoManifestService<F>,
+    command: &CompatCommand,
+) -> anyhow::Result<()> {
+    match command {
+        CompatCommand::Add {
+            dependencies,
+            section,
+            features,
+            optional,
+            no_default_features,
+            rename,
+            manifest: manifest_args,
+        } => {
+            if rename.is_some() && dependencies.len() > 1 {
+                anyhow::bail!("cannot specify multiple crates with `--rename`");
+            }
+            let features = features
+                .iter()
+                .flat_map(|f| f.split([',', ' ']))
+                .filter(|f| !f.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>();
+
+            let mut manifest = service.load_manifest(&manifest_args.manifest_path)?;
+            let path = manifest_args.select(&manifest)?;
+            let table = section.table();
+            for id in dependencies {
+                let Some((name, requirement)) = id.split_once('@') else {
+                    anyhow::bail!(
+                        "`{id}` has no version; the registry isn't queried, pass `{id}@<version>`"
+                    );
+                };
+                let dependency = NewDependency {
+                    package: rename.as_ref().map(|_| name.to_string()),
+                    table: table.clone(),
+                    features: features.clone(),
+                    optional: *optional,
+                    default_features: !*no_default_features,
+                    ..NewDependency::new(rename.as_deref().unwrap_or(name), requirement)
+                };
+
+                eprintln!(
+                    "{:>12} {name} v{requirement} to {}",
+                    "Adding",
+                    section_name(&table)
+                );
+                if !features.is_empty() {
+                    eprintln!("{:>12} Features:", "");
+                    for feature in &features {
+                        eprintln!("{:>12} + {feature}", "");
+                    }
+                }
+                service.add_dependency(&mut manifest, &path, &dependency)?;
+            }
+        }
+        CompatCommand::Rm {
+            dependencies,
+            section,
+            manifest: manifest_args,
+        } => {
+            let mut manifest = service.load_manifest(&manifest_args.manifest_path)?;
+            let path = manifest_args.select(&manifest)?;
+            let table = section.table();
+            for name in dependencies {
+                eprintln!("{:>12} {name} from {}", "Removing", section_name(&table));
+                service.remove_dependency(
+                    &mut manifest,
+                    &path,
+                    name,
+                    std::slice::from_ref(&table),
+                )?;
+            }
+        }
+        CompatCommand::Upgrade {
+            packages,
+            offline: _,
+            manifest_path,
+        } => {
+            let mut manifest = service.load_manifest(manifest_path)?;
+            let changes = service.upgrade(&mut manifest, packages)?;
+            for change in &changes {
+                eprintln!(
+                    "{:>12} {} v{} -> v{}",
+                    "Upgrading", change.location.name, change.from, change.to
+                );
+            }
+        }
+        CompatCommand::SetVersion {
+            target,
+            bump,
+            packages,
+            workspace,
+            exclude,
+            manifest_path,
+        } => {
+            let mut manifest = service.load_manifest(manifest_path)?;
+            let versions = manifest.package_versions();
+            for package in packages {
+                if !versions.iter().any(|(name, _)| name == package) {
+                    anyhow::bail!("package `{package}` not found in the workspace");
+                }
+            }
+
+            // Like cargo-edit, default to the root package, or to every
+            // package of a virtual workspace.
+            let root_package = versions
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .find(|name| manifest.package_manifest(name) == Some(manifest.root_path()));
+            let selected = |name: &str| {
+                if *workspace {
+                    !exclude.iter().any(|e| e == name)
+                } else if !packages.is_empty() {
+                    packages.iter().any(|p| p == name)
+                } else {
+                    root_package.is_none_or(|root| root == name)
+                }
+            };
+
+            let mut updates = Vec::new();
+            for (name, current) in versions.iter().filter(|(name, _)| selected(name)) {
+                let current = current
+                    .as_deref()
+                    .with_context(|| format!("`{name}` has no version"))?;
+                let version = match (target, bump) {
+                    (Some(target), _) => semver::Version::parse(target)
+                        .with_context(|| format!("`{target}` is not a valid version"))?,
+                    (None, Some(level)) => {
+                        let current = semver::Version::parse(current).with_context(|| {
+                            format!("`{name}` is at {current}, which can't be bumped")
+                        })?;
+                        level.apply(&current, None)?
+                    }
+                    (None, None) => unreachable!("clap requires a target or `--bump`"),
+                };
+
+                eprintln!("{:>12} {name} from {current} to {version}", "Upgrading");
+                updates.push((name.clone(), version.to_string()));
+            }
+            service.update_versions(&mut manifest, &updates)?;
+            after_version_change(
+                service,
+                &mut manifest,
+                manifest_path,
+                &ArtifactArgs::default(),
+                &updates,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// How cargo-edit names `table` in its status lines.
+fn section_name(table: &DependencyTable) -> String {
+    match &table.target {
+        Some(target) => format!("{} for target `{target}`", table.kind.table_name()),
+        None => table.kind.table_name().to_string(),
+    }
+}
@@ -0,0 +1,418 @@
+//! `cargo set daemon`: newline-delimited JSON-RPC 2.0 over a unix socket.
+//!
+//! Loaded workspaces are kept between requests, keyed by root manifest path.
+//! `code-actions` lists the edits available at a byte offset of a manifest
+//! and `resolve-action` turns one into text edits for the editor to apply.
+//! Edits made through the daemon keep the cached state current; call `load`
+//! after editing manifests by other means.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use cargo_set_lib::{display_path, CargoManifest, CargoManifestService, CodeAction, FileSystem};
+use serde_json::{json, Value};
+
+use crate::watch::Check;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const OPERATION_FAILED: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            code: OPERATION_FAILED,
+            message: format!("{error:#}"),
+        }
+    }
+}
+
+impl From<cargo_set_lib::Error> for RpcError {
+    fn from(error: cargo_set_lib::Error) -> Self {
+        anyhow::Error::from(error).into()
+    }
+}
+
+pub struct Daemon<F: FileSystem> {
+    service: CargoManifestService<F>,
+    workspaces: BTreeMap<PathBuf, CargoManifest>,
+}
+
+impl<F: FileSystem> Daemon<F> {
+    pub fn new(service: CargoManifestService<F>) -> Self {
+        Self {
+            service,
+            workspaces: BTreeMap::new(),
+        }
+    }
+
+    /// Serve connections on `socket` one at a time until a `shutdown`
+    /// request.
+    #[cfg(unix)]
+    pub fn serve(mut self, socket: &Path) -> anyhow::Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+        use std::os::unix::net::UnixListener;
+
+        // Only a socket left behind by an earlier daemon is replaced, never a
+        // file that happens to have the name.
+        match std::fs::symlink_metadata(socket) {
+            Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(socket)
+                .with_context(|| format!("failed to remove stale {}", display_path(socket)))?,
+            Ok(_) => anyhow::bail!(
+                "{} exists and isn't a socket, not replacing it",
+                display_path(socket)
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to read {}", display_path(socket)))
+            }
+        }
+        let listener = UnixListener::bind(socket)
+            .with_context(|| format!("failed to listen on {}", display_path(socket)))?;
+        tracing::info!(socket = display_path(socket), "daemon listening");
+
+        for stream in listener.incoming() {
+            // A client that goes away or sends garbage only ends its own
+            // connection.
+            let served = stream
+                .context("failed to accept connection")
+                .and_then(|stream| {
+                    let reader = BufReader::new(stream.try_clone()?);
+                    self.handle_connection(reader, stream)
+                });
+            match served {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("dropped a connection: {e:#}"),
+            }
+        }
+
+        std::fs::remove_file(socket).ok();
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn serve(self, _socket: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("the daemon needs unix sockets, which this platform doesn't have")
+    }
+
+    /// Answer every request of one connection. Returns whether a `shutdown`
+    /// was requested.
+    fn handle_connection(
+        &mut self,
+        reader: impl BufRead,
+        mut writer: impl Write,
+    ) -> anyhow::Result<bool> {
+        for line in reader.lines() {
+            let line = line.context("failed to read request")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (response, shutdown) = self.handle_line(&line);
+            writeln!(writer, "{response}").context("failed to write response")?;
+            if shutdown {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn handle_line(&mut self, line: &str) -> (Value, bool) {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return (
+                    error_response(
+                        Value::Null,
+                        RpcError {
+                            code: PARSE_ERROR,
+                            message: e.to_string(),
+                        },
+                    ),
+                    false,
+                )
+            }
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        if method == "shutdown" {
+            return (result_response(id, Value::Null), true);
+        }
+
+        match self.call(method, &params) {
+            Ok(result) => (result_response(id, result), false),
+            Err(error) => (error_response(id, error), false),
+        }
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        let path = params
+            .get("path")
+            .and_then(Value::as_str)
+            .unwrap_or("Cargo.toml");
+        let path = Path::new(path);
+
+        match method {
+            "load" => {
+                self.workspaces.remove(path);
+                Ok(packages(self.workspace(path)?))
+            }
+            "packages" => Ok(packages(self.workspace(path)?)),
+            "dependents" => {
+                let name = string_param(params, "crate")?;
+                let dependents = self
+                    .workspace(path)?
+                    .dependents(name)
+                    .iter()
+                    .map(|location| {
+                        json!({
+                            "manifest": display_path(&location.manifest),
+                            "key": location.key(),
+                        })
+                    })
+                    .collect();
+                Ok(Value::Array(dependents))
+            }
+            "check" => {
+                let check: Check = string_param(params, "check")?
+                    .parse()
+                    .map_err(|e: anyhow::Error| RpcError::invalid_params(e.to_string()))?;
+                Ok(json!(check.findings(self.workspace(path)?)))
+            }
+            "code-actions" => {
+                let manifest = Path::new(string_param(params, "manifest")?);
+                let offset = params
+                    .get("offset")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| {
+                        RpcError::invalid_params("missing integer parameter `offset`")
+                    })?;
+                let actions = self
+                    .workspace(path)?
+                    .code_actions(manifest, offset as usize)
+                    .into_iter()
+                    .map(|action| json!({ "title": action.to_string(), "action": action }))
+                    .collect();
+                Ok(Value::Array(actions))
+            }
+            "resolve-action" => {
+                let action: CodeAction =
+                    serde_json::from_value(params.get("action").cloned().unwrap_or_default())
+                        .map_err(|e| RpcError::invalid_params(format!("invalid `action`: {e}")))?;
+                self.workspace(path)?;
+                let edits = self
+                    .service
+                    .code_action_edits(&self.workspaces[path], &action)?;
+                Ok(json!(edits))
+            }
+            "set-version" => {
+                let name = string_param(params, "crate")?.to_string();
+                let version = string_param(params, "version")?.to_string();
+                self.workspace(path)?;
+                let workspace = self.workspaces.get_mut(path).expect("workspace was loaded");
+                self.service.update_version(workspace, name, version)?;
+                Ok(packages(workspace))
+            }
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method `{method}`"),
+            }),
+        }
+    }
+
+    /// The cached workspace rooted at `path`, loading it on first use.
+    fn workspace(&mut self, path: &Path) -> anyhow::Result<&CargoManifest> {
+        if !self.workspaces.contains_key(path) {
+            let workspace = self.service.load_manifest(path)?;
+            self.workspaces.insert(path.to_path_buf(), workspace);
+        }
+
+        Ok(&self.workspaces[path])
+    }
+}
+
+fn packages(workspace: &CargoManifest) -> Value {
+    workspace
+        .package_versions()
+        .into_iter()
+        .map(|(name, version)| (name, json!(version)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+fn string_param<'p>(params: &'p Value, name: &str) -> Result<&'p str, RpcError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params(format!("missing string parameter `{name}`")))
+}
+
+fn result_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use cargo_set_lib::MemoryFileSystem;
+
+    use super::*;
+
+    fn daemon() -> Daemon<MemoryFileSystem> {
+        let fs = MemoryFileSystem::new()
+            .with_file("Cargo.toml", "[workspace]\nmembers = [\"a\"]\n")
+            .with_file(
+                "a/Cargo.toml",
+                "[package]\nname = \"a\"\nversion = \"0.1.0\"\n",
+            );
+        Daemon::new(CargoManifestService::new(fs))
+    }
+
+    fn responses(output: Vec<u8>) -> anyhow::Result<Vec<Value>> {
+        String::from_utf8(output)?
+            .lines()
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    #[test]
+    fn answers_requests_of_a_connection() -> anyhow::Result<()> {
+        let mut daemon = daemon();
+        let requests = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"packages"}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"set-version","params":{"crate":"a","version":"0.2.0"}}"#,
+            "not json",
+            r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"packages"}"#,
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+
+        let shutdown = daemon.handle_connection(Cursor::new(requests), &mut output)?;
+        assert!(shutdown);
+        assert_eq!(
+            responses(output)?,
+            [
+                json!({ "jsonrpc": "2.0", "id": 1, "result": { "a": "0.1.0" } }),
+                json!({ "jsonrpc": "2.0", "id": 2, "result": { "a": "0.2.0" } }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": PARSE_ERROR, "message": "expected ident at line 1 column 2" },
+                }),
+                json!({ "jsonrpc": "2.0", "id": 3, "result": null }),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn a_bad_client_only_ends_its_connection() -> anyhow::Result<()> {
+        let mut daemon = daemon();
+        let mut input = br#"{"jsonrpc":"2.0","id":1,"method":"load"}"#.to_vec();
+        input.extend(b"\n\xff\xfe\n");
+        let mut output = Vec::new();
+        let error = daemon
+            .handle_connection(Cursor::new(input), &mut output)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "failed to read request");
+        assert_eq!(responses(output)?.len(), 1);
+
+        let mut output = Vec::new();
+        let shutdown = daemon.handle_connection(
+            Cursor::new(r#"{"jsonrpc":"2.0","id":2,"method":"packages"}"#),
+            &mut output,
+        )?;
+        assert!(!shutdown);
+        assert_eq!(
+            responses(output)?,
+            [json!({ "jsonrpc": "2.0", "id": 2, "result": { "a": "0.1.0" } })]
+        );
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_serving_after_a_client_hangs_up() -> anyhow::Result<()> {
+        use std::os::unix::net::UnixStream;
+
+        let socket =
+            std::env::temp_dir().join(format!("cargo-set-daemon-{}.sock", std::process::id()));
+        let server = {
+            let socket = socket.clone();
+            std::thread::spawn(move || daemon().serve(&socket))
+        };
+        let connect = || -> anyhow::Result<UnixStream> {
+            for _ in 0..100 {
+                if let Ok(stream) = UnixStream::connect(&socket) {
+                    return Ok(stream);
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            anyhow::bail!("the daemon didn't listen")
+        };
+
+        let mut bad = connect()?;
+        bad.write_all(b"\xff\n")?;
+        drop(bad);
+
+        let mut client = connect()?;
+        writeln!(client, r#"{{"jsonrpc":"2.0","id":1,"method":"shutdown"}}"#)?;
+        let mut response = String::new();
+        BufReader::new(&client).read_line(&mut response)?;
+        assert_eq!(
+            serde_json::from_str::<Value>(&response)?,
+            json!({ "jsonrpc": "2.0", "id": 1, "result": null })
+        );
+        server.join().unwrap()?;
+        assert!(!socket.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn leaves_a_file_that_isnt_a_socket() -> anyhow::Result<()> {
+        let socket =
+            std::env::temp_dir().join(format!("cargo-set-daemon-{}.file", std::process::id()));
+        std::fs::write(&socket, "keep me")?;
+
+        let error = daemon().serve(&socket).unwrap_err();
+        assert!(error.to_string().contains("isn't a socket"), "{error:#}");
+        assert_eq!(std::fs::read_to_string(&socket)?, "keep me");
+        std::fs::remove_file(&socket)?;
+
+        Ok(())
+    }
+}
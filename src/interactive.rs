@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use anyhow::Context;
+use cargo_set_lib::{BumpLevel, CargoManifest, VersionTarget, VersionsFile};
+
+/// Ask on `output` which members of `manifest` to change and what to,
+/// reading the answers from `input`. `level` is the bump for crates given no
+/// answer. An empty selection chooses nothing.
+pub fn choose_targets(
+    manifest: &CargoManifest,
+    level: BumpLevel,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> anyhow::Result<VersionsFile> {
+    let packages = manifest
+        .package_versions()
+        .into_iter()
+        .filter_map(|(name, version)| Some((name, version?)))
+        .collect::<Vec<_>>();
+    if packages.is_empty() {
+        anyhow::bail!("the workspace has no versioned packages");
+    }
+
+    let width = packages
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (i, (name, version)) in packages.iter().enumerate() {
+        writeln!(output, "{:>3}) {name:<width$}  {version}", i + 1)?;
+    }
+
+    let selected = loop {
+        let Some(answer) = ask(
+            input,
+            output,
+            "crates to change (numbers or names, `all`; empty for none): ",
+        )?
+        else {
+            return Ok(VersionsFile::default());
+        };
+        match select(&packages, &answer) {
+            Ok(selected) => break selected,
+            Err(e) => writeln!(output, "{e}")?,
+        }
+    };
+
+    let mut targets = BTreeMap::new();
+    for (name, version) in selected {
+        let target = loop {
+            let question = format!("{name} {version}: bump level or version [{level}]: ");
+            let Some(answer) = ask(input, output, &question)? else {
+                break VersionTarget::Bump(level);
+            };
+            match answer.parse::<VersionTarget>() {
+                Ok(target) => break target,
+                Err(e) => writeln!(output, "{e}")?,
+            }
+        };
+        targets.insert(name.clone(), target);
+    }
+
+    Ok(VersionsFile { targets })
+}
+
+/// Write `question` and read a line of `input`, `None` for an empty answer.
+fn ask(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+) -> anyhow::Result<Option<String>> {
+    write!(output, "{question}")?;
+    output.flush()?;
+
+    let mut answer = String::new();
+    if input
+        .read_line(&mut answer)
+        .context("failed to read the answer")?
+        == 0
+    {
+        anyhow::bail!("the input ended before every question was answered");
+    }
+
+    let answer = answer.trim();
+    Ok((!answer.is_empty()).then(|| answer.to_string()))
+}
+
+/// The `packages` an answer names, by their number in the list or their name,
+/// separated by commas or spaces.
+fn select<'p>(
+    packages: &'p [(String, String)],
+    answer: &str,
+) -> anyhow::Result<Vec<&'p (String, String)>> {
+    if answer == "all" {
+        return Ok(packages.iter().collect());
+    }
+
+    let mut selected = Vec::new();
+    for choice in answer.split([',', ' ']).filter(|choice| !choice.is_empty()) {
+        let package = match choice.parse::<usize>() {
+            Ok(number) => number.checked_sub(1).and_then(|i| packages.get(i)),
+            Err(_) => packages.iter().find(|(name, _)| name == choice),
+        }
+        .with_context(|| format!("`{choice}` is not one of the listed crates"))?;
+        if !selected.contains(&package) {
+            selected.push(package);
+        }
+    }
+
+    Ok(selected)
+}
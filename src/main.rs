@@ -1,7 +1,41 @@
+use std::process::ExitCode;
+
+use cargo_set_lib::ParseError;
+
 mod commands;
+mod compat;
+mod daemon;
+mod interactive;
+mod pr;
+mod remote;
+mod roots;
+mod watch;
 
-fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<ExitCode> {
     let args = std::env::args();
 
-    commands::cli_execute(args)
+    commands::cli_execute(args).or_else(report)
+}
+
+/// Show a failure to parse a file as the lines it is about, with what it
+/// failed on underlined; other errors are left to `main`.
+fn report(error: anyhow::Error) -> anyhow::Result<ExitCode> {
+    let Some(parse_error) = error.chain().find_map(as_parse_error) else {
+        return Err(error);
+    };
+
+    for context in error.chain().take_while(|e| as_parse_error(*e).is_none()) {
+        eprintln!("Error: {context}");
+    }
+    eprintln!("{:?}", miette::Report::new(parse_error.clone()));
+
+    Ok(ExitCode::FAILURE)
+}
+
+/// The [`ParseError`] `error` is, or holds as the library's error.
+fn as_parse_error<'e>(error: &'e (dyn std::error::Error + 'static)) -> Option<&'e ParseError> {
+    match error.downcast_ref::<cargo_set_lib::Error>() {
+        Some(cargo_set_lib::Error::Parse(parse_error)) => Some(parse_error),
+        _ => error.downcast_ref(),
+    }
 }
@@ -0,0 +1,267 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::Context;
+use cargo_set_lib::{changed_files, display_path};
+use clap::ValueEnum;
+use serde_json::json;
+
+/// Where pull requests are opened.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Forge {
+    #[value(name = "github")]
+    GitHub,
+    #[value(name = "gitlab")]
+    GitLab,
+}
+
+pub struct PullRequest<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    pub head: &'a str,
+    pub base: &'a str,
+}
+
+/// Fail if the git repository containing `dir` has uncommitted changes:
+/// the branch of the pull request would pick them up along with the
+/// command's.
+pub fn check_clean(dir: &Path) -> anyhow::Result<()> {
+    if !changed_files(dir)?.is_empty() {
+        anyhow::bail!(
+            "{} has uncommitted changes, commit or stash them first",
+            display_path(dir)
+        );
+    }
+
+    Ok(())
+}
+
+/// Open `pr` on the repository behind `remote_url`, authenticating with the
+/// token from `GITHUB_TOKEN` (or `GH_TOKEN`) or `GITLAB_TOKEN`.
+///
+/// Returns the web URL of the new pull request.
+pub fn open(forge: Option<Forge>, remote_url: &str, pr: &PullRequest) -> anyhow::Result<String> {
+    let (host, project) = repository(remote_url)
+        .with_context(|| format!("can't tell the repository of {remote_url}"))?;
+    let forge = match forge {
+        Some(forge) => forge,
+        None if host.contains("github") => Forge::GitHub,
+        None if host.contains("gitlab") => Forge::GitLab,
+        None => anyhow::bail!("can't tell whether {host} is GitHub or GitLab, pass `--forge`"),
+    };
+
+    match forge {
+        Forge::GitHub => {
+            let token = token(&["GITHUB_TOKEN", "GH_TOKEN"])?;
+            let api = match std::env::var("GITHUB_API_URL") {
+                Ok(api) => api,
+                Err(_) if host == "github.com" => "https://api.github.com".to_string(),
+                Err(_) => format!("https://{host}/api/v3"),
+            };
+            let response = post(
+                &format!("{api}/repos/{project}/pulls"),
+                &[
+                    format!("Authorization: Bearer {token}"),
+                    "Accept: application/vnd.github+json".to_string(),
+                ],
+                &json!({
+                    "title": pr.title,
+                    "body": pr.body,
+                    "head": pr.head,
+                    "base": pr.base,
+                }),
+            )?;
+            web_url(&response, "html_url")
+        }
+        Forge::GitLab => {
+            let token = token(&["GITLAB_TOKEN"])?;
+            let response = post(
+                &format!(
+                    "https://{host}/api/v4/projects/{}/merge_requests",
+                    project.replace('/', "%2F")
+                ),
+                &[format!("PRIVATE-TOKEN: {token}")],
+                &json!({
+                    "title": pr.title,
+                    "description": pr.body,
+                    "source_branch": pr.head,
+                    "target_branch": pr.base,
+                }),
+            )?;
+            web_url(&response, "web_url")
+        }
+    }
+}
+
+/// The pull request body: the versions that changed and the files edited.
+pub fn summary(
+    invocation: &str,
+    before: &[(String, Option<String>)],
+    after: &[(String, Option<String>)],
+    files: &[String],
+) -> String {
+    let mut body = format!("Generated by `{invocation}`.\n");
+
+    let changed = after
+        .iter()
+        .filter_map(|(name, version)| {
+            let previous = before.iter().find(|(n, _)| n == name)?.1.as_deref();
+            (previous != version.as_deref()).then(|| {
+                format!(
+                    "| {name} | {} | {} |\n",
+                    previous.unwrap_or("-"),
+                    version.as_deref().unwrap_or("-")
+                )
+            })
+        })
+        .collect::<String>();
+    if !changed.is_empty() {
+        body.push_str("\n| Crate | Before | After |\n| --- | --- | --- |\n");
+        body.push_str(&changed);
+    }
+
+    body.push_str("\nChanged files:\n");
+    for file in files {
+        body.push_str(&format!("- `{file}`\n"));
+    }
+
+    body
+}
+
+/// The host and `owner/name` path of a git remote URL, e.g.
+/// `git@github.com:org/repo.git`.
+fn repository(url: &str) -> Option<(String, String)> {
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        None => url,
+    };
+    let rest = rest.rsplit_once('@').map_or(rest, |(_, rest)| rest);
+    let (host, path) = match rest.find(['/', ':']) {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => return None,
+    };
+    // Drop a port, as in `ssh://git@host:22/org/repo`.
+    let path = match path.split_once('/') {
+        Some((port, path)) if port.chars().all(|c| c.is_ascii_digit()) => path,
+        _ => path,
+    };
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+
+    Some((host.to_string(), path.to_string()))
+}
+
+fn token(variables: &[&str]) -> anyhow::Result<String> {
+    variables
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|token| !token.is_empty()))
+        .with_context(|| {
+            format!(
+                "set {} to a token allowed to open pull requests",
+                variables.join(" or ")
+            )
+        })
+}
+
+/// POST `body` to `url` with curl. Headers are passed through its standard
+/// input so tokens don't show up in the process list.
+fn post(
+    url: &str,
+    headers: &[String],
+    body: &serde_json::Value,
+) -> anyhow::Result<serde_json::Value> {
+    let mut config = format!(
+        "url = {}\nrequest = \"POST\"\nheader = \"Content-Type: application/json\"\n",
+        quote(url)
+    );
+    for header in headers {
+        config.push_str(&format!("header = {}\n", quote(header)));
+    }
+    config.push_str(&format!("data = {}\n", quote(&body.to_string())));
+
+    let mut curl = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail-with-body",
+            "--config",
+            "-",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run curl")?;
+    curl.stdin
+        .take()
+        .context("failed to pass the request to curl")?
+        .write_all(config.as_bytes())?;
+    let output = curl.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "opening the pull request failed: {} {}",
+            String::from_utf8_lossy(&output.stderr).trim(),
+            String::from_utf8_lossy(&output.stdout).trim()
+        );
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .context("unexpected response when opening the pull request")
+}
+
+/// `value` as a double-quoted curl config string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn web_url(response: &serde_json::Value, field: &str) -> anyhow::Result<String> {
+    response[field]
+        .as_str()
+        .map(str::to_string)
+        .with_context(|| format!("the response has no `{field}`"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) -> anyhow::Result<()> {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()?;
+        anyhow::ensure!(status.success(), "`git {}` failed", args.join(" "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_a_repository_with_uncommitted_changes() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cargo-set-pr-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("Cargo.toml"), "[workspace]\n")?;
+        git(&dir, &["init", "--quiet"])?;
+        git(&dir, &["add", "--all"])?;
+        git(&dir, &["commit", "--quiet", "-m", "first"])?;
+        check_clean(&dir)?;
+
+        std::fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = []\n")?;
+        let error = check_clean(&dir).unwrap_err();
+        assert!(
+            error.to_string().contains("has uncommitted changes"),
+            "{error:#}"
+        );
+        git(&dir, &["checkout", "--quiet", "."])?;
+        std::fs::write(dir.join("notes.txt"), "")?;
+        assert!(check_clean(&dir).is_err());
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cargo_set_lib::{clone, diff_all, display_path, push_branch};
+
+use crate::commands::Commands;
+
+/// What happens to a remote checkout once the command ran.
+pub enum Outcome {
+    /// Print the changes as a diff and remove the checkout.
+    Diff,
+    /// Commit the changes to this new branch, push it and remove the
+    /// checkout.
+    Push(String),
+    /// Leave the checkout in place for inspection.
+    Keep,
+}
+
+/// Clone `url` at `branch` into a temporary directory, run `command` in it
+/// with its `--path` taken from the checkout's root, and settle the changes
+/// according to `outcome`.
+pub fn run(
+    url: &str,
+    branch: Option<&str>,
+    outcome: &Outcome,
+    command: &Commands,
+    execute: impl FnOnce(&Commands) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let checkout = temp_checkout();
+    clone(url, branch, &checkout)?;
+    eprintln!("cloned {url} into {}", display_path(&checkout));
+
+    let result = command
+        .map_root(|path| checkout.join(path))
+        .and_then(|command| execute(&command))
+        .and_then(|()| settle(&checkout, url, outcome));
+
+    match outcome {
+        Outcome::Keep => eprintln!("left the checkout at {}", display_path(&checkout)),
+        Outcome::Diff | Outcome::Push(_) => {
+            if let Err(e) = std::fs::remove_dir_all(&checkout) {
+                tracing::warn!(error = %e, "failed to remove {}", display_path(&checkout));
+            }
+        }
+    }
+
+    result
+}
+
+fn settle(checkout: &Path, url: &str, outcome: &Outcome) -> anyhow::Result<()> {
+    match outcome {
+        Outcome::Diff => {
+            let diff = diff_all(checkout)?;
+            if !diff.is_empty() {
+                println!("{diff}");
+            }
+        }
+        Outcome::Push(branch) => {
+            push_branch(checkout, branch, "Update manifests with cargo-set")?;
+            eprintln!("pushed {branch} to {url}");
+        }
+        Outcome::Keep => {}
+    }
+
+    Ok(())
+}
+
+/// A fresh path in the system's temporary directory.
+fn temp_checkout() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+
+    std::env::temp_dir().join(format!("cargo-set-{}-{nanos}", std::process::id()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use anyhow::Context;
+
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()?;
+        anyhow::ensure!(output.status.success(), "`git {}` failed", args.join(" "));
+
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    #[test]
+    fn pushes_the_changes_of_a_checkout() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cargo-set-remote-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("Cargo.toml"), "[workspace]\n")?;
+        git(&dir, &["init", "--quiet"])?;
+        git(&dir, &["add", "--all"])?;
+        git(&dir, &["commit", "--quiet", "-m", "first"])?;
+
+        let command = Commands::Sync {
+            path: PathBuf::from("Cargo.toml"),
+        };
+        let mut checkout = None;
+        run(
+            &dir.to_string_lossy(),
+            None,
+            &Outcome::Push("update".to_string()),
+            &command,
+            |command| {
+                let root = command.root()?;
+                std::fs::write(&root, "[workspace]\nresolver = \"2\"\n")?;
+                let dir = root.parent().context("the root has no directory")?;
+                // A fresh clone has no identity to commit with.
+                git(dir, &["config", "user.name", "test"])?;
+                git(dir, &["config", "user.email", "test@example.com"])?;
+                checkout = Some(dir.to_path_buf());
+                Ok(())
+            },
+        )?;
+
+        // The command ran in the checkout, the changes only reached the
+        // repository on the pushed branch, and the checkout is gone.
+        let checkout = checkout.expect("the command ran");
+        assert_ne!(checkout, dir);
+        assert!(!checkout.exists());
+        assert_eq!(
+            git(&dir, &["show", "update:Cargo.toml"])?,
+            "[workspace]\nresolver = \"2\"\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Cargo.toml"))?,
+            "[workspace]\n"
+        );
+
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use cargo_set_lib::{display_path, find_workspace_roots, RealFileSystem};
+
+use crate::commands::Commands;
+
+/// The workspace roots given with `--root`, followed by those listed in
+/// `roots_file` and those found below `scan`, without duplicates.
+/// Directories stand for their `Cargo.toml`; relative paths in the file are
+/// taken from its directory.
+pub fn collect(
+    roots: &[PathBuf],
+    roots_file: Option<&Path>,
+    scan: Option<&Path>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut collected = roots.to_vec();
+    if let Some(file) = roots_file {
+        let contents = std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read {}", display_path(file)))?;
+        let base = file.parent().unwrap_or_else(|| Path::new(""));
+        collected.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(|line| base.join(line)),
+        );
+    }
+
+    if let Some(dir) = scan {
+        let found = find_workspace_roots(&RealFileSystem, dir)?;
+        if found.is_empty() {
+            anyhow::bail!("found no workspace below {}", display_path(dir));
+        }
+        collected.extend(found);
+    }
+
+    let mut unique = Vec::new();
+    for root in collected {
+        let root = if root.is_dir() {
+            root.join("Cargo.toml")
+        } else {
+            root
+        };
+        if !unique.contains(&root) {
+            unique.push(root);
+        }
+    }
+
+    Ok(unique)
+}
+
+/// Run `run` for the command of every root, carrying on past failures, then
+/// report how each went. Fails if any root failed.
+pub fn run_each<T>(
+    runs: &[(&Path, T)],
+    mut run: impl FnMut(&T) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut results = Vec::new();
+    for (root, command) in runs {
+        eprintln!("==> {}", display_path(root));
+        let result = run(command);
+        if let Err(e) = &result {
+            eprintln!("error: {e:#}");
+        }
+        results.push((root, result));
+    }
+
+    eprintln!();
+    let mut failed = 0;
+    for (root, result) in &results {
+        match result {
+            Ok(()) => eprintln!("ok     {}", display_path(root)),
+            Err(e) => {
+                failed += 1;
+                eprintln!("FAILED {}: {e}", display_path(root));
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} workspace(s) failed", results.len());
+    }
+
+    Ok(())
+}
+
+impl Commands {
+    /// This command, run against the workspace at `root` instead of its
+    /// `--path`.
+    pub fn with_root(&self, root: &Path) -> anyhow::Result<Commands> {
+        self.map_root(|_| root.to_path_buf())
+    }
+
+    /// The root manifest the command works on.
+    pub fn root(&self) -> anyhow::Result<PathBuf> {
+        let mut root = PathBuf::new();
+        self.map_root(|path| {
+            root = path.to_path_buf();
+            root.clone()
+        })?;
+
+        Ok(root)
+    }
+
+    /// This command, with its `--path` replaced by `map(path)`.
+    pub fn map_root(&self, map: impl FnOnce(&Path) -> PathBuf) -> anyhow::Result<Commands> {
+        let mut command = self.clone();
+        match &mut command {
+            Commands::Set { path, .. } => {
+                *path = Some(map(path.as_deref().unwrap_or(Path::new("Cargo.toml"))))
+            }
+            Commands::Resume { path, .. }
+            | Commands::Revert { path, .. }
+            | Commands::Sync { path }
+            | Commands::CheckInternal { path }
+            | Commands::Freeze { path, .. }
+            | Commands::Unfreeze { path, .. }
+            | Commands::Normalize { path, .. }
+            | Commands::UnusedDeps { path, .. }
+            | Commands::BuildInfo { path, .. }
+            | Commands::Init { path, .. }
+            | Commands::ImportConfig { path, .. }
+            | Commands::Bump { path, .. }
+            | Commands::ApplyVersions { path, .. }
+            | Commands::Interactive { path, .. }
+            | Commands::Duplicates { path, .. }
+            | Commands::Fix { path, .. }
+            | Commands::Audit { path }
+            | Commands::Why { path, .. }
+            | Commands::Outdated { path, .. }
+            | Commands::Get { path, .. }
+            | Commands::SyncVersions { path, .. }
+            | Commands::Edition { path, .. }
+            | Commands::RustVersion { path, .. }
+            | Commands::Add { path, .. }
+            | Commands::Remove { path, .. }
+            | Commands::Features { path, .. }
+            | Commands::Rename { path, .. }
+            | Commands::Key { path, .. }
+            | Commands::Metadata { path, .. }
+            | Commands::Patch { path, .. }
+            | Commands::Member { path, .. }
+            | Commands::Profile { path, .. }
+            | Commands::Lints { path, .. }
+            | Commands::Order { path, .. }
+            | Commands::Publish { path, .. }
+            | Commands::Target { path, .. }
+            | Commands::Lint { path, .. }
+            | Commands::VerifyTags { path, .. }
+            | Commands::WorkspaceIfy { path, .. }
+            | Commands::Check { path, .. } => *path = map(path),
+            Commands::Compat { command } => command.map_manifest_path(map),
+            Commands::Schema { .. } => anyhow::bail!("`schema` doesn't work on a workspace"),
+            Commands::Completions { .. } => {
+                anyhow::bail!("`completions` doesn't work on a workspace")
+            }
+            Commands::Pr { .. } => {
+                anyhow::bail!("`pr` takes its workspace from the command it runs")
+            }
+            Commands::Watch { .. } | Commands::Daemon { .. } => {
+                anyhow::bail!("this command runs until stopped and takes a single workspace")
+            }
+        }
+
+        Ok(command)
+    }
+}
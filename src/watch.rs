@@ -0,0 +1,133 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::Context;
+use cargo_set_lib::{display_path, workspace_dir, CargoManifest, CargoManifestService, FileSystem};
+use notify::{RecursiveMode, Watcher};
+
+/// A validation `watch` re-runs.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Check {
+    Internal,
+    Duplicates,
+    UnusedDeps,
+}
+
+impl Check {
+    fn name(self) -> &'static str {
+        match self {
+            Check::Internal => "check-internal",
+            Check::Duplicates => "duplicates",
+            Check::UnusedDeps => "unused-deps",
+        }
+    }
+
+    pub fn findings(self, manifest: &CargoManifest) -> Vec<String> {
+        match self {
+            Check::Internal => manifest
+                .requirement_drift()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            Check::Duplicates => manifest
+                .duplicate_dependencies()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            Check::UnusedDeps => manifest
+                .unused_workspace_dependencies()
+                .iter()
+                .map(|name| format!("workspace.dependencies.{name} is not used by any member"))
+                .collect(),
+        }
+    }
+}
+
+impl std::str::FromStr for Check {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Check::Internal, Check::Duplicates, Check::UnusedDeps]
+            .into_iter()
+            .find(|check| check.name() == s)
+            .with_context(|| {
+                format!("`{s}` is not a check, use check-internal, duplicates or unused-deps")
+            })
+    }
+}
+
+/// How long to wait for more changes after one arrives, so a save touching
+/// several manifests runs the checks once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Run `checks` against the workspace rooted at `root_manifest`, then again
+/// every time one of its manifests changes. Only returns on error.
+pub fn watch<F: FileSystem>(
+    service: &CargoManifestService<F>,
+    root_manifest: &Path,
+    checks: &[Check],
+) -> anyhow::Result<()> {
+    let dir = workspace_dir(root_manifest);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).context("failed to start watching")?;
+    watcher
+        .watch(dir, RecursiveMode::Recursive)
+        .with_context(|| format!("failed to watch {}", display_path(dir)))?;
+
+    run_checks(service, root_manifest, checks);
+    loop {
+        let event = receiver.recv().context("stopped watching")?;
+        let mut changed = is_manifest_change(event);
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+            changed |= is_manifest_change(event);
+        }
+
+        if changed {
+            run_checks(service, root_manifest, checks);
+        }
+    }
+}
+
+fn is_manifest_change(event: notify::Result<notify::Event>) -> bool {
+    match event {
+        Ok(event) => {
+            !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name().is_some_and(|name| name == "Cargo.toml"))
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, "watch error");
+            false
+        }
+    }
+}
+
+fn run_checks<F: FileSystem>(
+    service: &CargoManifestService<F>,
+    root_manifest: &Path,
+    checks: &[Check],
+) {
+    let manifest = match service.load_manifest(root_manifest) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            println!("FAIL load: {e:#}");
+            return;
+        }
+    };
+
+    for check in checks {
+        let findings = check.findings(&manifest);
+        if findings.is_empty() {
+            println!("ok   {}", check.name());
+        } else {
+            println!("FAIL {}: {} finding(s)", check.name(), findings.len());
+            for finding in findings {
+                println!("  {finding}");
+            }
+        }
+    }
+}